@@ -0,0 +1,89 @@
+//! Compares recording a range dependency once per range with recording one edge per cell.
+//!
+//! Run with `cargo bench --no-default-features --features main2 --bench range_dependencies`.
+
+use rust_lab::expression_utils::parse_formula;
+use rust_lab::graph_extension::{
+    UndoRedoStack, add_dependency_extension, assign_cell_extension, delete_dependency_extension,
+    initialise_extension,
+};
+use std::time::{Duration, Instant};
+
+const ROWS: i32 = 999;
+const COLUMNS: i32 = 703;
+const ITERATIONS: u32 = 5;
+
+/// The ranges summed, with their number of rows and columns.
+const RANGES: [(&str, i32, i32); 4] = [
+    ("A1:A999", 999, 1),
+    ("A1:J999", 999, 10),
+    ("A1:CV999", 999, 100),
+    ("A1:ZZ999", 999, 702),
+];
+
+/// Runs `f` `ITERATIONS` times and returns the average time per run.
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    // The formula is placed in the last column, outside every range
+    let (dependent_row, dependent_col) = (0, COLUMNS - 1);
+    let empty = *parse_formula("0").unwrap();
+
+    println!(
+        "{:>10} {:>8} {:>8} {:>16} {:>16}",
+        "range", "cells", "entries", "range index", "per-cell edges"
+    );
+    for (range, rows, columns) in RANGES {
+        let formula = *parse_formula(&format!("SUM({})", range)).unwrap();
+
+        // Assigning and then clearing the formula adds and removes its dependencies
+        let mut sheet = initialise_extension(ROWS, COLUMNS);
+        let mut undo = UndoRedoStack::new();
+        let mut assign = |formula: &rust_lab::expression_parser::Expr| {
+            assign_cell_extension(
+                &mut sheet,
+                &mut undo,
+                dependent_row,
+                dependent_col,
+                formula.clone(),
+            )
+            .unwrap();
+        };
+        let indexed = time(|| {
+            assign(&formula);
+            assign(&empty);
+        });
+        assign(&formula);
+        let entries = sheet.range_dependents.entry_count();
+
+        // The same work with one edge per cell, as ranges were recorded before
+        let mut sheet = initialise_extension(ROWS, COLUMNS);
+        let expanded = time(|| {
+            for r in 0..rows {
+                for c in 0..columns {
+                    add_dependency_extension(&mut sheet, r, c, dependent_row, dependent_col);
+                }
+            }
+            for r in 0..rows {
+                for c in 0..columns {
+                    delete_dependency_extension(&mut sheet, r, c, dependent_row, dependent_col);
+                }
+            }
+        });
+
+        println!(
+            "{:>10} {:>8} {:>8} {:>16?} {:>16?}",
+            range,
+            rows * columns,
+            entries,
+            indexed,
+            expanded
+        );
+    }
+}
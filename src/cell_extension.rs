@@ -1,5 +1,126 @@
+use crate::cell_grid::{CellGrid, GridCell};
 use crate::expression_parser::Expr;
-use std::collections::HashSet;
+use crate::iterative_calc::IterationSettings;
+use crate::range_index::RangeIndex;
+use crate::sheet_structure::LineMove;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref EMPTY_CELL: CellExtension = CellExtension::empty(0, 0);
+}
+
+/// An error produced while evaluating a formula.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CellError {
+    /// Division by zero (`#DIV/0!`).
+    DivByZero,
+    /// A reference to a cell outside the sheet (`#REF!`).
+    Ref,
+    /// A formula that depends on itself (`#CYCLE!`).
+    Cycle,
+    /// An operand of the wrong type, such as text in arithmetic (`#VALUE!`).
+    Value,
+    /// An unknown function name (`#NAME?`).
+    Name,
+    /// A numeric result that overflows or is undefined, such as `10^400` (`#NUM!`).
+    Num,
+    /// A loop of formulas that did not settle under iterative calculation (`#CONV!`).
+    Convergence,
+}
+
+impl CellError {
+    /// Returns the error code shown in the sheet, e.g. `#DIV/0!`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CellError::DivByZero => "#DIV/0!",
+            CellError::Ref => "#REF!",
+            CellError::Cycle => "#CYCLE!",
+            CellError::Value => "#VALUE!",
+            CellError::Name => "#NAME?",
+            CellError::Num => "#NUM!",
+            CellError::Convergence => "#CONV!",
+        }
+    }
+
+    /// Parses an error code such as `#REF!`, returning `None` for any other text.
+    pub fn from_code(code: &str) -> Option<CellError> {
+        [
+            CellError::DivByZero,
+            CellError::Ref,
+            CellError::Cycle,
+            CellError::Value,
+            CellError::Name,
+            CellError::Num,
+            CellError::Convergence,
+        ]
+        .into_iter()
+        .find(|error| error.code() == code)
+    }
+}
+
+impl fmt::Display for CellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// The value held by a cell.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CellValue {
+    /// A numeric value.
+    Number(f64),
+    /// A text value.
+    Text(String),
+    /// A boolean value, produced by comparisons and logical functions.
+    Bool(bool),
+    /// An error value, propagated to every formula that uses it.
+    Error(CellError),
+}
+
+impl CellValue {
+    /// Returns the numeric value, or `None` if the cell holds text, a boolean or an error.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            CellValue::Number(n) => Some(*n),
+            CellValue::Text(_) | CellValue::Bool(_) | CellValue::Error(_) => None,
+        }
+    }
+
+    /// Returns `true` if the value is an error.
+    pub fn is_error(&self) -> bool {
+        matches!(self, CellValue::Error(_))
+    }
+
+    /// Converts the value into a constant expression that evaluates back to it.
+    pub fn to_expr(&self) -> Expr {
+        match self {
+            CellValue::Number(n) => Expr::Number(*n),
+            CellValue::Text(s) => Expr::Text(s.clone()),
+            CellValue::Bool(b) => Expr::Bool(*b),
+            CellValue::Error(e) => Expr::Error(*e),
+        }
+    }
+}
+
+impl Default for CellValue {
+    fn default() -> Self {
+        CellValue::Number(0.0)
+    }
+}
+
+impl fmt::Display for CellValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellValue::Number(n) => write!(f, "{}", n),
+            CellValue::Text(s) => write!(f, "{}", s),
+            CellValue::Bool(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
+            CellValue::Error(e) => write!(f, "{}", e),
+        }
+    }
+}
 
 /// Represents an  cell in the spreadsheet.
 ///
@@ -8,20 +129,19 @@ use std::collections::HashSet;
 #[derive(Clone)]
 pub struct CellExtension {
     /// The value of the cell.
-    pub value: i32,
+    pub value: CellValue,
     /// The formula associated with the cell, parsed as an expression.
     pub formula: Expr,
     /// The row index of the cell (0-based).
     pub r: i32,
     /// The column index of the cell (0-based).
     pub c: i32,
-    /// Indicates whether the cell contains an error.
-    pub is_error: bool,
     /// Indicates whether the cell needs to be recalculated.
     pub is_recalculate: bool,
-    /// A set of cells that depend on this cell.
+    /// The cells whose formulas refer to this cell on its own. Formulas that refer to it
+    /// through a range are kept in `SpreadsheetExtension::range_dependents`.
     pub dependents: HashSet<crate::cellsp::CellReference>,
-    /// A set of cells that this cell depends on.
+    /// The single cells this cell's formula refers to, not counting ranges.
     pub precedents: HashSet<crate::cellsp::CellReference>,
     /// Indicates whether the cell's content is bold.
     pub is_bold: bool,
@@ -29,6 +149,26 @@ pub struct CellExtension {
     pub is_italics: bool,
 }
 
+impl GridCell for CellExtension {
+    fn empty(r: i32, c: i32) -> Self {
+        CellExtension {
+            value: CellValue::Number(0.0),
+            formula: Expr::Number(0.0),
+            r,
+            c,
+            is_recalculate: false,
+            dependents: HashSet::new(),
+            precedents: HashSet::new(),
+            is_bold: false,
+            is_italics: false,
+        }
+    }
+
+    fn empty_ref() -> &'static Self {
+        &EMPTY_CELL
+    }
+}
+
 /// Represents the spreadsheet as a whole.
 ///
 /// This struct contains the dimensions of the spreadsheet and all the cells within it.
@@ -38,8 +178,61 @@ pub struct SpreadsheetExtension {
     pub rows: i32,
     /// The number of columns in the spreadsheet.
     pub columns: i32,
-    /// A 2D vector containing all the cells in the spreadsheet.
-    pub all_cells: Vec<Vec<CellExtension>>,
+    /// All the cells in the spreadsheet, indexed as `all_cells[row][column]`.
+    /// Only cells that have been written take memory.
+    pub all_cells: CellGrid<CellExtension>,
+    /// The formulas that depend on ranges of cells, such as `SUM(A1:A10)`.
+    pub range_dependents: RangeIndex,
+    /// How loops of formulas are evaluated, or `None` if loops are rejected as cycles.
+    pub iteration: Option<IterationSettings>,
+    /// The formulas that refer to other sheets of a workbook, keyed by the upper-case name
+    /// of the sheet they refer to. Single cells are stored as ranges of one cell.
+    pub external_dependents: HashMap<String, RangeIndex>,
+    /// The cells of other sheets that this sheet's formulas refer to, keyed by the upper-case
+    /// name of the sheet. Kept up to date by `Workbook::refresh_links`; a sheet missing here
+    /// does not exist.
+    pub linked: HashMap<String, LinkedSheet>,
+    /// The names defined for cells, ranges and constants, such as `revenue` for `B2:B120`,
+    /// keyed by the upper-case name.
+    pub names: BTreeMap<String, DefinedName>,
+    /// The rows and columns inserted, deleted or cut off by a resize since
+    /// `Workbook::refresh_links` last passed them on to the formulas of other sheets, in order.
+    pub line_moves: Vec<LineMove>,
+}
+
+/// A name that formulas can use in place of a cell, a range or a constant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DefinedName {
+    /// The name, as it was defined.
+    pub name: String,
+    /// What the name stands for: an `Expr::Cell`, `Expr::Range` or `Expr::SheetRef`, or a
+    /// constant. A reference becomes `#REF!` if its cells are deleted.
+    pub target: Expr,
+}
+
+impl DefinedName {
+    /// Returns the key the name is stored under in `SpreadsheetExtension::names`.
+    pub fn key(&self) -> String {
+        self.name.to_ascii_uppercase()
+    }
+}
+
+/// Returns the key a sheet name is stored under in `external_dependents` and `linked`.
+///
+/// Sheet names are not case-sensitive, so `Sheet2!A1` and `SHEET2!A1` refer to the same sheet.
+pub fn sheet_key(name: &str) -> String {
+    name.to_ascii_uppercase()
+}
+
+/// The values of another sheet of a workbook, as seen by the formulas of a sheet.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkedSheet {
+    /// The number of rows in the other sheet.
+    pub rows: i32,
+    /// The number of columns in the other sheet.
+    pub columns: i32,
+    /// The values of the referenced cells that are not empty, by `(row, column)`.
+    pub values: HashMap<(i32, i32), CellValue>,
 }
 
 /// A type alias for `SpreadsheetExtension`.
@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::ops::{Index, IndexMut};
+
+/// The most rows a sheet can have.
+pub const MAX_ROWS: i32 = 999;
+/// The most columns a sheet can have (`ZZZ`).
+pub const MAX_COLS: i32 = 18278;
+
+/// A cell that can be stored in a `CellGrid`.
+pub trait GridCell: Sized + 'static {
+    /// Creates an empty cell at the given position.
+    fn empty(r: i32, c: i32) -> Self;
+
+    /// Returns a shared empty cell, handed out when reading a cell that was never written.
+    ///
+    /// Its position fields are those of `A1` rather than of the cell that was read.
+    fn empty_ref() -> &'static Self;
+}
+
+/// The cells of a spreadsheet, stored sparsely so that empty cells take no memory.
+///
+/// Cells are read and written with the same `grid[row][column]` indexing as a
+/// `Vec<Vec<T>>`. Reading a cell that was never written returns a shared empty cell;
+/// writing to it stores a new empty cell at that position first. Indices outside the
+/// grid panic, as they would for a `Vec`.
+#[derive(Clone, Debug)]
+pub struct CellGrid<T> {
+    rows: Vec<GridRow<T>>,
+    columns: usize,
+}
+
+/// One row of a `CellGrid`.
+#[derive(Clone, Debug)]
+pub struct GridRow<T> {
+    row: usize,
+    columns: usize,
+    /// The cells that have been written, keyed by column.
+    cells: BTreeMap<usize, T>,
+}
+
+impl<T: GridCell> CellGrid<T> {
+    /// Creates a grid of empty cells.
+    ///
+    /// # Arguments
+    /// * `rows` - The number of rows.
+    /// * `columns` - The number of columns.
+    pub fn new(rows: i32, columns: i32) -> Self {
+        let columns = columns as usize;
+        CellGrid {
+            rows: (0..rows as usize)
+                .map(|row| GridRow::new(row, columns))
+                .collect(),
+            columns,
+        }
+    }
+
+    /// Returns the number of rows.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns `true` if the grid has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Iterates over the rows.
+    pub fn iter(&self) -> std::slice::Iter<'_, GridRow<T>> {
+        self.rows.iter()
+    }
+
+    /// Iterates mutably over the rows.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, GridRow<T>> {
+        self.rows.iter_mut()
+    }
+
+    /// Iterates over the cells that have been written, row by row, with their positions.
+    pub fn cells(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.rows
+            .iter()
+            .flat_map(|row| row.iter().map(move |(c, cell)| ((row.row, c), cell)))
+    }
+
+    /// Iterates mutably over the cells that have been written, row by row, with their
+    /// positions.
+    pub fn cells_mut(&mut self) -> impl Iterator<Item = ((usize, usize), &mut T)> {
+        self.rows.iter_mut().flat_map(|row| {
+            let r = row.row;
+            row.iter_mut().map(move |(c, cell)| ((r, c), cell))
+        })
+    }
+
+    /// Iterates over the cells that have been written inside a rectangle, row by row, with
+    /// their positions.
+    ///
+    /// # Arguments
+    /// * `start` - The `(row, column)` of the top-left corner.
+    /// * `end` - The `(row, column)` of the bottom-right corner. Parts of the rectangle
+    ///   outside the grid are ignored.
+    pub fn cells_in(
+        &self,
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> impl Iterator<Item = ((usize, usize), &T)> {
+        let rows = self
+            .rows
+            .get(start.0..=end.0.min(self.rows.len().saturating_sub(1)));
+        rows.into_iter().flatten().flat_map(move |row| {
+            row.cells
+                .range(start.1..=end.1)
+                .map(move |(c, cell)| ((row.row, *c), cell))
+        })
+    }
+
+    /// Inserts an empty row before `index`, moving the rows after it down.
+    pub fn insert_row(&mut self, index: usize) {
+        self.rows.insert(index, GridRow::new(index, self.columns));
+        self.renumber_rows(index + 1);
+    }
+
+    /// Removes the row at `index`, moving the rows after it up.
+    ///
+    /// # Returns
+    /// The cells of the removed row that had been written.
+    pub fn remove_row(&mut self, index: usize) -> Vec<T> {
+        let removed = self.rows.remove(index);
+        self.renumber_rows(index);
+        removed.cells.into_values().collect()
+    }
+
+    /// Inserts an empty column before `index`, moving the columns after it right.
+    pub fn insert_column(&mut self, index: usize) {
+        self.columns += 1;
+        for row in self.rows.iter_mut() {
+            row.columns += 1;
+            let moved = row.cells.split_off(&index);
+            row.cells
+                .extend(moved.into_iter().map(|(c, cell)| (c + 1, cell)));
+        }
+    }
+
+    /// Removes the column at `index`, moving the columns after it left.
+    ///
+    /// # Returns
+    /// The cells of the removed column that had been written, top to bottom.
+    pub fn remove_column(&mut self, index: usize) -> Vec<T> {
+        self.columns -= 1;
+        let mut removed = Vec::new();
+        for row in self.rows.iter_mut() {
+            row.columns -= 1;
+            let moved = row.cells.split_off(&index);
+            for (c, cell) in moved {
+                if c == index {
+                    removed.push(cell);
+                } else {
+                    row.cells.insert(c - 1, cell);
+                }
+            }
+        }
+        removed
+    }
+
+    /// Adds or removes rows and columns at the bottom and right of the grid.
+    ///
+    /// # Returns
+    /// The written cells that no longer fit, row by row.
+    pub fn resize(&mut self, rows: usize, columns: usize) -> Vec<T> {
+        let mut removed = Vec::new();
+        if rows < self.rows.len() {
+            for row in self.rows.drain(rows..) {
+                removed.extend(row.cells.into_values());
+            }
+        }
+        for row in self.rows.iter_mut() {
+            removed.extend(row.cells.split_off(&columns).into_values());
+            row.columns = columns;
+        }
+        for row in self.rows.len()..rows {
+            self.rows.push(GridRow::new(row, columns));
+        }
+        self.columns = columns;
+        removed
+    }
+
+    /// Updates the stored row numbers from `start` onwards after rows have moved.
+    fn renumber_rows(&mut self, start: usize) {
+        for (row, grid_row) in self.rows.iter_mut().enumerate().skip(start) {
+            grid_row.row = row;
+        }
+    }
+}
+
+impl<T> GridRow<T> {
+    fn new(row: usize, columns: usize) -> Self {
+        GridRow {
+            row,
+            columns,
+            cells: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the number of columns in the row.
+    pub fn len(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns `true` if the row has no columns.
+    pub fn is_empty(&self) -> bool {
+        self.columns == 0
+    }
+
+    /// Iterates over the cells of the row that have been written, with their columns.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.cells.iter().map(|(c, cell)| (*c, cell))
+    }
+
+    /// Iterates mutably over the cells of the row that have been written, with their
+    /// columns.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.cells.iter_mut().map(|(c, cell)| (*c, cell))
+    }
+}
+
+impl<T: GridCell> Index<usize> for CellGrid<T> {
+    type Output = GridRow<T>;
+
+    fn index(&self, row: usize) -> &GridRow<T> {
+        &self.rows[row]
+    }
+}
+
+impl<T: GridCell> IndexMut<usize> for CellGrid<T> {
+    fn index_mut(&mut self, row: usize) -> &mut GridRow<T> {
+        &mut self.rows[row]
+    }
+}
+
+impl<T: GridCell> Index<usize> for GridRow<T> {
+    type Output = T;
+
+    fn index(&self, column: usize) -> &T {
+        assert!(
+            column < self.columns,
+            "column {} is out of bounds for a row of {} columns",
+            column,
+            self.columns
+        );
+        self.cells.get(&column).unwrap_or_else(|| T::empty_ref())
+    }
+}
+
+impl<T: GridCell> IndexMut<usize> for GridRow<T> {
+    fn index_mut(&mut self, column: usize) -> &mut T {
+        assert!(
+            column < self.columns,
+            "column {} is out of bounds for a row of {} columns",
+            column,
+            self.columns
+        );
+        let row = self.row;
+        self.cells
+            .entry(column)
+            .or_insert_with(|| T::empty(row as i32, column as i32))
+    }
+}
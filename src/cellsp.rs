@@ -1,6 +1,7 @@
 use crate::display::get_column_name;
 use ::std::fmt;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 
 /// Represents an operand in a formula.
 ///
@@ -15,19 +16,63 @@ pub enum Operand {
 
 /// Represents a reference to a specific cell in the spreadsheet.
 ///
-/// This struct is used to identify a cell by its row and column indices.
-#[derive(Eq, Hash, PartialEq, Clone, Debug)]
+/// This struct is used to identify a cell by its row and column indices. The row and column
+/// can each be anchored with `$` (e.g. `$A1`, `A$1`, `$A$1`), which keeps them fixed when a
+/// formula is copied or moved. Anchoring does not change which cell is referenced, so two
+/// references to the same cell compare and hash as equal.
+#[derive(Clone, Debug)]
 pub struct CellReference {
     /// The row index of the cell (0-based).
     pub row: i32,
     /// The column index of the cell (0-based).
     pub column: i32,
+    /// Whether the row is anchored (`A$1`).
+    pub row_absolute: bool,
+    /// Whether the column is anchored (`$A1`).
+    pub column_absolute: bool,
+}
+
+impl CellReference {
+    /// Creates a relative reference to the cell at `row`, `column` (0-based).
+    pub fn new(row: i32, column: i32) -> Self {
+        CellReference {
+            row,
+            column,
+            row_absolute: false,
+            column_absolute: false,
+        }
+    }
+}
+
+impl PartialEq for CellReference {
+    fn eq(&self, other: &Self) -> bool {
+        self.row == other.row && self.column == other.column
+    }
+}
+
+impl Eq for CellReference {}
+
+impl Hash for CellReference {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.row.hash(state);
+        self.column.hash(state);
+    }
 }
 
 impl fmt::Display for CellReference {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let col_name = get_column_name(self.column); // Convert column index to name (e.g., 0 -> A)
-        write!(f, "{}{}", col_name, self.row + 1) // Convert row index to 1-based and format as A1, B2, etc.
+        let col_anchor = if self.column_absolute { "$" } else { "" };
+        let row_anchor = if self.row_absolute { "$" } else { "" };
+        // Convert row index to 1-based and format as A1, $B2, C$3, etc.
+        write!(
+            f,
+            "{}{}{}{}",
+            col_anchor,
+            col_name,
+            row_anchor,
+            self.row + 1
+        )
     }
 }
 /// Represents a cell in the spreadsheet.
@@ -1,4 +1,11 @@
+use crate::cell_grid::{CellGrid, GridCell};
+use lazy_static::lazy_static;
 use std::collections::HashSet;
+use std::fmt;
+
+lazy_static! {
+    static ref EMPTY_CELL: Cell = Cell::empty(0, 0);
+}
 
 /// Represents an operand in a formula, which can either be a constant value or a reference to a cell.
 #[derive(Clone, Debug)]
@@ -21,6 +28,21 @@ pub struct CellReference {
     pub column: i32,
 }
 
+impl fmt::Display for CellReference {
+    /// Formats the reference as a cell name such as `B3`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut column = self.column + 1;
+        let mut name = Vec::new();
+        while column > 0 {
+            column -= 1;
+            name.push(b'A' + (column % 26) as u8);
+            column /= 26;
+        }
+        name.reverse();
+        write!(f, "{}{}", String::from_utf8_lossy(&name), self.row + 1)
+    }
+}
+
 /// Represents a single cell in the spreadsheet.
 ///
 /// # Fields
@@ -46,17 +68,38 @@ pub struct Cell {
     pub precedents: HashSet<CellReference>,
 }
 
+impl GridCell for Cell {
+    fn empty(r: i32, c: i32) -> Self {
+        Cell {
+            value: 0,
+            operation_id: -1,
+            formula: Vec::new(),
+            r,
+            c,
+            is_recalculate: false,
+            is_error: false,
+            dependents: HashSet::new(),
+            precedents: HashSet::new(),
+        }
+    }
+
+    fn empty_ref() -> &'static Self {
+        &EMPTY_CELL
+    }
+}
+
 /// Represents the entire spreadsheet.
 ///
 /// # Fields
 ///
 /// * `rows` - The number of rows in the spreadsheet.
 /// * `columns` - The number of columns in the spreadsheet.
-/// * `all_cells` - A 2D vector containing all the cells in the spreadsheet.
+/// * `all_cells` - All the cells in the spreadsheet, indexed as `all_cells[row][column]`.
+///   Only cells that have been written take memory.
 pub struct Spreadsheet {
     pub rows: i32,
     pub columns: i32,
-    pub all_cells: Vec<Vec<Cell>>,
+    pub all_cells: CellGrid<Cell>,
 }
 
 /// Alias for the `Spreadsheet` type.
@@ -0,0 +1,218 @@
+use crate::cell_extension::{CellValue, SpreadsheetExtension};
+use crate::cellsp::CellReference;
+use crate::expression_parser::Expr;
+use crate::expression_utils::shift_formula;
+use crate::graph_extension::{UndoRedoStack, assign_cell_extension, record_cell_state};
+use crate::sheet_error::SheetError;
+use std::collections::HashMap;
+
+/// The register used when no register is named, like vim's `""`.
+pub const DEFAULT_REGISTER: char = '"';
+
+/// Chooses which parts of the yanked cells are pasted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasteMode {
+    /// Paste formulas and formatting.
+    All,
+    /// Paste the evaluated values as constants.
+    Values,
+    /// Paste formulas, leaving the destination formatting alone.
+    Formulas,
+    /// Paste bold and italics only, leaving the destination contents alone.
+    Formats,
+}
+
+/// A cell as it was when it was yanked.
+#[derive(Clone, Debug)]
+struct YankedCell {
+    formula: Expr,
+    value: CellValue,
+    is_bold: bool,
+    is_italics: bool,
+}
+
+/// A block of cells held in a register.
+#[derive(Clone, Debug)]
+pub struct Yank {
+    /// The `(row, column)` of the top-left cell the block was yanked from.
+    origin: (i32, i32),
+    rows: i32,
+    columns: i32,
+    /// The cells, row by row.
+    cells: Vec<YankedCell>,
+}
+
+impl Yank {
+    /// Copies a block of cells out of the sheet.
+    ///
+    /// # Arguments
+    /// * `sheet` - A reference to the spreadsheet.
+    /// * `start` - The `(row, column)` of the top-left cell.
+    /// * `end` - The `(row, column)` of the bottom-right cell.
+    ///
+    /// # Returns
+    /// The yanked block. Both corners must lie inside the sheet.
+    pub fn from_sheet(sheet: &SpreadsheetExtension, start: (i32, i32), end: (i32, i32)) -> Self {
+        let mut cells = Vec::new();
+        for r in start.0..=end.0 {
+            for c in start.1..=end.1 {
+                let cell = &sheet.all_cells[r as usize][c as usize];
+                cells.push(YankedCell {
+                    formula: cell.formula.clone(),
+                    value: cell.value.clone(),
+                    is_bold: cell.is_bold,
+                    is_italics: cell.is_italics,
+                });
+            }
+        }
+        Yank {
+            origin: start,
+            rows: end.0 - start.0 + 1,
+            columns: end.1 - start.1 + 1,
+            cells,
+        }
+    }
+
+    /// Returns the number of rows and columns in the block.
+    pub fn size(&self) -> (i32, i32) {
+        (self.rows, self.columns)
+    }
+
+    /// Pastes the block with its top-left cell at `anchor`.
+    ///
+    /// # Arguments
+    /// * `sheet` - A mutable reference to the spreadsheet.
+    /// * `undo_manager` - A mutable reference to the undo manager.
+    /// * `anchor` - The `(row, column)` of the top-left destination cell.
+    /// * `mode` - Which parts of the cells to paste.
+    ///
+    /// # Returns
+    /// * `Ok(())` if every cell was pasted.
+    /// * `Err(SheetError)` if the block does not fit in the sheet or a pasted formula creates
+    ///   a cycle.
+    ///
+    /// # Behavior
+    /// Formulas are pasted with their relative references moved by the distance from where
+    /// they were yanked (see `shift_formula`); references that move off the sheet become
+    /// `#REF!`. The whole paste is undone as a single step.
+    pub fn paste(
+        &self,
+        sheet: &mut SpreadsheetExtension,
+        undo_manager: &mut UndoRedoStack,
+        anchor: (i32, i32),
+        mode: PasteMode,
+    ) -> Result<(), SheetError> {
+        let last_row = anchor.0 + self.rows - 1;
+        let last_col = anchor.1 + self.columns - 1;
+        if last_row >= sheet.rows || last_col >= sheet.columns {
+            return Err(SheetError::OutOfBounds(
+                CellReference::new(last_row, last_col).to_string(),
+            ));
+        }
+
+        let row_offset = anchor.0 - self.origin.0;
+        let col_offset = anchor.1 - self.origin.1;
+
+        undo_manager.transaction(|undo_manager| {
+            for (index, cell) in self.cells.iter().enumerate() {
+                let row = anchor.0 + index as i32 / self.columns;
+                let col = anchor.1 + index as i32 % self.columns;
+
+                let formula = match (mode, &cell.formula) {
+                    (PasteMode::Formats, _) => None,
+                    (PasteMode::Values, _) => Some(cell.value.to_expr()),
+                    (_, formula) => Some(shift_formula(formula, row_offset, col_offset, sheet)),
+                };
+                match formula {
+                    Some(formula) => {
+                        assign_cell_extension(sheet, undo_manager, row, col, formula)?;
+                    }
+                    None => record_cell_state(sheet, undo_manager, row, col),
+                }
+
+                if matches!(mode, PasteMode::All | PasteMode::Formats) {
+                    let target = &mut sheet.all_cells[row as usize][col as usize];
+                    target.is_bold = cell.is_bold;
+                    target.is_italics = cell.is_italics;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The named registers that yanked cells are kept in for the session.
+#[derive(Clone, Debug, Default)]
+pub struct Registers {
+    registers: HashMap<char, Yank>,
+}
+
+impl Registers {
+    /// Creates an empty set of registers.
+    pub fn new() -> Self {
+        Registers::default()
+    }
+
+    /// Stores a yank in a register.
+    ///
+    /// # Arguments
+    /// * `name` - The register name: `DEFAULT_REGISTER` or a letter.
+    /// * `yank` - The cells to store.
+    ///
+    /// # Behavior
+    /// As in vim, a yank into a named register is also stored in the default register.
+    /// Upper- and lower-case letters name the same register.
+    pub fn store(&mut self, name: char, yank: Yank) {
+        let name = name.to_ascii_lowercase();
+        if name != DEFAULT_REGISTER {
+            self.registers.insert(DEFAULT_REGISTER, yank.clone());
+        }
+        self.registers.insert(name, yank);
+    }
+
+    /// Returns the contents of a register, if anything has been yanked into it.
+    pub fn get(&self, name: char) -> Option<&Yank> {
+        self.registers.get(&name.to_ascii_lowercase())
+    }
+}
+
+/// Parses a register argument such as `"a`.
+///
+/// # Arguments
+/// * `arg` - The argument, a `"` followed by a letter, or `""` for the default register.
+///
+/// # Returns
+/// * `Ok(name)` - The register name.
+/// * `Err(SheetError::InvalidCommand)` - If the argument does not name a register.
+pub fn parse_register(arg: &str) -> Result<char, SheetError> {
+    let invalid = || SheetError::InvalidCommand(format!("invalid register {}", arg));
+    let mut chars = arg.chars();
+    if chars.next() != Some('"') {
+        return Err(invalid());
+    }
+    match (chars.next(), chars.next()) {
+        (Some(name), None) if name.is_ascii_alphabetic() || name == DEFAULT_REGISTER => Ok(name),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses the paste mode argument of `p`.
+///
+/// # Arguments
+/// * `arg` - One of `all`, `values`, `formulas` or `formats`.
+///
+/// # Returns
+/// * `Ok(PasteMode)` - The matching mode.
+/// * `Err(SheetError::InvalidCommand)` - If the argument is not a paste mode.
+pub fn parse_paste_mode(arg: &str) -> Result<PasteMode, SheetError> {
+    match arg {
+        "all" => Ok(PasteMode::All),
+        "values" => Ok(PasteMode::Values),
+        "formulas" => Ok(PasteMode::Formulas),
+        "formats" => Ok(PasteMode::Formats),
+        _ => Err(SheetError::InvalidCommand(format!(
+            "invalid paste mode {}, expected one of: all, values, formulas, formats",
+            arg
+        ))),
+    }
+}
@@ -0,0 +1,258 @@
+use crate::cell_extension::{CellValue, DefinedName, SpreadsheetExtension};
+use crate::cellsp::CellReference;
+use crate::display::expr_to_string;
+use crate::expression_parser::Expr;
+use crate::expression_utils::{eval_expr, parse_formula};
+use crate::graph_extension::{
+    UndoEntry, UndoRedoStack, dependents_of_extension, rebuild_dependencies_extension,
+    recalculate_all_extension,
+};
+use crate::recalc_order::cycle_through;
+use crate::sheet_error::{CommandResult, Outcome, SheetError};
+
+/// Returns `true` for a formula made only of literals and arithmetic, such as `0.2` or `-1/12`.
+fn is_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(_) | Expr::Text(_) | Expr::Bool(_) => true,
+        Expr::UnaryOp(_, operand) => is_constant(operand),
+        Expr::BinaryOp(left, _, right) => is_constant(left) && is_constant(right),
+        _ => false,
+    }
+}
+
+/// Checks that a local reference lies inside the sheet.
+fn check_in_sheet(target: &Expr, sheet: &SpreadsheetExtension) -> Result<(), SheetError> {
+    let ends = match target {
+        Expr::Cell(cell_ref) => vec![cell_ref],
+        Expr::Range(start, end) => vec![start, end],
+        _ => return Ok(()),
+    };
+    let inside = ends.iter().all(|cell_ref| {
+        (0..sheet.rows).contains(&cell_ref.row) && (0..sheet.columns).contains(&cell_ref.column)
+    });
+    if inside {
+        Ok(())
+    } else {
+        Err(SheetError::OutOfBounds(expr_to_string(target)))
+    }
+}
+
+/// Returns `true` if a formula uses the name stored under `key`.
+fn uses_name(expr: &Expr, key: &str) -> bool {
+    match expr {
+        Expr::Name(name) => name == key,
+        Expr::UnaryOp(_, operand) => uses_name(operand, key),
+        Expr::BinaryOp(left, _, right) | Expr::Compare(left, _, right) => {
+            uses_name(left, key) || uses_name(right, key)
+        }
+        Expr::Function(_, args) => args.iter().any(|arg| uses_name(arg, key)),
+        _ => false,
+    }
+}
+
+/// Finds a loop of formulas that pointing the name stored under `key` at `target` would
+/// close, running through a formula that uses the name.
+///
+/// # Returns
+/// The cells forming the loop, as `find_cycle_extension` returns them, or `None` if there
+/// is no loop.
+fn cycle_through_name(
+    sheet: &SpreadsheetExtension,
+    key: &str,
+    target: &Expr,
+) -> Option<Vec<CellReference>> {
+    let (start, end) = match target {
+        Expr::Cell(cell_ref) => (cell_ref, cell_ref),
+        Expr::Range(start, end) => (start, end),
+        // References into other sheets are checked by the workbook; constants refer to nothing
+        _ => return None,
+    };
+    let rows = start.row.min(end.row)..=start.row.max(end.row);
+    let columns = start.column.min(end.column)..=start.column.max(end.column);
+    let users: Vec<(i32, i32)> = sheet
+        .all_cells
+        .cells()
+        .filter(|(_, cell)| uses_name(&cell.formula, key))
+        .map(|((r, c), _)| (r as i32, c as i32))
+        .collect();
+
+    // With the name defined, every formula using it also depends on the target cells
+    let cycle = users.iter().find_map(|&user| {
+        cycle_through(user, |(r, c)| {
+            let mut next: Vec<(i32, i32)> = dependents_of_extension(sheet, r, c)
+                .into_iter()
+                .map(|dependent| (dependent.row, dependent.column))
+                .collect();
+            if rows.contains(&r) && columns.contains(&c) {
+                next.extend(users.iter().copied());
+            }
+            next
+        })
+    })?;
+    Some(
+        cycle
+            .into_iter()
+            .map(|(r, c)| CellReference::new(r, c))
+            .collect(),
+    )
+}
+
+/// Rebuilds the dependencies and values of every formula after a name changes.
+fn refresh(sheet: &mut SpreadsheetExtension) {
+    rebuild_dependencies_extension(sheet);
+    recalculate_all_extension(sheet);
+}
+
+/// Defines a name for a cell, a range or a constant, or changes what it stands for.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+/// * `name` - The name, e.g. `revenue` or `tax_rate`. It may hold letters, and `_` followed
+///   by letters or digits, and must not read as a cell, e.g. `AB12`, or as `TRUE`/`FALSE`.
+/// * `target` - A cell such as `C1`, a range such as `B2:B120`, a reference into another
+///   sheet such as `Sheet2!A1`, or a constant such as `0.2`.
+///
+/// # Returns
+/// * `Ok(Outcome::Done)` if the name was defined.
+/// * `Err(SheetError::CyclicDependency)` with the cells forming the loop if a formula using
+///   the name would then refer to itself and iterative calculation is off; the sheet is
+///   left unchanged.
+/// * `Err(SheetError)` if the name or target is not allowed, or the target lies outside
+///   the sheet.
+///
+/// # Behavior
+/// Names are not case-sensitive. Formulas using the name, including those written before
+/// it was defined, are recalculated. Constants are evaluated once, when they are defined.
+/// The change is recorded as an undo step.
+pub fn define_name(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    name: &str,
+    target: &str,
+) -> CommandResult {
+    let key = name.to_ascii_uppercase();
+    if !matches!(parse_formula(name).map(|e| *e), Ok(Expr::Name(parsed)) if parsed == key) {
+        return Err(SheetError::InvalidCommand(format!(
+            "{} cannot be used as a name; use letters, and _ followed by letters or digits",
+            name
+        )));
+    }
+
+    let target = *parse_formula(target)?;
+    let target = match target {
+        Expr::Cell(_) | Expr::Range(_, _) | Expr::SheetRef(_, _) => {
+            check_in_sheet(&target, sheet)?;
+            target
+        }
+        _ if is_constant(&target) => match eval_expr(&target, sheet) {
+            CellValue::Error(error) => {
+                return Err(SheetError::InvalidCommand(format!(
+                    "the constant evaluates to {}",
+                    error
+                )));
+            }
+            value => value.to_expr(),
+        },
+        _ => {
+            return Err(SheetError::InvalidCommand(
+                "a name must stand for a cell, a range or a constant".into(),
+            ));
+        }
+    };
+
+    // Under iterative calculation loops are allowed and evaluated until they settle
+    if sheet.iteration.is_none()
+        && let Some(cycle) = cycle_through_name(sheet, &key, &target)
+    {
+        return Err(SheetError::CyclicDependency(cycle));
+    }
+
+    let previous = sheet.names.insert(
+        key,
+        DefinedName {
+            name: name.to_string(),
+            target,
+        },
+    );
+    undo_manager.push_entry(UndoEntry::Name {
+        name: name.to_string(),
+        previous,
+    });
+    refresh(sheet);
+    Ok(Outcome::Done)
+}
+
+/// Removes a defined name. Formulas that still use it evaluate to `#NAME?`. The change is
+/// recorded as an undo step.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+/// * `name` - The name, in any case.
+///
+/// # Returns
+/// * `Ok(Outcome::Done)` if the name was removed.
+/// * `Err(SheetError::UnknownName)` if no such name is defined.
+pub fn remove_name(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    name: &str,
+) -> CommandResult {
+    let Some(previous) = sheet.names.remove(&name.to_ascii_uppercase()) else {
+        return Err(SheetError::UnknownName(name.to_string()));
+    };
+    undo_manager.push_entry(UndoEntry::Name {
+        name: previous.name.clone(),
+        previous: Some(previous),
+    });
+    refresh(sheet);
+    Ok(Outcome::Done)
+}
+
+/// Lists the defined names with what they stand for, e.g. `revenue = B2:B120`, in
+/// alphabetical order.
+pub fn list_names(sheet: &SpreadsheetExtension) -> String {
+    if sheet.names.is_empty() {
+        return "No names are defined".to_string();
+    }
+    sheet
+        .names
+        .values()
+        .map(|defined| format!("{} = {}", defined.name, expr_to_string(&defined.target)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Handles the `name` and `names` commands.
+///
+/// # Arguments
+/// * `cmd` - `name <name> <target>` to define a name (see `define_name`), `name <name>` to
+///   remove it, or `names` to list every name.
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+///
+/// # Returns
+/// * `Ok(Outcome::Message)` listing the names for `names`, `Ok(Outcome::Done)` otherwise.
+/// * `Err(SheetError)` if the command is malformed or the name cannot be defined or removed.
+pub fn handle_name_command(
+    cmd: &str,
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+) -> CommandResult {
+    let cmd = cmd.trim();
+    if cmd == "names" {
+        return Ok(Outcome::Message(list_names(sheet)));
+    }
+    let args = cmd
+        .strip_prefix("name ")
+        .map(str::trim)
+        .filter(|args| !args.is_empty())
+        .ok_or_else(|| {
+            SheetError::InvalidCommand("expected: name <name> [<cell, range or constant>]".into())
+        })?;
+    match args.split_once(char::is_whitespace) {
+        Some((name, target)) => define_name(sheet, undo_manager, name, target.trim()),
+        None => remove_name(sheet, undo_manager, args),
+    }
+}
@@ -1,6 +1,8 @@
+use crate::cell_grid::CellGrid;
 use crate::cellsp2::{Cell, CellReference, Operand, Spreadsheet};
+use crate::recalc_order::{cycle_through, recalculation_order};
 use lazy_static::lazy_static;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::mpsc::{Receiver, Sender, channel};
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -14,10 +16,17 @@ lazy_static! {
         let (tx, rx) = channel::<CellCoord>();
         (Arc::new(Mutex::new(tx)), Arc::new(Mutex::new(rx)))
     };
+    /// The cells of the loop found by the last assignment that set `STATUS` to 3, each
+    /// referring to the next.
+    pub static ref LAST_CYCLE: Mutex<Vec<CellReference>> = Mutex::new(Vec::new());
 }
 
 pub static mut STATUS: i32 = 0;
 
+/// The operation ID of a cell whose formula referred to a deleted row or column.
+/// The cell stays an error until it is assigned again.
+pub const REF_ERROR_OPERATION: i32 = 13;
+
 /// Initializes a spreadsheet with the given number of rows and columns.
 ///
 /// # Arguments
@@ -27,33 +36,13 @@ pub static mut STATUS: i32 = 0;
 ///
 /// # Returns
 ///
-/// A `Spreadsheet` instance with all cells initialized.
+/// A `Spreadsheet` instance whose cells are all empty. Empty cells take no memory until
+/// they are written.
 pub fn initialise(rows: i32, columns: i32) -> Spreadsheet {
-    let mut all_cells = Vec::with_capacity(rows as usize);
-    for r in 0..rows {
-        let mut row: Vec<Cell> = Vec::with_capacity(columns as usize);
-        for c in 0..columns {
-            let curr_cell = Cell {
-                value: 0,
-                operation_id: -1,
-                formula: Vec::new(),
-                r,
-                c,
-                is_recalculate: false,
-                is_error: false,
-                dependents: HashSet::new(),
-                precedents: HashSet::new(),
-            };
-
-            row.push(curr_cell);
-        }
-        all_cells.push(row);
-    }
-
     Spreadsheet {
         rows,
         columns,
-        all_cells,
+        all_cells: CellGrid::new(rows, columns),
     }
 }
 
@@ -478,6 +467,12 @@ pub fn calculate_cell_value(sheet: &mut Spreadsheet, rt: i32, ct: i32) {
                 }
             }
         }
+        REF_ERROR_OPERATION => {
+            sheet.all_cells[rt as usize][ct as usize].is_error = true;
+            unsafe {
+                STATUS = 2;
+            }
+        }
         _ => {
             panic!("Unknown operation");
         }
@@ -649,55 +644,32 @@ pub fn precedent_has_error(sheet: &Spreadsheet, rt: i32, ct: i32) -> bool {
     false
 }
 
-/// Performs a depth-first search to detect cycles in the dependency graph.
+/// Finds the loop of formulas running through a cell, if there is one.
 ///
 /// # Arguments
 ///
 /// * `sheet` - A reference to the spreadsheet.
-/// * `rs` - Row index of the starting cell.
-/// * `cs` - Column index of the starting cell.
-/// * `visited` - A mutable reference to the set of visited cells.
-/// * `recursion_stack` - A mutable reference to the recursion stack.
+/// * `row` - Row index of the target cell.
+/// * `col` - Column index of the target cell.
 ///
 /// # Returns
 ///
-/// `true` if a cycle is detected, otherwise `false`.
-pub fn dfs_cycle_detection(
-    sheet: &Spreadsheet,
-    rs: i32,
-    cs: i32,
-    visited: &mut HashSet<(i32, i32)>,
-    recursion_stack: &mut HashSet<(i32, i32)>,
-) -> bool {
-    let cell_coord = (rs, cs);
-    if recursion_stack.contains(&cell_coord) {
-        return true;
-    }
-
-    if visited.contains(&cell_coord) {
-        return false;
-    }
-
-    visited.insert(cell_coord);
-    recursion_stack.insert(cell_coord);
-
-    let curr_cell = &sheet.all_cells[rs as usize][cs as usize];
-
-    for dependent in &curr_cell.dependents {
-        if dfs_cycle_detection(
-            sheet,
-            dependent.row,
-            dependent.column,
-            visited,
-            recursion_stack,
-        ) {
-            return true;
-        }
-    }
-
-    recursion_stack.remove(&cell_coord);
-
-    false
+/// The cells forming the loop, starting and ending with the target cell, each referring to
+/// the next (see `cycle_through`), or `None` if the cell is not on a loop.
+pub fn find_cycle(sheet: &Spreadsheet, row: i32, col: i32) -> Option<Vec<CellReference>> {
+    let cycle = cycle_through((row, col), |(r, c)| {
+        sheet.all_cells[r as usize][c as usize]
+            .dependents
+            .iter()
+            .map(|dependent| (dependent.row, dependent.column))
+            .collect::<Vec<_>>()
+    })?;
+    Some(
+        cycle
+            .into_iter()
+            .map(|(row, column)| CellReference { row, column })
+            .collect(),
+    )
 }
 
 /// Checks if a cycle exists in the dependency graph for a specific cell.
@@ -710,11 +682,9 @@ pub fn dfs_cycle_detection(
 ///
 /// # Returns
 ///
-/// `true` if a cycle exists, otherwise `false`.
+/// `true` if the cell is on a cycle, otherwise `false`.
 pub fn has_cycle(sheet: &Spreadsheet, row: i32, col: i32) -> bool {
-    let mut visited: HashSet<(i32, i32)> = HashSet::new();
-    let mut recursion_stack: HashSet<(i32, i32)> = HashSet::new();
-    dfs_cycle_detection(sheet, row, col, &mut visited, &mut recursion_stack)
+    find_cycle(sheet, row, col).is_some()
 }
 
 /// Recalculates the values of all dependents of a specific cell.
@@ -724,16 +694,19 @@ pub fn has_cycle(sheet: &Spreadsheet, row: i32, col: i32) -> bool {
 /// * `sheet` - A mutable reference to the spreadsheet.
 /// * `rs` - Row index of the starting cell.
 /// * `cs` - Column index of the starting cell.
+///
+/// Dependents are evaluated in topological order (see `recalculation_order`), so each is
+/// evaluated once, after its precedents.
 pub fn recalculate_dependents(sheet: &mut Spreadsheet, rs: i32, cs: i32) {
-    let mut q: VecDeque<(i32, i32)> = VecDeque::new();
-    {
-        let cell = &sheet.all_cells[rs as usize][cs as usize];
-        for dependents in &cell.dependents {
-            q.push_back((dependents.row, dependents.column));
-        }
-    }
+    let order = recalculation_order((rs, cs), |(r, c)| {
+        sheet.all_cells[r as usize][c as usize]
+            .dependents
+            .iter()
+            .map(|dependent| (dependent.row, dependent.column))
+            .collect::<Vec<_>>()
+    });
 
-    while let Some((curr_r, curr_c)) = q.pop_front() {
+    for (curr_r, curr_c) in order {
         if zero_div_err(sheet, curr_r, curr_c) || precedent_has_error(sheet, curr_r, curr_c) {
             sheet.all_cells[curr_r as usize][curr_c as usize].is_error = true;
             unsafe {
@@ -742,11 +715,6 @@ pub fn recalculate_dependents(sheet: &mut Spreadsheet, rs: i32, cs: i32) {
         } else {
             calculate_cell_value(sheet, curr_r, curr_c);
         }
-
-        let curr_cell = &sheet.all_cells[curr_r as usize][curr_c as usize];
-        for dep_ref in &curr_cell.dependents {
-            q.push_back((dep_ref.row, dep_ref.column));
-        }
     }
 }
 
@@ -788,32 +756,282 @@ pub fn assign_cell(
         }
     }
 
-    if !has_cycle(sheet, rt, ct) {
-        if !zero_div_err(sheet, rt, ct) {
-            calculate_cell_value(sheet, rt, ct);
-            recalculate_dependents(sheet, rt, ct);
+    match find_cycle(sheet, rt, ct) {
+        None => {
+            if !zero_div_err(sheet, rt, ct) {
+                calculate_cell_value(sheet, rt, ct);
+                recalculate_dependents(sheet, rt, ct);
+                unsafe {
+                    STATUS = 0;
+                }
+            } else {
+                sheet.all_cells[rt as usize][ct as usize].is_error = true;
+                unsafe { STATUS = 2 }
+                recalculate_dependents(sheet, rt, ct);
+            }
+        }
+        Some(cycle) => {
             unsafe {
-                STATUS = 0;
+                STATUS = 3;
+            }
+            *LAST_CYCLE.lock().unwrap() = cycle;
+            sheet.all_cells[rt as usize][ct as usize].operation_id = operation_id;
+            sheet.all_cells[rt as usize][ct as usize].formula = temp_formula.clone();
+
+            for old_precedent in &temp_precedents {
+                add_dependency(sheet, old_precedent.row, old_precedent.column, rt, ct);
+            }
+            for new_precedent in &temp_formula {
+                if let Operand::CellOperand(cell_ref) = new_precedent {
+                    delete_dependency(sheet, cell_ref.row, cell_ref.column, rt, ct);
+                }
             }
-        } else {
-            sheet.all_cells[rt as usize][ct as usize].is_error = true;
-            unsafe { STATUS = 2 }
-            recalculate_dependents(sheet, rt, ct);
         }
-    } else {
-        unsafe {
-            STATUS = 3;
+    }
+}
+
+/// Whether a row or a column is inserted or deleted.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Row,
+    Column,
+}
+
+/// How row or column indices move when a line is inserted or deleted.
+#[derive(Clone, Copy)]
+enum LineEdit {
+    Insert(i32),
+    Delete(i32),
+}
+
+impl LineEdit {
+    /// Moves a single index, or returns `None` if its line was deleted.
+    fn move_index(self, index: i32) -> Option<i32> {
+        match self {
+            LineEdit::Insert(at) => Some(if index >= at { index + 1 } else { index }),
+            LineEdit::Delete(at) if index == at => None,
+            LineEdit::Delete(at) => Some(if index > at { index - 1 } else { index }),
+        }
+    }
+
+    /// Moves the ends of a span of lines, or returns `None` if every line in it was deleted.
+    fn move_span(self, start: i32, end: i32) -> Option<(i32, i32)> {
+        match self {
+            LineEdit::Insert(_) => Some((self.move_index(start)?, self.move_index(end)?)),
+            LineEdit::Delete(at) if start == at && end == at => None,
+            LineEdit::Delete(at) => Some((
+                if start > at { start - 1 } else { start },
+                if end >= at { end - 1 } else { end },
+            )),
+        }
+    }
+}
+
+/// Rewrites the references in a cell's formula for a line edit.
+///
+/// Range functions (operations 7-11) keep their rectangle, which grows or shrinks with the
+/// edit. Any other formula that refers to a deleted cell becomes `REF_ERROR_OPERATION`.
+fn rewrite_formula(cell: &mut Cell, axis: Axis, edit: LineEdit) {
+    let index_on = |cell_ref: &CellReference| match axis {
+        Axis::Row => cell_ref.row,
+        Axis::Column => cell_ref.column,
+    };
+
+    match cell.operation_id {
+        7..=11 => {
+            // Ranges are stored expanded, row by row, so the corners come first and last
+            let (Some(Operand::CellOperand(first)), Some(Operand::CellOperand(last))) =
+                (cell.formula.first(), cell.formula.last())
+            else {
+                return;
+            };
+            let (mut r1, mut c1, mut r2, mut c2) = (first.row, first.column, last.row, last.column);
+            match edit.move_span(index_on(first), index_on(last)) {
+                Some((start, end)) if axis == Axis::Row => (r1, r2) = (start, end),
+                Some((start, end)) => (c1, c2) = (start, end),
+                None => {
+                    cell.operation_id = REF_ERROR_OPERATION;
+                    cell.formula = Vec::new();
+                    return;
+                }
+            }
+            cell.formula = (r1..=r2)
+                .flat_map(|row| (c1..=c2).map(move |column| (row, column)))
+                .map(|(row, column)| Operand::CellOperand(CellReference { row, column }))
+                .collect();
         }
-        sheet.all_cells[rt as usize][ct as usize].operation_id = operation_id;
-        sheet.all_cells[rt as usize][ct as usize].formula = temp_formula.clone();
+        2..=6 | 12 => {
+            for operand in cell.formula.iter_mut() {
+                if let Operand::CellOperand(cell_ref) = operand {
+                    match (edit.move_index(index_on(cell_ref)), axis) {
+                        (Some(index), Axis::Row) => cell_ref.row = index,
+                        (Some(index), Axis::Column) => cell_ref.column = index,
+                        (None, _) => {
+                            cell.operation_id = REF_ERROR_OPERATION;
+                            cell.formula = Vec::new();
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
 
-        for old_precedent in &temp_precedents {
-            add_dependency(sheet, old_precedent.row, old_precedent.column, rt, ct);
+/// Rebuilds every cell's dependents and precedents from the formulas.
+///
+/// # Arguments
+///
+/// * `sheet` - A mutable reference to the spreadsheet.
+pub fn rebuild_dependencies(sheet: &mut Spreadsheet) {
+    let mut edges = Vec::new();
+    for ((r, c), cell) in sheet.all_cells.cells_mut() {
+        cell.dependents.clear();
+        cell.precedents.clear();
+        for operand in &cell.formula {
+            if let Operand::CellOperand(cell_ref) = operand {
+                edges.push((cell_ref.clone(), r as i32, c as i32));
+            }
         }
-        for new_precedent in &temp_formula {
-            if let Operand::CellOperand(cell_ref) = new_precedent {
-                delete_dependency(sheet, cell_ref.row, cell_ref.column, rt, ct);
+    }
+    for (precedent, r, c) in edges {
+        add_dependency(sheet, precedent.row, precedent.column, r, c);
+    }
+}
+
+/// Recalculates every formula that refers to other cells, precedents first.
+///
+/// # Arguments
+///
+/// * `sheet` - A mutable reference to the spreadsheet.
+pub fn recalculate_all(sheet: &mut Spreadsheet) {
+    let mut pending: HashMap<(i32, i32), usize> = sheet
+        .all_cells
+        .cells()
+        .map(|((r, c), cell)| ((r as i32, c as i32), cell.precedents.len()))
+        .collect();
+    let mut q: VecDeque<(i32, i32)> = pending
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(position, _)| *position)
+        .collect();
+
+    while let Some((curr_r, curr_c)) = q.pop_front() {
+        let curr_cell = &sheet.all_cells[curr_r as usize][curr_c as usize];
+        // Constants and empty cells are unchanged by moving cells around
+        if curr_cell.operation_id == REF_ERROR_OPERATION || !curr_cell.precedents.is_empty() {
+            if zero_div_err(sheet, curr_r, curr_c) || precedent_has_error(sheet, curr_r, curr_c) {
+                sheet.all_cells[curr_r as usize][curr_c as usize].is_error = true;
+            } else {
+                sheet.all_cells[curr_r as usize][curr_c as usize].is_error = false;
+                calculate_cell_value(sheet, curr_r, curr_c);
+            }
+        }
+
+        let dependents: Vec<CellReference> = sheet.all_cells[curr_r as usize][curr_c as usize]
+            .dependents
+            .iter()
+            .cloned()
+            .collect();
+        for dep_ref in dependents {
+            let count = pending
+                .get_mut(&(dep_ref.row, dep_ref.column))
+                .expect("dependents are stored cells");
+            *count -= 1;
+            if *count == 0 {
+                q.push_back((dep_ref.row, dep_ref.column));
             }
         }
     }
 }
+
+/// Inserts or deletes a line of cells, rewriting formulas and recalculating the sheet.
+fn edit_line(sheet: &mut Spreadsheet, axis: Axis, edit: LineEdit) {
+    let count = match axis {
+        Axis::Row => sheet.rows,
+        Axis::Column => sheet.columns,
+    };
+    let valid = match edit {
+        LineEdit::Insert(index) => (0..=count).contains(&index),
+        LineEdit::Delete(index) => (0..count).contains(&index) && count > 1,
+    };
+    if !valid {
+        unsafe {
+            STATUS = 1;
+        }
+        return;
+    }
+
+    for (_, cell) in sheet.all_cells.cells_mut() {
+        rewrite_formula(cell, axis, edit);
+    }
+    match (edit, axis) {
+        (LineEdit::Insert(index), Axis::Row) => {
+            sheet.all_cells.insert_row(index as usize);
+            sheet.rows += 1;
+        }
+        (LineEdit::Insert(index), Axis::Column) => {
+            sheet.all_cells.insert_column(index as usize);
+            sheet.columns += 1;
+        }
+        (LineEdit::Delete(index), Axis::Row) => {
+            sheet.all_cells.remove_row(index as usize);
+            sheet.rows -= 1;
+        }
+        (LineEdit::Delete(index), Axis::Column) => {
+            sheet.all_cells.remove_column(index as usize);
+            sheet.columns -= 1;
+        }
+    }
+    for ((r, c), cell) in sheet.all_cells.cells_mut() {
+        cell.r = r as i32;
+        cell.c = c as i32;
+    }
+
+    rebuild_dependencies(sheet);
+    recalculate_all(sheet);
+    unsafe {
+        STATUS = 0;
+    }
+}
+
+/// Inserts an empty row before `row`, moving the rows after it down by one.
+///
+/// # Arguments
+///
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `row` - The 0-based index of the new row; `sheet.rows` appends a row.
+///
+/// # Notes
+///
+/// References are rewritten to follow the cells they refer to, and ranges that the new row
+/// falls inside grow to include it. Sets `STATUS` to 1 if `row` is out of bounds.
+pub fn insert_row(sheet: &mut Spreadsheet, row: i32) {
+    edit_line(sheet, Axis::Row, LineEdit::Insert(row));
+}
+
+/// Deletes a row, moving the rows after it up by one.
+///
+/// # Arguments
+///
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `row` - The 0-based index of the row to delete.
+///
+/// # Notes
+///
+/// Ranges lose the deleted row; any other formula that referred to it becomes an error.
+/// Sets `STATUS` to 1 if `row` is out of bounds or is the only row.
+pub fn delete_row(sheet: &mut Spreadsheet, row: i32) {
+    edit_line(sheet, Axis::Row, LineEdit::Delete(row));
+}
+
+/// Inserts an empty column before `col`. See `insert_row`.
+pub fn insert_col(sheet: &mut Spreadsheet, col: i32) {
+    edit_line(sheet, Axis::Column, LineEdit::Insert(col));
+}
+
+/// Deletes a column. See `delete_row`.
+pub fn delete_col(sheet: &mut Spreadsheet, col: i32) {
+    edit_line(sheet, Axis::Column, LineEdit::Delete(col));
+}
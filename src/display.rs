@@ -1,17 +1,23 @@
 use fltk::{
     app,
-    button::{Button, RadioButton},
-    dialog::alert,
+    browser::HoldBrowser,
+    button::{Button, CheckButton, RadioButton},
+    dialog,
     enums::{Align, Color, Font, FrameType},
     frame::Frame,
+    group::{Pack, PackType},
     input::Input,
     prelude::*,
     window::Window,
 };
 
-use crate::cell_extension::SpreadsheetExtension;
-use crate::graph_extension::UndoRedoStack;
+use crate::cell_extension::CellValue;
+use crate::clipboard::Registers;
 use crate::parser_visual_mode::parser_visual;
+use crate::sheet_error::{CommandResult, SheetError, status_text};
+use crate::undo_history::format_history;
+use crate::workbook::Workbook;
+use crate::write_mode::handle_write_command;
 use crate::{expression_parser::Expr, read_mode::handle_read_command};
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -30,14 +36,33 @@ pub fn get_column_name(mut col_index: i32) -> String {
     col_name
 }
 
-fn expr_to_string(expr: &Expr) -> String {
+/// Converts an expression back into formula text that `parse_formula` accepts.
+///
+/// # Arguments
+/// * `expr` - A reference to the expression to convert.
+///
+/// # Returns
+/// The formula as a string, without the leading `=`.
+pub fn expr_to_string(expr: &Expr) -> String {
     match expr {
-        // Handle constant integer values
+        // Handle constant numeric values
         Expr::Number(value) => value.to_string(),
 
+        // Handle text, doubling any quotes inside it
+        Expr::Text(text) => format!("\"{}\"", text.replace('"', "\"\"")),
+
+        // Handle boolean literals
+        Expr::Bool(value) => if *value { "TRUE" } else { "FALSE" }.to_string(),
+
+        // Handle error literals such as `#REF!`
+        Expr::Error(error) => error.to_string(),
+
         // Handle cell references
         Expr::Cell(cell_ref) => format!("{}", cell_ref),
 
+        // Handle unary operations
+        Expr::UnaryOp(op, operand) => format!("{}{}", op, expr_to_string(operand)),
+
         // Handle binary operations
         Expr::BinaryOp(left, op, right) => {
             format!(
@@ -48,6 +73,16 @@ fn expr_to_string(expr: &Expr) -> String {
             )
         }
 
+        // Handle comparisons
+        Expr::Compare(left, op, right) => {
+            format!(
+                "({} {} {})",
+                expr_to_string(left),
+                op.symbol(),
+                expr_to_string(right)
+            )
+        }
+
         // Handle functions
         Expr::Function(name, args) => {
             let args_str = args
@@ -60,6 +95,52 @@ fn expr_to_string(expr: &Expr) -> String {
 
         // Handle ranges
         Expr::Range(start, end) => format!("{}:{}", start, end),
+
+        // Handle defined names
+        Expr::Name(name) => name.clone(),
+
+        // Handle references into other sheets
+        Expr::SheetRef(name, target) => {
+            format!("{}!{}", sheet_name_to_string(name), expr_to_string(target))
+        }
+    }
+}
+
+/// Writes a sheet name as it appears before `!` in a formula.
+///
+/// Names made only of letters, digits and `_`, not starting with a digit, are written as
+/// they are. Any other name is quoted, e.g. `'My Sheet'`.
+pub fn sheet_name_to_string(name: &str) -> String {
+    let plain = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        format!("'{}'", name)
+    }
+}
+
+/// Formats a cell value so it fits in a grid cell.
+///
+/// Whole numbers are shown without a decimal point, other values are rounded to six decimal
+/// places with trailing zeros removed, and very large or very small values use scientific notation.
+///
+/// # Arguments
+/// * `value` - The value to format.
+///
+/// # Returns
+/// The formatted value as a string.
+pub fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else if value.abs() >= 1e15 || value.abs() < 1e-6 {
+        format!("{:e}", value)
+    } else {
+        let fixed = format!("{:.6}", value);
+        fixed
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
     }
 }
 
@@ -68,12 +149,11 @@ const CELL_HEIGHT: i32 = 30;
 
 pub fn launch_gui(
     _data: Arc<Mutex<Vec<Vec<String>>>>,
-    sheet: Arc<Mutex<SpreadsheetExtension>>, // Pass the complete sheet
+    workbook: Arc<Mutex<Workbook>>, // Pass the workbook; the active sheet is shown
     input_text: Arc<Mutex<String>>,
-    status_extension: Arc<Mutex<i32>>,
+    status: Arc<Mutex<CommandResult>>,
     current_row: Arc<Mutex<usize>>,
     current_col: Arc<Mutex<usize>>,
-    undo_manager: Arc<Mutex<UndoRedoStack>>,
 ) {
     thread::spawn(move || {
         let app = app::App::default();
@@ -120,6 +200,17 @@ pub fn launch_gui(
             "←",
         );
 
+        // Status bar showing the result of the last command
+        let mut status_bar = Frame::new(
+            2 * scroll_btn_size + 20,
+            screen_height - scroll_btn_size - 30,
+            screen_width - 5 * scroll_btn_size - 40,
+            scroll_btn_size,
+            "",
+        );
+        status_bar.set_align(Align::Inside | Align::Left);
+        status_bar.set_frame(FrameType::FlatBox);
+
         // Add Undo and Redo buttons
         let mut undo_btn = Button::new(screen_width - 220, 10, 100, 30, "Undo");
         let mut redo_btn = Button::new(screen_width - 110, 10, 100, 30, "Redo");
@@ -140,13 +231,66 @@ pub fn launch_gui(
             app::awake(); // Trigger the main thread to process the input
         });
 
+        // The "History" button lists the undo history of the sheet; picking a state moves
+        // the sheet to it
+        let mut history_btn = Button::new(screen_width - 660, 10, 100, 30, "History");
+
+        let workbook_clone = workbook.clone();
+        let input_text_clone = input_text.clone();
+        history_btn.set_callback(move |_| {
+            let popup = Rc::new(RefCell::new(Window::new(
+                300,
+                150,
+                500,
+                400,
+                "Undo History",
+            )));
+            let mut browser = HoldBrowser::new(10, 10, 480, 340, "");
+            let mut close_btn = Button::new(200, 360, 100, 30, "Close");
+
+            let lines = {
+                let mut workbook = workbook_clone.lock().unwrap();
+                format_history(workbook.active_parts().1)
+            };
+            for line in &lines {
+                browser.add(line);
+                if line.starts_with('*') {
+                    browser.select(browser.size());
+                }
+            }
+
+            // Move through the command thread, as the Undo button does
+            let input_text_inner = input_text_clone.clone();
+            let popup_clone = popup.clone();
+            browser.set_callback(move |b| {
+                let number = b.selected_text().and_then(|line| {
+                    line.trim_start_matches('*')
+                        .split_whitespace()
+                        .next()
+                        .map(str::to_string)
+                });
+                if let Some(number) = number {
+                    *input_text_inner.lock().unwrap() = format!("undo_to {}", number);
+                    app::awake();
+                    popup_clone.borrow_mut().hide();
+                }
+            });
+
+            let popup_clone = popup.clone();
+            close_btn.set_callback(move |_| {
+                popup_clone.borrow_mut().hide();
+            });
+            popup.borrow_mut().end();
+            popup.borrow_mut().show();
+        });
+
         // Add the "Read" button
         // Add the "Read" button
         let mut read_btn = Button::new(screen_width - 440, 10, 100, 30, "Read");
 
         // Read button callback
-        let sheet_clone = sheet.clone();
-        let undo_manager_clone = undo_manager.clone();
+        let workbook_clone = workbook.clone();
+        let status_clone = status.clone();
         // let input_text_clone = input_text.clone();
 
         read_btn.set_callback(move |_| {
@@ -162,8 +306,8 @@ pub fn launch_gui(
             let mut submit_btn = Button::new(150, 120, 100, 30, "Submit");
 
             // Handle the submit button click
-            let sheet_clone_inner = sheet_clone.clone(); // Clone the value for use in the closure
-            let undo_manager_clone_inner = undo_manager_clone.clone(); // Clone the undo manager
+            let workbook_clone_inner = workbook_clone.clone(); // Clone the value for use in the closure
+            let status_clone_inner = status_clone.clone();
             let popup_clone = popup.clone(); // Clone Rc for use in the closure
             submit_btn.set_callback(move |_| {
                 let filename = input.value();
@@ -172,9 +316,11 @@ pub fn launch_gui(
                     let command = format!("read {}", filename);
 
                     // Send the command to the input_text shared variable
-                    let mut sheet = sheet_clone_inner.lock().unwrap();
-                    let mut undo_manager = undo_manager_clone_inner.lock().unwrap();
-                    handle_read_command(&command, &mut sheet, &mut undo_manager);
+                    let mut workbook = workbook_clone_inner.lock().unwrap();
+                    let (sheet, undo_manager) = workbook.active_parts();
+                    *status_clone_inner.lock().unwrap() =
+                        handle_read_command(&command, sheet, undo_manager);
+                    workbook.refresh_links();
 
                     // Trigger the GUI to refresh
                     app::awake(); // Notify the main thread to update the GUI
@@ -185,12 +331,58 @@ pub fn launch_gui(
             popup.borrow_mut().show();
         });
 
+        // Add the "Save" button
+        let mut save_btn = Button::new(screen_width - 550, 10, 100, 30, "Save");
+
+        // Save button callback
+        let workbook_clone = workbook.clone();
+        let status_clone = status.clone();
+
+        save_btn.set_callback(move |_| {
+            // Create a popup window for input
+            let popup = Rc::new(RefCell::new(Window::new(
+                300,
+                200,
+                400,
+                200,
+                "Enter Filename",
+            )));
+            let input = Input::new(50, 50, 300, 30, "Filename:");
+            let save_formulas = CheckButton::new(50, 85, 200, 25, "Save formulas");
+            let mut submit_btn = Button::new(150, 120, 100, 30, "Submit");
+
+            // Handle the submit button click
+            let workbook_clone_inner = workbook_clone.clone(); // Clone the value for use in the closure
+            let status_clone_inner = status_clone.clone();
+            let popup_clone = popup.clone(); // Clone Rc for use in the closure
+            submit_btn.set_callback(move |_| {
+                let filename = input.value();
+                if !filename.is_empty() {
+                    // Build the same command the CLI accepts
+                    let mode = if save_formulas.value() {
+                        "formulas"
+                    } else {
+                        "values"
+                    };
+                    let command = format!("write {} {}", filename, mode);
+
+                    let workbook = workbook_clone_inner.lock().unwrap();
+                    *status_clone_inner.lock().unwrap() =
+                        handle_write_command(&command, workbook.active());
+                    app::awake();
+                }
+                popup_clone.borrow_mut().hide(); // Close the popup after submission
+            });
+            popup.borrow_mut().end();
+            popup.borrow_mut().show();
+        });
+
         // Add the "Plot Graph" button
         let mut plot_btn = Button::new(screen_width - 330, 10, 100, 30, "Plot Graph");
 
         // Plot Graph button callback
-        let sheet_clone = sheet.clone();
-        let undo_manager_clone = undo_manager.clone();
+        let workbook_clone = workbook.clone();
+        let status_clone = status.clone();
         plot_btn.set_callback(move |_| {
             // Create a popup window for input
             let popup = Rc::new(RefCell::new(Window::new(
@@ -216,8 +408,8 @@ pub fn launch_gui(
             forecast.set_value(false);
 
             // Handle the submit button click
-            let sheet_clone = sheet_clone.clone();
-            let undo_manager_clone = undo_manager_clone.clone();
+            let workbook_clone = workbook_clone.clone();
+            let status_clone = status_clone.clone();
             let popup_clone = popup.clone(); // Clone Rc for use in the closure
             submit_btn.set_callback(move |_| {
                 let input_value = input.value();
@@ -239,9 +431,13 @@ pub fn launch_gui(
                     let final_input = format!("{} {}", selected_option, input_value);
 
                     // Call parser_visual with the concatenated input
-                    let mut sheet = sheet_clone.lock().unwrap();
-                    let mut undo_manager = undo_manager_clone.lock().unwrap();
-                    parser_visual(&final_input, &mut sheet, &mut undo_manager);
+                    let mut workbook = workbook_clone.lock().unwrap();
+                    let (sheet, undo_manager) = workbook.active_parts();
+                    // Plot commands do not use the clipboard registers
+                    *status_clone.lock().unwrap() =
+                        parser_visual(&final_input, sheet, undo_manager, &mut Registers::new());
+                    workbook.refresh_links();
+                    app::awake();
                 }
                 popup_clone.borrow_mut().hide(); // Close the popup after submission
             });
@@ -250,10 +446,25 @@ pub fn launch_gui(
             popup.borrow_mut().show();
         });
 
-        let mut input = Input::new(10, 10, screen_width - 600, 30, "");
+        // The command input shares the top row with the sheet tabs
+        let input_width = (screen_width - 710) / 2;
+        let mut input = Input::new(10, 10, input_width, 30, "");
         input.set_align(Align::Left);
         input.set_text_size(14);
 
+        // One tab per sheet, rebuilt whenever sheets are added, renamed, deleted or switched
+        let mut tabs = Pack::new(
+            input_width + 20,
+            10,
+            screen_width - 690 - input_width,
+            30,
+            "",
+        );
+        tabs.set_type(PackType::Horizontal);
+        tabs.set_spacing(5);
+        tabs.end();
+        let mut shown_tabs: (Vec<String>, usize) = (Vec::new(), 0);
+
         // Create a grid of frames to represent the spreadsheet cells
         let mut frames: Vec<Vec<Frame>> = vec![];
         for row in 0..rows {
@@ -279,7 +490,7 @@ pub fn launch_gui(
                 let current_row = current_row.clone();
                 let current_col = current_col.clone();
                 // let data = data.clone(); // Clone the Arc<Mutex<Vec<Vec<String>>>> for use in the closure
-                let sheet = sheet.clone(); // Clone the Arc<Mutex<SpreadsheetExtension>> for use in the closure
+                let workbook = workbook.clone(); // Clone the Arc<Mutex<Workbook>> for use in the closure
 
                 frame.handle(move |_, ev| {
                     if ev == fltk::enums::Event::Push {
@@ -295,7 +506,8 @@ pub fn launch_gui(
                             // let cell_ref = format!("{}{}", col_name, actual_row + 1);
 
                             // Retrieve the formula from the sheet
-                            let sheet_data = sheet.lock().unwrap();
+                            let workbook = workbook.lock().unwrap();
+                            let sheet_data = workbook.active();
                             if actual_row < sheet_data.rows as usize
                                 && actual_col < sheet_data.columns as usize
                             {
@@ -337,11 +549,15 @@ pub fn launch_gui(
 
         {
             let current_row = Arc::clone(&current_row);
+            let workbook = Arc::clone(&workbook);
 
             down_btn.set_callback(move |_| {
                 // println!("current_row: {:?}", current_row);
                 let mut row = current_row.lock().unwrap();
-                *row += 1;
+                // The sheet can be resized while the GUI is running
+                if *row + 1 < workbook.lock().unwrap().active().rows as usize {
+                    *row += 1;
+                }
                 app::awake();
             });
         }
@@ -361,17 +577,20 @@ pub fn launch_gui(
 
         {
             let current_col = Arc::clone(&current_col);
+            let workbook = Arc::clone(&workbook);
 
             right_btn.set_callback(move |_| {
                 // println!("current_col: {:?}", current_col);
                 let mut col = current_col.lock().unwrap();
-                *col += 1;
+                if *col + 1 < workbook.lock().unwrap().active().columns as usize {
+                    *col += 1;
+                }
                 app::awake();
             });
         }
 
         // Handle input submission
-        let input_text = input_text.clone();
+        let input_text_clone = input_text.clone();
         let selected_cell_clone = selected_cell.clone(); // Clone for this closure
         let current_row_clone = current_row.clone(); // Clone for this closure
         let current_col_clone = current_col.clone(); // Clone for this closure
@@ -380,7 +599,7 @@ pub fn launch_gui(
             if ev == fltk::enums::Event::KeyDown
                 && fltk::app::event_key() == fltk::enums::Key::Enter
             {
-                let mut text = input_text.lock().unwrap();
+                let mut text = input_text_clone.lock().unwrap();
                 let selected = selected_cell_clone.lock().unwrap();
                 let current_row_val = *current_row_clone.lock().unwrap();
                 let current_col_val = *current_col_clone.lock().unwrap();
@@ -410,7 +629,38 @@ pub fn launch_gui(
         // let mut prev_data : SpreadsheetExtension;
         // Add an idle callback to update the GUI dynamically
         app::add_idle3(move |_| {
-            let sheet_data = sheet.lock().unwrap();
+            let workbook = workbook.lock().unwrap();
+            let sheet_data = workbook.active();
+
+            let tab_names: Vec<String> = workbook.names().iter().map(|n| n.to_string()).collect();
+            if shown_tabs != (tab_names.clone(), workbook.active_index()) {
+                shown_tabs = (tab_names, workbook.active_index());
+                tabs.clear();
+                tabs.begin();
+                for (index, name) in shown_tabs.0.iter().enumerate() {
+                    let mut tab = Button::new(0, 0, 100, 30, None);
+                    tab.set_label(name);
+                    if index == shown_tabs.1 {
+                        tab.set_color(Color::from_rgb(200, 230, 255));
+                    }
+                    // Switch through the command thread, as the Undo button does
+                    let command = format!("sheet switch '{}'", name);
+                    let input_text = input_text.clone();
+                    tab.set_callback(move |_| {
+                        *input_text.lock().unwrap() = command.clone();
+                        app::awake();
+                    });
+                }
+                let mut add_tab = Button::new(0, 0, 30, 30, "+");
+                let input_text = input_text.clone();
+                add_tab.set_callback(move |_| {
+                    *input_text.lock().unwrap() = "sheet add".to_string();
+                    app::awake();
+                });
+                tabs.end();
+                tabs.redraw();
+            }
+
             let current_row_val = *current_row.lock().unwrap();
             let current_col_val = *current_col.lock().unwrap();
             let selected_cell = selected_cell.lock().unwrap();
@@ -452,11 +702,12 @@ pub fn launch_gui(
                     }
 
                     // Highlight selected cell
-                    if let (Some(sel_row), Some(sel_col)) = *selected_cell {
-                        if row == sel_row as usize && col == sel_col as usize {
-                            frame.set_color(Color::from_rgb(200, 230, 255));
-                            frame.set_frame(FrameType::FlatBox);
-                        }
+                    if let (Some(sel_row), Some(sel_col)) = *selected_cell
+                        && row == sel_row as usize
+                        && col == sel_col as usize
+                    {
+                        frame.set_color(Color::from_rgb(200, 230, 255));
+                        frame.set_frame(FrameType::FlatBox);
                     }
 
                     // Display cell content
@@ -469,7 +720,11 @@ pub fn launch_gui(
                             && actual_col < sheet_data.columns as usize
                         {
                             let cell = &sheet_data.all_cells[actual_row][actual_col];
-                            let cell_display = format!("{:9}", cell.value);
+                            let cell_display = match &cell.value {
+                                CellValue::Number(n) => format!("{:>9}", format_value(*n)),
+                                CellValue::Text(text) => text.clone(),
+                                CellValue::Bool(_) | CellValue::Error(_) => cell.value.to_string(),
+                            };
 
                             // Apply formatting based on cell properties
                             if cell.is_bold && cell.is_italics {
@@ -485,8 +740,7 @@ pub fn launch_gui(
                                 frame.set_label(&cell_display);
                             }
 
-                            if cell.is_error {
-                                frame.set_label("ERR");
+                            if cell.value.is_error() {
                                 frame.set_color(Color::Red);
                             }
                         } else {
@@ -497,24 +751,33 @@ pub fn launch_gui(
                 }
             }
 
-            // Check for errors in STATUS_EXTENSION
-            let mut status = status_extension.lock().unwrap();
-            if *status == 1 {
-                alert(200, 200, "Invalid input!");
-                *status = 0;
-            }
-            if *status == 2 {
-                alert(200, 200, "division by zero!");
-                *status = 0;
-            } else if *status == 3 {
-                alert(200, 200, "cyclic dependence found");
-                *status = 0;
-            } else if *status > 3 {
-                alert(200, 200, "error occured!");
-                *status = 0;
+            // Show the result of the last command, errors in red
+            let status = status.lock().unwrap();
+            let status_label = status_text(&status);
+            let mut cycle_alert = None;
+            if status_bar.label() != status_label {
+                status_bar.set_label(&status_label);
+                status_bar.set_label_color(if status.is_err() {
+                    Color::Red
+                } else {
+                    Color::Black
+                });
+                status_bar.redraw();
+                // A cycle can run far outside the view, so spell out the whole loop
+                if matches!(*status, Err(SheetError::CyclicDependency(_))) {
+                    cycle_alert = Some(status_label);
+                }
             }
 
             // prev_data = sheet_data;
+
+            // The alert waits for the user, so release the sheet for the command thread first
+            drop(status);
+            drop(selected_cell);
+            drop(workbook);
+            if let Some(message) = cycle_alert {
+                dialog::alert_default(&message);
+            }
         });
 
         app.run().unwrap();
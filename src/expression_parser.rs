@@ -1,22 +1,40 @@
+use crate::cell_extension::CellError;
 use crate::cellsp::CellReference;
 
 /// Represents an expression in the spreadsheet.
 ///
 /// This enum is used to define various types of expressions that can be evaluated
 /// in the spreadsheet, such as numbers, cell references, binary operations, functions, and ranges.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
-    /// A constant integer value.
-    Number(i32),
+    /// A constant numeric value.
+    Number(f64),
+    /// A constant text value.
+    Text(String),
+    /// A constant boolean value (`TRUE` or `FALSE`).
+    Bool(bool),
     /// A reference to a specific cell in the spreadsheet.
     Cell(CellReference),
+    /// A unary operation on an expression.
+    ///
+    /// # Fields
+    /// * `char` - The operator (`+` or `-`).
+    /// * `Box<Expr>` - The operand.
+    UnaryOp(char, Box<Expr>),
     /// A binary operation between two expressions.
     ///
     /// # Fields
     /// * `Box<Expr>` - The left-hand side of the binary operation.
-    /// * `char` - The operator (e.g., `+`, `-`, `*`, `/`).
+    /// * `char` - The operator (`+`, `-`, `*`, `/`, `%` for modulo or `^` for exponent).
     /// * `Box<Expr>` - The right-hand side of the binary operation.
     BinaryOp(Box<Expr>, char, Box<Expr>),
+    /// A comparison between two expressions, evaluating to a boolean.
+    ///
+    /// # Fields
+    /// * `Box<Expr>` - The left-hand side of the comparison.
+    /// * `CompareOp` - The comparison operator.
+    /// * `Box<Expr>` - The right-hand side of the comparison.
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
     /// A function applied to a list of expressions.
     ///
     /// # Fields
@@ -29,4 +47,58 @@ pub enum Expr {
     /// * `CellReference` - The starting cell of the range.
     /// * `CellReference` - The ending cell of the range.
     Range(CellReference, CellReference),
+    /// A constant error value, such as `#REF!`.
+    Error(CellError),
+    /// A reference into another sheet of the workbook, such as `Sheet2!A1` or
+    /// `'My Sheet'!A1:B5`.
+    ///
+    /// # Fields
+    /// * `String` - The name of the sheet, as written in the formula.
+    /// * `Box<Expr>` - The `Expr::Cell` or `Expr::Range` on that sheet.
+    SheetRef(String, Box<Expr>),
+    /// A name defined with the `name` command, such as `REVENUE`, standing for a cell, a
+    /// range or a constant. Names are stored in upper case.
+    Name(String),
+}
+
+impl Expr {
+    /// Returns `true` for a range of cells, on this sheet or another one.
+    pub fn is_range(&self) -> bool {
+        match self {
+            Expr::Range(_, _) => true,
+            Expr::SheetRef(_, inner) => inner.is_range(),
+            _ => false,
+        }
+    }
+}
+
+/// A comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    /// `=`
+    Eq,
+    /// `<>`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+impl CompareOp {
+    /// Returns the operator as written in a formula.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "<>",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        }
+    }
 }
@@ -1,8 +1,11 @@
-use crate::cell_extension::SpreadsheetExtension;
+use crate::cell_extension::{CellError, CellValue, LinkedSheet, SpreadsheetExtension, sheet_key};
+use crate::cell_grid::{MAX_COLS, MAX_ROWS};
 use crate::cellsp::CellReference;
-use crate::expression_parser::Expr;
+use crate::expression_parser::{CompareOp, Expr};
 use crate::formula::FormulaParser;
-use crate::graph_extension::STATUS_EXTENSION;
+use crate::function_registry::call_function;
+use crate::sheet_error::SheetError;
+use std::cmp::Ordering;
 use std::str;
 
 /// Parses a formula string into an expression.
@@ -12,12 +15,12 @@ use std::str;
 ///
 /// # Returns
 /// * `Ok(Box<Expr>)` - The parsed expression if successful.
-/// * `Err(String)` - An error message if parsing fails.
-pub fn parse_formula(input: &str) -> Result<Box<Expr>, String> {
+/// * `Err(SheetError::Parse)` - If parsing fails, with the parser's description of the problem.
+pub fn parse_formula(input: &str) -> Result<Box<Expr>, SheetError> {
     let parser = FormulaParser::new();
     parser
         .parse(input)
-        .map_err(|e| format!("Parse error: {:?}", e))
+        .map_err(|e| SheetError::Parse(e.to_string()))
 }
 
 /// Recursively extracts precedents (cell references) from an expression.
@@ -28,16 +31,23 @@ pub fn parse_formula(input: &str) -> Result<Box<Expr>, String> {
 ///
 /// # Behavior
 /// This function traverses the expression tree and collects all cell references
-/// (e.g., `A1`, `B2`) and ranges (e.g., `A1:B2`) into the accumulator.
+/// (e.g., `A1`, `B2`) and ranges (e.g., `A1:B2`) into the accumulator. References into
+/// other sheets and defined names are left out.
 pub fn extract_precedents_helper(expr: &Expr, acc: &mut Vec<CellReference>) {
     match expr {
-        Expr::Number(_) => {}
+        Expr::Number(_) | Expr::Text(_) | Expr::Bool(_) | Expr::Error(_) => {}
+
+        Expr::SheetRef(_, _) | Expr::Name(_) => {}
 
         Expr::Cell(cell_ref) => {
             acc.push(cell_ref.clone());
         }
 
-        Expr::BinaryOp(left, _, right) => {
+        Expr::UnaryOp(_, operand) => {
+            extract_precedents_helper(operand, acc);
+        }
+
+        Expr::BinaryOp(left, _, right) | Expr::Compare(left, _, right) => {
             extract_precedents_helper(left, acc);
             extract_precedents_helper(right, acc);
         }
@@ -51,7 +61,7 @@ pub fn extract_precedents_helper(expr: &Expr, acc: &mut Vec<CellReference>) {
         Expr::Range(start, end) => {
             for r in start.row..=end.row {
                 for c in start.column..=end.column {
-                    acc.push(CellReference { row: r, column: c });
+                    acc.push(CellReference::new(r, c));
                 }
             }
         }
@@ -71,6 +81,264 @@ pub fn extract_precedents(expr: &crate::expression_parser::Expr) -> Vec<CellRefe
     acc
 }
 
+/// Extracts the cell references and ranges in an expression, without expanding ranges.
+///
+/// # Arguments
+/// * `expr` - A reference to the expression to analyze.
+///
+/// # Returns
+/// The single cell references, and the two ends of each range, in the order they appear.
+/// References into other sheets are left out; see `extract_sheet_references`. Defined names
+/// are left out too; see `resolve_names`.
+pub fn extract_references(
+    expr: &Expr,
+) -> (Vec<CellReference>, Vec<(CellReference, CellReference)>) {
+    fn walk(
+        expr: &Expr,
+        cells: &mut Vec<CellReference>,
+        ranges: &mut Vec<(CellReference, CellReference)>,
+    ) {
+        match expr {
+            Expr::Number(_) | Expr::Text(_) | Expr::Bool(_) | Expr::Error(_) => {}
+            Expr::SheetRef(_, _) | Expr::Name(_) => {}
+            Expr::Cell(cell_ref) => cells.push(cell_ref.clone()),
+            Expr::Range(start, end) => ranges.push((start.clone(), end.clone())),
+            Expr::UnaryOp(_, operand) => walk(operand, cells, ranges),
+            Expr::BinaryOp(left, _, right) | Expr::Compare(left, _, right) => {
+                walk(left, cells, ranges);
+                walk(right, cells, ranges);
+            }
+            Expr::Function(_, args) => {
+                for arg in args {
+                    walk(arg, cells, ranges);
+                }
+            }
+        }
+    }
+
+    let mut cells = Vec::new();
+    let mut ranges = Vec::new();
+    walk(expr, &mut cells, &mut ranges);
+    (cells, ranges)
+}
+
+/// Extracts the references into other sheets in an expression.
+///
+/// # Arguments
+/// * `expr` - A reference to the expression to analyze.
+///
+/// # Returns
+/// The sheet name, as written, and the two ends of each reference, in the order they appear.
+/// A single cell is returned as a range that starts and ends at it.
+pub fn extract_sheet_references(expr: &Expr) -> Vec<(String, CellReference, CellReference)> {
+    fn walk(expr: &Expr, acc: &mut Vec<(String, CellReference, CellReference)>) {
+        match expr {
+            Expr::Number(_) | Expr::Text(_) | Expr::Bool(_) | Expr::Error(_) => {}
+            Expr::Cell(_) | Expr::Range(_, _) | Expr::Name(_) => {}
+            Expr::SheetRef(name, target) => match target.as_ref() {
+                Expr::Cell(cell_ref) => {
+                    acc.push((name.clone(), cell_ref.clone(), cell_ref.clone()))
+                }
+                Expr::Range(start, end) => acc.push((name.clone(), start.clone(), end.clone())),
+                _ => {}
+            },
+            Expr::UnaryOp(_, operand) => walk(operand, acc),
+            Expr::BinaryOp(left, _, right) | Expr::Compare(left, _, right) => {
+                walk(left, acc);
+                walk(right, acc);
+            }
+            Expr::Function(_, args) => {
+                for arg in args {
+                    walk(arg, acc);
+                }
+            }
+        }
+    }
+
+    let mut acc = Vec::new();
+    walk(expr, &mut acc);
+    acc
+}
+
+/// Replaces the defined names in a formula with what they stand for.
+///
+/// # Arguments
+/// * `expr` - A reference to the formula.
+/// * `sheet` - A reference to the spreadsheet the names are defined in.
+///
+/// # Returns
+/// The formula with each defined name replaced by its cell, range or constant. Names that
+/// are not defined are kept.
+pub fn resolve_names(expr: &Expr, sheet: &SpreadsheetExtension) -> Expr {
+    let resolve = |e: &Expr| resolve_names(e, sheet);
+    match expr {
+        Expr::Name(name) => match sheet.names.get(name) {
+            Some(defined) => defined.target.clone(),
+            None => expr.clone(),
+        },
+        Expr::UnaryOp(op, operand) => Expr::UnaryOp(*op, Box::new(resolve(operand))),
+        Expr::BinaryOp(left, op, right) => {
+            Expr::BinaryOp(Box::new(resolve(left)), *op, Box::new(resolve(right)))
+        }
+        Expr::Compare(left, op, right) => {
+            Expr::Compare(Box::new(resolve(left)), *op, Box::new(resolve(right)))
+        }
+        Expr::Function(name, args) => {
+            Expr::Function(name.clone(), args.iter().map(resolve).collect())
+        }
+        _ => expr.clone(),
+    }
+}
+
+/// Rebuilds a formula with every cell reference and range passed through a mapping.
+///
+/// # Arguments
+/// * `expr` - A reference to the formula to rewrite.
+/// * `map_cell` - Maps a single reference; `None` turns it into `#REF!`.
+/// * `map_range` - Maps the two ends of a range; `None` turns it into `#REF!`.
+///
+/// # Returns
+/// The rewritten formula. References into other sheets and defined names are kept as they
+/// are; `map_sheet_references` rewrites references into another sheet.
+pub fn map_references(
+    expr: &Expr,
+    map_cell: &impl Fn(&CellReference) -> Option<CellReference>,
+    map_range: &impl Fn(&CellReference, &CellReference) -> Option<(CellReference, CellReference)>,
+) -> Expr {
+    let map_expr = |e: &Expr| map_references(e, map_cell, map_range);
+
+    match expr {
+        Expr::Number(_) | Expr::Text(_) | Expr::Bool(_) | Expr::Error(_) => expr.clone(),
+
+        Expr::SheetRef(_, _) | Expr::Name(_) => expr.clone(),
+
+        Expr::Cell(cell_ref) => match map_cell(cell_ref) {
+            Some(mapped) => Expr::Cell(mapped),
+            None => Expr::Error(CellError::Ref),
+        },
+
+        Expr::UnaryOp(op, operand) => Expr::UnaryOp(*op, Box::new(map_expr(operand))),
+
+        Expr::BinaryOp(left, op, right) => {
+            Expr::BinaryOp(Box::new(map_expr(left)), *op, Box::new(map_expr(right)))
+        }
+
+        Expr::Compare(left, op, right) => {
+            Expr::Compare(Box::new(map_expr(left)), *op, Box::new(map_expr(right)))
+        }
+
+        Expr::Function(name, args) => {
+            Expr::Function(name.clone(), args.iter().map(map_expr).collect())
+        }
+
+        Expr::Range(start, end) => match map_range(start, end) {
+            Some((start, end)) => Expr::Range(start, end),
+            None => Expr::Error(CellError::Ref),
+        },
+    }
+}
+
+/// Rebuilds a formula with every reference into one other sheet passed through a mapping.
+///
+/// # Arguments
+/// * `expr` - A reference to the formula to rewrite.
+/// * `key` - The upper-case name of the other sheet, as returned by `sheet_key`.
+/// * `map_cell` - Maps a single reference; `None` turns it into `#REF!`.
+/// * `map_range` - Maps the two ends of a range; `None` turns it into `#REF!`.
+///
+/// # Returns
+/// The rewritten formula. References within the sheet, into any other sheet and defined
+/// names are kept as they are.
+pub fn map_sheet_references(
+    expr: &Expr,
+    key: &str,
+    map_cell: &impl Fn(&CellReference) -> Option<CellReference>,
+    map_range: &impl Fn(&CellReference, &CellReference) -> Option<(CellReference, CellReference)>,
+) -> Expr {
+    map_external_references(expr, &|name| sheet_key(name) == key, map_cell, map_range)
+}
+
+/// Rebuilds a formula with the references into the other sheets that `is_mapped` accepts,
+/// by name, passed through a mapping. See `map_sheet_references`.
+fn map_external_references(
+    expr: &Expr,
+    is_mapped: &impl Fn(&str) -> bool,
+    map_cell: &impl Fn(&CellReference) -> Option<CellReference>,
+    map_range: &impl Fn(&CellReference, &CellReference) -> Option<(CellReference, CellReference)>,
+) -> Expr {
+    let map_expr = |e: &Expr| map_external_references(e, is_mapped, map_cell, map_range);
+
+    match expr {
+        Expr::SheetRef(name, target) if is_mapped(name) => {
+            match map_references(target, map_cell, map_range) {
+                Expr::Error(error) => Expr::Error(error),
+                target => Expr::SheetRef(name.clone(), Box::new(target)),
+            }
+        }
+        Expr::UnaryOp(op, operand) => Expr::UnaryOp(*op, Box::new(map_expr(operand))),
+        Expr::BinaryOp(left, op, right) => {
+            Expr::BinaryOp(Box::new(map_expr(left)), *op, Box::new(map_expr(right)))
+        }
+        Expr::Compare(left, op, right) => {
+            Expr::Compare(Box::new(map_expr(left)), *op, Box::new(map_expr(right)))
+        }
+        Expr::Function(name, args) => {
+            Expr::Function(name.clone(), args.iter().map(map_expr).collect())
+        }
+        _ => expr.clone(),
+    }
+}
+
+/// Moves a formula by an offset, as when it is copied from one cell to another.
+///
+/// # Arguments
+/// * `expr` - A reference to the formula to move.
+/// * `row_offset` - The number of rows to move by (negative moves up).
+/// * `column_offset` - The number of columns to move by (negative moves left).
+/// * `sheet` - A reference to the spreadsheet, used to check the moved references.
+///
+/// # Returns
+/// The moved formula.
+///
+/// # Behavior
+/// * Relative rows and columns are moved; those anchored with `$` stay where they are.
+/// * A reference, or a range with either end, that moves off the sheet becomes `#REF!`.
+/// * References into other sheets move the same way, and become `#REF!` past `MAX_ROWS`
+///   or `MAX_COLS`.
+pub fn shift_formula(
+    expr: &Expr,
+    row_offset: i32,
+    column_offset: i32,
+    sheet: &SpreadsheetExtension,
+) -> Expr {
+    let move_by_offset = |cell_ref: &CellReference| {
+        let mut moved = cell_ref.clone();
+        if !moved.row_absolute {
+            moved.row += row_offset;
+        }
+        if !moved.column_absolute {
+            moved.column += column_offset;
+        }
+        moved
+    };
+    let shift = |cell_ref: &CellReference| {
+        Some(move_by_offset(cell_ref)).filter(|moved| is_in_bounds(moved, sheet))
+    };
+    // The size of another sheet may change, so only the largest sheet size is checked
+    let shift_external = |cell_ref: &CellReference| {
+        Some(move_by_offset(cell_ref)).filter(|moved| {
+            (0..MAX_ROWS).contains(&moved.row) && (0..MAX_COLS).contains(&moved.column)
+        })
+    };
+
+    let shifted = map_references(expr, &shift, &|start, end| {
+        Some((shift(start)?, shift(end)?))
+    });
+    map_external_references(&shifted, &|_| true, &shift_external, &|start, end| {
+        Some((shift_external(start)?, shift_external(end)?))
+    })
+}
+
 /// Evaluates an expression in the context of a spreadsheet.
 ///
 /// # Arguments
@@ -78,193 +346,335 @@ pub fn extract_precedents(expr: &crate::expression_parser::Expr) -> Vec<CellRefe
 /// * `sheet` - A reference to the spreadsheet containing cell values.
 ///
 /// # Returns
-/// The evaluated result as an integer.
+/// The evaluated result as a `CellValue`.
 ///
 /// # Behavior
-/// * Supports basic arithmetic operations (`+`, `-`, `*`, `/`).
+/// * Supports arithmetic operations (`+`, `-`, `*`, `/`), modulo (`%`), exponents (`^`) and
+///   unary `+`/`-`.
 /// * Supports functions like `SUM`, `MAX`, `MIN`, `AVG`, and `STDEV`.
-/// * Handles ranges (e.g., `A1:B2`) for functions like `SUM`.
-/// * Returns `0` for invalid operations or division by zero.
-pub fn eval_expr(expr: &Expr, sheet: &SpreadsheetExtension) -> i32 {
+/// * Handles ranges (e.g., `A1:B2`) for functions like `SUM`; text cells in a range are skipped.
+/// * Text literals and references to text cells evaluate to text.
+/// * Comparisons (`=`, `<>`, `<`, `<=`, `>`, `>=`) evaluate to booleans. Text compares
+///   case-insensitively; values of different types order as numbers, then text, then booleans.
+/// * `IF(c, x, y)` evaluates only the branch selected by `c`; `AND`, `OR` and `NOT` combine
+///   conditions. Numbers count as `TRUE` when non-zero and booleans count as `1`/`0` in
+///   arithmetic.
+/// * `ISERROR(x)` returns whether `x` is an error; `IFERROR(x, y)` returns `y` if `x` is an
+///   error and `x` otherwise.
+/// * Errors are returned as `CellValue::Error`: `#DIV/0!` for division by zero, `#VALUE!` for
+///   text used as a number, `#REF!` for references outside the sheet and `#NAME?` for unknown
+///   functions. An error in any operand or range cell is propagated unchanged.
+/// * References into other sheets, such as `Sheet2!A1`, read the values in `sheet.linked`.
+///   They are `#REF!` if the sheet does not exist or the reference lies outside it.
+/// * Defined names evaluate as what they stand for, or to `#NAME?` if they are not defined.
+pub fn eval_expr(expr: &Expr, sheet: &SpreadsheetExtension) -> CellValue {
     match expr {
-        Expr::Number(value) => *value,
+        // Literals too large for an f64, such as `1e999`, are out of range
+        Expr::Number(value) => number_or_error(Ok(*value)),
+
+        Expr::Text(text) => CellValue::Text(text.clone()),
+
+        Expr::Bool(value) => CellValue::Bool(*value),
+
+        Expr::Error(error) => CellValue::Error(*error),
 
         Expr::Cell(cell_ref) => {
-            sheet.all_cells[cell_ref.row as usize][cell_ref.column as usize].value
+            if !is_in_bounds(cell_ref, sheet) {
+                return CellValue::Error(CellError::Ref);
+            }
+            sheet.all_cells[cell_ref.row as usize][cell_ref.column as usize]
+                .value
+                .clone()
+        }
+
+        Expr::UnaryOp(op, operand) => {
+            let result = eval_number(operand, sheet).map(|n| if *op == '-' { -n } else { n });
+            number_or_error(result)
         }
 
         Expr::BinaryOp(left, op, right) => {
-            match op {
-                '+' => eval_expr(left, sheet) + eval_expr(right, sheet),
-                '-' => eval_expr(left, sheet) - eval_expr(right, sheet),
-                '*' => eval_expr(left, sheet) * eval_expr(right, sheet),
-                '/' => {
-                    let divisor = eval_expr(right, sheet);
-                    if divisor == 0 {
-                        unsafe {
-                            STATUS_EXTENSION = 2; // Error status for division by zero
+            let result = eval_number(left, sheet).and_then(|l| {
+                let r = eval_number(right, sheet)?;
+                match op {
+                    '+' => Ok(l + r),
+                    '-' => Ok(l - r),
+                    '*' => Ok(l * r),
+                    '/' => {
+                        if r == 0.0 {
+                            Err(CellError::DivByZero)
+                        } else {
+                            Ok(l / r)
                         }
-                        0 // Return 0 for division by zero
-                    } else {
-                        eval_expr(left, sheet) / divisor
                     }
+                    '%' => modulo(l, r),
+                    '^' => Ok(l.powf(r)),
+                    _ => unreachable!("Not a valid binary operation"),
                 }
-                _ => unsafe {
-                    STATUS_EXTENSION = 12;
-                    panic!("Not a valid binary operation");
-                },
-            }
+            });
+            number_or_error(result)
         }
 
-        Expr::Function(name, args) => {
-            match name.as_str() {
-                "SUM" | "Sum" => {
-                    let mut sum = 0;
-                    for arg in args {
-                        match arg {
-                            Expr::Range(start, end) => {
-                                for r in start.row..=end.row {
-                                    for c in start.column..=end.column {
-                                        // let cell_ref = CellReference { row: r, column: c };
-                                        sum += sheet.all_cells[r as usize][c as usize].value;
-                                    }
-                                }
-                            }
-                            _ => sum += eval_expr(arg, sheet),
-                        }
-                    }
-                    sum
-                }
+        Expr::Compare(left, op, right) => {
+            let l = eval_expr(left, sheet);
+            if l.is_error() {
+                return l;
+            }
+            let r = eval_expr(right, sheet);
+            if r.is_error() {
+                return r;
+            }
+            let ordering = compare_values(&l, &r);
+            CellValue::Bool(match op {
+                CompareOp::Eq => ordering == Ordering::Equal,
+                CompareOp::Ne => ordering != Ordering::Equal,
+                CompareOp::Lt => ordering == Ordering::Less,
+                CompareOp::Le => ordering != Ordering::Greater,
+                CompareOp::Gt => ordering == Ordering::Greater,
+                CompareOp::Ge => ordering != Ordering::Less,
+            })
+        }
 
-                "MAX" | "Max" => {
-                    let mut values = Vec::new();
-
-                    for arg in args {
-                        match arg {
-                            Expr::Range(start, end) => {
-                                for r in start.row..=end.row {
-                                    for c in start.column..=end.column {
-                                        values.push(sheet.all_cells[r as usize][c as usize].value);
-                                    }
-                                }
-                            }
-                            _ => values.push(eval_expr(arg, sheet)),
-                        }
-                    }
+        Expr::Function(name, args) => call_function(name, args, sheet),
 
-                    if values.is_empty() {
-                        0
-                    } else {
-                        *values.iter().max().unwrap_or(&0)
-                    }
-                }
+        // A bare range is not a value
+        Expr::Range(_, _) => CellValue::Error(CellError::Value),
 
-                "MIN" | "Min" => {
-                    let mut values = Vec::new();
-
-                    for arg in args {
-                        match arg {
-                            Expr::Range(start, end) => {
-                                for r in start.row..=end.row {
-                                    for c in start.column..=end.column {
-                                        values.push(sheet.all_cells[r as usize][c as usize].value);
-                                    }
-                                }
-                            }
-                            _ => values.push(eval_expr(arg, sheet)),
-                        }
-                    }
+        Expr::SheetRef(name, target) => match target.as_ref() {
+            Expr::Cell(cell_ref) => match linked_sheet(name, cell_ref, cell_ref, sheet) {
+                Some(linked) => linked
+                    .values
+                    .get(&(cell_ref.row, cell_ref.column))
+                    .cloned()
+                    .unwrap_or_default(),
+                None => CellValue::Error(CellError::Ref),
+            },
+            _ => CellValue::Error(CellError::Value),
+        },
 
-                    if values.is_empty() {
-                        0
-                    } else {
-                        *values.iter().min().unwrap_or(&0)
-                    }
-                }
+        Expr::Name(name) => match sheet.names.get(name) {
+            Some(defined) => eval_expr(&defined.target, sheet),
+            None => CellValue::Error(CellError::Name),
+        },
+    }
+}
 
-                "AVG" | "Avg" => {
-                    let mut values = Vec::new();
-
-                    for arg in args {
-                        match arg {
-                            Expr::Range(start, end) => {
-                                for r in start.row..=end.row {
-                                    for c in start.column..=end.column {
-                                        values.push(sheet.all_cells[r as usize][c as usize].value);
-                                    }
-                                }
-                            }
-                            _ => values.push(eval_expr(arg, sheet)),
-                        }
-                    }
+/// Finds the values of the sheet a reference into another sheet points to.
+///
+/// # Returns
+/// The sheet's values, or `None` if the sheet does not exist or the cells from `start` to
+/// `end` do not lie inside it.
+fn linked_sheet<'a>(
+    name: &str,
+    start: &CellReference,
+    end: &CellReference,
+    sheet: &'a SpreadsheetExtension,
+) -> Option<&'a LinkedSheet> {
+    sheet.linked.get(&sheet_key(name)).filter(|linked| {
+        [start, end].into_iter().all(|cell_ref| {
+            (0..linked.rows).contains(&cell_ref.row)
+                && (0..linked.columns).contains(&cell_ref.column)
+        })
+    })
+}
 
-                    if values.is_empty() {
-                        0
-                    } else {
-                        values.iter().sum::<i32>() / values.len() as i32
-                    }
-                }
+/// Visits the value of each cell in a range argument, on this sheet or another one, given
+/// directly or by a defined name.
+///
+/// # Arguments
+/// * `arg` - A function argument.
+/// * `sheet` - A reference to the spreadsheet containing cell values.
+/// * `visit` - Called with each value, row by row; an error stops the visit.
+///
+/// # Returns
+/// * `None` - If the argument is not a range.
+/// * `Some(Ok(()))` - Once every value has been visited.
+/// * `Some(Err(CellError))` - `#REF!` if the range lies outside its sheet, or the error
+///   returned by `visit`.
+fn for_each_in_range(
+    arg: &Expr,
+    sheet: &SpreadsheetExtension,
+    mut visit: impl FnMut(&CellValue) -> Result<(), CellError>,
+) -> Option<Result<(), CellError>> {
+    const EMPTY: CellValue = CellValue::Number(0.0);
 
-                "STDEV" | "Stdev" => {
-                    let mut values = Vec::new();
-
-                    for arg in args {
-                        match arg {
-                            Expr::Range(start, end) => {
-                                for r in start.row..=end.row {
-                                    for c in start.column..=end.column {
-                                        values.push(sheet.all_cells[r as usize][c as usize].value);
-                                    }
-                                }
-                            }
-                            _ => values.push(eval_expr(arg, sheet)),
-                        }
-                    }
+    let result = match arg {
+        Expr::Range(start, end) => {
+            if !is_in_bounds(start, sheet) || !is_in_bounds(end, sheet) {
+                return Some(Err(CellError::Ref));
+            }
+            (start.row..=end.row).try_for_each(|r| {
+                (start.column..=end.column)
+                    .try_for_each(|c| visit(&sheet.all_cells[r as usize][c as usize].value))
+            })
+        }
+        Expr::SheetRef(name, target) => {
+            let Expr::Range(start, end) = target.as_ref() else {
+                return None;
+            };
+            let Some(linked) = linked_sheet(name, start, end, sheet) else {
+                return Some(Err(CellError::Ref));
+            };
+            (start.row..=end.row).try_for_each(|r| {
+                (start.column..=end.column)
+                    .try_for_each(|c| visit(linked.values.get(&(r, c)).unwrap_or(&EMPTY)))
+            })
+        }
+        Expr::Name(name) => {
+            return sheet
+                .names
+                .get(name)
+                .and_then(|defined| for_each_in_range(&defined.target, sheet, visit));
+        }
+        _ => return None,
+    };
+    Some(result)
+}
 
-                    if values.is_empty() {
-                        0
-                    } else {
-                        // Calculate mean
-                        let n = values.len() as f64;
-                        let mean: f64 = values.iter().map(|&x| x as f64).sum::<f64>() / n;
-
-                        // Calculate variance
-                        let variance = values
-                            .iter()
-                            .map(|&x| {
-                                let diff = (x as f64) - mean;
-                                diff * diff
-                            })
-                            .sum::<f64>()
-                            / n;
-
-                        // Return standard deviation as integer
-                        variance.sqrt() as i32
-                    }
-                }
+/// Evaluates an expression whose result is used as a number.
+///
+/// # Arguments
+/// * `expr` - A reference to the expression to evaluate.
+/// * `sheet` - A reference to the spreadsheet containing cell values.
+///
+/// # Returns
+/// * `Ok(f64)` - The numeric result.
+/// * `Err(CellError)` - The error the expression evaluated to, or `CellError::Value` if it
+///   evaluated to text.
+pub fn eval_number(expr: &Expr, sheet: &SpreadsheetExtension) -> Result<f64, CellError> {
+    match eval_expr(expr, sheet) {
+        CellValue::Number(n) => Ok(n),
+        CellValue::Bool(b) => Ok(if b { 1.0 } else { 0.0 }),
+        CellValue::Text(_) => Err(CellError::Value),
+        CellValue::Error(e) => Err(e),
+    }
+}
 
-                "SLEEP" | "Sleep" => {
-                    if let Some(arg) = args.first() {
-                        eval_expr(arg, sheet)
-                    } else {
-                        0
-                    }
-                }
+/// Evaluates an expression whose result is used as a condition.
+///
+/// # Arguments
+/// * `expr` - A reference to the expression to evaluate.
+/// * `sheet` - A reference to the spreadsheet containing cell values.
+///
+/// # Returns
+/// * `Ok(bool)` - The condition; numbers are `true` when non-zero.
+/// * `Err(CellError)` - The error the expression evaluated to, or `CellError::Value` if it
+///   evaluated to text.
+pub fn eval_condition(expr: &Expr, sheet: &SpreadsheetExtension) -> Result<bool, CellError> {
+    match eval_expr(expr, sheet) {
+        CellValue::Bool(b) => Ok(b),
+        CellValue::Number(n) => Ok(n != 0.0),
+        CellValue::Text(_) => Err(CellError::Value),
+        CellValue::Error(e) => Err(e),
+    }
+}
 
-                _ => {
-                    unsafe {
-                        STATUS_EXTENSION = 1;
-                    }
-                    0
-                }
+/// Collects the conditions passed to `AND` or `OR`.
+///
+/// # Arguments
+/// * `args` - The function arguments; ranges are expanded cell by cell.
+/// * `sheet` - A reference to the spreadsheet containing cell values.
+///
+/// # Returns
+/// * `Ok(Vec<bool>)` - The conditions. Text cells inside a range are skipped.
+/// * `Err(CellError)` - The first error found in an argument or range cell.
+pub fn collect_conditions(
+    args: &[Expr],
+    sheet: &SpreadsheetExtension,
+) -> Result<Vec<bool>, CellError> {
+    let mut conditions = Vec::new();
+    for arg in args {
+        let range = for_each_in_range(arg, sheet, |value| {
+            match value {
+                CellValue::Bool(b) => conditions.push(*b),
+                CellValue::Number(n) => conditions.push(*n != 0.0),
+                CellValue::Text(_) => {}
+                CellValue::Error(e) => return Err(*e),
             }
+            Ok(())
+        });
+        match range {
+            Some(result) => result?,
+            None => conditions.push(eval_condition(arg, sheet)?),
         }
+    }
+    Ok(conditions)
+}
+
+/// Orders two non-error values for the comparison operators.
+///
+/// Values of the same type compare naturally, with text compared case-insensitively.
+/// Values of different types order as numbers, then text, then booleans.
+fn compare_values(left: &CellValue, right: &CellValue) -> Ordering {
+    fn type_rank(value: &CellValue) -> u8 {
+        match value {
+            CellValue::Number(_) => 0,
+            CellValue::Text(_) => 1,
+            CellValue::Bool(_) => 2,
+            CellValue::Error(_) => 3,
+        }
+    }
 
-        Expr::Range(_, _) => {
-            unsafe {
-                STATUS_EXTENSION = 1;
+    match (left, right) {
+        (CellValue::Number(l), CellValue::Number(r)) => l.partial_cmp(r).unwrap_or(Ordering::Equal),
+        (CellValue::Text(l), CellValue::Text(r)) => l.to_lowercase().cmp(&r.to_lowercase()),
+        (CellValue::Bool(l), CellValue::Bool(r)) => l.cmp(r),
+        _ => type_rank(left).cmp(&type_rank(right)),
+    }
+}
+
+/// Collects the numeric values of a function's arguments.
+///
+/// # Arguments
+/// * `args` - The function arguments; ranges are expanded cell by cell.
+/// * `sheet` - A reference to the spreadsheet containing cell values.
+///
+/// # Returns
+/// * `Ok(Vec<f64>)` - The numbers, in argument order. Text and boolean cells inside a range
+///   are skipped.
+/// * `Err(CellError)` - The first error found in an argument or range cell.
+pub fn collect_numbers(args: &[Expr], sheet: &SpreadsheetExtension) -> Result<Vec<f64>, CellError> {
+    let mut values = Vec::new();
+    for arg in args {
+        let range = for_each_in_range(arg, sheet, |value| {
+            match value {
+                CellValue::Number(n) => values.push(*n),
+                CellValue::Text(_) | CellValue::Bool(_) => {}
+                CellValue::Error(e) => return Err(*e),
             }
-            0
+            Ok(())
+        });
+        match range {
+            Some(result) => result?,
+            None => values.push(eval_number(arg, sheet)?),
         }
     }
+    Ok(values)
+}
+
+/// Computes `number` modulo `divisor`; the result takes the sign of the divisor.
+pub(crate) fn modulo(number: f64, divisor: f64) -> Result<f64, CellError> {
+    if divisor == 0.0 {
+        Err(CellError::DivByZero)
+    } else {
+        Ok(number - divisor * (number / divisor).floor())
+    }
+}
+
+/// Converts the result of a numeric computation into a cell value.
+///
+/// Infinite and NaN results (e.g. from overflow or `(-8)^0.5`) become `#NUM!`.
+pub fn number_or_error(result: Result<f64, CellError>) -> CellValue {
+    match result {
+        Ok(n) if n.is_finite() => CellValue::Number(n),
+        Ok(_) => CellValue::Error(CellError::Num),
+        Err(e) => CellValue::Error(e),
+    }
+}
+
+/// Checks whether a cell reference lies inside the sheet.
+fn is_in_bounds(cell_ref: &CellReference, sheet: &SpreadsheetExtension) -> bool {
+    cell_ref.row >= 0
+        && cell_ref.row < sheet.rows
+        && cell_ref.column >= 0
+        && cell_ref.column < sheet.columns
 }
@@ -1,7 +1,9 @@
 // auto-generated: "lalrpop 0.22.1"
-// sha3: b184736d60d01e2d6d457637c406449ce5d1eaa0aac658d3301d3168df426319
+// sha3: f6429c927ef2cc34e70d78e071ebdde7d6ebc0215de707b5ddb038b76866b9a6
+use crate::cell_extension::CellError;
 use crate::cellsp::CellReference;
-use crate::expression_parser::Expr;
+use crate::expression_parser::{CompareOp, Expr};
+use lalrpop_util::ParseError;
 use std::str::FromStr;
 #[allow(unused_extern_crates)]
 extern crate lalrpop_util as __lalrpop_util;
@@ -15,8 +17,10 @@ extern crate alloc;
 mod __parse__Formula {
 
     use std::str::FromStr;
-    use crate::expression_parser::Expr;
+    use lalrpop_util::ParseError;
+    use crate::expression_parser::{CompareOp, Expr};
     use crate::cellsp::CellReference;
+    use crate::cell_extension::CellError;
     #[allow(unused_extern_crates)]
     extern crate lalrpop_util as __lalrpop_util;
     #[allow(unused_imports)]
@@ -28,101 +32,185 @@ mod __parse__Formula {
     pub(crate) enum __Symbol<'input>
      {
         Variant0(&'input str),
-        Variant1(Expr),
-        Variant2(alloc::vec::Vec<Expr>),
-        Variant3(CellReference),
-        Variant4(i32),
-        Variant5(Option<Expr>),
-        Variant6(Vec<Expr>),
-        Variant7(Box<Expr>),
+        Variant1(Option<&'input str>),
+        Variant2(Expr),
+        Variant3(alloc::vec::Vec<Expr>),
+        Variant4(CellReference),
+        Variant5(i32),
+        Variant6(CompareOp),
+        Variant7(Option<Expr>),
         Variant8(String),
+        Variant9(CellError),
+        Variant10(Vec<Expr>),
+        Variant11(Box<Expr>),
+        Variant12(f64),
     }
     const __ACTION: &[i8] = &[
         // State 0
-        26, 27, 3, 0, 0, 0, 0, 0, 0, 0, 20, 21, 22, 23, 24, 25,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 1
-        26, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 51, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 2
-        26, 27, 3, 0, 0, 0, 0, 0, 0, 0, 20, 21, 22, 23, 24, 25,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -20, 0, 12, -20, 13, 0, 0, 52, 53, 54, 55, 56, 57, 0,
         // State 3
-        0, 27, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 48, 0, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 4
-        26, 27, 3, 0, 0, 0, 0, 0, 0, 0, 20, 21, 22, 23, 24, 25,
+        0, 0, 0, 0, 0, 48, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 5
-        26, 27, 3, 0, 0, 0, 0, 0, 0, 0, 20, 21, 22, 23, 24, 25,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 6
-        26, 27, 3, -14, 0, 0, 0, 0, 0, 0, 20, 21, 22, 23, 24, 25,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 7
-        26, 27, 3, 0, 0, 0, 0, 0, 0, 0, 20, 21, 22, 23, 24, 25,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 8
-        26, 27, 3, 0, 0, 0, 0, 0, 0, 0, 20, 21, 22, 23, 24, 25,
+        0, 0, 0, 0, 0, 48, 0, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 9
-        26, 27, 3, -16, 0, 0, 0, 0, 0, 0, 20, 21, 22, 23, 24, 25,
+        0, 0, 51, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 10
-        0, 0, 0, -18, -18, -18, -18, -18, -18, 4, 0, 0, 0, 0, 0, 0,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 11
-        0, 0, 0, 0, 0, 5, 0, 6, 0, 0, 0, 0, 0, 0, 0, 0,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 12
-        0, 0, 0, -34, -34, -34, -34, -34, -34, 0, 0, 0, 0, 0, 0, 0,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 13
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 14
-        0, 0, 0, -19, -19, -19, -19, -19, -19, 0, 0, 0, 0, 0, 0, 0,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, -35, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 15
-        0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 16
-        0, 0, 0, -17, -17, -17, -17, -17, -17, 0, 0, 0, 0, 0, 0, 0,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 17
-        0, 0, 0, -20, -20, -20, -20, -20, -20, 0, 0, 0, 0, 0, 0, 0,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, 0, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 18
-        0, 0, 0, -10, 8, -10, -10, -10, 9, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 51, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 21, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 19
-        0, 0, -27, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        43, 44, 45, 46, 47, 48, 49, 36, 37, 38, 39, 40, 41, 42, 5, 0, 6, -37, 0, 7, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 20
-        0, 0, -26, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 51, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 21
-        0, 0, -25, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -43, 0, -43, -43, -43, -43, -43, -43, 9, -43, -43, -43, -43, -43, -43, -43,
         // State 22
-        0, 0, -29, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 23
-        0, 0, -28, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -41, 0, -41, -41, -41, -41, -41, -41, 0, -41, -41, -41, -41, -41, -41, -41,
         // State 24
-        0, 0, -24, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -42, 0, -42, -42, -42, -42, -42, -42, 0, -42, -42, -42, -42, -42, -42, -42,
         // State 25
-        0, 0, 0, -30, -30, -30, -30, -30, -30, -30, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -55, 0, -55, -55, -55, -55, -55, -55, 0, -55, -55, -55, -55, -55, -55, 14,
         // State 26
-        -7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         // State 27
-        0, 0, 0, -6, -6, -6, -6, -6, -6, -6, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -44, 0, -44, -44, -44, -44, -44, -44, 0, -44, -44, -44, -44, -44, -44, -44,
         // State 28
-        0, 0, 0, 37, 0, 5, 0, 6, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, -12, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -12, -40, 15, -40, -40, -40, -40, -40, -40, 0, -40, -40, -40, -40, -40, -40, -40,
         // State 29
-        0, 0, 0, -31, -31, -31, -31, -31, -31, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -38, 0, -38, -38, -38, -38, -38, -38, 0, -38, -38, -38, -38, -38, -38, -38,
         // State 30
-        0, 0, 0, -8, 8, -8, -8, -8, 9, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -67, 0, -67, -67, -67, -67, -67, -67, 0, -67, -67, -67, -67, -67, -67, 0,
         // State 31
-        0, 0, 0, -9, 8, -9, -9, -9, 9, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -45, 0, -45, -45, -45, -45, -45, -45, 0, -45, -45, -45, -45, -45, -45, -45,
         // State 32
-        0, 0, 0, -13, 0, 5, 39, 6, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 16, 0, -33, 17, -33, -33, -33, 18, 0, -33, -33, -33, -33, -33, -33, 0,
         // State 33
-        0, 0, 0, 40, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -39, 0, -39, -39, -39, -39, -39, -39, 0, -39, -39, -39, -39, -39, -39, -39,
         // State 34
-        0, 0, 0, -32, -32, -32, -32, -32, -32, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -63, 0, -63, -63, -63, -63, -63, -63, 0, -63, -63, -63, -63, -63, -63, 0,
         // State 35
-        0, 0, 0, -33, -33, -33, -33, -33, -33, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -30, 0, -30, -30, -30, -30, -30, -30, 0, -30, -30, -30, -30, -30, -30, -30,
         // State 36
-        0, 0, 0, -21, -21, -21, -21, -21, -21, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -26, 0, -26, -26, -26, -26, -26, -26, 0, -26, -26, -26, -26, -26, -26, -26,
         // State 37
-        0, 0, 0, -15, 0, 5, 41, 6, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -24, 0, -24, -24, -24, -24, -24, -24, 0, -24, -24, -24, -24, -24, -24, -24,
         // State 38
-        -4, -4, -4, -4, 0, 0, 0, 0, 0, 0, -4, -4, -4, -4, -4, -4,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -28, 0, -28, -28, -28, -28, -28, -28, 0, -28, -28, -28, -28, -28, -28, -28,
         // State 39
-        0, 0, 0, -23, -23, -23, -23, -23, -23, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -29, 0, -29, -29, -29, -29, -29, -29, 0, -29, -29, -29, -29, -29, -29, -29,
         // State 40
-        -5, -5, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, -5, -5, -5, -5,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -25, 0, -25, -25, -25, -25, -25, -25, 0, -25, -25, -25, -25, -25, -25, -25,
+        // State 41
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -27, 0, -27, -27, -27, -27, -27, -27, 0, -27, -27, -27, -27, -27, -27, -27,
+        // State 42
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -64, 0, -64, -64, -64, -64, -64, -64, 0, -64, -64, -64, -64, -64, -64, -64,
+        // State 43
+        0, 0, 0, 0, 0, -59, 0, 0, 0, 0, 0, 0, 0, 0, -59, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 44
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -52, 0, -52, -52, -52, -52, -52, -52, 0, -52, -52, -52, -52, -52, -52, -52,
+        // State 45
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -53, 0, -53, -53, -53, -53, -53, -53, 0, -53, -53, -53, -53, -53, -53, -53,
+        // State 46
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -23, 0, -23, -23, -23, -23, -23, -23, 0, -23, -23, -23, -23, -23, -23, -23,
+        // State 47
+        0, 0, -51, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -51, -51, -51, -51, -51, -51, -51, -51, -51, 0, -51, -51, -51, -51, -51, -51, -51,
+        // State 48
+        0, 0, 0, 0, 0, -58, 0, 0, 0, 0, 0, 0, 0, 0, -58, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 49
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -11, 0, -11, -11, -11, -11, -11, -11, -11, -11, -11, -11, -11, -11, -11, -11,
+        // State 50
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -57, 0, -57, -57, -57, -57, -57, -57, -57, -57, -57, -57, -57, -57, -57, -57,
+        // State 51
+        -15, -15, -15, -15, -15, -15, -15, -15, -15, -15, -15, -15, -15, -15, -15, 0, -15, 0, 0, -15, 0, -15, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 52
+        -16, -16, -16, -16, -16, -16, -16, -16, -16, -16, -16, -16, -16, -16, -16, 0, -16, 0, 0, -16, 0, -16, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 53
+        -14, -14, -14, -14, -14, -14, -14, -14, -14, -14, -14, -14, -14, -14, -14, 0, -14, 0, 0, -14, 0, -14, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 54
+        -13, -13, -13, -13, -13, -13, -13, -13, -13, -13, -13, -13, -13, -13, -13, 0, -13, 0, 0, -13, 0, -13, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 55
+        -17, -17, -17, -17, -17, -17, -17, -17, -17, -17, -17, -17, -17, -17, -17, 0, -17, 0, 0, -17, 0, -17, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 56
+        -18, -18, -18, -18, -18, -18, -18, -18, -18, -18, -18, -18, -18, -18, -18, 0, -18, 0, 0, -18, 0, -18, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 57
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -46, 0, -46, -46, -46, -46, -46, -46, 9, -46, -46, -46, -46, -46, -46, -46,
+        // State 58
+        0, 0, -12, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -12, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 59
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -47, 0, -47, -47, -47, -47, -47, -47, 0, -47, -47, -47, -47, -47, -47, -47,
+        // State 60
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 76, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 61
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -66, 0, -66, -66, -66, -66, -66, -66, 0, -66, -66, -66, -66, -66, -66, 0,
+        // State 62
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -65, 0, -65, -65, -65, -65, -65, -65, 0, -65, -65, -65, -65, -65, -65, 0,
+        // State 63
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -56, 0, -56, -56, -56, -56, -56, -56, 0, -56, -56, -56, -56, -56, -56, -56,
+        // State 64
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -10, 0, -10, -10, -10, -10, -10, -10, -10, -10, -10, -10, -10, -10, -10, -10,
+        // State 65
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -19, 0, 12, -19, 13, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 66
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 16, 0, -31, 17, -31, -31, -31, 18, 0, -31, -31, -31, -31, -31, -31, 0,
+        // State 67
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 16, 0, -32, 17, -32, -32, -32, 18, 0, -32, -32, -32, -32, -32, -32, 0,
+        // State 68
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -54, 0, -54, -54, -54, -54, -54, -54, 0, -54, -54, -54, -54, -54, -54, 0,
+        // State 69
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -34, 0, 0, 78, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 70
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 79, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 71
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -62, 0, -62, -62, -62, -62, -62, -62, 0, -62, -62, -62, -62, -62, -62, 0,
+        // State 72
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -60, 0, -60, -60, -60, -60, -60, -60, 0, -60, -60, -60, -60, -60, -60, 0,
+        // State 73
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -61, 0, -61, -61, -61, -61, -61, -61, 0, -61, -61, -61, -61, -61, -61, 0,
+        // State 74
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -9, 0, -9, -9, -9, -9, -9, -9, -9, -9, -9, -9, -9, -9, -9, -9,
+        // State 75
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -48, 0, -48, -48, -48, -48, -48, -48, 0, -48, -48, -48, -48, -48, -48, -48,
+        // State 76
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -36, 0, 0, 81, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 77
+        -6, -6, -6, -6, -6, -6, -6, -6, -6, -6, -6, -6, -6, -6, -6, 0, -6, -6, 0, -6, 0, -6, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // State 78
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -50, 0, -50, -50, -50, -50, -50, -50, 0, -50, -50, -50, -50, -50, -50, -50,
+        // State 79
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -8, 0, -8, -8, -8, -8, -8, -8, -8, -8, -8, -8, -8, -8, -8, -8,
+        // State 80
+        -7, -7, -7, -7, -7, -7, -7, -7, -7, -7, -7, -7, -7, -7, -7, 0, -7, -7, 0, -7, 0, -7, 0, 0, 0, 0, 0, 0, 0, 0, 0,
     ];
     fn __action(state: i8, integer: usize) -> i8 {
-        __ACTION[(state as usize) * 16 + integer]
+        __ACTION[(state as usize) * 31 + integer]
     }
     const __EOF_ACTION: &[i8] = &[
         // State 0
@@ -130,7 +218,7 @@ mod __parse__Formula {
         // State 1
         0,
         // State 2
-        0,
+        -20,
         // State 3
         0,
         // State 4
@@ -146,108 +234,230 @@ mod __parse__Formula {
         // State 9
         0,
         // State 10
-        -18,
+        0,
         // State 11
-        -22,
+        0,
         // State 12
-        -34,
+        0,
         // State 13
-        -35,
+        0,
         // State 14
-        -19,
+        0,
         // State 15
         0,
         // State 16
-        -17,
+        0,
         // State 17
-        -20,
+        0,
         // State 18
-        -10,
+        0,
         // State 19
         0,
         // State 20
         0,
         // State 21
-        0,
+        -43,
         // State 22
-        0,
+        -49,
         // State 23
-        0,
+        -41,
         // State 24
-        0,
+        -42,
         // State 25
-        -30,
+        -55,
         // State 26
-        0,
+        -68,
         // State 27
-        -6,
+        -44,
         // State 28
-        0,
+        -40,
         // State 29
-        -31,
+        -38,
         // State 30
-        -8,
+        -67,
         // State 31
-        -9,
+        -45,
         // State 32
-        0,
+        -33,
         // State 33
-        0,
+        -39,
         // State 34
-        -32,
+        -63,
         // State 35
-        -33,
+        -30,
         // State 36
-        -21,
+        -26,
         // State 37
-        0,
+        -24,
         // State 38
-        0,
+        -28,
         // State 39
-        -23,
+        -29,
         // State 40
+        -25,
+        // State 41
+        -27,
+        // State 42
+        -64,
+        // State 43
+        0,
+        // State 44
+        -52,
+        // State 45
+        -53,
+        // State 46
+        -23,
+        // State 47
+        -51,
+        // State 48
+        0,
+        // State 49
+        -11,
+        // State 50
+        -57,
+        // State 51
+        0,
+        // State 52
+        0,
+        // State 53
+        0,
+        // State 54
+        0,
+        // State 55
+        0,
+        // State 56
+        0,
+        // State 57
+        -46,
+        // State 58
+        0,
+        // State 59
+        -47,
+        // State 60
+        0,
+        // State 61
+        -66,
+        // State 62
+        -65,
+        // State 63
+        -56,
+        // State 64
+        -10,
+        // State 65
+        -19,
+        // State 66
+        -31,
+        // State 67
+        -32,
+        // State 68
+        -54,
+        // State 69
+        0,
+        // State 70
+        0,
+        // State 71
+        -62,
+        // State 72
+        -60,
+        // State 73
+        -61,
+        // State 74
+        -9,
+        // State 75
+        -48,
+        // State 76
+        0,
+        // State 77
+        0,
+        // State 78
+        -50,
+        // State 79
+        -8,
+        // State 80
         0,
     ];
     fn __goto(state: i8, nt: usize) -> i8 {
         match nt {
-            2 => 9,
-            3 => match state {
-                3 => 29,
-                _ => 10,
+            3 => 19,
+            4 => match state {
+                3 => 57,
+                8 => 63,
+                _ => 21,
             },
-            4 => 1,
             5 => match state {
-                2 => 28,
-                6 => 32,
-                9 => 37,
-                _ => 11,
+                4 => 18,
+                _ => 1,
+            },
+            6 => 10,
+            7 => match state {
+                5 => 60,
+                14 => 69,
+                19 => 76,
+                _ => 22,
+            },
+            9 => 23,
+            10 => 24,
+            11 => match state {
+                10 => 65,
+                _ => 2,
+            },
+            12 => 70,
+            13 => 25,
+            14 => 26,
+            15 => 27,
+            16 => match state {
+                3..=4 | 8 => 58,
+                _ => 28,
             },
-            7 => 33,
-            8 => match state {
-                7 => 34,
-                8 => 35,
-                _ => 12,
+            17 => 29,
+            18 => 30,
+            19 => match state {
+                3 => 59,
+                _ => 31,
             },
-            9 => 13,
-            10 => 14,
-            11 => 15,
-            12 => match state {
-                1 => 27,
-                _ => 16,
+            20 => match state {
+                9 => 64,
+                18 => 74,
+                20 => 79,
+                _ => 49,
             },
-            13 => 17,
-            14 => match state {
-                4 => 30,
-                5 => 31,
-                _ => 18,
+            21 => 3,
+            22 => match state {
+                11 => 66,
+                12 => 67,
+                _ => 32,
+            },
+            23 => 33,
+            24 => match state {
+                6 => 61,
+                7 => 62,
+                13 => 68,
+                15 => 71,
+                16 => 72,
+                17 => 73,
+                _ => 34,
             },
             _ => 0,
         }
     }
     #[allow(clippy::needless_raw_string_hashes)]
     const __TERMINAL: &[&str] = &[
+        r###"r#"\"([^\"]|\"\")*\""#"###,
+        r###"r#"'[^']+'!"#"###,
         r###"r#"[0-9]+"#"###,
-        r###"r#"[A-Z]+"#"###,
+        r###"r#"[0-9]+\\.[0-9]*([eE][+-]?[0-9]+)?|\\.[0-9]+([eE][+-]?[0-9]+)?|[0-9]+[eE][+-]?[0-9]+"#"###,
+        r###"r#"[A-Za-z]*_[A-Za-z0-9_]*"#"###,
+        r###"r#"[A-Za-z]+"#"###,
+        r###"r#"[A-Za-z_][A-Za-z0-9_]*!"#"###,
+        r###""#CONV!""###,
+        r###""#CYCLE!""###,
+        r###""#DIV/0!""###,
+        r###""#NAME?""###,
+        r###""#NUM!""###,
+        r###""#REF!""###,
+        r###""#VALUE!""###,
+        r###""$""###,
+        r###""%""###,
         r###""(""###,
         r###"")""###,
         r###""*""###,
@@ -256,12 +466,13 @@ mod __parse__Formula {
         r###""-""###,
         r###""/""###,
         r###"":""###,
-        r###""Avg""###,
-        r###""Max""###,
-        r###""Min""###,
-        r###""Sleep""###,
-        r###""Stdev""###,
-        r###""Sum""###,
+        r###""<""###,
+        r###""<=""###,
+        r###""<>""###,
+        r###""=""###,
+        r###"">""###,
+        r###"">=""###,
+        r###""^""###,
     ];
     fn __expected_tokens(__state: i8) -> alloc::vec::Vec<alloc::string::String> {
         __TERMINAL.iter().enumerate().filter_map(|(index, terminal)| {
@@ -330,7 +541,7 @@ mod __parse__Formula {
 
         #[inline]
         fn error_action(&self, state: i8) -> i8 {
-            __action(state, 16 - 1)
+            __action(state, 31 - 1)
         }
 
         #[inline]
@@ -414,6 +625,21 @@ mod __parse__Formula {
             Token(13, _) if true => Some(13),
             Token(14, _) if true => Some(14),
             Token(15, _) if true => Some(15),
+            Token(16, _) if true => Some(16),
+            Token(17, _) if true => Some(17),
+            Token(18, _) if true => Some(18),
+            Token(19, _) if true => Some(19),
+            Token(20, _) if true => Some(20),
+            Token(21, _) if true => Some(21),
+            Token(22, _) if true => Some(22),
+            Token(23, _) if true => Some(23),
+            Token(24, _) if true => Some(24),
+            Token(25, _) if true => Some(25),
+            Token(26, _) if true => Some(26),
+            Token(27, _) if true => Some(27),
+            Token(28, _) if true => Some(28),
+            Token(29, _) if true => Some(29),
+            Token(30, _) if true => Some(30),
             _ => None,
         }
     }
@@ -426,8 +652,8 @@ mod __parse__Formula {
     ) -> __Symbol<'input>
     {
         #[allow(clippy::manual_range_patterns)]match __token_index {
-            0 | 1 | 2 | 3 | 4 | 5 | 6 | 7 | 8 | 9 | 10 | 11 | 12 | 13 | 14 | 15 => match __token {
-                Token(0, __tok0) | Token(1, __tok0) | Token(2, __tok0) | Token(3, __tok0) | Token(4, __tok0) | Token(5, __tok0) | Token(6, __tok0) | Token(7, __tok0) | Token(8, __tok0) | Token(9, __tok0) | Token(10, __tok0) | Token(11, __tok0) | Token(12, __tok0) | Token(13, __tok0) | Token(14, __tok0) | Token(15, __tok0) if true => __Symbol::Variant0(__tok0),
+            0 | 1 | 2 | 3 | 4 | 5 | 6 | 7 | 8 | 9 | 10 | 11 | 12 | 13 | 14 | 15 | 16 | 17 | 18 | 19 | 20 | 21 | 22 | 23 | 24 | 25 | 26 | 27 | 28 | 29 | 30 => match __token {
+                Token(0, __tok0) | Token(1, __tok0) | Token(2, __tok0) | Token(3, __tok0) | Token(4, __tok0) | Token(5, __tok0) | Token(6, __tok0) | Token(7, __tok0) | Token(8, __tok0) | Token(9, __tok0) | Token(10, __tok0) | Token(11, __tok0) | Token(12, __tok0) | Token(13, __tok0) | Token(14, __tok0) | Token(15, __tok0) | Token(16, __tok0) | Token(17, __tok0) | Token(18, __tok0) | Token(19, __tok0) | Token(20, __tok0) | Token(21, __tok0) | Token(22, __tok0) | Token(23, __tok0) | Token(24, __tok0) | Token(25, __tok0) | Token(26, __tok0) | Token(27, __tok0) | Token(28, __tok0) | Token(29, __tok0) | Token(30, __tok0) if true => __Symbol::Variant0(__tok0),
                 _ => unreachable!(),
             },
             _ => unreachable!(),
@@ -443,31 +669,31 @@ mod __parse__Formula {
         match __reduce_index {
             0 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 2,
+                    states_to_pop: 1,
                     nonterminal_produced: 0,
                 }
             }
             1 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 0,
-                    nonterminal_produced: 1,
+                    nonterminal_produced: 0,
                 }
             }
             2 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 1,
+                    states_to_pop: 2,
                     nonterminal_produced: 1,
                 }
             }
             3 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 2,
+                    states_to_pop: 0,
                     nonterminal_produced: 2,
                 }
             }
             4 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 3,
+                    states_to_pop: 1,
                     nonterminal_produced: 2,
                 }
             }
@@ -479,270 +705,468 @@ mod __parse__Formula {
             }
             6 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 1,
-                    nonterminal_produced: 4,
+                    states_to_pop: 3,
+                    nonterminal_produced: 3,
                 }
             }
             7 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 3,
-                    nonterminal_produced: 5,
+                    states_to_pop: 4,
+                    nonterminal_produced: 4,
                 }
             }
             8 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 3,
-                    nonterminal_produced: 5,
+                    nonterminal_produced: 4,
                 }
             }
             9 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 1,
-                    nonterminal_produced: 5,
+                    states_to_pop: 3,
+                    nonterminal_produced: 4,
                 }
             }
             10 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 1,
-                    nonterminal_produced: 6,
+                    states_to_pop: 2,
+                    nonterminal_produced: 4,
                 }
             }
             11 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 0,
-                    nonterminal_produced: 6,
+                    states_to_pop: 1,
+                    nonterminal_produced: 5,
                 }
             }
             12 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 7,
+                    nonterminal_produced: 6,
                 }
             }
             13 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 0,
-                    nonterminal_produced: 7,
+                    states_to_pop: 1,
+                    nonterminal_produced: 6,
                 }
             }
             14 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 2,
-                    nonterminal_produced: 7,
+                    states_to_pop: 1,
+                    nonterminal_produced: 6,
                 }
             }
             15 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 7,
+                    nonterminal_produced: 6,
                 }
             }
             16 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 8,
+                    nonterminal_produced: 6,
                 }
             }
             17 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 8,
+                    nonterminal_produced: 6,
                 }
             }
             18 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 1,
-                    nonterminal_produced: 8,
+                    states_to_pop: 3,
+                    nonterminal_produced: 7,
                 }
             }
             19 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 8,
+                    nonterminal_produced: 7,
                 }
             }
             20 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 3,
+                    states_to_pop: 1,
                     nonterminal_produced: 8,
                 }
             }
             21 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 1,
-                    nonterminal_produced: 9,
+                    states_to_pop: 0,
+                    nonterminal_produced: 8,
                 }
             }
             22 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 4,
-                    nonterminal_produced: 10,
+                    states_to_pop: 1,
+                    nonterminal_produced: 9,
                 }
             }
             23 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 11,
+                    nonterminal_produced: 10,
                 }
             }
             24 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 11,
+                    nonterminal_produced: 10,
                 }
             }
             25 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 11,
+                    nonterminal_produced: 10,
                 }
             }
             26 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 11,
+                    nonterminal_produced: 10,
                 }
             }
             27 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 11,
+                    nonterminal_produced: 10,
                 }
             }
             28 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 11,
+                    nonterminal_produced: 10,
                 }
             }
             29 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 12,
+                    nonterminal_produced: 10,
                 }
             }
             30 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 3,
-                    nonterminal_produced: 13,
+                    nonterminal_produced: 11,
                 }
             }
             31 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 3,
-                    nonterminal_produced: 14,
+                    nonterminal_produced: 11,
                 }
             }
             32 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop: 3,
-                    nonterminal_produced: 14,
+                    states_to_pop: 1,
+                    nonterminal_produced: 11,
                 }
             }
             33 => {
                 __state_machine::SimulatedReduce::Reduce {
                     states_to_pop: 1,
-                    nonterminal_produced: 14,
+                    nonterminal_produced: 12,
                 }
             }
-            34 => __state_machine::SimulatedReduce::Accept,
-            _ => panic!("invalid reduction index {}", __reduce_index)
-        }
-    }
-    pub struct FormulaParser {
-        builder: __lalrpop_util::lexer::MatcherBuilder,
-        _priv: (),
-    }
-
-    impl Default for FormulaParser { fn default() -> Self { Self::new() } }
-    impl FormulaParser {
-        pub fn new() -> FormulaParser {
-            let __builder = super::__intern_token::new_builder();
-            FormulaParser {
-                builder: __builder,
-                _priv: (),
-            }
-        }
-
-        #[allow(dead_code)]
-        pub fn parse<
-            'input,
-        >(
-            &self,
-            input: &'input str,
-        ) -> Result<Box<Expr>, __lalrpop_util::ParseError<usize, Token<'input>, &'static str>>
-        {
-            let mut __tokens = self.builder.matcher(input);
-            __state_machine::Parser::drive(
-                __StateMachine {
-                    input,
-                    __phantom: core::marker::PhantomData::<(&())>,
-                },
-                __tokens,
-            )
-        }
-    }
-    fn __accepts<
-        'input,
-    >(
-        __error_state: Option<i8>,
-        __states: &[i8],
-        __opt_integer: Option<usize>,
-        _: core::marker::PhantomData<(&'input ())>,
-    ) -> bool
-    {
-        let mut __states = __states.to_vec();
-        __states.extend(__error_state);
-        loop {
-            let mut __states_len = __states.len();
-            let __top = __states[__states_len - 1];
-            let __action = match __opt_integer {
-                None => __EOF_ACTION[__top as usize],
-                Some(__integer) => __action(__top, __integer),
-            };
-            if __action == 0 { return false; }
-            if __action > 0 { return true; }
-            let (__to_pop, __nt) = match __simulate_reduce(-(__action + 1), core::marker::PhantomData::<(&())>) {
+            34 => {
                 __state_machine::SimulatedReduce::Reduce {
-                    states_to_pop, nonterminal_produced
-                } => (states_to_pop, nonterminal_produced),
-                __state_machine::SimulatedReduce::Accept => return true,
-            };
-            __states_len -= __to_pop;
-            __states.truncate(__states_len);
-            let __top = __states[__states_len - 1];
-            let __next_state = __goto(__top, __nt);
-            __states.push(__next_state);
-        }
-    }
-    fn __reduce<
-        'input,
-    >(
-        input: &'input str,
-        __action: i8,
-        __lookahead_start: Option<&usize>,
-        __states: &mut alloc::vec::Vec<i8>,
-        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
-        _: core::marker::PhantomData<(&'input ())>,
-    ) -> Option<Result<Box<Expr>,__lalrpop_util::ParseError<usize, Token<'input>, &'static str>>>
-    {
-        let (__pop_states, __nonterminal) = match __action {
-            0 => {
-                __reduce0(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+                    states_to_pop: 0,
+                    nonterminal_produced: 12,
+                }
             }
-            1 => {
-                __reduce1(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            35 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 2,
+                    nonterminal_produced: 12,
+                }
             }
-            2 => {
-                __reduce2(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            36 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 12,
+                }
             }
-            3 => {
-                __reduce3(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            37 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 13,
+                }
             }
-            4 => {
-                __reduce4(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            38 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 13,
+                }
             }
-            5 => {
+            39 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 13,
+                }
+            }
+            40 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 13,
+                }
+            }
+            41 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 13,
+                }
+            }
+            42 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 13,
+                }
+            }
+            43 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 13,
+                }
+            }
+            44 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 13,
+                }
+            }
+            45 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 2,
+                    nonterminal_produced: 13,
+                }
+            }
+            46 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 2,
+                    nonterminal_produced: 13,
+                }
+            }
+            47 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 3,
+                    nonterminal_produced: 13,
+                }
+            }
+            48 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 14,
+                }
+            }
+            49 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 4,
+                    nonterminal_produced: 15,
+                }
+            }
+            50 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 16,
+                }
+            }
+            51 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 17,
+                }
+            }
+            52 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 17,
+                }
+            }
+            53 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 3,
+                    nonterminal_produced: 18,
+                }
+            }
+            54 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 18,
+                }
+            }
+            55 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 3,
+                    nonterminal_produced: 19,
+                }
+            }
+            56 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 20,
+                }
+            }
+            57 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 21,
+                }
+            }
+            58 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 21,
+                }
+            }
+            59 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 3,
+                    nonterminal_produced: 22,
+                }
+            }
+            60 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 3,
+                    nonterminal_produced: 22,
+                }
+            }
+            61 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 3,
+                    nonterminal_produced: 22,
+                }
+            }
+            62 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 22,
+                }
+            }
+            63 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 23,
+                }
+            }
+            64 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 2,
+                    nonterminal_produced: 24,
+                }
+            }
+            65 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 2,
+                    nonterminal_produced: 24,
+                }
+            }
+            66 => {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop: 1,
+                    nonterminal_produced: 24,
+                }
+            }
+            67 => __state_machine::SimulatedReduce::Accept,
+            _ => panic!("invalid reduction index {}", __reduce_index)
+        }
+    }
+    pub struct FormulaParser {
+        builder: __lalrpop_util::lexer::MatcherBuilder,
+        _priv: (),
+    }
+
+    impl Default for FormulaParser { fn default() -> Self { Self::new() } }
+    impl FormulaParser {
+        pub fn new() -> FormulaParser {
+            let __builder = super::__intern_token::new_builder();
+            FormulaParser {
+                builder: __builder,
+                _priv: (),
+            }
+        }
+
+        #[allow(dead_code)]
+        pub fn parse<
+            'input,
+        >(
+            &self,
+            input: &'input str,
+        ) -> Result<Box<Expr>, __lalrpop_util::ParseError<usize, Token<'input>, &'static str>>
+        {
+            let mut __tokens = self.builder.matcher(input);
+            __state_machine::Parser::drive(
+                __StateMachine {
+                    input,
+                    __phantom: core::marker::PhantomData::<(&())>,
+                },
+                __tokens,
+            )
+        }
+    }
+    fn __accepts<
+        'input,
+    >(
+        __error_state: Option<i8>,
+        __states: &[i8],
+        __opt_integer: Option<usize>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> bool
+    {
+        let mut __states = __states.to_vec();
+        __states.extend(__error_state);
+        loop {
+            let mut __states_len = __states.len();
+            let __top = __states[__states_len - 1];
+            let __action = match __opt_integer {
+                None => __EOF_ACTION[__top as usize],
+                Some(__integer) => __action(__top, __integer),
+            };
+            if __action == 0 { return false; }
+            if __action > 0 { return true; }
+            let (__to_pop, __nt) = match __simulate_reduce(-(__action + 1), core::marker::PhantomData::<(&())>) {
+                __state_machine::SimulatedReduce::Reduce {
+                    states_to_pop, nonterminal_produced
+                } => (states_to_pop, nonterminal_produced),
+                __state_machine::SimulatedReduce::Accept => return true,
+            };
+            __states_len -= __to_pop;
+            __states.truncate(__states_len);
+            let __top = __states[__states_len - 1];
+            let __next_state = __goto(__top, __nt);
+            __states.push(__next_state);
+        }
+    }
+    fn __reduce<
+        'input,
+    >(
+        input: &'input str,
+        __action: i8,
+        __lookahead_start: Option<&usize>,
+        __states: &mut alloc::vec::Vec<i8>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> Option<Result<Box<Expr>,__lalrpop_util::ParseError<usize, Token<'input>, &'static str>>>
+    {
+        let (__pop_states, __nonterminal) = match __action {
+            0 => {
+                __reduce0(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            1 => {
+                __reduce1(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            2 => {
+                __reduce2(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            3 => {
+                __reduce3(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            4 => {
+                __reduce4(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            5 => {
                 __reduce5(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
             }
             6 => {
@@ -761,7 +1185,16 @@ mod __parse__Formula {
                 __reduce10(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
             }
             11 => {
-                __reduce11(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+                // Column = Name => ActionFn(37);
+                let __sym0 = __pop_Variant8(__symbols);
+                let __start = __sym0.0;
+                let __end = __sym0.2;
+                let __nt = match super::__action37::<>(input, __sym0) {
+                    Ok(v) => v,
+                    Err(e) => return Some(Err(e)),
+                };
+                __symbols.push((__start, __Symbol::Variant5(__nt), __end));
+                (1, 5)
             }
             12 => {
                 __reduce12(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
@@ -830,8 +1263,116 @@ mod __parse__Formula {
                 __reduce33(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
             }
             34 => {
+                __reduce34(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            35 => {
+                __reduce35(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            36 => {
+                __reduce36(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            37 => {
+                __reduce37(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            38 => {
+                __reduce38(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            39 => {
+                __reduce39(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            40 => {
+                __reduce40(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            41 => {
+                __reduce41(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            42 => {
+                __reduce42(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            43 => {
+                __reduce43(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            44 => {
+                __reduce44(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            45 => {
+                __reduce45(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            46 => {
+                __reduce46(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            47 => {
+                __reduce47(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            48 => {
+                __reduce48(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            49 => {
+                __reduce49(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            50 => {
+                __reduce50(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            51 => {
+                __reduce51(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            52 => {
+                __reduce52(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            53 => {
+                __reduce53(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            54 => {
+                __reduce54(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            55 => {
+                __reduce55(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            56 => {
+                // Row = r#"[0-9]+"# => ActionFn(42);
+                let __sym0 = __pop_Variant0(__symbols);
+                let __start = __sym0.0;
+                let __end = __sym0.2;
+                let __nt = match super::__action42::<>(input, __sym0) {
+                    Ok(v) => v,
+                    Err(e) => return Some(Err(e)),
+                };
+                __symbols.push((__start, __Symbol::Variant5(__nt), __end));
+                (1, 20)
+            }
+            57 => {
+                __reduce57(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            58 => {
+                __reduce58(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            59 => {
+                __reduce59(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            60 => {
+                __reduce60(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            61 => {
+                __reduce61(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            62 => {
+                __reduce62(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            63 => {
+                __reduce63(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            64 => {
+                __reduce64(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            65 => {
+                __reduce65(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            66 => {
+                __reduce66(input, __lookahead_start, __symbols, core::marker::PhantomData::<(&())>)
+            }
+            67 => {
                 // __Formula = Formula => ActionFn(0);
-                let __sym0 = __pop_Variant7(__symbols);
+                let __sym0 = __pop_Variant11(__symbols);
                 let __start = __sym0.0;
                 let __end = __sym0.2;
                 let __nt = super::__action0::<>(input, __sym0);
@@ -850,47 +1391,80 @@ mod __parse__Formula {
     fn __symbol_type_mismatch() -> ! {
         panic!("symbol type mismatch")
     }
-    fn __pop_Variant7<
+    fn __pop_Variant11<
       'input,
     >(
         __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
     ) -> (usize, Box<Expr>, usize)
      {
         match __symbols.pop() {
-            Some((__l, __Symbol::Variant7(__v), __r)) => (__l, __v, __r),
+            Some((__l, __Symbol::Variant11(__v), __r)) => (__l, __v, __r),
             _ => __symbol_type_mismatch()
         }
     }
-    fn __pop_Variant3<
+    fn __pop_Variant9<
+      'input,
+    >(
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
+    ) -> (usize, CellError, usize)
+     {
+        match __symbols.pop() {
+            Some((__l, __Symbol::Variant9(__v), __r)) => (__l, __v, __r),
+            _ => __symbol_type_mismatch()
+        }
+    }
+    fn __pop_Variant4<
       'input,
     >(
         __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
     ) -> (usize, CellReference, usize)
      {
         match __symbols.pop() {
-            Some((__l, __Symbol::Variant3(__v), __r)) => (__l, __v, __r),
+            Some((__l, __Symbol::Variant4(__v), __r)) => (__l, __v, __r),
             _ => __symbol_type_mismatch()
         }
     }
-    fn __pop_Variant1<
+    fn __pop_Variant6<
+      'input,
+    >(
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
+    ) -> (usize, CompareOp, usize)
+     {
+        match __symbols.pop() {
+            Some((__l, __Symbol::Variant6(__v), __r)) => (__l, __v, __r),
+            _ => __symbol_type_mismatch()
+        }
+    }
+    fn __pop_Variant2<
       'input,
     >(
         __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
     ) -> (usize, Expr, usize)
      {
         match __symbols.pop() {
-            Some((__l, __Symbol::Variant1(__v), __r)) => (__l, __v, __r),
+            Some((__l, __Symbol::Variant2(__v), __r)) => (__l, __v, __r),
             _ => __symbol_type_mismatch()
         }
     }
-    fn __pop_Variant5<
+    fn __pop_Variant7<
       'input,
     >(
         __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
     ) -> (usize, Option<Expr>, usize)
      {
         match __symbols.pop() {
-            Some((__l, __Symbol::Variant5(__v), __r)) => (__l, __v, __r),
+            Some((__l, __Symbol::Variant7(__v), __r)) => (__l, __v, __r),
+            _ => __symbol_type_mismatch()
+        }
+    }
+    fn __pop_Variant1<
+      'input,
+    >(
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
+    ) -> (usize, Option<&'input str>, usize)
+     {
+        match __symbols.pop() {
+            Some((__l, __Symbol::Variant1(__v), __r)) => (__l, __v, __r),
             _ => __symbol_type_mismatch()
         }
     }
@@ -905,51 +1479,540 @@ mod __parse__Formula {
             _ => __symbol_type_mismatch()
         }
     }
-    fn __pop_Variant6<
-      'input,
+    fn __pop_Variant10<
+      'input,
+    >(
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
+    ) -> (usize, Vec<Expr>, usize)
+     {
+        match __symbols.pop() {
+            Some((__l, __Symbol::Variant10(__v), __r)) => (__l, __v, __r),
+            _ => __symbol_type_mismatch()
+        }
+    }
+    fn __pop_Variant3<
+      'input,
+    >(
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
+    ) -> (usize, alloc::vec::Vec<Expr>, usize)
+     {
+        match __symbols.pop() {
+            Some((__l, __Symbol::Variant3(__v), __r)) => (__l, __v, __r),
+            _ => __symbol_type_mismatch()
+        }
+    }
+    fn __pop_Variant12<
+      'input,
+    >(
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
+    ) -> (usize, f64, usize)
+     {
+        match __symbols.pop() {
+            Some((__l, __Symbol::Variant12(__v), __r)) => (__l, __v, __r),
+            _ => __symbol_type_mismatch()
+        }
+    }
+    fn __pop_Variant5<
+      'input,
+    >(
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
+    ) -> (usize, i32, usize)
+     {
+        match __symbols.pop() {
+            Some((__l, __Symbol::Variant5(__v), __r)) => (__l, __v, __r),
+            _ => __symbol_type_mismatch()
+        }
+    }
+    fn __pop_Variant0<
+      'input,
+    >(
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
+    ) -> (usize, &'input str, usize)
+     {
+        match __symbols.pop() {
+            Some((__l, __Symbol::Variant0(__v), __r)) => (__l, __v, __r),
+            _ => __symbol_type_mismatch()
+        }
+    }
+    fn __reduce0<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // "$"? = "$" => ActionFn(53);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action53::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
+        (1, 0)
+    }
+    fn __reduce1<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // "$"? =  => ActionFn(54);
+        let __start = __lookahead_start.cloned().or_else(|| __symbols.last().map(|s| s.2)).unwrap_or_default();
+        let __end = __start;
+        let __nt = super::__action54::<>(input, &__start, &__end);
+        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
+        (0, 0)
+    }
+    fn __reduce2<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // (<Comparison> ",") = Comparison, "," => ActionFn(59);
+        assert!(__symbols.len() >= 2);
+        let __sym1 = __pop_Variant0(__symbols);
+        let __sym0 = __pop_Variant2(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym1.2;
+        let __nt = super::__action59::<>(input, __sym0, __sym1);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (2, 1)
+    }
+    fn __reduce3<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // (<Comparison> ",")* =  => ActionFn(57);
+        let __start = __lookahead_start.cloned().or_else(|| __symbols.last().map(|s| s.2)).unwrap_or_default();
+        let __end = __start;
+        let __nt = super::__action57::<>(input, &__start, &__end);
+        __symbols.push((__start, __Symbol::Variant3(__nt), __end));
+        (0, 2)
+    }
+    fn __reduce4<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // (<Comparison> ",")* = (<Comparison> ",")+ => ActionFn(58);
+        let __sym0 = __pop_Variant3(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action58::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant3(__nt), __end));
+        (1, 2)
+    }
+    fn __reduce5<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // (<Comparison> ",")+ = Comparison, "," => ActionFn(66);
+        assert!(__symbols.len() >= 2);
+        let __sym1 = __pop_Variant0(__symbols);
+        let __sym0 = __pop_Variant2(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym1.2;
+        let __nt = super::__action66::<>(input, __sym0, __sym1);
+        __symbols.push((__start, __Symbol::Variant3(__nt), __end));
+        (2, 3)
+    }
+    fn __reduce6<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // (<Comparison> ",")+ = (<Comparison> ",")+, Comparison, "," => ActionFn(67);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant0(__symbols);
+        let __sym1 = __pop_Variant2(__symbols);
+        let __sym0 = __pop_Variant3(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym2.2;
+        let __nt = super::__action67::<>(input, __sym0, __sym1, __sym2);
+        __symbols.push((__start, __Symbol::Variant3(__nt), __end));
+        (3, 3)
+    }
+    fn __reduce7<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // CellRef = "$", Column, "$", Row => ActionFn(62);
+        assert!(__symbols.len() >= 4);
+        let __sym3 = __pop_Variant5(__symbols);
+        let __sym2 = __pop_Variant0(__symbols);
+        let __sym1 = __pop_Variant5(__symbols);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym3.2;
+        let __nt = super::__action62::<>(input, __sym0, __sym1, __sym2, __sym3);
+        __symbols.push((__start, __Symbol::Variant4(__nt), __end));
+        (4, 4)
+    }
+    fn __reduce8<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // CellRef = "$", Column, Row => ActionFn(63);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant5(__symbols);
+        let __sym1 = __pop_Variant5(__symbols);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym2.2;
+        let __nt = super::__action63::<>(input, __sym0, __sym1, __sym2);
+        __symbols.push((__start, __Symbol::Variant4(__nt), __end));
+        (3, 4)
+    }
+    fn __reduce9<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // CellRef = Column, "$", Row => ActionFn(64);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant5(__symbols);
+        let __sym1 = __pop_Variant0(__symbols);
+        let __sym0 = __pop_Variant5(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym2.2;
+        let __nt = super::__action64::<>(input, __sym0, __sym1, __sym2);
+        __symbols.push((__start, __Symbol::Variant4(__nt), __end));
+        (3, 4)
+    }
+    fn __reduce10<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // CellRef = Column, Row => ActionFn(65);
+        assert!(__symbols.len() >= 2);
+        let __sym1 = __pop_Variant5(__symbols);
+        let __sym0 = __pop_Variant5(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym1.2;
+        let __nt = super::__action65::<>(input, __sym0, __sym1);
+        __symbols.push((__start, __Symbol::Variant4(__nt), __end));
+        (2, 4)
+    }
+    fn __reduce12<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // CompareOp = "=" => ActionFn(4);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action4::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant6(__nt), __end));
+        (1, 6)
+    }
+    fn __reduce13<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // CompareOp = "<>" => ActionFn(5);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action5::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant6(__nt), __end));
+        (1, 6)
+    }
+    fn __reduce14<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // CompareOp = "<" => ActionFn(6);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action6::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant6(__nt), __end));
+        (1, 6)
+    }
+    fn __reduce15<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // CompareOp = "<=" => ActionFn(7);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action7::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant6(__nt), __end));
+        (1, 6)
+    }
+    fn __reduce16<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // CompareOp = ">" => ActionFn(8);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action8::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant6(__nt), __end));
+        (1, 6)
+    }
+    fn __reduce17<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // CompareOp = ">=" => ActionFn(9);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action9::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant6(__nt), __end));
+        (1, 6)
+    }
+    fn __reduce18<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // Comparison = Expr, CompareOp, Expr => ActionFn(2);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant2(__symbols);
+        let __sym1 = __pop_Variant6(__symbols);
+        let __sym0 = __pop_Variant2(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym2.2;
+        let __nt = super::__action2::<>(input, __sym0, __sym1, __sym2);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (3, 7)
+    }
+    fn __reduce19<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // Comparison = Expr => ActionFn(3);
+        let __sym0 = __pop_Variant2(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action3::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 7)
+    }
+    fn __reduce20<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // Comparison? = Comparison => ActionFn(55);
+        let __sym0 = __pop_Variant2(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action55::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant7(__nt), __end));
+        (1, 8)
+    }
+    fn __reduce21<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // Comparison? =  => ActionFn(56);
+        let __start = __lookahead_start.cloned().or_else(|| __symbols.last().map(|s| s.2)).unwrap_or_default();
+        let __end = __start;
+        let __nt = super::__action56::<>(input, &__start, &__end);
+        __symbols.push((__start, __Symbol::Variant7(__nt), __end));
+        (0, 8)
+    }
+    fn __reduce22<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // DefinedName = r#"[A-Za-z]*_[A-Za-z0-9_]*"# => ActionFn(39);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action39::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant8(__nt), __end));
+        (1, 9)
+    }
+    fn __reduce23<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // ErrorLiteral = "#DIV/0!" => ActionFn(44);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action44::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant9(__nt), __end));
+        (1, 10)
+    }
+    fn __reduce24<
+        'input,
     >(
-        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
-    ) -> (usize, Vec<Expr>, usize)
-     {
-        match __symbols.pop() {
-            Some((__l, __Symbol::Variant6(__v), __r)) => (__l, __v, __r),
-            _ => __symbol_type_mismatch()
-        }
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // ErrorLiteral = "#REF!" => ActionFn(45);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action45::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant9(__nt), __end));
+        (1, 10)
     }
-    fn __pop_Variant2<
-      'input,
+    fn __reduce25<
+        'input,
     >(
-        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
-    ) -> (usize, alloc::vec::Vec<Expr>, usize)
-     {
-        match __symbols.pop() {
-            Some((__l, __Symbol::Variant2(__v), __r)) => (__l, __v, __r),
-            _ => __symbol_type_mismatch()
-        }
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // ErrorLiteral = "#CYCLE!" => ActionFn(46);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action46::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant9(__nt), __end));
+        (1, 10)
     }
-    fn __pop_Variant4<
-      'input,
+    fn __reduce26<
+        'input,
     >(
-        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
-    ) -> (usize, i32, usize)
-     {
-        match __symbols.pop() {
-            Some((__l, __Symbol::Variant4(__v), __r)) => (__l, __v, __r),
-            _ => __symbol_type_mismatch()
-        }
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // ErrorLiteral = "#VALUE!" => ActionFn(47);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action47::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant9(__nt), __end));
+        (1, 10)
     }
-    fn __pop_Variant0<
-      'input,
+    fn __reduce27<
+        'input,
     >(
-        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>
-    ) -> (usize, &'input str, usize)
-     {
-        match __symbols.pop() {
-            Some((__l, __Symbol::Variant0(__v), __r)) => (__l, __v, __r),
-            _ => __symbol_type_mismatch()
-        }
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // ErrorLiteral = "#NAME?" => ActionFn(48);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action48::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant9(__nt), __end));
+        (1, 10)
     }
-    fn __reduce0<
+    fn __reduce28<
         'input,
     >(
         input: &'input str,
@@ -958,17 +2021,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // (<Expr> ",") = Expr, "," => ActionFn(29);
-        assert!(__symbols.len() >= 2);
-        let __sym1 = __pop_Variant0(__symbols);
-        let __sym0 = __pop_Variant1(__symbols);
+        // ErrorLiteral = "#NUM!" => ActionFn(49);
+        let __sym0 = __pop_Variant0(__symbols);
         let __start = __sym0.0;
-        let __end = __sym1.2;
-        let __nt = super::__action29::<>(input, __sym0, __sym1);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (2, 0)
+        let __end = __sym0.2;
+        let __nt = super::__action49::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant9(__nt), __end));
+        (1, 10)
     }
-    fn __reduce1<
+    fn __reduce29<
         'input,
     >(
         input: &'input str,
@@ -977,14 +2038,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // (<Expr> ",")* =  => ActionFn(27);
-        let __start = __lookahead_start.cloned().or_else(|| __symbols.last().map(|s| s.2)).unwrap_or_default();
-        let __end = __start;
-        let __nt = super::__action27::<>(input, &__start, &__end);
-        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
-        (0, 1)
+        // ErrorLiteral = "#CONV!" => ActionFn(50);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action50::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant9(__nt), __end));
+        (1, 10)
     }
-    fn __reduce2<
+    fn __reduce30<
         'input,
     >(
         input: &'input str,
@@ -993,15 +2055,18 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // (<Expr> ",")* = (<Expr> ",")+ => ActionFn(28);
+        // Expr = Expr, "+", Term => ActionFn(10);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant2(__symbols);
+        let __sym1 = __pop_Variant0(__symbols);
         let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
-        let __end = __sym0.2;
-        let __nt = super::__action28::<>(input, __sym0);
+        let __end = __sym2.2;
+        let __nt = super::__action10::<>(input, __sym0, __sym1, __sym2);
         __symbols.push((__start, __Symbol::Variant2(__nt), __end));
-        (1, 1)
+        (3, 11)
     }
-    fn __reduce3<
+    fn __reduce31<
         'input,
     >(
         input: &'input str,
@@ -1010,17 +2075,18 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // (<Expr> ",")+ = Expr, "," => ActionFn(32);
-        assert!(__symbols.len() >= 2);
+        // Expr = Expr, "-", Term => ActionFn(11);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant2(__symbols);
         let __sym1 = __pop_Variant0(__symbols);
-        let __sym0 = __pop_Variant1(__symbols);
+        let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
-        let __end = __sym1.2;
-        let __nt = super::__action32::<>(input, __sym0, __sym1);
+        let __end = __sym2.2;
+        let __nt = super::__action11::<>(input, __sym0, __sym1, __sym2);
         __symbols.push((__start, __Symbol::Variant2(__nt), __end));
-        (2, 2)
+        (3, 11)
     }
-    fn __reduce4<
+    fn __reduce32<
         'input,
     >(
         input: &'input str,
@@ -1029,18 +2095,48 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // (<Expr> ",")+ = (<Expr> ",")+, Expr, "," => ActionFn(33);
-        assert!(__symbols.len() >= 3);
-        let __sym2 = __pop_Variant0(__symbols);
-        let __sym1 = __pop_Variant1(__symbols);
+        // Expr = Term => ActionFn(12);
         let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
-        let __end = __sym2.2;
-        let __nt = super::__action33::<>(input, __sym0, __sym1, __sym2);
+        let __end = __sym0.2;
+        let __nt = super::__action12::<>(input, __sym0);
         __symbols.push((__start, __Symbol::Variant2(__nt), __end));
-        (3, 2)
+        (1, 11)
     }
-    fn __reduce5<
+    fn __reduce33<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // ExprList = Comparison => ActionFn(70);
+        let __sym0 = __pop_Variant2(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action70::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant10(__nt), __end));
+        (1, 12)
+    }
+    fn __reduce34<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // ExprList =  => ActionFn(71);
+        let __start = __lookahead_start.cloned().or_else(|| __symbols.last().map(|s| s.2)).unwrap_or_default();
+        let __end = __start;
+        let __nt = super::__action71::<>(input, &__start, &__end);
+        __symbols.push((__start, __Symbol::Variant10(__nt), __end));
+        (0, 12)
+    }
+    fn __reduce35<
         'input,
     >(
         input: &'input str,
@@ -1049,17 +2145,17 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // CellRef = Column, Number => ActionFn(16);
+        // ExprList = (<Comparison> ",")+, Comparison => ActionFn(72);
         assert!(__symbols.len() >= 2);
-        let __sym1 = __pop_Variant4(__symbols);
-        let __sym0 = __pop_Variant4(__symbols);
+        let __sym1 = __pop_Variant2(__symbols);
+        let __sym0 = __pop_Variant3(__symbols);
         let __start = __sym0.0;
         let __end = __sym1.2;
-        let __nt = super::__action16::<>(input, __sym0, __sym1);
-        __symbols.push((__start, __Symbol::Variant3(__nt), __end));
-        (2, 3)
+        let __nt = super::__action72::<>(input, __sym0, __sym1);
+        __symbols.push((__start, __Symbol::Variant10(__nt), __end));
+        (2, 12)
     }
-    fn __reduce6<
+    fn __reduce36<
         'input,
     >(
         input: &'input str,
@@ -1068,15 +2164,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Column = r#"[A-Z]+"# => ActionFn(17);
-        let __sym0 = __pop_Variant0(__symbols);
+        // ExprList = (<Comparison> ",")+ => ActionFn(73);
+        let __sym0 = __pop_Variant3(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
-        let __nt = super::__action17::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant4(__nt), __end));
-        (1, 4)
+        let __nt = super::__action73::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant10(__nt), __end));
+        (1, 12)
     }
-    fn __reduce7<
+    fn __reduce37<
         'input,
     >(
         input: &'input str,
@@ -1085,18 +2181,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Expr = Expr, "+", Term => ActionFn(2);
-        assert!(__symbols.len() >= 3);
-        let __sym2 = __pop_Variant1(__symbols);
-        let __sym1 = __pop_Variant0(__symbols);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Factor = Number => ActionFn(22);
+        let __sym0 = __pop_Variant12(__symbols);
         let __start = __sym0.0;
-        let __end = __sym2.2;
-        let __nt = super::__action2::<>(input, __sym0, __sym1, __sym2);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (3, 5)
+        let __end = __sym0.2;
+        let __nt = super::__action22::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 13)
     }
-    fn __reduce8<
+    fn __reduce38<
         'input,
     >(
         input: &'input str,
@@ -1105,18 +2198,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Expr = Expr, "-", Term => ActionFn(3);
-        assert!(__symbols.len() >= 3);
-        let __sym2 = __pop_Variant1(__symbols);
-        let __sym1 = __pop_Variant0(__symbols);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Factor = Text => ActionFn(23);
+        let __sym0 = __pop_Variant8(__symbols);
         let __start = __sym0.0;
-        let __end = __sym2.2;
-        let __nt = super::__action3::<>(input, __sym0, __sym1, __sym2);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (3, 5)
+        let __end = __sym0.2;
+        let __nt = super::__action23::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 13)
     }
-    fn __reduce9<
+    fn __reduce39<
         'input,
     >(
         input: &'input str,
@@ -1125,15 +2215,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Expr = Term => ActionFn(4);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Factor = Name => ActionFn(24);
+        let __sym0 = __pop_Variant8(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
-        let __nt = super::__action4::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (1, 5)
+        let __nt = super::__action24::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 13)
     }
-    fn __reduce10<
+    fn __reduce40<
         'input,
     >(
         input: &'input str,
@@ -1142,15 +2232,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Expr? = Expr => ActionFn(25);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Factor = DefinedName => ActionFn(25);
+        let __sym0 = __pop_Variant8(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
         let __nt = super::__action25::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant5(__nt), __end));
-        (1, 6)
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 13)
     }
-    fn __reduce11<
+    fn __reduce41<
         'input,
     >(
         input: &'input str,
@@ -1159,14 +2249,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Expr? =  => ActionFn(26);
-        let __start = __lookahead_start.cloned().or_else(|| __symbols.last().map(|s| s.2)).unwrap_or_default();
-        let __end = __start;
-        let __nt = super::__action26::<>(input, &__start, &__end);
-        __symbols.push((__start, __Symbol::Variant5(__nt), __end));
-        (0, 6)
+        // Factor = ErrorLiteral => ActionFn(26);
+        let __sym0 = __pop_Variant9(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action26::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 13)
     }
-    fn __reduce12<
+    fn __reduce42<
         'input,
     >(
         input: &'input str,
@@ -1175,15 +2266,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // ExprList = Expr => ActionFn(36);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Factor = CellRef => ActionFn(27);
+        let __sym0 = __pop_Variant4(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
-        let __nt = super::__action36::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant6(__nt), __end));
-        (1, 7)
+        let __nt = super::__action27::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 13)
     }
-    fn __reduce13<
+    fn __reduce43<
         'input,
     >(
         input: &'input str,
@@ -1192,14 +2283,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // ExprList =  => ActionFn(37);
-        let __start = __lookahead_start.cloned().or_else(|| __symbols.last().map(|s| s.2)).unwrap_or_default();
-        let __end = __start;
-        let __nt = super::__action37::<>(input, &__start, &__end);
-        __symbols.push((__start, __Symbol::Variant6(__nt), __end));
-        (0, 7)
+        // Factor = FunctionCall => ActionFn(28);
+        let __sym0 = __pop_Variant2(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action28::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 13)
     }
-    fn __reduce14<
+    fn __reduce44<
         'input,
     >(
         input: &'input str,
@@ -1208,17 +2300,34 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // ExprList = (<Expr> ",")+, Expr => ActionFn(38);
-        assert!(__symbols.len() >= 2);
-        let __sym1 = __pop_Variant1(__symbols);
+        // Factor = Range => ActionFn(29);
         let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
+        let __end = __sym0.2;
+        let __nt = super::__action29::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 13)
+    }
+    fn __reduce45<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // Factor = SheetPrefix, CellRef => ActionFn(30);
+        assert!(__symbols.len() >= 2);
+        let __sym1 = __pop_Variant4(__symbols);
+        let __sym0 = __pop_Variant8(__symbols);
+        let __start = __sym0.0;
         let __end = __sym1.2;
-        let __nt = super::__action38::<>(input, __sym0, __sym1);
-        __symbols.push((__start, __Symbol::Variant6(__nt), __end));
-        (2, 7)
+        let __nt = super::__action30::<>(input, __sym0, __sym1);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (2, 13)
     }
-    fn __reduce15<
+    fn __reduce46<
         'input,
     >(
         input: &'input str,
@@ -1227,15 +2336,17 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // ExprList = (<Expr> ",")+ => ActionFn(39);
-        let __sym0 = __pop_Variant2(__symbols);
+        // Factor = SheetPrefix, Range => ActionFn(31);
+        assert!(__symbols.len() >= 2);
+        let __sym1 = __pop_Variant2(__symbols);
+        let __sym0 = __pop_Variant8(__symbols);
         let __start = __sym0.0;
-        let __end = __sym0.2;
-        let __nt = super::__action39::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant6(__nt), __end));
-        (1, 7)
+        let __end = __sym1.2;
+        let __nt = super::__action31::<>(input, __sym0, __sym1);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (2, 13)
     }
-    fn __reduce16<
+    fn __reduce47<
         'input,
     >(
         input: &'input str,
@@ -1244,15 +2355,35 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Factor = Number => ActionFn(8);
-        let __sym0 = __pop_Variant4(__symbols);
+        // Factor = "(", Comparison, ")" => ActionFn(32);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant0(__symbols);
+        let __sym1 = __pop_Variant2(__symbols);
+        let __sym0 = __pop_Variant0(__symbols);
+        let __start = __sym0.0;
+        let __end = __sym2.2;
+        let __nt = super::__action32::<>(input, __sym0, __sym1, __sym2);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (3, 13)
+    }
+    fn __reduce48<
+        'input,
+    >(
+        input: &'input str,
+        __lookahead_start: Option<&usize>,
+        __symbols: &mut alloc::vec::Vec<(usize,__Symbol<'input>,usize)>,
+        _: core::marker::PhantomData<(&'input ())>,
+    ) -> (usize, usize)
+    {
+        // Formula = Comparison => ActionFn(1);
+        let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
-        let __nt = super::__action8::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (1, 8)
+        let __nt = super::__action1::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant11(__nt), __end));
+        (1, 14)
     }
-    fn __reduce17<
+    fn __reduce49<
         'input,
     >(
         input: &'input str,
@@ -1261,15 +2392,19 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Factor = CellRef => ActionFn(9);
-        let __sym0 = __pop_Variant3(__symbols);
+        // FunctionCall = Name, "(", ExprList, ")" => ActionFn(33);
+        assert!(__symbols.len() >= 4);
+        let __sym3 = __pop_Variant0(__symbols);
+        let __sym2 = __pop_Variant10(__symbols);
+        let __sym1 = __pop_Variant0(__symbols);
+        let __sym0 = __pop_Variant8(__symbols);
         let __start = __sym0.0;
-        let __end = __sym0.2;
-        let __nt = super::__action9::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (1, 8)
+        let __end = __sym3.2;
+        let __nt = super::__action33::<>(input, __sym0, __sym1, __sym2, __sym3);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (4, 15)
     }
-    fn __reduce18<
+    fn __reduce50<
         'input,
     >(
         input: &'input str,
@@ -1278,15 +2413,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Factor = FunctionCall => ActionFn(10);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Name = r#"[A-Za-z]+"# => ActionFn(38);
+        let __sym0 = __pop_Variant0(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
-        let __nt = super::__action10::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (1, 8)
+        let __nt = super::__action38::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant8(__nt), __end));
+        (1, 16)
     }
-    fn __reduce19<
+    fn __reduce51<
         'input,
     >(
         input: &'input str,
@@ -1295,15 +2430,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Factor = Range => ActionFn(11);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Number = r#"[0-9]+"# => ActionFn(51);
+        let __sym0 = __pop_Variant0(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
-        let __nt = super::__action11::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (1, 8)
+        let __nt = super::__action51::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant12(__nt), __end));
+        (1, 17)
     }
-    fn __reduce20<
+    fn __reduce52<
         'input,
     >(
         input: &'input str,
@@ -1312,18 +2447,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Factor = "(", Expr, ")" => ActionFn(12);
-        assert!(__symbols.len() >= 3);
-        let __sym2 = __pop_Variant0(__symbols);
-        let __sym1 = __pop_Variant1(__symbols);
+        // Number = r#"[0-9]+\\.[0-9]*([eE][+-]?[0-9]+)?|\\.[0-9]+([eE][+-]?[0-9]+)?|[0-9]+[eE][+-]?[0-9]+"# => ActionFn(52);
         let __sym0 = __pop_Variant0(__symbols);
         let __start = __sym0.0;
-        let __end = __sym2.2;
-        let __nt = super::__action12::<>(input, __sym0, __sym1, __sym2);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (3, 8)
+        let __end = __sym0.2;
+        let __nt = super::__action52::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant12(__nt), __end));
+        (1, 17)
     }
-    fn __reduce21<
+    fn __reduce53<
         'input,
     >(
         input: &'input str,
@@ -1332,15 +2464,18 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Formula = Expr => ActionFn(1);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Power = Factor, "^", Unary => ActionFn(20);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant2(__symbols);
+        let __sym1 = __pop_Variant0(__symbols);
+        let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
-        let __end = __sym0.2;
-        let __nt = super::__action1::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant7(__nt), __end));
-        (1, 9)
+        let __end = __sym2.2;
+        let __nt = super::__action20::<>(input, __sym0, __sym1, __sym2);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (3, 18)
     }
-    fn __reduce22<
+    fn __reduce54<
         'input,
     >(
         input: &'input str,
@@ -1349,19 +2484,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // FunctionCall = FunctionName, "(", ExprList, ")" => ActionFn(13);
-        assert!(__symbols.len() >= 4);
-        let __sym3 = __pop_Variant0(__symbols);
-        let __sym2 = __pop_Variant6(__symbols);
-        let __sym1 = __pop_Variant0(__symbols);
-        let __sym0 = __pop_Variant8(__symbols);
+        // Power = Factor => ActionFn(21);
+        let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
-        let __end = __sym3.2;
-        let __nt = super::__action13::<>(input, __sym0, __sym1, __sym2, __sym3);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (4, 10)
+        let __end = __sym0.2;
+        let __nt = super::__action21::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 18)
     }
-    fn __reduce23<
+    fn __reduce55<
         'input,
     >(
         input: &'input str,
@@ -1370,15 +2501,18 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // FunctionName = "Sum" => ActionFn(19);
-        let __sym0 = __pop_Variant0(__symbols);
+        // Range = CellRef, ":", CellRef => ActionFn(35);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant4(__symbols);
+        let __sym1 = __pop_Variant0(__symbols);
+        let __sym0 = __pop_Variant4(__symbols);
         let __start = __sym0.0;
-        let __end = __sym0.2;
-        let __nt = super::__action19::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant8(__nt), __end));
-        (1, 11)
+        let __end = __sym2.2;
+        let __nt = super::__action35::<>(input, __sym0, __sym1, __sym2);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (3, 19)
     }
-    fn __reduce24<
+    fn __reduce57<
         'input,
     >(
         input: &'input str,
@@ -1387,15 +2521,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // FunctionName = "Min" => ActionFn(20);
+        // SheetPrefix = r#"[A-Za-z_][A-Za-z0-9_]*!"# => ActionFn(40);
         let __sym0 = __pop_Variant0(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
-        let __nt = super::__action20::<>(input, __sym0);
+        let __nt = super::__action40::<>(input, __sym0);
         __symbols.push((__start, __Symbol::Variant8(__nt), __end));
-        (1, 11)
+        (1, 21)
     }
-    fn __reduce25<
+    fn __reduce58<
         'input,
     >(
         input: &'input str,
@@ -1404,15 +2538,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // FunctionName = "Max" => ActionFn(21);
+        // SheetPrefix = r#"'[^']+'!"# => ActionFn(41);
         let __sym0 = __pop_Variant0(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
-        let __nt = super::__action21::<>(input, __sym0);
+        let __nt = super::__action41::<>(input, __sym0);
         __symbols.push((__start, __Symbol::Variant8(__nt), __end));
-        (1, 11)
+        (1, 21)
     }
-    fn __reduce26<
+    fn __reduce59<
         'input,
     >(
         input: &'input str,
@@ -1421,15 +2555,18 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // FunctionName = "Avg" => ActionFn(22);
-        let __sym0 = __pop_Variant0(__symbols);
+        // Term = Term, "*", Unary => ActionFn(13);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant2(__symbols);
+        let __sym1 = __pop_Variant0(__symbols);
+        let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
-        let __end = __sym0.2;
-        let __nt = super::__action22::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant8(__nt), __end));
-        (1, 11)
+        let __end = __sym2.2;
+        let __nt = super::__action13::<>(input, __sym0, __sym1, __sym2);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (3, 22)
     }
-    fn __reduce27<
+    fn __reduce60<
         'input,
     >(
         input: &'input str,
@@ -1438,15 +2575,18 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // FunctionName = "Stdev" => ActionFn(23);
-        let __sym0 = __pop_Variant0(__symbols);
+        // Term = Term, "/", Unary => ActionFn(14);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant2(__symbols);
+        let __sym1 = __pop_Variant0(__symbols);
+        let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
-        let __end = __sym0.2;
-        let __nt = super::__action23::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant8(__nt), __end));
-        (1, 11)
+        let __end = __sym2.2;
+        let __nt = super::__action14::<>(input, __sym0, __sym1, __sym2);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (3, 22)
     }
-    fn __reduce28<
+    fn __reduce61<
         'input,
     >(
         input: &'input str,
@@ -1455,15 +2595,18 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // FunctionName = "Sleep" => ActionFn(24);
-        let __sym0 = __pop_Variant0(__symbols);
+        // Term = Term, "%", Unary => ActionFn(15);
+        assert!(__symbols.len() >= 3);
+        let __sym2 = __pop_Variant2(__symbols);
+        let __sym1 = __pop_Variant0(__symbols);
+        let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
-        let __end = __sym0.2;
-        let __nt = super::__action24::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant8(__nt), __end));
-        (1, 11)
+        let __end = __sym2.2;
+        let __nt = super::__action15::<>(input, __sym0, __sym1, __sym2);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (3, 22)
     }
-    fn __reduce29<
+    fn __reduce62<
         'input,
     >(
         input: &'input str,
@@ -1472,15 +2615,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Number = r#"[0-9]+"# => ActionFn(18);
-        let __sym0 = __pop_Variant0(__symbols);
+        // Term = Unary => ActionFn(16);
+        let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
-        let __nt = super::__action18::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant4(__nt), __end));
-        (1, 12)
+        let __nt = super::__action16::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 22)
     }
-    fn __reduce30<
+    fn __reduce63<
         'input,
     >(
         input: &'input str,
@@ -1489,18 +2632,15 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Range = CellRef, ":", CellRef => ActionFn(15);
-        assert!(__symbols.len() >= 3);
-        let __sym2 = __pop_Variant3(__symbols);
-        let __sym1 = __pop_Variant0(__symbols);
-        let __sym0 = __pop_Variant3(__symbols);
+        // Text = r#"\"([^\"]|\"\")*\""# => ActionFn(43);
+        let __sym0 = __pop_Variant0(__symbols);
         let __start = __sym0.0;
-        let __end = __sym2.2;
-        let __nt = super::__action15::<>(input, __sym0, __sym1, __sym2);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (3, 13)
+        let __end = __sym0.2;
+        let __nt = super::__action43::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant8(__nt), __end));
+        (1, 23)
     }
-    fn __reduce31<
+    fn __reduce64<
         'input,
     >(
         input: &'input str,
@@ -1509,18 +2649,17 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Term = Term, "*", Factor => ActionFn(5);
-        assert!(__symbols.len() >= 3);
-        let __sym2 = __pop_Variant1(__symbols);
-        let __sym1 = __pop_Variant0(__symbols);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Unary = "-", Unary => ActionFn(17);
+        assert!(__symbols.len() >= 2);
+        let __sym1 = __pop_Variant2(__symbols);
+        let __sym0 = __pop_Variant0(__symbols);
         let __start = __sym0.0;
-        let __end = __sym2.2;
-        let __nt = super::__action5::<>(input, __sym0, __sym1, __sym2);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (3, 14)
+        let __end = __sym1.2;
+        let __nt = super::__action17::<>(input, __sym0, __sym1);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (2, 24)
     }
-    fn __reduce32<
+    fn __reduce65<
         'input,
     >(
         input: &'input str,
@@ -1529,18 +2668,17 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Term = Term, "/", Factor => ActionFn(6);
-        assert!(__symbols.len() >= 3);
-        let __sym2 = __pop_Variant1(__symbols);
-        let __sym1 = __pop_Variant0(__symbols);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Unary = "+", Unary => ActionFn(18);
+        assert!(__symbols.len() >= 2);
+        let __sym1 = __pop_Variant2(__symbols);
+        let __sym0 = __pop_Variant0(__symbols);
         let __start = __sym0.0;
-        let __end = __sym2.2;
-        let __nt = super::__action6::<>(input, __sym0, __sym1, __sym2);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (3, 14)
+        let __end = __sym1.2;
+        let __nt = super::__action18::<>(input, __sym0, __sym1);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (2, 24)
     }
-    fn __reduce33<
+    fn __reduce66<
         'input,
     >(
         input: &'input str,
@@ -1549,13 +2687,13 @@ mod __parse__Formula {
         _: core::marker::PhantomData<(&'input ())>,
     ) -> (usize, usize)
     {
-        // Term = Factor => ActionFn(7);
-        let __sym0 = __pop_Variant1(__symbols);
+        // Unary = Power => ActionFn(19);
+        let __sym0 = __pop_Variant2(__symbols);
         let __start = __sym0.0;
         let __end = __sym0.2;
-        let __nt = super::__action7::<>(input, __sym0);
-        __symbols.push((__start, __Symbol::Variant1(__nt), __end));
-        (1, 14)
+        let __nt = super::__action19::<>(input, __sym0);
+        __symbols.push((__start, __Symbol::Variant2(__nt), __end));
+        (1, 24)
     }
 }
 #[allow(unused_imports)]
@@ -1564,8 +2702,10 @@ pub use self::__parse__Formula::FormulaParser;
 mod __intern_token {
     #![allow(unused_imports)]
     use std::str::FromStr;
-    use crate::expression_parser::Expr;
+    use lalrpop_util::ParseError;
+    use crate::expression_parser::{CompareOp, Expr};
     use crate::cellsp::CellReference;
+    use crate::cell_extension::CellError;
     #[allow(unused_extern_crates)]
     extern crate lalrpop_util as __lalrpop_util;
     #[allow(unused_imports)]
@@ -1574,8 +2714,22 @@ mod __intern_token {
     extern crate alloc;
     pub fn new_builder() -> __lalrpop_util::lexer::MatcherBuilder {
         let __strs: &[(&str, bool)] = &[
+            ("(?:\"((?:[\0-!\\#-\u{10ffff}]|(?:\"\")))*\")", false),
+            ("(?:'[\0-\\&\\(-\u{10ffff}]+(?:'!))", false),
             ("[0-9]+", false),
-            ("[A-Z]+", false),
+            ("(?:(?:[0-9]+\\.[0-9]*((?:[Ee][\\+\\-]?[0-9]+))?)|(?:\\.[0-9]+((?:[Ee][\\+\\-]?[0-9]+))?)|(?:[0-9]+[Ee][\\+\\-]?[0-9]+))", false),
+            ("(?:[A-Za-z]*_[0-9A-Z_a-z]*)", false),
+            ("[A-Za-z]+", false),
+            ("(?:[A-Z_a-z][0-9A-Z_a-z]*!)", false),
+            ("(?:\\#CONV!)", false),
+            ("(?:\\#CYCLE!)", false),
+            ("(?:\\#DIV/0!)", false),
+            ("(?:\\#NAME\\?)", false),
+            ("(?:\\#NUM!)", false),
+            ("(?:\\#REF!)", false),
+            ("(?:\\#VALUE!)", false),
+            ("\\$", false),
+            ("%", false),
             ("\\(", false),
             ("\\)", false),
             ("\\*", false),
@@ -1584,18 +2738,154 @@ mod __intern_token {
             ("\\-", false),
             ("/", false),
             (":", false),
-            ("(?:Avg)", false),
-            ("(?:Max)", false),
-            ("(?:Min)", false),
-            ("(?:Sleep)", false),
-            ("(?:Stdev)", false),
-            ("(?:Sum)", false),
+            ("<", false),
+            ("(?:<=)", false),
+            ("(?:<>)", false),
+            ("=", false),
+            (">", false),
+            ("(?:>=)", false),
+            ("\\^", false),
             (r"\s+", true),
         ];
         __lalrpop_util::lexer::MatcherBuilder::new(__strs.iter().copied()).unwrap()
     }
 }
-pub(crate) use self::__lalrpop_util::lexer::Token;
+pub(crate) use self::__lalrpop_util::lexer::Token;
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action0<'input>(input: &'input str, (_, __0, _): (usize, Box<Expr>, usize)) -> Box<Expr> {
+    __0
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action1<'input>(input: &'input str, (_, e, _): (usize, Expr, usize)) -> Box<Expr> {
+    Box::new(e)
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action2<'input>(
+    input: &'input str,
+    (_, l, _): (usize, Expr, usize),
+    (_, op, _): (usize, CompareOp, usize),
+    (_, r, _): (usize, Expr, usize),
+) -> Expr {
+    Expr::Compare(Box::new(l), op, Box::new(r))
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action3<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> Expr {
+    __0
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action4<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CompareOp {
+    CompareOp::Eq
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action5<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CompareOp {
+    CompareOp::Ne
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action6<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CompareOp {
+    CompareOp::Lt
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action7<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CompareOp {
+    CompareOp::Le
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action8<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CompareOp {
+    CompareOp::Gt
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action9<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CompareOp {
+    CompareOp::Ge
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action10<'input>(
+    input: &'input str,
+    (_, l, _): (usize, Expr, usize),
+    (_, _, _): (usize, &'input str, usize),
+    (_, r, _): (usize, Expr, usize),
+) -> Expr {
+    Expr::BinaryOp(Box::new(l), '+', Box::new(r))
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action11<'input>(
+    input: &'input str,
+    (_, l, _): (usize, Expr, usize),
+    (_, _, _): (usize, &'input str, usize),
+    (_, r, _): (usize, Expr, usize),
+) -> Expr {
+    Expr::BinaryOp(Box::new(l), '-', Box::new(r))
+}
 
 #[allow(unused_variables)]
 #[allow(
@@ -1603,7 +2893,7 @@ pub(crate) use self::__lalrpop_util::lexer::Token;
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action0<'input>(input: &'input str, (_, __0, _): (usize, Box<Expr>, usize)) -> Box<Expr> {
+fn __action12<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> Expr {
     __0
 }
 
@@ -1613,8 +2903,13 @@ fn __action0<'input>(input: &'input str, (_, __0, _): (usize, Box<Expr>, usize))
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action1<'input>(input: &'input str, (_, e, _): (usize, Expr, usize)) -> Box<Expr> {
-    Box::new(e)
+fn __action13<'input>(
+    input: &'input str,
+    (_, l, _): (usize, Expr, usize),
+    (_, _, _): (usize, &'input str, usize),
+    (_, r, _): (usize, Expr, usize),
+) -> Expr {
+    Expr::BinaryOp(Box::new(l), '*', Box::new(r))
 }
 
 #[allow(unused_variables)]
@@ -1623,13 +2918,13 @@ fn __action1<'input>(input: &'input str, (_, e, _): (usize, Expr, usize)) -> Box
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action2<'input>(
+fn __action14<'input>(
     input: &'input str,
     (_, l, _): (usize, Expr, usize),
     (_, _, _): (usize, &'input str, usize),
     (_, r, _): (usize, Expr, usize),
 ) -> Expr {
-    Expr::BinaryOp(Box::new(l), '+', Box::new(r))
+    Expr::BinaryOp(Box::new(l), '/', Box::new(r))
 }
 
 #[allow(unused_variables)]
@@ -1638,13 +2933,13 @@ fn __action2<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action3<'input>(
+fn __action15<'input>(
     input: &'input str,
     (_, l, _): (usize, Expr, usize),
     (_, _, _): (usize, &'input str, usize),
     (_, r, _): (usize, Expr, usize),
 ) -> Expr {
-    Expr::BinaryOp(Box::new(l), '-', Box::new(r))
+    Expr::BinaryOp(Box::new(l), '%', Box::new(r))
 }
 
 #[allow(unused_variables)]
@@ -1653,7 +2948,7 @@ fn __action3<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action4<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> Expr {
+fn __action16<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> Expr {
     __0
 }
 
@@ -1663,13 +2958,36 @@ fn __action4<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> E
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action5<'input>(
+fn __action17<'input>(
     input: &'input str,
-    (_, l, _): (usize, Expr, usize),
     (_, _, _): (usize, &'input str, usize),
-    (_, r, _): (usize, Expr, usize),
+    (_, e, _): (usize, Expr, usize),
 ) -> Expr {
-    Expr::BinaryOp(Box::new(l), '*', Box::new(r))
+    Expr::UnaryOp('-', Box::new(e))
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action18<'input>(
+    input: &'input str,
+    (_, _, _): (usize, &'input str, usize),
+    (_, e, _): (usize, Expr, usize),
+) -> Expr {
+    Expr::UnaryOp('+', Box::new(e))
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action19<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> Expr {
+    __0
 }
 
 #[allow(unused_variables)]
@@ -1678,13 +2996,13 @@ fn __action5<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action6<'input>(
+fn __action20<'input>(
     input: &'input str,
     (_, l, _): (usize, Expr, usize),
     (_, _, _): (usize, &'input str, usize),
     (_, r, _): (usize, Expr, usize),
 ) -> Expr {
-    Expr::BinaryOp(Box::new(l), '/', Box::new(r))
+    Expr::BinaryOp(Box::new(l), '^', Box::new(r))
 }
 
 #[allow(unused_variables)]
@@ -1693,7 +3011,7 @@ fn __action6<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action7<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> Expr {
+fn __action21<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> Expr {
     __0
 }
 
@@ -1703,7 +3021,7 @@ fn __action7<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> E
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action8<'input>(input: &'input str, (_, n, _): (usize, i32, usize)) -> Expr {
+fn __action22<'input>(input: &'input str, (_, n, _): (usize, f64, usize)) -> Expr {
     Expr::Number(n)
 }
 
@@ -1713,7 +3031,51 @@ fn __action8<'input>(input: &'input str, (_, n, _): (usize, i32, usize)) -> Expr
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action9<'input>(input: &'input str, (_, c, _): (usize, CellReference, usize)) -> Expr {
+fn __action23<'input>(input: &'input str, (_, s, _): (usize, String, usize)) -> Expr {
+    Expr::Text(s)
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action24<'input>(input: &'input str, (_, name, _): (usize, String, usize)) -> Expr {
+    match name.as_str() {
+        "TRUE" => Expr::Bool(true),
+        "FALSE" => Expr::Bool(false),
+        _ => Expr::Name(name),
+    }
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action25<'input>(input: &'input str, (_, name, _): (usize, String, usize)) -> Expr {
+    Expr::Name(name)
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action26<'input>(input: &'input str, (_, e, _): (usize, CellError, usize)) -> Expr {
+    Expr::Error(e)
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action27<'input>(input: &'input str, (_, c, _): (usize, CellReference, usize)) -> Expr {
     Expr::Cell(c)
 }
 
@@ -1723,7 +3085,7 @@ fn __action9<'input>(input: &'input str, (_, c, _): (usize, CellReference, usize
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action10<'input>(input: &'input str, (_, f, _): (usize, Expr, usize)) -> Expr {
+fn __action28<'input>(input: &'input str, (_, f, _): (usize, Expr, usize)) -> Expr {
     f
 }
 
@@ -1733,7 +3095,7 @@ fn __action10<'input>(input: &'input str, (_, f, _): (usize, Expr, usize)) -> Ex
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action11<'input>(input: &'input str, (_, r, _): (usize, Expr, usize)) -> Expr {
+fn __action29<'input>(input: &'input str, (_, r, _): (usize, Expr, usize)) -> Expr {
     r
 }
 
@@ -1743,7 +3105,35 @@ fn __action11<'input>(input: &'input str, (_, r, _): (usize, Expr, usize)) -> Ex
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action12<'input>(
+fn __action30<'input>(
+    input: &'input str,
+    (_, s, _): (usize, String, usize),
+    (_, c, _): (usize, CellReference, usize),
+) -> Expr {
+    Expr::SheetRef(s, Box::new(Expr::Cell(c)))
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action31<'input>(
+    input: &'input str,
+    (_, s, _): (usize, String, usize),
+    (_, r, _): (usize, Expr, usize),
+) -> Expr {
+    Expr::SheetRef(s, Box::new(r))
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action32<'input>(
     input: &'input str,
     (_, _, _): (usize, &'input str, usize),
     (_, e, _): (usize, Expr, usize),
@@ -1758,7 +3148,7 @@ fn __action12<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action13<'input>(
+fn __action33<'input>(
     input: &'input str,
     (_, name, _): (usize, String, usize),
     (_, _, _): (usize, &'input str, usize),
@@ -1774,7 +3164,7 @@ fn __action13<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action14<'input>(
+fn __action34<'input>(
     input: &'input str,
     (_, v, _): (usize, alloc::vec::Vec<Expr>, usize),
     (_, e, _): (usize, Option<Expr>, usize),
@@ -1795,7 +3185,7 @@ fn __action14<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action15<'input>(
+fn __action35<'input>(
     input: &'input str,
     (_, start, _): (usize, CellReference, usize),
     (_, _, _): (usize, &'input str, usize),
@@ -1810,15 +3200,19 @@ fn __action15<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action16<'input>(
+fn __action36<'input>(
     input: &'input str,
+    (_, column_absolute, _): (usize, Option<&'input str>, usize),
     (_, col, _): (usize, i32, usize),
+    (_, row_absolute, _): (usize, Option<&'input str>, usize),
     (_, row, _): (usize, i32, usize),
 ) -> CellReference {
     {
         CellReference {
             row: row - 1, // Adjusting to 0-based indexing
             column: col,
+            row_absolute: row_absolute.is_some(),
+            column_absolute: column_absolute.is_some(),
         }
     }
 }
@@ -1829,13 +3223,21 @@ fn __action16<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action17<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)) -> i32 {
+fn __action37<'input>(
+    input: &'input str,
+    (_, name, _): (usize, String, usize),
+) -> Result<i32, __lalrpop_util::ParseError<usize, Token<'input>, &'static str>> {
     {
-        let mut col = 0;
-        for c in s.chars() {
-            col = col * 26 + ((c.to_ascii_uppercase() as i32) - ('A' as i32) + 1);
+        let mut col: i32 = 0;
+        for c in name.chars() {
+            col = col
+                .checked_mul(26)
+                .and_then(|col| col.checked_add((c as i32) - ('A' as i32) + 1))
+                .ok_or(ParseError::User {
+                    error: "column name is too long",
+                })?;
         }
-        col - 1 // Convert to 0-based indexing
+        Ok(col - 1) // Convert to 0-based indexing
     }
 }
 
@@ -1845,8 +3247,113 @@ fn __action17<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action18<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)) -> i32 {
-    i32::from_str(s).unwrap()
+fn __action38<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)) -> String {
+    s.to_ascii_uppercase()
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action39<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)) -> String {
+    s.to_ascii_uppercase()
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action40<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)) -> String {
+    s[..s.len() - 1].to_string()
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action41<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)) -> String {
+    s[1..s.len() - 2].to_string()
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action42<'input>(
+    input: &'input str,
+    (_, s, _): (usize, &'input str, usize),
+) -> Result<i32, __lalrpop_util::ParseError<usize, Token<'input>, &'static str>> {
+    i32::from_str(s).map_err(|_| ParseError::User {
+        error: "row number is too large",
+    })
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action43<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)) -> String {
+    s[1..s.len() - 1].replace("\"\"", "\"")
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action44<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CellError {
+    CellError::DivByZero
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action45<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CellError {
+    CellError::Ref
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action46<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CellError {
+    CellError::Cycle
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action47<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CellError {
+    CellError::Value
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action48<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CellError {
+    CellError::Name
 }
 
 #[allow(unused_variables)]
@@ -1855,8 +3362,8 @@ fn __action18<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action19<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> String {
-    "SUM".to_string()
+fn __action49<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CellError {
+    CellError::Num
 }
 
 #[allow(unused_variables)]
@@ -1865,8 +3372,8 @@ fn __action19<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usiz
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action20<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> String {
-    "MIN".to_string()
+fn __action50<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> CellError {
+    CellError::Convergence
 }
 
 #[allow(unused_variables)]
@@ -1875,8 +3382,8 @@ fn __action20<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usiz
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action21<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> String {
-    "MAX".to_string()
+fn __action51<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)) -> f64 {
+    f64::from_str(s).unwrap()
 }
 
 #[allow(unused_variables)]
@@ -1885,8 +3392,8 @@ fn __action21<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usiz
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action22<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> String {
-    "AVG".to_string()
+fn __action52<'input>(input: &'input str, (_, s, _): (usize, &'input str, usize)) -> f64 {
+    f64::from_str(s).unwrap()
 }
 
 #[allow(unused_variables)]
@@ -1895,8 +3402,11 @@ fn __action22<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usiz
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action23<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> String {
-    "STDEV".to_string()
+fn __action53<'input>(
+    input: &'input str,
+    (_, __0, _): (usize, &'input str, usize),
+) -> Option<&'input str> {
+    Some(__0)
 }
 
 #[allow(unused_variables)]
@@ -1905,8 +3415,12 @@ fn __action23<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usiz
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action24<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usize)) -> String {
-    "SLEEP".to_string()
+fn __action54<'input>(
+    input: &'input str,
+    __lookbehind: &usize,
+    __lookahead: &usize,
+) -> Option<&'input str> {
+    None
 }
 
 #[allow(unused_variables)]
@@ -1915,7 +3429,7 @@ fn __action24<'input>(input: &'input str, (_, __0, _): (usize, &'input str, usiz
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action25<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> Option<Expr> {
+fn __action55<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) -> Option<Expr> {
     Some(__0)
 }
 
@@ -1925,7 +3439,7 @@ fn __action25<'input>(input: &'input str, (_, __0, _): (usize, Expr, usize)) ->
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action26<'input>(
+fn __action56<'input>(
     input: &'input str,
     __lookbehind: &usize,
     __lookahead: &usize,
@@ -1939,7 +3453,7 @@ fn __action26<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action27<'input>(
+fn __action57<'input>(
     input: &'input str,
     __lookbehind: &usize,
     __lookahead: &usize,
@@ -1953,7 +3467,7 @@ fn __action27<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action28<'input>(
+fn __action58<'input>(
     input: &'input str,
     (_, v, _): (usize, alloc::vec::Vec<Expr>, usize),
 ) -> alloc::vec::Vec<Expr> {
@@ -1966,7 +3480,7 @@ fn __action28<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action29<'input>(
+fn __action59<'input>(
     input: &'input str,
     (_, __0, _): (usize, Expr, usize),
     (_, _, _): (usize, &'input str, usize),
@@ -1980,7 +3494,7 @@ fn __action29<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action30<'input>(
+fn __action60<'input>(
     input: &'input str,
     (_, __0, _): (usize, Expr, usize),
 ) -> alloc::vec::Vec<Expr> {
@@ -1993,7 +3507,7 @@ fn __action30<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action31<'input>(
+fn __action61<'input>(
     input: &'input str,
     (_, v, _): (usize, alloc::vec::Vec<Expr>, usize),
     (_, e, _): (usize, Expr, usize),
@@ -2011,16 +3525,108 @@ fn __action31<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action32<'input>(
+fn __action62<'input>(
+    input: &'input str,
+    __0: (usize, &'input str, usize),
+    __1: (usize, i32, usize),
+    __2: (usize, &'input str, usize),
+    __3: (usize, i32, usize),
+) -> CellReference {
+    let __start0 = __0.0;
+    let __end0 = __0.2;
+    let __start1 = __2.0;
+    let __end1 = __2.2;
+    let __temp0 = __action53(input, __0);
+    let __temp0 = (__start0, __temp0, __end0);
+    let __temp1 = __action53(input, __2);
+    let __temp1 = (__start1, __temp1, __end1);
+    __action36(input, __temp0, __1, __temp1, __3)
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action63<'input>(
+    input: &'input str,
+    __0: (usize, &'input str, usize),
+    __1: (usize, i32, usize),
+    __2: (usize, i32, usize),
+) -> CellReference {
+    let __start0 = __0.0;
+    let __end0 = __0.2;
+    let __start1 = __1.2;
+    let __end1 = __2.0;
+    let __temp0 = __action53(input, __0);
+    let __temp0 = (__start0, __temp0, __end0);
+    let __temp1 = __action54(input, &__start1, &__end1);
+    let __temp1 = (__start1, __temp1, __end1);
+    __action36(input, __temp0, __1, __temp1, __2)
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action64<'input>(
+    input: &'input str,
+    __0: (usize, i32, usize),
+    __1: (usize, &'input str, usize),
+    __2: (usize, i32, usize),
+) -> CellReference {
+    let __start0 = __0.0;
+    let __end0 = __0.0;
+    let __start1 = __1.0;
+    let __end1 = __1.2;
+    let __temp0 = __action54(input, &__start0, &__end0);
+    let __temp0 = (__start0, __temp0, __end0);
+    let __temp1 = __action53(input, __1);
+    let __temp1 = (__start1, __temp1, __end1);
+    __action36(input, __temp0, __0, __temp1, __2)
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action65<'input>(
+    input: &'input str,
+    __0: (usize, i32, usize),
+    __1: (usize, i32, usize),
+) -> CellReference {
+    let __start0 = __0.0;
+    let __end0 = __0.0;
+    let __start1 = __0.2;
+    let __end1 = __1.0;
+    let __temp0 = __action54(input, &__start0, &__end0);
+    let __temp0 = (__start0, __temp0, __end0);
+    let __temp1 = __action54(input, &__start1, &__end1);
+    let __temp1 = (__start1, __temp1, __end1);
+    __action36(input, __temp0, __0, __temp1, __1)
+}
+
+#[allow(unused_variables)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::needless_lifetimes,
+    clippy::just_underscores_and_digits
+)]
+fn __action66<'input>(
     input: &'input str,
     __0: (usize, Expr, usize),
     __1: (usize, &'input str, usize),
 ) -> alloc::vec::Vec<Expr> {
     let __start0 = __0.0;
     let __end0 = __1.2;
-    let __temp0 = __action29(input, __0, __1);
+    let __temp0 = __action59(input, __0, __1);
     let __temp0 = (__start0, __temp0, __end0);
-    __action30(input, __temp0)
+    __action60(input, __temp0)
 }
 
 #[allow(unused_variables)]
@@ -2029,7 +3635,7 @@ fn __action32<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action33<'input>(
+fn __action67<'input>(
     input: &'input str,
     __0: (usize, alloc::vec::Vec<Expr>, usize),
     __1: (usize, Expr, usize),
@@ -2037,9 +3643,9 @@ fn __action33<'input>(
 ) -> alloc::vec::Vec<Expr> {
     let __start0 = __1.0;
     let __end0 = __2.2;
-    let __temp0 = __action29(input, __1, __2);
+    let __temp0 = __action59(input, __1, __2);
     let __temp0 = (__start0, __temp0, __end0);
-    __action31(input, __0, __temp0)
+    __action61(input, __0, __temp0)
 }
 
 #[allow(unused_variables)]
@@ -2048,12 +3654,12 @@ fn __action33<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action34<'input>(input: &'input str, __0: (usize, Option<Expr>, usize)) -> Vec<Expr> {
+fn __action68<'input>(input: &'input str, __0: (usize, Option<Expr>, usize)) -> Vec<Expr> {
     let __start0 = __0.0;
     let __end0 = __0.0;
-    let __temp0 = __action27(input, &__start0, &__end0);
+    let __temp0 = __action57(input, &__start0, &__end0);
     let __temp0 = (__start0, __temp0, __end0);
-    __action14(input, __temp0, __0)
+    __action34(input, __temp0, __0)
 }
 
 #[allow(unused_variables)]
@@ -2062,16 +3668,16 @@ fn __action34<'input>(input: &'input str, __0: (usize, Option<Expr>, usize)) ->
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action35<'input>(
+fn __action69<'input>(
     input: &'input str,
     __0: (usize, alloc::vec::Vec<Expr>, usize),
     __1: (usize, Option<Expr>, usize),
 ) -> Vec<Expr> {
     let __start0 = __0.0;
     let __end0 = __0.2;
-    let __temp0 = __action28(input, __0);
+    let __temp0 = __action58(input, __0);
     let __temp0 = (__start0, __temp0, __end0);
-    __action14(input, __temp0, __1)
+    __action34(input, __temp0, __1)
 }
 
 #[allow(unused_variables)]
@@ -2080,12 +3686,12 @@ fn __action35<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action36<'input>(input: &'input str, __0: (usize, Expr, usize)) -> Vec<Expr> {
+fn __action70<'input>(input: &'input str, __0: (usize, Expr, usize)) -> Vec<Expr> {
     let __start0 = __0.0;
     let __end0 = __0.2;
-    let __temp0 = __action25(input, __0);
+    let __temp0 = __action55(input, __0);
     let __temp0 = (__start0, __temp0, __end0);
-    __action34(input, __temp0)
+    __action68(input, __temp0)
 }
 
 #[allow(unused_variables)]
@@ -2094,12 +3700,12 @@ fn __action36<'input>(input: &'input str, __0: (usize, Expr, usize)) -> Vec<Expr
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action37<'input>(input: &'input str, __lookbehind: &usize, __lookahead: &usize) -> Vec<Expr> {
+fn __action71<'input>(input: &'input str, __lookbehind: &usize, __lookahead: &usize) -> Vec<Expr> {
     let __start0 = *__lookbehind;
     let __end0 = *__lookahead;
-    let __temp0 = __action26(input, &__start0, &__end0);
+    let __temp0 = __action56(input, &__start0, &__end0);
     let __temp0 = (__start0, __temp0, __end0);
-    __action34(input, __temp0)
+    __action68(input, __temp0)
 }
 
 #[allow(unused_variables)]
@@ -2108,16 +3714,16 @@ fn __action37<'input>(input: &'input str, __lookbehind: &usize, __lookahead: &us
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action38<'input>(
+fn __action72<'input>(
     input: &'input str,
     __0: (usize, alloc::vec::Vec<Expr>, usize),
     __1: (usize, Expr, usize),
 ) -> Vec<Expr> {
     let __start0 = __1.0;
     let __end0 = __1.2;
-    let __temp0 = __action25(input, __1);
+    let __temp0 = __action55(input, __1);
     let __temp0 = (__start0, __temp0, __end0);
-    __action35(input, __0, __temp0)
+    __action69(input, __0, __temp0)
 }
 
 #[allow(unused_variables)]
@@ -2126,12 +3732,12 @@ fn __action38<'input>(
     clippy::needless_lifetimes,
     clippy::just_underscores_and_digits
 )]
-fn __action39<'input>(input: &'input str, __0: (usize, alloc::vec::Vec<Expr>, usize)) -> Vec<Expr> {
+fn __action73<'input>(input: &'input str, __0: (usize, alloc::vec::Vec<Expr>, usize)) -> Vec<Expr> {
     let __start0 = __0.2;
     let __end0 = __0.2;
-    let __temp0 = __action26(input, &__start0, &__end0);
+    let __temp0 = __action56(input, &__start0, &__end0);
     let __temp0 = (__start0, __temp0, __end0);
-    __action35(input, __0, __temp0)
+    __action69(input, __0, __temp0)
 }
 
 #[allow(clippy::type_complexity, dead_code)]
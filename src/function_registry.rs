@@ -0,0 +1,287 @@
+use crate::cell_extension::{CellError, CellValue, SpreadsheetExtension};
+use crate::expression_parser::Expr;
+use crate::expression_utils::{
+    collect_conditions, collect_numbers, eval_condition, eval_expr, eval_number, modulo,
+    number_or_error,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref FUNCTION_REGISTRY: RwLock<FunctionRegistry> =
+        RwLock::new(FunctionRegistry::with_builtins());
+}
+
+/// Evaluates a function call from its unevaluated arguments.
+///
+/// Arguments are passed as expressions so a function can decide which of them to evaluate
+/// (e.g. `IF` only evaluates the branch it takes). Use `eval_expr`, `eval_number` or
+/// `collect_numbers` from `expression_utils` to evaluate them.
+pub type FunctionEval = dyn Fn(&[Expr], &SpreadsheetExtension) -> CellValue + Send + Sync;
+
+/// Describes a function that can be called from formulas.
+#[derive(Clone)]
+pub struct FunctionSpec {
+    /// The name used in formulas, stored in upper case.
+    pub name: String,
+    /// The fewest arguments the function takes.
+    pub min_args: usize,
+    /// The most arguments the function takes, or `None` if there is no limit.
+    pub max_args: Option<usize>,
+    /// Whether arguments may be ranges such as `A1:B3`.
+    pub accepts_ranges: bool,
+    /// Computes the function's value.
+    pub eval: Arc<FunctionEval>,
+}
+
+impl FunctionSpec {
+    /// Creates a function description.
+    ///
+    /// # Arguments
+    /// * `name` - The name used in formulas; calls match it in any case.
+    /// * `min_args` - The fewest arguments the function takes.
+    /// * `max_args` - The most arguments the function takes, or `None` for no limit.
+    /// * `accepts_ranges` - Whether arguments may be ranges.
+    /// * `eval` - Computes the function's value from its arguments.
+    ///
+    /// # Returns
+    /// A `FunctionSpec` ready to be registered.
+    pub fn new(
+        name: &str,
+        min_args: usize,
+        max_args: Option<usize>,
+        accepts_ranges: bool,
+        eval: impl Fn(&[Expr], &SpreadsheetExtension) -> CellValue + Send + Sync + 'static,
+    ) -> Self {
+        FunctionSpec {
+            name: name.to_ascii_uppercase(),
+            min_args,
+            max_args,
+            accepts_ranges,
+            eval: Arc::new(eval),
+        }
+    }
+
+    /// Calls the function, or returns `#VALUE!` if the arguments do not match its signature.
+    fn call(&self, args: &[Expr], sheet: &SpreadsheetExtension) -> CellValue {
+        let accepts = args.len() >= self.min_args
+            && self.max_args.is_none_or(|max| args.len() <= max)
+            && (self.accepts_ranges || !args.iter().any(Expr::is_range));
+        if accepts {
+            (self.eval)(args, sheet)
+        } else {
+            CellValue::Error(CellError::Value)
+        }
+    }
+}
+
+/// A set of functions that formulas can call, looked up by name in any case.
+#[derive(Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, FunctionSpec>,
+}
+
+impl FunctionRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        FunctionRegistry::default()
+    }
+
+    /// Creates a registry holding the built-in functions (`SUM`, `IF`, `MOD`, ...).
+    pub fn with_builtins() -> Self {
+        let mut registry = FunctionRegistry::new();
+        for spec in builtin_functions() {
+            registry.register(spec);
+        }
+        registry
+    }
+
+    /// Adds a function, replacing any function with the same name.
+    ///
+    /// # Arguments
+    /// * `spec` - The function to add.
+    ///
+    /// # Returns
+    /// The function previously registered under the same name, if any.
+    pub fn register(&mut self, spec: FunctionSpec) -> Option<FunctionSpec> {
+        self.functions.insert(spec.name.clone(), spec)
+    }
+
+    /// Looks up a function by name, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&FunctionSpec> {
+        self.functions.get(&name.to_ascii_uppercase())
+    }
+
+    /// Calls a function.
+    ///
+    /// # Arguments
+    /// * `name` - The function name, in any case.
+    /// * `args` - The unevaluated arguments.
+    /// * `sheet` - A reference to the spreadsheet containing cell values.
+    ///
+    /// # Returns
+    /// The function's value, `#NAME?` if no function has that name, or `#VALUE!` if the
+    /// arguments do not match its signature.
+    pub fn call(&self, name: &str, args: &[Expr], sheet: &SpreadsheetExtension) -> CellValue {
+        match self.get(name) {
+            Some(spec) => spec.call(args, sheet),
+            None => CellValue::Error(CellError::Name),
+        }
+    }
+}
+
+/// Adds a function to the registry used when evaluating formulas.
+///
+/// # Arguments
+/// * `spec` - The function to add; it replaces any function with the same name,
+///   including built-in ones.
+///
+/// # Returns
+/// The function previously registered under the same name, if any.
+///
+/// # Behavior
+/// Cells are not recalculated; formulas calling the function pick it up the next time they
+/// are evaluated.
+pub fn register_function(spec: FunctionSpec) -> Option<FunctionSpec> {
+    FUNCTION_REGISTRY.write().unwrap().register(spec)
+}
+
+/// Calls a function from the registry used when evaluating formulas.
+///
+/// # Arguments
+/// * `name` - The function name, in any case.
+/// * `args` - The unevaluated arguments.
+/// * `sheet` - A reference to the spreadsheet containing cell values.
+///
+/// # Returns
+/// The function's value, or an error value as described for `FunctionRegistry::call`.
+pub fn call_function(name: &str, args: &[Expr], sheet: &SpreadsheetExtension) -> CellValue {
+    // Release the lock before evaluating, since the arguments may call functions too
+    let spec = FUNCTION_REGISTRY.read().unwrap().get(name).cloned();
+    match spec {
+        Some(spec) => spec.call(args, sheet),
+        None => CellValue::Error(CellError::Name),
+    }
+}
+
+/// Returns the built-in functions.
+fn builtin_functions() -> Vec<FunctionSpec> {
+    vec![
+        FunctionSpec::new("SUM", 0, None, true, |args, sheet| {
+            number_or_error(collect_numbers(args, sheet).map(|values| values.iter().sum()))
+        }),
+        FunctionSpec::new("MAX", 0, None, true, |args, sheet| {
+            number_or_error(collect_numbers(args, sheet).map(|values| {
+                if values.is_empty() {
+                    0.0
+                } else {
+                    values.iter().cloned().fold(f64::NEG_INFINITY, f64::max)
+                }
+            }))
+        }),
+        FunctionSpec::new("MIN", 0, None, true, |args, sheet| {
+            number_or_error(collect_numbers(args, sheet).map(|values| {
+                if values.is_empty() {
+                    0.0
+                } else {
+                    values.iter().cloned().fold(f64::INFINITY, f64::min)
+                }
+            }))
+        }),
+        FunctionSpec::new("AVG", 0, None, true, |args, sheet| {
+            number_or_error(collect_numbers(args, sheet).map(|values| {
+                if values.is_empty() {
+                    0.0
+                } else {
+                    values.iter().sum::<f64>() / values.len() as f64
+                }
+            }))
+        }),
+        FunctionSpec::new("STDEV", 0, None, true, |args, sheet| {
+            number_or_error(collect_numbers(args, sheet).map(|values| {
+                if values.is_empty() {
+                    0.0
+                } else {
+                    // Calculate mean
+                    let n = values.len() as f64;
+                    let mean: f64 = values.iter().sum::<f64>() / n;
+
+                    // Calculate variance
+                    let variance = values
+                        .iter()
+                        .map(|&x| {
+                            let diff = x - mean;
+                            diff * diff
+                        })
+                        .sum::<f64>()
+                        / n;
+
+                    variance.sqrt()
+                }
+            }))
+        }),
+        FunctionSpec::new("MOD", 2, Some(2), false, |args, sheet| {
+            number_or_error(
+                eval_number(&args[0], sheet)
+                    .and_then(|n| eval_number(&args[1], sheet).and_then(|d| modulo(n, d))),
+            )
+        }),
+        FunctionSpec::new("SLEEP", 0, Some(1), false, |args, sheet| {
+            number_or_error(args.first().map_or(Ok(0.0), |arg| eval_number(arg, sheet)))
+        }),
+        // Only the branch selected by the condition is evaluated
+        FunctionSpec::new("IF", 2, Some(3), false, |args, sheet| match eval_condition(
+            &args[0], sheet,
+        ) {
+            Ok(true) => eval_expr(&args[1], sheet),
+            Ok(false) => args
+                .get(2)
+                .map_or(CellValue::Bool(false), |e| eval_expr(e, sheet)),
+            Err(e) => CellValue::Error(e),
+        }),
+        FunctionSpec::new(
+            "AND",
+            1,
+            None,
+            true,
+            |args, sheet| match collect_conditions(args, sheet) {
+                Ok(conditions) if conditions.is_empty() => CellValue::Error(CellError::Value),
+                Ok(conditions) => CellValue::Bool(conditions.iter().all(|&c| c)),
+                Err(e) => CellValue::Error(e),
+            },
+        ),
+        FunctionSpec::new(
+            "OR",
+            1,
+            None,
+            true,
+            |args, sheet| match collect_conditions(args, sheet) {
+                Ok(conditions) if conditions.is_empty() => CellValue::Error(CellError::Value),
+                Ok(conditions) => CellValue::Bool(conditions.iter().any(|&c| c)),
+                Err(e) => CellValue::Error(e),
+            },
+        ),
+        FunctionSpec::new(
+            "NOT",
+            1,
+            Some(1),
+            false,
+            |args, sheet| match eval_condition(&args[0], sheet) {
+                Ok(condition) => CellValue::Bool(!condition),
+                Err(e) => CellValue::Error(e),
+            },
+        ),
+        FunctionSpec::new("ISERROR", 1, Some(1), false, |args, sheet| {
+            CellValue::Bool(eval_expr(&args[0], sheet).is_error())
+        }),
+        // IFERROR may return any value, not only numbers
+        FunctionSpec::new("IFERROR", 2, Some(2), false, |args, sheet| match eval_expr(
+            &args[0], sheet,
+        ) {
+            CellValue::Error(_) => eval_expr(&args[1], sheet),
+            value => value,
+        }),
+    ]
+}
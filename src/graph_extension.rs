@@ -1,8 +1,17 @@
 use crate::cell_extension::*;
+use crate::cell_grid::CellGrid;
 use crate::cellsp::CellReference;
 use crate::expression_parser::Expr;
-use crate::expression_utils::{eval_expr, extract_precedents};
-use std::collections::{HashSet, VecDeque};
+use crate::expression_utils::{
+    eval_expr, extract_references, extract_sheet_references, resolve_names,
+};
+use crate::iterative_calc::{IterationSettings, iterate_cycle};
+use crate::range_index::RangeIndex;
+use crate::recalc_order::{RecalcStep, cycle_through, recalculation_order, recalculation_steps};
+use crate::sheet_error::{CommandResult, Outcome, SheetError};
+use crate::sheet_structure::{MAX_COLS, MAX_ROWS, StructureChange, revert_structure_change};
+use chrono::{DateTime, Local};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 
@@ -18,10 +27,8 @@ lazy_static! {
     };
 }
 
-/// A global status variable for tracking errors in the spreadsheet.
-pub static mut STATUS_EXTENSION: i32 = 0;
-
-const MAX_UNDO: usize = 17;
+/// The number of undo steps kept by a new `UndoRedoStack`.
+pub const DEFAULT_UNDO_DEPTH: usize = 17;
 
 /// Initializes the spreadsheet with the given number of rows and columns.
 ///
@@ -30,35 +37,19 @@ const MAX_UNDO: usize = 17;
 /// * `columns` - The number of columns in the spreadsheet.
 ///
 /// # Returns
-/// A `SpreadsheetExtension` object with all cells initialized.
+/// A `SpreadsheetExtension` object whose cells are all empty. Empty cells take no memory
+/// until they are written.
 pub fn initialise_extension(rows: i32, columns: i32) -> SpreadsheetExtension {
-    let mut all_cells = Vec::with_capacity(rows as usize);
-    for r in 0..rows {
-        let mut row: Vec<CellExtension> = Vec::with_capacity(columns as usize);
-        for c in 0..columns {
-            let curr_cell = CellExtension {
-                value: 0,
-                // expression: None,
-                // formula_str: String::new(),
-                formula: Expr::Number(0),
-                r,
-                c,
-                is_recalculate: false,
-                is_error: false,
-                dependents: HashSet::new(),
-                precedents: HashSet::new(),
-                is_bold: false,
-                is_italics: false,
-            };
-            row.push(curr_cell);
-        }
-        all_cells.push(row);
-    }
-
     SpreadsheetExtension {
         rows,
         columns,
-        all_cells,
+        all_cells: CellGrid::new(rows, columns),
+        range_dependents: RangeIndex::new(),
+        iteration: None,
+        external_dependents: HashMap::new(),
+        linked: HashMap::new(),
+        names: BTreeMap::new(),
+        line_moves: Vec::new(),
     }
 }
 
@@ -77,14 +68,8 @@ pub fn add_dependency_extension(
     rt: i32,
     ct: i32,
 ) {
-    let dependent_cell = CellReference {
-        row: rt,
-        column: ct,
-    };
-    let precedent_cell = CellReference {
-        row: rf,
-        column: cf,
-    };
+    let dependent_cell = CellReference::new(rt, ct);
+    let precedent_cell = CellReference::new(rf, cf);
     sheet.all_cells[rf as usize][cf as usize]
         .dependents
         .insert(dependent_cell);
@@ -108,10 +93,7 @@ pub fn delete_dependency_extension(
     rt: i32,
     ct: i32,
 ) {
-    let dependent_cell = CellReference {
-        row: rt,
-        column: ct,
-    };
+    let dependent_cell = CellReference::new(rt, ct);
     sheet.all_cells[rf as usize][cf as usize]
         .dependents
         .remove(&dependent_cell);
@@ -128,269 +110,209 @@ pub fn clear_precedents_extension(sheet: &mut SpreadsheetExtension, rt: i32, ct:
     cell.precedents = HashSet::new();
 }
 
-/// Calculates the value of a cell based on its formula.
+/// Checks whether a reference points inside the sheet.
+fn in_sheet(sheet: &SpreadsheetExtension, cell_ref: &CellReference) -> bool {
+    (0..sheet.rows).contains(&cell_ref.row) && (0..sheet.columns).contains(&cell_ref.column)
+}
+
+/// Clips a range to the sheet, or returns `None` if none of it is inside the sheet.
+fn clip_range(
+    sheet: &SpreadsheetExtension,
+    start: &CellReference,
+    end: &CellReference,
+) -> Option<(CellReference, CellReference)> {
+    let mut start = start.clone();
+    let mut end = end.clone();
+    start.row = start.row.max(0);
+    start.column = start.column.max(0);
+    end.row = end.row.min(sheet.rows - 1);
+    end.column = end.column.min(sheet.columns - 1);
+    (start.row <= end.row && start.column <= end.column).then_some((start, end))
+}
+
+/// Clips a range on another sheet to the largest sheet allowed, or returns `None` if none
+/// of it could be inside a sheet.
+fn clip_external_range(
+    start: &CellReference,
+    end: &CellReference,
+) -> Option<(CellReference, CellReference)> {
+    let mut start = start.clone();
+    let mut end = end.clone();
+    start.row = start.row.max(0);
+    start.column = start.column.max(0);
+    end.row = end.row.min(MAX_ROWS - 1);
+    end.column = end.column.min(MAX_COLS - 1);
+    (start.row <= end.row && start.column <= end.column).then_some((start, end))
+}
+
+/// Records the dependencies of a formula held by a cell.
 ///
 /// # Arguments
 /// * `sheet` - A mutable reference to the spreadsheet.
+/// * `formula` - The formula held by the cell.
 /// * `rt` - The row index of the cell.
 /// * `ct` - The column index of the cell.
 ///
 /// # Behavior
-/// Updates the cell's value and error status based on its formula.
-pub fn calculate_cell_value_extension(sheet: &mut SpreadsheetExtension, rt: i32, ct: i32) {
-    let formula = &sheet.all_cells[rt as usize][ct as usize].formula;
-    let value = eval_expr(formula, sheet);
-
-    match formula {
-        Expr::Number(_) => {
-            sheet.all_cells[rt as usize][ct as usize].value = value;
-            sheet.all_cells[rt as usize][ct as usize].is_error = false;
-            unsafe {
-                STATUS_EXTENSION = 0;
-            }
+/// Single references are recorded as edges between the two cells. Each range is recorded
+/// once in `range_dependents`, however many cells it covers. References outside the sheet
+/// (which evaluate to `#REF!`) are not recorded, and ranges are clipped to the sheet.
+/// References into other sheets are recorded in `external_dependents`. Defined names are
+/// recorded as the cells they currently stand for.
+pub fn link_formula_extension(sheet: &mut SpreadsheetExtension, formula: &Expr, rt: i32, ct: i32) {
+    let dependent = CellReference::new(rt, ct);
+    let formula = &resolve_names(formula, sheet);
+    let (cells, ranges) = extract_references(formula);
+    for prec in cells {
+        if in_sheet(sheet, &prec) {
+            add_dependency_extension(sheet, prec.row, prec.column, rt, ct);
         }
-
-        Expr::Cell(cell_ref) => {
-            if sheet.all_cells[cell_ref.row as usize][cell_ref.column as usize].is_error {
-                sheet.all_cells[rt as usize][ct as usize].is_error = true;
-                unsafe {
-                    STATUS_EXTENSION = 2;
-                }
-            } else {
-                sheet.all_cells[rt as usize][ct as usize].value = value;
-                sheet.all_cells[rt as usize][ct as usize].is_error = false;
-                unsafe {
-                    STATUS_EXTENSION = 0;
-                }
-            }
-        }
-
-        Expr::BinaryOp(_, _, _) => {
-            if expr_has_error(formula, sheet) {
-                sheet.all_cells[rt as usize][ct as usize].is_error = true;
-                unsafe {
-                    STATUS_EXTENSION = 2;
-                }
-            } else {
-                sheet.all_cells[rt as usize][ct as usize].value = value;
-                sheet.all_cells[rt as usize][ct as usize].is_error = false;
-                unsafe {
-                    STATUS_EXTENSION = 0;
-                }
-            }
-        }
-
-        Expr::Function(_, _) => {
-            if expr_has_error(formula, sheet) {
-                sheet.all_cells[rt as usize][ct as usize].is_error = true;
-                unsafe {
-                    STATUS_EXTENSION = 2;
-                }
-            } else {
-                sheet.all_cells[rt as usize][ct as usize].value = value;
-                sheet.all_cells[rt as usize][ct as usize].is_error = false;
-                unsafe {
-                    STATUS_EXTENSION = 0;
-                }
-            }
+    }
+    for (start, end) in ranges {
+        if let Some((start, end)) = clip_range(sheet, &start, &end) {
+            sheet.range_dependents.insert(&start, &end, &dependent);
         }
-
-        Expr::Range(_, _) => {
-            unsafe {
-                STATUS_EXTENSION = 12;
-                //review this later
-            }
+    }
+    for (name, start, end) in extract_sheet_references(formula) {
+        if let Some((start, end)) = clip_external_range(&start, &end) {
+            sheet
+                .external_dependents
+                .entry(sheet_key(&name))
+                .or_default()
+                .insert(&start, &end, &dependent);
         }
     }
 }
 
-/// Checks if an expression has an error.
+/// Removes the dependencies recorded for a formula by `link_formula_extension`.
 ///
 /// # Arguments
-/// * `expr` - A reference to the expression.
-/// * `sheet` - A reference to the spreadsheet.
-///
-/// # Returns
-/// `true` if the expression has an error, `false` otherwise.
-pub fn expr_has_error(expr: &Expr, sheet: &SpreadsheetExtension) -> bool {
-    match expr {
-        Expr::Number(_) => {
-            // Constants can't have errors
-            false
-        }
-
-        Expr::Cell(cell_ref) => {
-            // Check if the referenced cell has an error
-            if cell_ref.row < 0
-                || cell_ref.row >= sheet.rows
-                || cell_ref.column < 0
-                || cell_ref.column >= sheet.columns
-            {
-                // Out of bounds reference is an error
-                true
-            } else {
-                sheet.all_cells[cell_ref.row as usize][cell_ref.column as usize].is_error
-            }
-        }
-
-        Expr::BinaryOp(left, op, right) => {
-            // Check if either operand has an error
-            expr_has_error(left, sheet)
-                || expr_has_error(right, sheet)
-                || (*op == '/' && matches!(*(*right), Expr::Number(n) if n == 0))
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `formula` - The formula the dependencies were recorded for.
+/// * `rt` - The row index of the cell holding the formula.
+/// * `ct` - The column index of the cell holding the formula.
+pub fn unlink_formula_extension(
+    sheet: &mut SpreadsheetExtension,
+    formula: &Expr,
+    rt: i32,
+    ct: i32,
+) {
+    let dependent = CellReference::new(rt, ct);
+    let formula = &resolve_names(formula, sheet);
+    let (cells, ranges) = extract_references(formula);
+    for prec in cells {
+        if in_sheet(sheet, &prec) {
+            delete_dependency_extension(sheet, prec.row, prec.column, rt, ct);
         }
-
-        Expr::Function(_, args) => {
-            // Check if any function argument has an error
-            for arg in args {
-                if expr_has_error(arg, sheet) {
-                    return true;
-                }
-            }
-            false
+    }
+    clear_precedents_extension(sheet, rt, ct);
+    for (start, end) in ranges {
+        if let Some((start, end)) = clip_range(sheet, &start, &end) {
+            sheet.range_dependents.remove(&start, &end, &dependent);
         }
-
-        Expr::Range(start, end) => {
-            // Check all cells in the range for errors
-            for r in start.row..=end.row {
-                for c in start.column..=end.column {
-                    if r < 0 || r >= sheet.rows || c < 0 || c >= sheet.columns {
-                        return true; // Out of bounds
-                    }
-                    if sheet.all_cells[r as usize][c as usize].is_error {
-                        return true;
-                    }
-                }
+    }
+    for (name, start, end) in extract_sheet_references(formula) {
+        let key = sheet_key(&name);
+        if let (Some(index), Some((start, end))) = (
+            sheet.external_dependents.get_mut(&key),
+            clip_external_range(&start, &end),
+        ) {
+            index.remove(&start, &end, &dependent);
+            if index.entry_count() == 0 {
+                sheet.external_dependents.remove(&key);
             }
-            false
         }
     }
 }
 
-/// Checks if a division by zero occurs in the formula of a specific cell.
+/// Returns the cells whose formulas refer to a cell, on its own or through a range.
 ///
 /// # Arguments
 /// * `sheet` - A reference to the spreadsheet.
-/// * `rt` - The row index of the cell.
-/// * `ct` - The column index of the cell.
+/// * `row` - The row index of the cell.
+/// * `column` - The column index of the cell.
 ///
 /// # Returns
-/// `true` if a division by zero is detected, `false` otherwise.
-pub fn zero_div_err_extension(sheet: &SpreadsheetExtension, rt: i32, ct: i32) -> bool {
-    let formula = &sheet.all_cells[rt as usize][ct as usize].formula;
-    check_division_by_zero(formula, sheet)
+/// Each dependent cell once.
+pub fn dependents_of_extension(
+    sheet: &SpreadsheetExtension,
+    row: i32,
+    column: i32,
+) -> HashSet<CellReference> {
+    let mut dependents = sheet.all_cells[row as usize][column as usize]
+        .dependents
+        .clone();
+    dependents.extend(sheet.range_dependents.dependents_of(row, column).cloned());
+    dependents
 }
 
-/// Recursively checks if a division by zero occurs in an expression.
-///
-/// # Arguments
-/// * `expr` - A reference to the expression to analyze.
-/// * `sheet` - A reference to the spreadsheet.
-///
-/// # Returns
-/// `true` if a division by zero is detected, `false` otherwise.
-fn check_division_by_zero(expr: &Expr, sheet: &SpreadsheetExtension) -> bool {
-    match expr {
-        Expr::Number(_) => false,
-
-        Expr::Cell(cell_ref) => {
-            sheet.all_cells[cell_ref.row as usize][cell_ref.column as usize].is_error
-        }
-
-        Expr::BinaryOp(left, op, right) => {
-            if *op == '/' {
-                // Check if right operand evaluates to zero
-                let right_value = eval_expr(right, sheet);
-                if right_value == 0 {
-                    return true;
-                }
-            }
-
-            // Check both sides recursively
-            check_division_by_zero(left, sheet) || check_division_by_zero(right, sheet)
-        }
+/// Returns the `(row, column)` of the cells whose formulas refer to a cell.
+fn dependent_cells(sheet: &SpreadsheetExtension, row: i32, column: i32) -> Vec<(i32, i32)> {
+    dependents_of_extension(sheet, row, column)
+        .into_iter()
+        .map(|dependent| (dependent.row, dependent.column))
+        .collect()
+}
 
-        Expr::Function(_, args) => {
-            // Check all function arguments recursively
-            for arg in args {
-                if check_division_by_zero(arg, sheet) {
-                    return true;
-                }
-            }
-            false
+/// Returns the written cells that a cell's formula depends on, each once.
+fn precedent_cells(sheet: &SpreadsheetExtension, row: i32, column: i32) -> HashSet<(i32, i32)> {
+    let cell = &sheet.all_cells[row as usize][column as usize];
+    let (_, ranges) = extract_references(&resolve_names(&cell.formula, sheet));
+    let mut precedents: HashSet<(i32, i32)> = cell
+        .precedents
+        .iter()
+        .map(|prec| (prec.row, prec.column))
+        .collect();
+    for (start, end) in ranges {
+        if let Some((start, end)) = clip_range(sheet, &start, &end) {
+            let written = sheet.all_cells.cells_in(
+                (start.row as usize, start.column as usize),
+                (end.row as usize, end.column as usize),
+            );
+            precedents.extend(written.map(|((r, c), _)| (r as i32, c as i32)));
         }
-
-        Expr::Range(_, _) => false, // Range itself doesn't have division
     }
+    precedents
 }
 
-/// Checks if any of the precedents of a specific cell have an error.
+/// Calculates the value of a cell based on its formula.
 ///
 /// # Arguments
-/// * `sheet` - A reference to the spreadsheet.
+/// * `sheet` - A mutable reference to the spreadsheet.
 /// * `rt` - The row index of the cell.
 /// * `ct` - The column index of the cell.
 ///
-/// # Returns
-/// `true` if any precedent of the cell has an error, `false` otherwise.
-pub fn precedent_has_error_extension(sheet: &SpreadsheetExtension, rt: i32, ct: i32) -> bool {
-    let precedents = &sheet.all_cells[rt as usize][ct as usize].precedents;
-    for cell_ref in precedents {
-        if sheet.all_cells[cell_ref.row as usize][cell_ref.column as usize].is_error {
-            return true;
-        } else {
-            continue;
-        }
-    }
-    false
+/// # Behavior
+/// Updates the cell's value from its formula. Errors, including those of precedent cells,
+/// are stored in the value as `CellValue::Error`.
+pub fn calculate_cell_value_extension(sheet: &mut SpreadsheetExtension, rt: i32, ct: i32) {
+    let formula = &sheet.all_cells[rt as usize][ct as usize].formula;
+    let value = eval_expr(formula, sheet);
+    sheet.all_cells[rt as usize][ct as usize].value = value;
 }
-/// Performs a depth-first search (DFS) to detect cycles in the dependency graph.
+
+/// Finds the loop of formulas running through a cell, if there is one.
 ///
 /// # Arguments
 /// * `sheet` - A reference to the spreadsheet.
-/// * `rs` - The row index of the current cell.
-/// * `cs` - The column index of the current cell.
-/// * `visited` - A mutable set of visited cells.
-/// * `recursion_stack` - A mutable set representing the current recursion stack.
+/// * `row` - The row index of the cell.
+/// * `col` - The column index of the cell.
 ///
 /// # Returns
-/// `true` if a cycle is detected, `false` otherwise.
-pub fn dfs_cycle_detection(
+/// The cells forming the loop, starting and ending with the given cell, each referring to
+/// the next (see `cycle_through`), or `None` if the cell is not on a loop.
+pub fn find_cycle_extension(
     sheet: &SpreadsheetExtension,
-    rs: i32,
-    cs: i32,
-    visited: &mut HashSet<(i32, i32)>,
-    recursion_stack: &mut HashSet<(i32, i32)>,
-) -> bool {
-    let cell_coord = (rs, cs);
-    if recursion_stack.contains(&cell_coord) {
-        return true;
-    }
-
-    if visited.contains(&cell_coord) {
-        return false;
-    }
-
-    visited.insert(cell_coord);
-    recursion_stack.insert(cell_coord);
-
-    let curr_cell = &sheet.all_cells[rs as usize][cs as usize];
-
-    for dependent in &curr_cell.dependents {
-        if dfs_cycle_detection(
-            sheet,
-            dependent.row,
-            dependent.column,
-            visited,
-            recursion_stack,
-        ) {
-            return true;
-        }
-    }
-
-    recursion_stack.remove(&cell_coord);
-
-    false
+    row: i32,
+    col: i32,
+) -> Option<Vec<CellReference>> {
+    let cycle = cycle_through((row, col), |(r, c)| dependent_cells(sheet, r, c))?;
+    Some(
+        cycle
+            .into_iter()
+            .map(|(r, c)| CellReference::new(r, c))
+            .collect(),
+    )
 }
 
 /// Checks if a cycle exists in the dependency graph starting from a specific cell.
@@ -401,11 +323,9 @@ pub fn dfs_cycle_detection(
 /// * `col` - The column index of the starting cell.
 ///
 /// # Returns
-/// `true` if a cycle is detected, `false` otherwise.
+/// `true` if the cell is on a cycle, `false` otherwise.
 pub fn has_cycle(sheet: &SpreadsheetExtension, row: i32, col: i32) -> bool {
-    let mut visited: HashSet<(i32, i32)> = HashSet::new();
-    let mut recursion_stack: HashSet<(i32, i32)> = HashSet::new();
-    dfs_cycle_detection(sheet, row, col, &mut visited, &mut recursion_stack)
+    find_cycle_extension(sheet, row, col).is_some()
 }
 
 /// Recalculates the values of all dependent cells in the spreadsheet.
@@ -416,30 +336,117 @@ pub fn has_cycle(sheet: &SpreadsheetExtension, row: i32, col: i32) -> bool {
 /// * `cs` - The column index of the starting cell.
 ///
 /// # Behavior
-/// Updates the values of all dependent cells and propagates errors if necessary.
+/// Updates the values of all dependent cells in topological order (see
+/// `recalculation_order`), so each is evaluated once, after its precedents. Error values
+/// propagate to every dependent through `eval_expr`. Under iterative calculation, loops of
+/// formulas are evaluated with `iterate_cycle`, including one through the starting cell.
 pub fn recalculate_dependents_extension(sheet: &mut SpreadsheetExtension, rs: i32, cs: i32) {
-    let mut q: VecDeque<(i32, i32)> = VecDeque::new();
-    {
-        let cell = &sheet.all_cells[rs as usize][cs as usize];
-        for dependent in &cell.dependents {
-            q.push_back((dependent.row, dependent.column));
+    if let Some(settings) = sheet.iteration {
+        let steps = recalculation_steps(&[(rs, cs)], |(r, c)| dependent_cells(sheet, r, c));
+        // The starting cell has already been evaluated unless it is on a loop
+        for step in steps {
+            if step != RecalcStep::Cell((rs, cs)) {
+                evaluate_step(sheet, step, settings);
+            }
         }
+        return;
+    }
+
+    let order = recalculation_order((rs, cs), |(r, c)| dependent_cells(sheet, r, c));
+    for (curr_r, curr_c) in order {
+        calculate_cell_value_extension(sheet, curr_r, curr_c);
     }
+}
+
+/// Evaluates one step of `recalculation_steps`.
+fn evaluate_step(sheet: &mut SpreadsheetExtension, step: RecalcStep, settings: IterationSettings) {
+    match step {
+        RecalcStep::Cell((r, c)) => calculate_cell_value_extension(sheet, r, c),
+        RecalcStep::Cycle(cells) => iterate_cycle(sheet, &cells, settings),
+    }
+}
+
+/// Rebuilds every cell's `dependents` and `precedents`, and the sheet's
+/// `range_dependents` and `external_dependents`, from the formulas.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+///
+/// # Behavior
+/// Used after cells have moved, when the stored dependencies no longer match the
+/// positions of the cells. References outside the sheet (which evaluate to `#REF!`) are
+/// not recorded.
+pub fn rebuild_dependencies_extension(sheet: &mut SpreadsheetExtension) {
+    let mut formulas = Vec::new();
+    for ((r, c), cell) in sheet.all_cells.cells_mut() {
+        cell.dependents.clear();
+        cell.precedents.clear();
+        formulas.push((cell.formula.clone(), r as i32, c as i32));
+    }
+    sheet.range_dependents.clear();
+    sheet.external_dependents.clear();
+
+    for (formula, r, c) in formulas {
+        link_formula_extension(sheet, &formula, r, c);
+    }
+}
+
+/// Recalculates every cell, each after the cells it depends on.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+///
+/// # Behavior
+/// Cells are evaluated in dependency order, so each is evaluated once. Cells that are part
+/// of a cycle, or depend on one, are set to `#CYCLE!`, or evaluated with `iterate_cycle`
+/// under iterative calculation. Cells that were never written are skipped.
+pub fn recalculate_all_extension(sheet: &mut SpreadsheetExtension) {
+    let mut pending: HashMap<(i32, i32), usize> = sheet
+        .all_cells
+        .cells()
+        .map(|((r, c), _)| {
+            let (r, c) = (r as i32, c as i32);
+            ((r, c), precedent_cells(sheet, r, c).len())
+        })
+        .collect();
+
+    let mut q: VecDeque<(i32, i32)> = pending
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(position, _)| *position)
+        .collect();
 
     while let Some((curr_r, curr_c)) = q.pop_front() {
-        if zero_div_err_extension(sheet, curr_r, curr_c)
-            || precedent_has_error_extension(sheet, curr_r, curr_c)
-        {
-            sheet.all_cells[curr_r as usize][curr_c as usize].is_error = true;
-            unsafe {
-                STATUS_EXTENSION = 2;
+        calculate_cell_value_extension(sheet, curr_r, curr_c);
+        for dep_ref in dependents_of_extension(sheet, curr_r, curr_c) {
+            let count = pending
+                .get_mut(&(dep_ref.row, dep_ref.column))
+                .expect("dependents are stored cells");
+            *count -= 1;
+            if *count == 0 {
+                q.push_back((dep_ref.row, dep_ref.column));
+            }
+        }
+    }
+
+    // Whatever was never reached is waiting on a cycle
+    let mut waiting: Vec<(i32, i32)> = pending
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(position, _)| position)
+        .collect();
+    waiting.sort_unstable();
+    match sheet.iteration {
+        Some(settings) => {
+            let steps = recalculation_steps(&waiting, |(r, c)| dependent_cells(sheet, r, c));
+            for step in steps {
+                evaluate_step(sheet, step, settings);
             }
-        } else {
-            calculate_cell_value_extension(sheet, curr_r, curr_c);
         }
-        let curr_cell = &sheet.all_cells[curr_r as usize][curr_c as usize];
-        for dep_ref in &curr_cell.dependents {
-            q.push_back((dep_ref.row, dep_ref.column));
+        None => {
+            for (r, c) in waiting {
+                sheet.all_cells[r as usize][c as usize].value = CellValue::Error(CellError::Cycle);
+            }
         }
     }
 }
@@ -452,6 +459,12 @@ pub fn recalculate_dependents_extension(sheet: &mut SpreadsheetExtension, rs: i3
 /// * `ct` - The column index of the cell.
 /// * `formula` - The formula to assign to the cell.
 ///
+/// # Returns
+/// * `Ok(Outcome::Done)` if the formula was assigned.
+/// * `Ok(Outcome::Message)` if the formula was assigned but the cell evaluates to an error.
+/// * `Err(SheetError::CyclicDependency)` with the cells forming the loop if the formula
+///   would create a cycle and iterative calculation is off; the cell is left unchanged.
+///
 /// # Behavior
 /// * Updates the cell's formula and recalculates its value.
 /// * Updates the dependency graph and propagates changes to dependent cells.
@@ -462,88 +475,201 @@ pub fn assign_cell_extension(
     rt: i32,
     ct: i32,
     formula: Expr,
-) {
-    let old_state = CellState {
-        row: rt,
-        column: ct,
-        is_error: sheet.all_cells[rt as usize][ct as usize].is_error,
-        formula: sheet.all_cells[rt as usize][ct as usize].formula.clone(),
-        precedents: sheet.all_cells[rt as usize][ct as usize].precedents.clone(),
-    };
+) -> CommandResult {
+    let old_state = current_cell_state(sheet, rt, ct);
+    let old_formula = old_state.formula.clone();
 
-    undo_manager.push_state(old_state);
+    unlink_formula_extension(sheet, &old_formula, rt, ct);
+    sheet.all_cells[rt as usize][ct as usize].formula = formula.clone();
+    link_formula_extension(sheet, &formula, rt, ct);
 
-    let old_precedents = {
-        let cell = &sheet.all_cells[rt as usize][ct as usize];
-        cell.precedents.clone()
-    };
+    // Under iterative calculation loops are allowed and evaluated until they settle
+    if sheet.iteration.is_none()
+        && let Some(cycle) = find_cycle_extension(sheet, rt, ct)
+    {
+        unlink_formula_extension(sheet, &formula, rt, ct);
+        link_formula_extension(sheet, &old_formula, rt, ct);
+        sheet.all_cells[rt as usize][ct as usize].formula = old_formula;
 
-    for prec_ref in &old_precedents {
-        delete_dependency_extension(sheet, prec_ref.row, prec_ref.column, rt, ct);
+        return Err(SheetError::CyclicDependency(cycle));
     }
 
-    clear_precedents_extension(sheet, rt, ct);
-
-    let old_formula = {
-        let cell = &sheet.all_cells[rt as usize][ct as usize];
-        cell.formula.clone()
-    };
+    undo_manager.push_state(old_state);
+    calculate_cell_value_extension(sheet, rt, ct);
+    recalculate_dependents_extension(sheet, rt, ct);
+    match &sheet.all_cells[rt as usize][ct as usize].value {
+        CellValue::Error(error) => Ok(Outcome::Message(format!(
+            "{} evaluates to {}",
+            CellReference::new(rt, ct),
+            error
+        ))),
+        _ => Ok(Outcome::Done),
+    }
+}
+/// A snapshot of a single cell, recorded before it is changed so the change can be undone.
+#[derive(Clone)]
+pub struct CellState {
+    /// The row index of the cell (0-based).
+    pub row: i32,
+    /// The column index of the cell (0-based).
+    pub column: i32,
+    /// The formula the cell held when the snapshot was taken.
+    pub formula: Expr,
+    /// Whether the cell was bold.
+    pub is_bold: bool,
+    /// Whether the cell was italicized.
+    pub is_italics: bool,
+}
 
-    {
-        let cell = &mut sheet.all_cells[rt as usize][ct as usize];
-        cell.formula = formula.clone();
+impl CellState {
+    /// Takes a snapshot of everything about a cell that a command can change.
+    pub fn capture(cell: &CellExtension) -> Self {
+        CellState {
+            row: cell.r,
+            column: cell.c,
+            formula: cell.formula.clone(),
+            is_bold: cell.is_bold,
+            is_italics: cell.is_italics,
+        }
     }
+}
+
+/// A change recorded on the undo or redo stack.
+#[derive(Clone)]
+pub enum UndoEntry {
+    /// A cell was assigned a new formula; holds the cell as it was before.
+    Cell(CellState),
+    /// A row or column was inserted or deleted.
+    Structure(StructureChange),
+    /// A name was defined, changed or removed; holds its definition as it was before, or
+    /// `None` if it was not defined.
+    Name {
+        name: String,
+        previous: Option<DefinedName>,
+    },
+    /// The changes made by one command, in the order they were made; undone as one step.
+    Group(Vec<UndoEntry>),
+}
 
-    let precedents = extract_precedents(&formula);
-    for cell_ref in &precedents {
-        add_dependency_extension(sheet, cell_ref.row, cell_ref.column, rt, ct);
+impl UndoEntry {
+    /// Returns `true` if the entry inserts, deletes or resizes rows or columns.
+    fn changes_structure(&self) -> bool {
+        match self {
+            UndoEntry::Cell(_) | UndoEntry::Name { .. } => false,
+            UndoEntry::Structure(_) => true,
+            UndoEntry::Group(entries) => entries.iter().any(UndoEntry::changes_structure),
+        }
     }
 
-    if !has_cycle(sheet, rt, ct) {
-        if !zero_div_err_extension(sheet, rt, ct) {
-            calculate_cell_value_extension(sheet, rt, ct);
-            recalculate_dependents_extension(sheet, rt, ct);
-            unsafe {
-                STATUS_EXTENSION = 0;
-            }
-        } else {
-            sheet.all_cells[rt as usize][ct as usize].is_error = true;
-            unsafe {
-                STATUS_EXTENSION = 2;
+    /// Collects the cell states of the entry, in the order they were recorded.
+    fn cell_states<'a>(&'a self, states: &mut Vec<&'a CellState>) {
+        match self {
+            UndoEntry::Cell(state) => states.push(state),
+            UndoEntry::Structure(_) | UndoEntry::Name { .. } => {}
+            UndoEntry::Group(entries) => {
+                for entry in entries {
+                    entry.cell_states(states);
+                }
             }
-            recalculate_dependents_extension(sheet, rt, ct);
         }
-    } else {
-        unsafe {
-            STATUS_EXTENSION = 3;
-        }
-        sheet.all_cells[rt as usize][ct as usize].formula = old_formula;
+    }
+}
 
-        for old_precedent in &old_precedents {
-            add_dependency_extension(sheet, old_precedent.row, old_precedent.column, rt, ct);
+/// Describes a change for the undo history: the structural changes and names it made and the
+/// cells it changed, e.g. `A1`, `B2:C5`, `name revenue` or `insert_row 3, A4`.
+fn change_label(entry: &UndoEntry) -> String {
+    fn structure_labels(entry: &UndoEntry, labels: &mut Vec<String>) {
+        match entry {
+            UndoEntry::Cell(_) => {}
+            UndoEntry::Structure(change) => labels.push(change.label()),
+            UndoEntry::Name { name, .. } => labels.push(format!("name {}", name)),
+            UndoEntry::Group(entries) => {
+                for entry in entries {
+                    structure_labels(entry, labels);
+                }
+            }
         }
+    }
 
-        let new_precedents = extract_precedents(&formula);
-        for new_precedent in &new_precedents {
-            delete_dependency_extension(sheet, new_precedent.row, new_precedent.column, rt, ct);
+    let mut labels = Vec::new();
+    structure_labels(entry, &mut labels);
+    let mut states = Vec::new();
+    entry.cell_states(&mut states);
+    let rows = states.iter().map(|state| state.row);
+    let columns = states.iter().map(|state| state.column);
+    if let (Some(start_row), Some(end_row), Some(start_col), Some(end_col)) = (
+        rows.clone().min(),
+        rows.max(),
+        columns.clone().min(),
+        columns.max(),
+    ) {
+        let start = CellReference::new(start_row, start_col);
+        if (start_row, start_col) == (end_row, end_col) {
+            labels.push(start.to_string());
+        } else {
+            labels.push(format!(
+                "{}:{}",
+                start,
+                CellReference::new(end_row, end_col)
+            ));
         }
     }
+    labels.join(", ")
 }
+
+/// A state of the sheet in the undo history, reached from its parent by one change.
 #[derive(Clone)]
-pub struct CellState {
-    row: i32,
-    column: i32,
-    formula: Expr,
-    is_error: bool,
-    precedents: HashSet<CellReference>,
+struct UndoNode {
+    /// The state this one was reached from, or `None` for the oldest state kept.
+    parent: Option<usize>,
+    /// The change between the parent and this state. While this state is on the path from
+    /// the oldest state to the current one, it holds the change that undoes it; otherwise it
+    /// holds the change that redoes it.
+    change: Option<UndoEntry>,
+    /// Describes the change, see `change_label`.
+    label: String,
+    /// When the change was made.
+    time: DateTime<Local>,
+    /// The child that redo moves to: the one most recently made or undone.
+    redo_child: Option<usize>,
+}
+
+/// A state in the undo history, as listed by `UndoRedoStack::history`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryState {
+    /// The number `undo_to` takes to return to this state. Numbers count up as changes are
+    /// made and are never reused.
+    pub number: usize,
+    /// The state this one was reached from, or `None` for the oldest state kept.
+    pub parent: Option<usize>,
+    /// The change that led to this state, e.g. `A1`, `B2:C5` or `insert_row 3`.
+    pub label: String,
+    /// When the change was made.
+    pub time: DateTime<Local>,
+    /// Whether the sheet is in this state now.
+    pub is_current: bool,
 }
 
-//When an assignment is done to a cell push onto the undo stack
-//When an undo is done pop off the undo stack and push onto the redo stack
+/// The undo history of a sheet, kept as a tree like vim's undo tree.
+///
+/// Each change moves the sheet to a new state, a child of the state it was made in. Undo
+/// moves to the parent and redo to the child most recently made or undone, so a change made
+/// after an undo starts a new branch rather than discarding the undone changes; they can
+/// still be reached with `undo_to`.
 #[derive(Clone)]
 pub struct UndoRedoStack {
-    undo_stack: Vec<CellState>,
-    redo_stack: Vec<CellState>,
+    /// Every state kept, by number.
+    nodes: BTreeMap<usize, UndoNode>,
+    /// The oldest state kept; it cannot be undone.
+    root: usize,
+    /// The state the sheet is in.
+    current: usize,
+    /// The number of the next state.
+    next_number: usize,
+    /// The most undo steps kept from the current state; older states are dropped.
+    max_depth: usize,
+    /// The changes recorded so far by the open transaction, if any.
+    transaction: Option<Vec<UndoEntry>>,
 }
 
 impl Default for UndoRedoStack {
@@ -552,124 +678,404 @@ impl Default for UndoRedoStack {
     }
 }
 
+/// Returns the undo steps recorded after the last structural change, oldest first, each as
+/// the cell states it restores.
+fn cell_steps_since_structure_change<'a>(entries: &[&'a UndoEntry]) -> Vec<Vec<&'a CellState>> {
+    let start = entries
+        .iter()
+        .rposition(|entry| entry.changes_structure())
+        .map_or(0, |index| index + 1);
+    entries[start..]
+        .iter()
+        .map(|entry| {
+            let mut states = Vec::new();
+            entry.cell_states(&mut states);
+            states
+        })
+        // Steps that only changed names are not saved
+        .filter(|states| !states.is_empty())
+        .collect()
+}
+
+/// Turns a saved undo step back into an entry.
+fn entry_from_step(mut step: Vec<CellState>) -> UndoEntry {
+    if step.len() == 1 {
+        UndoEntry::Cell(step.remove(0))
+    } else {
+        UndoEntry::Group(step.into_iter().map(UndoEntry::Cell).collect())
+    }
+}
+
 impl UndoRedoStack {
     pub fn new() -> Self {
+        let root = UndoNode {
+            parent: None,
+            change: None,
+            label: "original".to_string(),
+            time: Local::now(),
+            redo_child: None,
+        };
         UndoRedoStack {
-            undo_stack: Vec::with_capacity(MAX_UNDO),
-            redo_stack: Vec::new(),
+            nodes: BTreeMap::from([(0, root)]),
+            root: 0,
+            current: 0,
+            next_number: 1,
+            max_depth: DEFAULT_UNDO_DEPTH,
+            transaction: None,
         }
     }
 
-    pub fn push_state(&mut self, state: CellState) {
-        self.undo_stack.push(state);
+    /// Rebuilds an undo manager from previously saved undo and redo steps, each given as the
+    /// cell states it restores. The redo steps are in the order `redo_steps` returns them.
+    ///
+    /// The oldest undo steps are dropped if there are more than `DEFAULT_UNDO_DEPTH`.
+    pub fn from_steps(undo_steps: Vec<Vec<CellState>>, redo_steps: Vec<Vec<CellState>>) -> Self {
+        let mut manager = UndoRedoStack::new();
+        for step in undo_steps {
+            manager.current = manager.add_node(manager.current, entry_from_step(step));
+        }
+        let mut parent = manager.current;
+        for step in redo_steps.into_iter().rev() {
+            parent = manager.add_node(parent, entry_from_step(step));
+        }
+        manager.trim();
+        manager
+    }
 
-        // self.redo_stack.clear();
+    /// Returns the states from `number` back to the oldest state kept, `number` first.
+    fn path_to_root(&self, number: usize) -> Vec<usize> {
+        let mut path = vec![number];
+        while let Some(parent) = self.nodes[path.last().unwrap()].parent {
+            path.push(parent);
+        }
+        path
+    }
 
-        // self.redo_stack.clear();
+    /// Returns the undo steps, oldest first, each as the cell states it restores.
+    ///
+    /// Only the steps recorded after the last row or column insert or delete are returned;
+    /// older steps describe cells at positions that have since moved. Steps that only
+    /// changed names are left out.
+    pub fn undo_steps(&self) -> Vec<Vec<&CellState>> {
+        let entries: Vec<&UndoEntry> = self
+            .path_to_root(self.current)
+            .iter()
+            .rev()
+            .filter_map(|number| self.nodes[number].change.as_ref())
+            .collect();
+        cell_steps_since_structure_change(&entries)
+    }
 
-        if self.undo_stack.len() > MAX_UNDO {
-            self.undo_stack.remove(0);
+    /// Returns the steps redo would make, last first, each as the cell states it restores.
+    ///
+    /// As with `undo_steps`, steps older than the last structural change are left out. Only
+    /// the branch redo follows is returned.
+    pub fn redo_steps(&self) -> Vec<Vec<&CellState>> {
+        let mut entries = Vec::new();
+        let mut next = self.nodes[&self.current].redo_child;
+        while let Some(number) = next {
+            let node = &self.nodes[&number];
+            entries.extend(node.change.as_ref());
+            next = node.redo_child;
         }
+        entries.reverse();
+        cell_steps_since_structure_change(&entries)
     }
 
-    pub fn can_undo(&self) -> bool {
-        !self.undo_stack.is_empty()
+    /// Lists every state kept, oldest first.
+    pub fn history(&self) -> Vec<HistoryState> {
+        self.nodes
+            .iter()
+            .map(|(&number, node)| HistoryState {
+                number,
+                parent: node.parent,
+                label: node.label.clone(),
+                time: node.time,
+                is_current: number == self.current,
+            })
+            .collect()
     }
 
-    pub fn can_redo(&self) -> bool {
-        !self.redo_stack.is_empty()
+    /// Returns the number of the state the sheet is in.
+    pub fn current_state(&self) -> usize {
+        self.current
     }
 
-    pub fn pop_undo(&mut self) -> Option<CellState> {
-        self.undo_stack.pop()
+    /// Returns the most undo steps kept.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
     }
 
-    pub fn push_redo(&mut self, state: CellState) {
-        self.redo_stack.push(state);
+    /// Changes the most undo steps kept, dropping the oldest states if there are more.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+        self.trim();
     }
 
-    pub fn pop_redo(&mut self) -> Option<CellState> {
-        self.redo_stack.pop()
+    pub fn push_state(&mut self, state: CellState) {
+        self.push_entry(UndoEntry::Cell(state));
     }
-}
 
-pub fn perform_undo(sheet: &mut SpreadsheetExtension, undo_manager: &mut UndoRedoStack) -> bool {
-    if let Some(old_state) = undo_manager.pop_undo() {
-        let current_state = CellState {
-            row: old_state.row,
-            column: old_state.column,
-            formula: sheet.all_cells[old_state.row as usize][old_state.column as usize]
-                .formula
-                .clone(),
-            precedents: sheet.all_cells[old_state.row as usize][old_state.column as usize]
-                .precedents
-                .clone(),
-            is_error: sheet.all_cells[old_state.row as usize][old_state.column as usize].is_error,
-        };
+    /// Records a fresh change. Inside a transaction it joins the transaction's step;
+    /// otherwise it becomes an undo step of its own, on a new branch if the current state
+    /// already has later changes. Either way there is nothing left to redo.
+    pub fn push_entry(&mut self, entry: UndoEntry) {
+        match &mut self.transaction {
+            Some(entries) => entries.push(entry),
+            None => self.push_undo(entry),
+        }
+    }
 
-        undo_manager.push_redo(current_state);
+    /// Runs `f`, recording every change it makes as a single undo step.
+    ///
+    /// # Arguments
+    /// * `f` - The changes to make, given this undo manager to record them with.
+    ///
+    /// # Returns
+    /// Whatever `f` returns.
+    ///
+    /// # Behavior
+    /// The step is recorded even if `f` fails part way, so undo reverts whatever it did.
+    /// Nothing is recorded if `f` made no changes. A transaction started inside another
+    /// joins the outer one.
+    pub fn transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        if self.transaction.is_some() {
+            return f(self);
+        }
+        self.transaction = Some(Vec::new());
+        let result = f(self);
+        let mut entries = self.transaction.take().unwrap_or_default();
+        match entries.len() {
+            0 => {}
+            1 => self.push_undo(entries.remove(0)),
+            _ => self.push_undo(UndoEntry::Group(entries)),
+        }
+        result
+    }
 
-        restore_cell_state(sheet, old_state);
-        true
-    } else {
-        false
+    /// Adds a state reached from `parent` by `change`, and makes it the state redo moves to.
+    fn add_node(&mut self, parent: usize, change: UndoEntry) -> usize {
+        let number = self.next_number;
+        self.next_number += 1;
+        self.nodes.insert(
+            number,
+            UndoNode {
+                parent: Some(parent),
+                label: change_label(&change),
+                change: Some(change),
+                time: Local::now(),
+                redo_child: None,
+            },
+        );
+        self.nodes.get_mut(&parent).unwrap().redo_child = Some(number);
+        number
     }
-}
 
-pub fn perform_redo(sheet: &mut SpreadsheetExtension, undo_manager: &mut UndoRedoStack) -> bool {
-    if let Some(redo_state) = undo_manager.pop_redo() {
-        let current_state = CellState {
-            row: redo_state.row,
-            column: redo_state.column,
-            is_error: redo_state.is_error,
-            formula: sheet.all_cells[redo_state.row as usize][redo_state.column as usize]
-                .formula
-                .clone(),
-            precedents: sheet.all_cells[redo_state.row as usize][redo_state.column as usize]
-                .precedents
-                .clone(),
+    /// Moves to a new state reached from the current one by `entry`.
+    fn push_undo(&mut self, entry: UndoEntry) {
+        self.current = self.add_node(self.current, entry);
+        self.trim();
+    }
+
+    /// Drops the oldest states while the current state is more than `max_depth` undo steps
+    /// from the oldest, along with the branches that only they lead to.
+    fn trim(&mut self) {
+        loop {
+            let path = self.path_to_root(self.current);
+            if path.len() <= self.max_depth + 1 {
+                return;
+            }
+            let new_root = path[path.len() - 2];
+            let kept: HashSet<usize> = self
+                .nodes
+                .keys()
+                .copied()
+                .filter(|&number| self.path_to_root(number).contains(&new_root))
+                .collect();
+            self.nodes.retain(|number, _| kept.contains(number));
+            let root = self.nodes.get_mut(&new_root).unwrap();
+            root.parent = None;
+            root.change = None;
+            self.root = new_root;
+        }
+    }
+
+    /// Reverts the change that led to the current state, moving to its parent.
+    fn step_back(&mut self, sheet: &mut SpreadsheetExtension) -> bool {
+        let current = self.current;
+        let node = self.nodes.get_mut(&current).unwrap();
+        let Some(parent) = node.parent else {
+            return false;
         };
+        if let Some(change) = node.change.take() {
+            node.change = Some(revert_entry(sheet, change));
+        }
+        self.nodes.get_mut(&parent).unwrap().redo_child = Some(current);
+        self.current = parent;
+        true
+    }
 
-        restore_cell_state(sheet, redo_state);
+    /// Makes the change that leads from the current state to its child `child`.
+    fn step_forward(&mut self, sheet: &mut SpreadsheetExtension, child: usize) {
+        let node = self.nodes.get_mut(&child).unwrap();
+        if let Some(change) = node.change.take() {
+            node.change = Some(revert_entry(sheet, change));
+        }
+        self.nodes.get_mut(&self.current).unwrap().redo_child = Some(child);
+        self.current = child;
+    }
 
-        undo_manager.push_state(current_state);
+    pub fn can_undo(&self) -> bool {
+        self.current != self.root
+    }
 
-        true
-    } else {
-        false
+    pub fn can_redo(&self) -> bool {
+        self.nodes[&self.current].redo_child.is_some()
     }
 }
 
-pub fn restore_cell_state(sheet: &mut SpreadsheetExtension, state: CellState) {
-    let precedents = sheet.all_cells[state.row as usize][state.column as usize]
-        .precedents
-        .clone();
+/// Captures the current state of a cell, so that restoring it reverts a change.
+fn current_cell_state(sheet: &SpreadsheetExtension, row: i32, column: i32) -> CellState {
+    CellState {
+        row,
+        column,
+        ..CellState::capture(&sheet.all_cells[row as usize][column as usize])
+    }
+}
 
-    for prec_ref in precedents {
-        delete_dependency_extension(
-            sheet,
-            prec_ref.row,
-            prec_ref.column,
-            state.row,
-            state.column,
-        );
+/// Records a cell on the undo stack before a command changes it directly, rather than
+/// through `assign_cell_extension` (e.g. its formatting).
+///
+/// # Arguments
+/// * `sheet` - A reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+/// * `row` - The row index of the cell.
+/// * `column` - The column index of the cell.
+pub fn record_cell_state(
+    sheet: &SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    row: i32,
+    column: i32,
+) {
+    undo_manager.push_state(current_cell_state(sheet, row, column));
+}
+
+/// Reverts a recorded change, returning the change that reverts it again.
+///
+/// The changes of a group are reverted newest first, so reverting the returned group
+/// replays them in their original order.
+fn revert_entry(sheet: &mut SpreadsheetExtension, entry: UndoEntry) -> UndoEntry {
+    match entry {
+        UndoEntry::Cell(state) => {
+            let current_state = current_cell_state(sheet, state.row, state.column);
+            restore_cell_state(sheet, state);
+            UndoEntry::Cell(current_state)
+        }
+        UndoEntry::Structure(change) => {
+            UndoEntry::Structure(revert_structure_change(sheet, change))
+        }
+        UndoEntry::Name { name, previous } => {
+            let key = name.to_ascii_uppercase();
+            let current = match previous {
+                Some(defined) => sheet.names.insert(key, defined),
+                None => sheet.names.remove(&key),
+            };
+            rebuild_dependencies_extension(sheet);
+            recalculate_all_extension(sheet);
+            UndoEntry::Name {
+                name,
+                previous: current,
+            }
+        }
+        UndoEntry::Group(entries) => UndoEntry::Group(
+            entries
+                .into_iter()
+                .rev()
+                .map(|entry| revert_entry(sheet, entry))
+                .collect(),
+        ),
     }
+}
 
-    clear_precedents_extension(sheet, state.row, state.column);
+/// Undoes the most recent undo step, which may span many cells.
+///
+/// # Returns
+/// `true` if a step was undone, `false` if there was nothing to undo.
+pub fn perform_undo(sheet: &mut SpreadsheetExtension, undo_manager: &mut UndoRedoStack) -> bool {
+    undo_manager.step_back(sheet)
+}
 
-    sheet.all_cells[state.row as usize][state.column as usize].formula = state.formula;
+/// Redoes the most recently undone step, following the branch last made or undone.
+///
+/// # Returns
+/// `true` if a step was redone, `false` if there was nothing to redo.
+pub fn perform_redo(sheet: &mut SpreadsheetExtension, undo_manager: &mut UndoRedoStack) -> bool {
+    match undo_manager.nodes[&undo_manager.current].redo_child {
+        Some(child) => {
+            undo_manager.step_forward(sheet, child);
+            true
+        }
+        None => false,
+    }
+}
 
-    for prec_ref in &state.precedents {
-        add_dependency_extension(
-            sheet,
-            prec_ref.row,
-            prec_ref.column,
-            state.row,
-            state.column,
-        );
+/// Moves the sheet to any state in its undo history, on any branch.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+/// * `number` - The number of the state, as listed by `UndoRedoStack::history`.
+///
+/// # Returns
+/// * `Ok(())` if the sheet is now in that state.
+/// * `Err(SheetError::UnknownHistoryState)` if no state has that number, e.g. because it
+///   was dropped from the history.
+///
+/// # Behavior
+/// Undoes changes back to the last state shared by the current branch and the target,
+/// then redoes the changes leading to the target. Redo then follows the target's branch.
+pub fn undo_to(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    number: usize,
+) -> Result<(), SheetError> {
+    if !undo_manager.nodes.contains_key(&number) {
+        return Err(SheetError::UnknownHistoryState(number));
+    }
+    let target_path = undo_manager.path_to_root(number);
+    while !target_path.contains(&undo_manager.current) {
+        undo_manager.step_back(sheet);
+    }
+    let forward: Vec<usize> = target_path
+        .iter()
+        .copied()
+        .take_while(|&state| state != undo_manager.current)
+        .collect();
+    for child in forward.into_iter().rev() {
+        undo_manager.step_forward(sheet, child);
+    }
+    Ok(())
+}
+
+/// Puts a cell back as recorded in `state`: its formula, its formatting and, for constant
+/// cells, its value. Cells that depend on it are recalculated.
+pub fn restore_cell_state(sheet: &mut SpreadsheetExtension, state: CellState) {
+    let (row, column) = (state.row as usize, state.column as usize);
+    let current_formula = sheet.all_cells[row][column].formula.clone();
+    unlink_formula_extension(sheet, &current_formula, state.row, state.column);
+    link_formula_extension(sheet, &state.formula, state.row, state.column);
+    let cell = &mut sheet.all_cells[row][column];
+    cell.is_bold = state.is_bold;
+    cell.is_italics = state.is_italics;
+    cell.formula = state.formula;
+
+    // Restoring cells out of order can briefly close a cycle; mark the cell instead of
+    // recalculating around the loop forever
+    if sheet.iteration.is_none() && has_cycle(sheet, state.row, state.column) {
+        sheet.all_cells[row][column].value = CellValue::Error(CellError::Cycle);
+        return;
     }
 
     calculate_cell_value_extension(sheet, state.row, state.column);
-
     recalculate_dependents_extension(sheet, state.row, state.column);
 }
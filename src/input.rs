@@ -1,4 +1,5 @@
-use crate::cellsp2::{CellReference, Operand};
+use crate::cellsp2::{CellReference, Operand, Spreadsheet};
+use crate::dependency_graph_final::{delete_col, delete_row, insert_col, insert_row};
 
 /// Checks if a string contains any alphabetic characters.
 ///
@@ -545,3 +546,58 @@ pub fn parse_input(
         // println!("i was here 6");
     }
 }
+
+/// Parses a column name such as `C` or `AB` into its 0-based index.
+///
+/// # Notes
+///
+/// If the name contains anything other than letters, it sets a global status flag.
+fn parse_column_name_1(name: &str) -> i32 {
+    let mut row = 0;
+    let mut col = 0;
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic()) {
+        parse_cell_name_1(name, &mut row, &mut col);
+    } else {
+        unsafe {
+            crate::dependency_graph_final::STATUS = 1;
+        }
+    }
+    col
+}
+
+/// Parses and applies a row or column command.
+///
+/// # Arguments
+///
+/// * `input` - The command, e.g. `insert_row 3` or `delete_col C`. Rows are given by number
+///   and columns by letter.
+/// * `sheet` - A mutable reference to the spreadsheet.
+///
+/// # Notes
+///
+/// If the command is malformed or names a line outside the sheet, it sets a global status flag.
+pub fn parse_structure_command(input: &str, sheet: &mut Spreadsheet) {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let [command, line] = parts.as_slice() else {
+        unsafe {
+            crate::dependency_graph_final::STATUS = 1;
+        }
+        return;
+    };
+
+    let (apply, index): (fn(&mut Spreadsheet, i32), i32) = match *command {
+        "insert_row" => (insert_row, string_to_int(line) - 1),
+        "delete_row" => (delete_row, string_to_int(line) - 1),
+        "insert_col" => (insert_col, parse_column_name_1(line)),
+        "delete_col" => (delete_col, parse_column_name_1(line)),
+        _ => {
+            unsafe {
+                crate::dependency_graph_final::STATUS = 1;
+            }
+            return;
+        }
+    };
+    if unsafe { crate::dependency_graph_final::STATUS } != 1 {
+        apply(sheet, index);
+    }
+}
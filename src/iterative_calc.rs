@@ -0,0 +1,121 @@
+use crate::cell_extension::{CellError, CellValue, SpreadsheetExtension};
+use crate::graph_extension::{calculate_cell_value_extension, recalculate_all_extension};
+use crate::parser_visual_mode::string_to_int;
+use crate::sheet_error::{CommandResult, Outcome, SheetError};
+
+/// How loops of formulas are evaluated when iterative calculation is turned on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IterationSettings {
+    /// The most times the cells of a loop are evaluated before giving up.
+    pub max_iterations: usize,
+    /// The largest change in any value of a loop between two passes that counts as converged.
+    pub tolerance: f64,
+}
+
+impl Default for IterationSettings {
+    fn default() -> Self {
+        IterationSettings {
+            max_iterations: 100,
+            tolerance: 0.001,
+        }
+    }
+}
+
+/// Evaluates the cells of a loop of formulas until their values settle.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `cells` - The `(row, column)` of the cells that refer to each other in a loop.
+/// * `settings` - The iteration limit and tolerance.
+///
+/// # Behavior
+/// Each pass evaluates every cell of the loop once, in order, starting from the values the
+/// cells already hold. Iteration stops once no value changes by more than the tolerance.
+/// If that does not happen within `max_iterations` passes, every cell of the loop is set to
+/// `#CONV!`. A cell already holding `#CONV!` starts again from 0.
+pub fn iterate_cycle(
+    sheet: &mut SpreadsheetExtension,
+    cells: &[(i32, i32)],
+    settings: IterationSettings,
+) {
+    for &(r, c) in cells {
+        let cell = &mut sheet.all_cells[r as usize][c as usize];
+        if cell.value == CellValue::Error(CellError::Convergence) {
+            cell.value = CellValue::Number(0.0);
+        }
+    }
+
+    for _ in 0..settings.max_iterations {
+        let mut converged = true;
+        for &(r, c) in cells {
+            let before = sheet.all_cells[r as usize][c as usize].value.clone();
+            calculate_cell_value_extension(sheet, r, c);
+            let after = &sheet.all_cells[r as usize][c as usize].value;
+            converged &= match (&before, after) {
+                (CellValue::Number(x), CellValue::Number(y)) => (x - y).abs() <= settings.tolerance,
+                _ => before == *after,
+            };
+        }
+        if converged {
+            return;
+        }
+    }
+
+    for &(r, c) in cells {
+        sheet.all_cells[r as usize][c as usize].value = CellValue::Error(CellError::Convergence);
+    }
+}
+
+/// Handles the `iterate` command, which turns iterative calculation on or off.
+///
+/// # Arguments
+/// * `cmd` - `iterate on [max_iterations] [tolerance]` or `iterate off`. Settings left out
+///   take their default values (100 passes, a tolerance of 0.001).
+/// * `sheet` - A mutable reference to the spreadsheet.
+///
+/// # Returns
+/// * `Ok(Outcome::Message)` describing the new setting.
+/// * `Err(SheetError::InvalidCommand)` if the command is malformed.
+///
+/// # Behavior
+/// While iterative calculation is on, formulas may refer to themselves through a loop and
+/// the loop is evaluated with `iterate_cycle`. Turning it off sets the cells of every loop
+/// to `#CYCLE!`. The whole sheet is recalculated either way.
+pub fn handle_iterate_command(cmd: &str, sheet: &mut SpreadsheetExtension) -> CommandResult {
+    let parts: Vec<&str> = cmd.split_whitespace().collect();
+    let invalid = || {
+        SheetError::InvalidCommand(
+            "expected: iterate on [max_iterations] [tolerance] or iterate off".into(),
+        )
+    };
+    let settings = match parts.as_slice() {
+        ["iterate", "off"] => None,
+        ["iterate", "on", rest @ ..] if rest.len() <= 2 => {
+            let mut settings = IterationSettings::default();
+            if let Some(max_iterations) = rest.first() {
+                settings.max_iterations = match string_to_int(max_iterations)? {
+                    n if n > 0 => n as usize,
+                    _ => return Err(invalid()),
+                };
+            }
+            if let Some(tolerance) = rest.get(1) {
+                settings.tolerance = match tolerance.parse::<f64>() {
+                    Ok(t) if t >= 0.0 && t.is_finite() => t,
+                    _ => return Err(invalid()),
+                };
+            }
+            Some(settings)
+        }
+        _ => return Err(invalid()),
+    };
+
+    sheet.iteration = settings;
+    recalculate_all_extension(sheet);
+    Ok(Outcome::Message(match settings {
+        Some(settings) => format!(
+            "Iterative calculation on: up to {} passes, tolerance {}",
+            settings.max_iterations, settings.tolerance
+        ),
+        None => "Iterative calculation off".to_string(),
+    }))
+}
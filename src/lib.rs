@@ -1,9 +1,15 @@
 #[cfg(feature = "main2")]
 pub mod cell_extension;
+#[cfg(any(feature = "main1", feature = "main2"))]
+pub mod cell_grid;
 #[cfg(feature = "main2")]
 pub mod cellsp;
 #[cfg(feature = "main1")]
 pub mod cellsp2;
+#[cfg(feature = "main2")]
+pub mod clipboard;
+#[cfg(feature = "main2")]
+pub mod defined_names;
 #[cfg(feature = "main1")]
 pub mod dependency_graph_final;
 #[cfg(feature = "main2")]
@@ -17,12 +23,32 @@ pub mod forecast;
 #[cfg(feature = "main2")]
 pub mod formula;
 #[cfg(feature = "main2")]
+pub mod function_registry;
+#[cfg(feature = "main2")]
 pub mod graph_extension;
 #[cfg(feature = "main1")]
 pub mod input;
 #[cfg(feature = "main2")]
+pub mod iterative_calc;
+#[cfg(feature = "main2")]
 pub mod parser_visual_mode;
 #[cfg(feature = "main2")]
 pub mod plot_graph;
 #[cfg(feature = "main2")]
+pub mod project_file;
+#[cfg(feature = "main2")]
+pub mod range_index;
+#[cfg(feature = "main2")]
 pub mod read_mode;
+#[cfg(any(feature = "main1", feature = "main2"))]
+pub mod recalc_order;
+#[cfg(feature = "main2")]
+pub mod sheet_error;
+#[cfg(feature = "main2")]
+pub mod sheet_structure;
+#[cfg(feature = "main2")]
+pub mod undo_history;
+#[cfg(feature = "main2")]
+pub mod workbook;
+#[cfg(feature = "main2")]
+pub mod write_mode;
@@ -7,23 +7,38 @@ use std::process;
 #[cfg(feature = "main1")]
 use std::time::Instant;
 
-// #[cfg(feature = "main2")]
-// use rust_lab::cell_extension::SpreadsheetExtension;
 #[cfg(feature = "main2")]
-use rust_lab::expression_utils::parse_formula;
+use rust_lab::cell_extension::SpreadsheetExtension;
+use rust_lab::cell_grid::{MAX_COLS, MAX_ROWS};
+#[cfg(feature = "main2")]
+use rust_lab::clipboard::Registers;
 #[cfg(feature = "main2")]
-use rust_lab::graph_extension::STATUS_EXTENSION;
+use rust_lab::defined_names::handle_name_command;
 #[cfg(feature = "main2")]
-use rust_lab::graph_extension::initialise_extension;
+use rust_lab::expression_utils::parse_formula;
 #[cfg(feature = "main2")]
 use rust_lab::graph_extension::{UndoRedoStack, assign_cell_extension, perform_redo, perform_undo};
 #[cfg(feature = "main2")]
+use rust_lab::iterative_calc::handle_iterate_command;
+#[cfg(feature = "main2")]
 use rust_lab::parser_visual_mode::parse_cell_name;
 #[cfg(feature = "main2")]
 use rust_lab::parser_visual_mode::parser_visual;
 #[cfg(feature = "main2")]
+use rust_lab::project_file::{handle_load_command, handle_save_command};
+#[cfg(feature = "main2")]
 use rust_lab::read_mode::handle_read_command;
 #[cfg(feature = "main2")]
+use rust_lab::sheet_error::{CommandResult, Outcome, SheetError};
+#[cfg(feature = "main2")]
+use rust_lab::sheet_structure::handle_structure_command;
+#[cfg(feature = "main2")]
+use rust_lab::undo_history::handle_history_command;
+#[cfg(feature = "main2")]
+use rust_lab::workbook::{Workbook, handle_sheet_command, handle_undo_depth_command};
+#[cfg(feature = "main2")]
+use rust_lab::write_mode::handle_write_command;
+#[cfg(feature = "main2")]
 use std::sync::{Arc, Mutex};
 
 #[cfg(feature = "main1")]
@@ -31,6 +46,8 @@ use rust_lab::cellsp2::Operand;
 #[cfg(feature = "main1")]
 use rust_lab::cellsp2::Spreadsheet;
 #[cfg(feature = "main1")]
+use rust_lab::dependency_graph_final::LAST_CYCLE;
+#[cfg(feature = "main1")]
 use rust_lab::dependency_graph_final::STATUS;
 #[cfg(feature = "main1")]
 use rust_lab::dependency_graph_final::assign_cell;
@@ -40,9 +57,9 @@ use rust_lab::dependency_graph_final::initialise;
 use rust_lab::input::parse_cell_name_1;
 #[cfg(feature = "main1")]
 use rust_lab::input::parse_input;
+#[cfg(feature = "main1")]
+use rust_lab::input::parse_structure_command;
 
-const MAX_ROWS: i32 = 999;
-const MAX_COLS: i32 = 18278;
 #[cfg(feature = "main1")]
 const VIEWPORT_SIZE: i32 = 10;
 #[cfg(feature = "main1")]
@@ -56,6 +73,7 @@ static mut DISPLAY_COLUMN: i32 = 0;
 static mut CURRENT_MODE: ModeOfSpreadsheet = ModeOfSpreadsheet::Insert;
 
 #[cfg(feature = "main2")]
+#[derive(Clone, Copy)]
 enum ModeOfSpreadsheet {
     Normal,
     Insert,
@@ -111,6 +129,49 @@ fn parser_normal(
     }
 }
 
+/// Switches the input mode for `main2`.
+///
+/// # Arguments
+///
+/// * `mode` - The mode to switch to.
+#[cfg(feature = "main2")]
+fn set_mode(mode: ModeOfSpreadsheet) -> CommandResult {
+    unsafe {
+        CURRENT_MODE = mode;
+    }
+    Ok(Outcome::Done)
+}
+
+/// Parses a command in insert mode for `main2`, i.e. `<cell_name>=<expression>`, and assigns
+/// the expression to the cell.
+///
+/// # Arguments
+///
+/// * `input` - The command input string.
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+#[cfg(feature = "main2")]
+fn parser_insert(
+    input: &str,
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+) -> CommandResult {
+    let Some((cell_name, expression)) = input.split_once('=') else {
+        return Err(SheetError::InvalidCommand(
+            "expected: <cell_name>=<expression>".to_string(),
+        ));
+    };
+    let cell_name = cell_name.trim();
+    let (edit_r, edit_col) = parse_cell_name(cell_name)?;
+
+    if edit_r < 0 || edit_col < 0 || edit_r >= sheet.rows || edit_col >= sheet.columns {
+        return Err(SheetError::OutOfBounds(cell_name.to_string()));
+    }
+
+    let expr = *parse_formula(expression.trim())?;
+    assign_cell_extension(sheet, undo_manager, edit_r, edit_col, expr)
+}
+
 /// Displays the spreadsheet in the terminal for `main1`.
 ///
 /// # Arguments
@@ -184,6 +245,25 @@ fn get_col_label(col: i32) -> String {
 /// * `a` - Scroll left.
 /// * `d` - Scroll right.
 /// * `scroll_to <cell>` - Scroll to a specific cell.
+/// * `write <file> [values|formulas]` - Save the spreadsheet as a CSV file.
+/// * `save <file> [history]` - Save every sheet as a project file, optionally with undo history.
+/// * `load <file>` - Replace the workbook with a saved project file.
+/// * `insert_row <row>` / `delete_row <row>` - Insert an empty row before, or delete, a row.
+/// * `insert_col <column>` / `delete_col <column>` - Insert an empty column before, or delete,
+///   a column (given by letter).
+/// * `resize <rows> <columns>` - Add or cut off rows and columns at the bottom and right.
+/// * `iterate on [max_iterations] [tolerance]` / `iterate off` - Allow formulas that refer to
+///   themselves through a loop, evaluating the loop until it settles.
+/// * `sheet add [name]` / `sheet rename <old> <new>` / `sheet delete <name>` /
+///   `sheet switch <name>` - Manage the sheets of the workbook. Formulas refer to other
+///   sheets as `Sheet2!A1` or `'My Sheet'!A1:B5`.
+/// * `name <name> <cell, range or constant>` / `name <name>` / `names` - Define, remove or
+///   list names that formulas can use in place of cells, ranges and constants. Defining
+///   and removing names can be undone.
+/// * `undo_depth [steps]` - Show or change how many commands can be undone on each sheet.
+/// * `history` / `undo_to <n>` - List the undo history of the sheet, with the time of each
+///   change, or move to any state in it. Changes made after an undo start a new branch, so
+///   undone changes stay in the history.
 /// * `<cell_name>=<expression>` - Assign a formula or value to a cell.
 #[cfg(feature = "main2")]
 fn main_functionality2() {
@@ -208,27 +288,24 @@ fn main_functionality2() {
         process::exit(1);
     }
 
-    let sheet = Arc::new(Mutex::new(initialise_extension(rows, columns))); // Wrap the sheet
-    let undo_manager = Arc::new(Mutex::new(UndoRedoStack::new())); // Wrap the undo_manager in Arc<Mutex<>>
+    // Every sheet, with its undo history; commands edit the active sheet
+    let workbook = Arc::new(Mutex::new(Workbook::new(rows, columns)));
 
-    let shared_data = Arc::new(Mutex::new(vec![
-        vec![String::new(); columns as usize];
-        rows as usize
-    ]));
+    // Cell text is read from the sheet itself, so this is not sized to the sheet
+    let shared_data = Arc::new(Mutex::new(Vec::new()));
     let input_text = Arc::new(Mutex::new(String::new()));
-    let status_extension = Arc::new(Mutex::new(0)); // Shared status variable
+    let status: Arc<Mutex<CommandResult>> = Arc::new(Mutex::new(Ok(Outcome::Done))); // Result of the last command
     let current_row = Arc::new(Mutex::new(0)); // Starting row of the visible portion
     let current_col = Arc::new(Mutex::new(0)); // Starting column of the visible portion
 
     // Launch the GUI on the main thread
     rust_lab::display::launch_gui(
         shared_data.clone(), // Pass the shared data
-        sheet.clone(),       // Pass the sheet
+        workbook.clone(),    // Pass the workbook
         input_text.clone(),
-        status_extension.clone(),
+        status.clone(),
         current_row.clone(),
         current_col.clone(),
-        undo_manager.clone(), // Pass the undo_manager
     );
     // display_sheet( &sheet.lock().unwrap(), &shared_data,&current_row,&current_col);
 
@@ -236,8 +313,10 @@ fn main_functionality2() {
     let input_processing_thread = {
         let _shared_data = shared_data.clone();
         let input_text = input_text.clone();
-        let status_extension = status_extension.clone();
+        let status = status.clone();
         std::thread::spawn(move || {
+            // Clipboard registers are kept for the whole session
+            let mut registers = Registers::new();
             // let mut status_str = String::new();
             // let mut execution_time = 0.0;
             // let mut print_flag = true;
@@ -256,161 +335,154 @@ fn main_functionality2() {
                 *input = String::new(); // Clear the input after reading
                 drop(input); // Release the lock
 
-                unsafe {
-                    // status_str = match STATUS_EXTENSION {
-                    //     0 => "ok".to_string(),
-                    //     1 => {
-                    //         STATUS_EXTENSION = 0;
-                    //         "Invalid Input".to_string()
-                    //     }
-                    //     2 => {
-                    //         STATUS_EXTENSION = 0;
-                    //         "ok".to_string()
-                    //     }
-                    //     3 => {
-                    //         STATUS_EXTENSION = 0;
-                    //         "cyclic dependence".to_string()
-                    //     }
-                    //     _ => "unknown status".to_string(),
-                    // };
-
-                    // let start = Instant::now();
-
-                    match command.as_str() {
-                        "q" => break,
-                        "undo" => {
-                            perform_undo(
-                                &mut sheet.lock().unwrap(),
-                                &mut undo_manager.lock().unwrap(),
-                            );
-                        }
-                        "redo" => {
-                            perform_redo(
-                                &mut sheet.lock().unwrap(),
-                                &mut undo_manager.lock().unwrap(),
-                            );
-                        }
-                        "read" => CURRENT_MODE = ModeOfSpreadsheet::Read,
-                        "insert" => CURRENT_MODE = ModeOfSpreadsheet::Insert,
-                        "normal" => CURRENT_MODE = ModeOfSpreadsheet::Normal,
-                        "visual" => CURRENT_MODE = ModeOfSpreadsheet::Visual,
-                        "w" => {
-                            let mut dr = current_row.lock().unwrap();
-                            *dr = if *dr as i32 - 24 < 0 { 0 } else { *dr - 24 };
+                let mut workbook = workbook.lock().unwrap();
+                let active_sheet = workbook.active_index();
+                // The size changes with row and column commands, `load` and CSV reads,
+                // including reads started from the GUI
+                let (rows, columns) = {
+                    let sheet = workbook.active();
+                    (sheet.rows, sheet.columns)
+                };
+                let (sheet, undo_manager) = workbook.active_parts();
+
+                let result = match command.as_str() {
+                    "q" => break,
+                    "undo" => {
+                        if perform_undo(sheet, undo_manager) {
+                            Ok(Outcome::Done)
+                        } else {
+                            Ok(Outcome::Message("Nothing to undo".to_string()))
                         }
-                        "s" => {
-                            let mut dr = current_row.lock().unwrap();
-                            *dr = if *dr + 24 > rows as usize {
-                                *dr // No change if exceeding
-                            } else if *dr + 48 > rows as usize {
-                                rows as usize - 24
-                            } else {
-                                *dr + 24
-                            };
-                        }
-                        "a" => {
-                            let mut dc = current_col.lock().unwrap();
-                            *dc = if *dc as i32 - 15 < 0 { 0 } else { *dc - 15 };
+                    }
+                    "redo" => {
+                        if perform_redo(sheet, undo_manager) {
+                            Ok(Outcome::Done)
+                        } else {
+                            Ok(Outcome::Message("Nothing to redo".to_string()))
                         }
-                        "d" => {
-                            let mut dc = current_col.lock().unwrap();
-                            *dc = if *dc + 15 > columns as usize {
-                                *dc // No change if exceeding
-                            } else if *dc + 30 > columns as usize {
-                                columns as usize - 15
-                            } else {
-                                *dc + 15
-                            };
+                    }
+                    "read" => set_mode(ModeOfSpreadsheet::Read),
+                    "insert" => set_mode(ModeOfSpreadsheet::Insert),
+                    "normal" => set_mode(ModeOfSpreadsheet::Normal),
+                    "visual" => set_mode(ModeOfSpreadsheet::Visual),
+                    "w" => {
+                        let mut dr = current_row.lock().unwrap();
+                        *dr = if *dr as i32 - 24 < 0 { 0 } else { *dr - 24 };
+                        Ok(Outcome::Done)
+                    }
+                    "s" => {
+                        let mut dr = current_row.lock().unwrap();
+                        *dr = if *dr + 24 > rows as usize {
+                            *dr // No change if exceeding
+                        } else if *dr + 48 > rows as usize {
+                            rows as usize - 24
+                        } else {
+                            *dr + 24
+                        };
+                        Ok(Outcome::Done)
+                    }
+                    "a" => {
+                        let mut dc = current_col.lock().unwrap();
+                        *dc = if *dc as i32 - 15 < 0 { 0 } else { *dc - 15 };
+                        Ok(Outcome::Done)
+                    }
+                    "d" => {
+                        let mut dc = current_col.lock().unwrap();
+                        *dc = if *dc + 15 > columns as usize {
+                            *dc // No change if exceeding
+                        } else if *dc + 30 > columns as usize {
+                            columns as usize - 15
+                        } else {
+                            *dc + 15
+                        };
+                        Ok(Outcome::Done)
+                    }
+                    _ if command.starts_with("write ") => handle_write_command(&command, sheet),
+                    _ if command.starts_with("save ") => handle_save_command(&command, &workbook),
+                    _ if command.starts_with("load ") => {
+                        let result = handle_load_command(&command, &mut workbook);
+                        if result.is_ok() {
+                            *current_row.lock().unwrap() = 0;
+                            *current_col.lock().unwrap() = 0;
                         }
-                        _ if command.starts_with("scroll_to ") => {
-                            let mut new_row = 0;
-                            let mut new_col = 0;
-                            parse_cell_name(&command[10..], &mut new_row, &mut new_col);
+                        result
+                    }
+                    _ if command.starts_with("scroll_to ") => {
+                        let cell_name = &command[10..];
+                        parse_cell_name(cell_name).and_then(|(new_row, new_col)| {
                             if new_row < rows && new_col < columns {
-                                let mut dr = current_row.lock().unwrap();
-                                *dr = new_row as usize;
-                                let mut dc = current_col.lock().unwrap();
-                                *dc = new_col as usize;
-                                DISPLAY_ROW = 0;
-                                DISPLAY_COLUMN = 0;
-                            } else {
-                                eprintln!("Out of bounds rows or columns");
-                                STATUS_EXTENSION = 1;
-                            }
-                        }
-                        _ => match CURRENT_MODE {
-                            ModeOfSpreadsheet::Read => {
-                                handle_read_command(
-                                    &command,
-                                    &mut sheet.lock().unwrap(),
-                                    &mut undo_manager.lock().unwrap(),
-                                );
-                            }
-                            ModeOfSpreadsheet::Normal => {
-                                parser_normal(&command, rows, columns, &current_row, &current_col);
-                            }
-                            ModeOfSpreadsheet::Insert => {
-                                let parts: Vec<&str> = command.split('=').collect();
-                                if parts.len() != 2 {
-                                    // eprintln!("Invalid input format. Expected 'cell_name=expression'.");
-                                    STATUS_EXTENSION = 1;
-                                    let mut status = status_extension.lock().unwrap();
-                                    *status = STATUS_EXTENSION;
-                                    continue;
-                                }
-                                let cell_name = parts[0].trim();
-                                let expression = parts[1].trim();
-                                let mut edit_r = 0;
-                                let mut edit_col = 0;
-                                parse_cell_name(cell_name, &mut edit_r, &mut edit_col);
-
-                                if edit_r < 0
-                                    || edit_col < 0
-                                    || edit_r >= rows
-                                    || edit_col >= columns
-                                    || STATUS_EXTENSION == 1
-                                {
-                                    STATUS_EXTENSION = 1;
-                                    continue;
-                                }
-
-                                match parse_formula(expression) {
-                                    Ok(parsed_formula) => {
-                                        let expr = *parsed_formula;
-                                        if STATUS_EXTENSION != 1 {
-                                            assign_cell_extension(
-                                                &mut sheet.lock().unwrap(),
-                                                &mut undo_manager.lock().unwrap(),
-                                                edit_r,
-                                                edit_col,
-                                                expr,
-                                            );
-                                        }
-                                    }
-                                    Err(e) => {
-                                        eprintln!("Error parsing formula: {}", e);
-                                        STATUS_EXTENSION = 1;
-                                    }
+                                *current_row.lock().unwrap() = new_row as usize;
+                                *current_col.lock().unwrap() = new_col as usize;
+                                unsafe {
+                                    DISPLAY_ROW = 0;
+                                    DISPLAY_COLUMN = 0;
                                 }
+                                Ok(Outcome::Done)
+                            } else {
+                                Err(SheetError::OutOfBounds(cell_name.to_string()))
                             }
-                            ModeOfSpreadsheet::Visual => parser_visual(
-                                &command,
-                                &mut sheet.lock().unwrap(),
-                                &mut undo_manager.lock().unwrap(),
-                            ),
-                        },
+                        })
                     }
+                    _ if matches!(
+                        command.split_whitespace().next(),
+                        Some("insert_row" | "delete_row" | "insert_col" | "delete_col" | "resize")
+                    ) =>
+                    {
+                        handle_structure_command(&command, sheet, undo_manager)
+                    }
+                    _ if command.split_whitespace().next() == Some("iterate") => {
+                        handle_iterate_command(&command, sheet)
+                    }
+                    _ if matches!(command.split_whitespace().next(), Some("name" | "names")) => {
+                        handle_name_command(&command, sheet, undo_manager)
+                    }
+                    _ if command.split_whitespace().next() == Some("sheet") => {
+                        handle_sheet_command(&command, &mut workbook)
+                    }
+                    _ if matches!(
+                        command.split_whitespace().next(),
+                        Some("history" | "undo_to")
+                    ) =>
+                    {
+                        handle_history_command(&command, sheet, undo_manager)
+                    }
+                    _ if command.split_whitespace().next() == Some("undo_depth") => {
+                        handle_undo_depth_command(&command, &mut workbook)
+                    }
+                    _ => match unsafe { CURRENT_MODE } {
+                        ModeOfSpreadsheet::Read => {
+                            handle_read_command(&command, sheet, undo_manager)
+                        }
+                        ModeOfSpreadsheet::Normal => {
+                            parser_normal(&command, rows, columns, &current_row, &current_col);
+                            Ok(Outcome::Done)
+                        }
+                        ModeOfSpreadsheet::Insert => parser_insert(&command, sheet, undo_manager),
+                        ModeOfSpreadsheet::Visual => {
+                            parser_visual(&command, sheet, undo_manager, &mut registers)
+                        }
+                    },
+                };
 
-                    let mut status = status_extension.lock().unwrap();
-                    *status = STATUS_EXTENSION;
-
-                    // execution_time = start.elapsed().as_secs_f64();
+                // Pass the changes on to formulas on other sheets
+                workbook.refresh_links();
 
-                    // if print_flag {
-                    //     display_sheet(&sheet.lock().unwrap(), &shared_data,&current_row,&current_col);
+                // Start at the top left of a sheet that was switched to
+                if workbook.active_index() != active_sheet {
+                    *current_row.lock().unwrap() = 0;
+                    *current_col.lock().unwrap() = 0;
+                }
 
-                    // }
+                // Keep the view on the sheet after it shrinks
+                {
+                    let sheet = workbook.active();
+                    let mut dr = current_row.lock().unwrap();
+                    *dr = (*dr).min(sheet.rows as usize - 1);
+                    let mut dc = current_col.lock().unwrap();
+                    *dc = (*dc).min(sheet.columns as usize - 1);
                 }
+
+                *status.lock().unwrap() = result;
             }
         })
     };
@@ -436,6 +508,8 @@ fn main_functionality2() {
 /// * `a` - Scroll left.
 /// * `d` - Scroll right.
 /// * `scroll_to <cell>` - Scroll to a specific cell.
+/// * `insert_row <row>` / `delete_row <row>` - Insert an empty row before, or delete, a row.
+/// * `insert_col <col>` / `delete_col <col>` - Insert an empty column before, or delete, a column.
 /// * `<cell_name>=<expression>` - Assign a formula or value to a cell.
 #[cfg(feature = "main1")]
 fn main_functionality1() {
@@ -445,12 +519,12 @@ fn main_functionality1() {
         process::exit(1);
     }
 
-    let rows: i32 = args[1].parse().unwrap_or_else(|_| {
+    let mut rows: i32 = args[1].parse().unwrap_or_else(|_| {
         eprintln!("Invalid row number");
         process::exit(1);
     });
 
-    let columns: i32 = args[2].parse().unwrap_or_else(|_| {
+    let mut columns: i32 = args[2].parse().unwrap_or_else(|_| {
         eprintln!("Invalid column number");
         process::exit(1);
     });
@@ -490,7 +564,13 @@ fn main_functionality1() {
                 }
                 3 => {
                     STATUS = 0;
-                    "cyclic dependence".to_string()
+                    let cycle: Vec<String> = LAST_CYCLE
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect();
+                    format!("cyclic dependence: {}", cycle.join(" → "))
                 }
                 _ => "unknown status".to_string(),
             };
@@ -551,6 +631,14 @@ fn main_functionality1() {
                         eprintln!("Out of bounds rows or columns");
                     }
                 }
+                _ if ["insert_row ", "delete_row ", "insert_col ", "delete_col "]
+                    .iter()
+                    .any(|command| input.starts_with(command)) =>
+                {
+                    parse_structure_command(input, &mut sheet);
+                    rows = sheet.rows;
+                    columns = sheet.columns;
+                }
                 _ => {
                     parse_input(
                         input,
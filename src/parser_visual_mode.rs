@@ -1,46 +1,65 @@
 use crate::cell_extension::SpreadsheetExtension;
-use crate::expression_utils::parse_formula;
+use crate::cellsp::CellReference;
+use crate::clipboard::{
+    DEFAULT_REGISTER, PasteMode, Registers, Yank, parse_paste_mode, parse_register,
+};
+use crate::expression_parser::Expr;
 use crate::forecast::forecast;
-use crate::graph_extension::STATUS_EXTENSION;
-use crate::graph_extension::{UndoRedoStack, assign_cell_extension};
+use crate::graph_extension::{UndoRedoStack, assign_cell_extension, record_cell_state};
 use crate::plot_graph::{plot_histogram, plot_line, plot_scatter};
+use crate::sheet_error::{CommandResult, Outcome, SheetError};
+
+/// A cell position as `(row, column)`, both 0-based.
+type CellPosition = (i32, i32);
 
 /// Parses a cell name (e.g., "A1") into its row and column indices.
 ///
 /// # Arguments
 /// * `cell_name` - The cell name as a string (e.g., "A1").
-/// * `row` - A mutable reference to store the parsed row index.
-/// * `col` - A mutable reference to store the parsed column index.
-pub fn parse_cell_name(cell_name: &str, row: &mut i32, col: &mut i32) {
-    let chars: Vec<char> = cell_name.chars().collect();
-    let mut i = 0;
-    *col = 0;
+///
+/// # Returns
+/// * `Ok((row, col))` - The 0-based row and column indices.
+/// * `Err(SheetError::InvalidCell)` - If the name is not letters followed by a row number.
+pub fn parse_cell_name(cell_name: &str) -> Result<CellPosition, SheetError> {
+    let invalid = || SheetError::InvalidCell(cell_name.to_string());
+    let split_index = cell_name
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(cell_name.len());
+    let (col_part, row_part) = cell_name.split_at(split_index);
+
+    if col_part.is_empty() || row_part.is_empty() {
+        return Err(invalid());
+    }
 
-    // Extract column part (letters)
-    loop {
-        if i >= chars.len() {
-            break;
-        }
+    let col = parse_column_name(col_part).map_err(|_| invalid())?;
+    let row = string_to_int(row_part).map_err(|_| invalid())?;
+    Ok((row - 1, col))
+}
 
-        if chars[i] >= '0' && chars[i] <= '9' {
-            break;
-        } else if (chars[i].to_ascii_uppercase() as i32 - 'A' as i32) >= 26
-            || (chars[i].to_ascii_uppercase() as i32 - 'A' as i32) < 0
-        {
-            unsafe {
-                STATUS_EXTENSION = 1;
-            }
-            break;
+/// Parses a column name (e.g., "C" or "AA") into its 0-based index.
+///
+/// # Arguments
+/// * `name` - The column letters, in any case.
+///
+/// # Returns
+/// * `Ok(col)` - The 0-based column index.
+/// * `Err(SheetError::InvalidCell)` - If the name is empty, has other characters or is too long.
+pub fn parse_column_name(name: &str) -> Result<i32, SheetError> {
+    let invalid = || SheetError::InvalidCell(name.to_string());
+    if name.is_empty() {
+        return Err(invalid());
+    }
+    let mut col: i32 = 0;
+    for c in name.chars() {
+        if !c.is_ascii_alphabetic() {
+            return Err(invalid());
         }
-
-        *col = *col * 26 + (chars[i].to_ascii_uppercase() as i32 - 'A' as i32 + 1);
-        i += 1;
+        col = col
+            .checked_mul(26)
+            .and_then(|col| col.checked_add(c.to_ascii_uppercase() as i32 - 'A' as i32 + 1))
+            .ok_or_else(invalid)?;
     }
-    *col -= 1;
-
-    // Extract row part (numbers)
-    let row_part = &cell_name[i..];
-    *row = string_to_int(row_part) - 1;
+    Ok(col - 1)
 }
 
 /// Converts a numeric string to an integer.
@@ -49,13 +68,15 @@ pub fn parse_cell_name(cell_name: &str, row: &mut i32, col: &mut i32) {
 /// * `num_str` - The numeric string to convert.
 ///
 /// # Returns
-/// The integer value of the string. Returns `0` if the string is invalid.
-pub fn string_to_int(num_str: &str) -> i32 {
-    let mut num = 0;
+/// * `Ok(i32)` - The integer value of the string.
+/// * `Err(SheetError::InvalidCommand)` - If the string is not a number.
+pub fn string_to_int(num_str: &str) -> Result<i32, SheetError> {
+    let mut num: i32 = 0;
     let mut i = 0;
     let mut sign = 1;
 
     let chars: Vec<char> = num_str.chars().collect();
+    let invalid = || SheetError::InvalidCommand(format!("expected a number, found {}", num_str));
 
     if !chars.is_empty() {
         if chars[0] == '-' {
@@ -65,19 +86,199 @@ pub fn string_to_int(num_str: &str) -> i32 {
             i = 1;
         }
     }
+    if i >= chars.len() {
+        return Err(invalid());
+    }
     while i < chars.len() {
         let digit = chars[i] as i32 - '0' as i32;
         if !(0..=9).contains(&digit) {
-            unsafe {
-                STATUS_EXTENSION = 1;
-            }
-            break;
+            return Err(invalid());
         }
-        num = num * 10 + digit;
+        num = num
+            .checked_mul(10)
+            .and_then(|n| n.checked_add(digit))
+            .ok_or_else(invalid)?;
         i += 1;
     }
 
-    num * sign
+    Ok(num * sign)
+}
+
+/// Parses a cell name and checks that it lies inside the sheet.
+///
+/// # Arguments
+/// * `cell_name` - The cell name as a string (e.g., "A1").
+/// * `sheet` - A reference to the spreadsheet.
+///
+/// # Returns
+/// * `Ok((row, col))` - The 0-based row and column indices.
+/// * `Err(SheetError)` - If the name is invalid or the cell is outside the sheet.
+fn parse_cell_in_sheet(
+    cell_name: &str,
+    sheet: &SpreadsheetExtension,
+) -> Result<CellPosition, SheetError> {
+    let (row, col) = parse_cell_name(cell_name)?;
+    if row < 0 || col < 0 || row >= sheet.rows || col >= sheet.columns {
+        return Err(SheetError::OutOfBounds(cell_name.to_string()));
+    }
+    Ok((row, col))
+}
+
+/// Parses a range (e.g., "A1:B2") and checks that both corners lie inside the sheet.
+///
+/// # Arguments
+/// * `range` - The range as a string.
+/// * `sheet` - A reference to the spreadsheet.
+///
+/// # Returns
+/// * `Ok((start, end))` - The positions of the top-left and bottom-right cells.
+/// * `Err(SheetError)` - If the range is malformed or a corner is outside the sheet.
+fn parse_range_in_sheet(
+    range: &str,
+    sheet: &SpreadsheetExtension,
+) -> Result<(CellPosition, CellPosition), SheetError> {
+    let (start, end) = range.split_once(':').ok_or_else(|| {
+        SheetError::InvalidCommand(format!("expected a range such as A1:B2, found {}", range))
+    })?;
+    let start = parse_cell_in_sheet(start, sheet)?;
+    let end = parse_cell_in_sheet(end, sheet)?;
+    if start.0 > end.0 || start.1 > end.1 {
+        return Err(SheetError::InvalidCommand(format!(
+            "range {} must run from the top-left to the bottom-right cell",
+            range
+        )));
+    }
+    Ok((start, end))
+}
+
+/// Parses either a range (e.g., "A1:B2") or a single cell (e.g., "A1", the range `A1:A1`).
+fn parse_block_in_sheet(
+    block: &str,
+    sheet: &SpreadsheetExtension,
+) -> Result<(CellPosition, CellPosition), SheetError> {
+    if block.contains(':') {
+        parse_range_in_sheet(block, sheet)
+    } else {
+        let cell = parse_cell_in_sheet(block, sheet)?;
+        Ok((cell, cell))
+    }
+}
+
+/// Splits an optional leading register argument (e.g. `"a`) off a command's arguments.
+///
+/// # Returns
+/// The register, `DEFAULT_REGISTER` if none is given, and the remaining arguments.
+fn split_register<'a>(args: &'a [&'a str]) -> Result<(char, &'a [&'a str]), SheetError> {
+    match args.split_first() {
+        Some((first, rest)) if first.starts_with('"') => Ok((parse_register(first)?, rest)),
+        _ => Ok((DEFAULT_REGISTER, args)),
+    }
+}
+
+/// Clears the cells of a range, except those inside `keep`. Cells that have never been
+/// written are already clear and are left alone.
+fn clear_range(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    range: (CellPosition, CellPosition),
+    keep: Option<(CellPosition, CellPosition)>,
+) -> Result<(), SheetError> {
+    let ((start_row, start_col), (end_row, end_col)) = range;
+    let written: Vec<CellPosition> = sheet
+        .all_cells
+        .cells_in(
+            (start_row as usize, start_col as usize),
+            (end_row as usize, end_col as usize),
+        )
+        .map(|((row, col), _)| (row as i32, col as i32))
+        .filter(|&(row, col)| {
+            !keep.is_some_and(
+                |((keep_start_row, keep_start_col), (keep_end_row, keep_end_col))| {
+                    (keep_start_row..=keep_end_row).contains(&row)
+                        && (keep_start_col..=keep_end_col).contains(&col)
+                },
+            )
+        })
+        .collect();
+    undo_manager.transaction(|undo_manager| {
+        for (row, col) in written {
+            assign_cell_extension(sheet, undo_manager, row, col, Expr::Number(0.0))?;
+        }
+        Ok(())
+    })
+}
+
+/// Collects the numeric values of a range, row by row. Cells without a number count as `0`.
+fn range_values(sheet: &SpreadsheetExtension, range: (CellPosition, CellPosition)) -> Vec<f64> {
+    let ((start_row, start_col), (end_row, end_col)) = range;
+    let mut values = Vec::new();
+    for r in start_row..=end_row {
+        for c in start_col..=end_col {
+            values.push(
+                sheet.all_cells[r as usize][c as usize]
+                    .value
+                    .as_number()
+                    .unwrap_or(0.0),
+            );
+        }
+    }
+    values
+}
+
+/// Moves or copies the cells of a range to another range of the same size.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+/// * `registers` - The clipboard registers; the source cells are yanked into `register`.
+/// * `register` - The register to yank into.
+/// * `source` - The source range.
+/// * `dest` - The destination range.
+/// * `cut` - Whether to clear the source cells.
+///
+/// # Returns
+/// * `Ok(Outcome::Done)` if every cell was pasted.
+/// * `Err(SheetError)` if the ranges differ in size or a pasted cell creates a cycle.
+///
+/// # Behavior
+/// Formulas are pasted rather than their values, with relative references moved by the
+/// distance between the two ranges (see `Yank::paste`). Formatting is not copied.
+fn paste_range(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    registers: &mut Registers,
+    register: char,
+    source: (CellPosition, CellPosition),
+    dest: (CellPosition, CellPosition),
+    cut: bool,
+) -> CommandResult {
+    let ((start_row, start_col), (end_row, end_col)) = source;
+    let ((dest_start_row, dest_start_col), (dest_end_row, dest_end_col)) = dest;
+
+    // Ensure the source and destination ranges have the same dimensions
+    if (end_row - start_row) != (dest_end_row - dest_start_row)
+        || (end_col - start_col) != (dest_end_col - dest_start_col)
+    {
+        return Err(SheetError::InvalidCommand(
+            "source and destination ranges must have the same size".to_string(),
+        ));
+    }
+
+    // The yank is a snapshot, so overlapping ranges paste the original cells
+    let yank = Yank::from_sheet(sheet, source.0, source.1);
+    undo_manager.transaction(|undo_manager| {
+        if cut {
+            clear_range(sheet, undo_manager, source, Some(dest))?;
+        }
+        yank.paste(sheet, undo_manager, dest.0, PasteMode::Formulas)
+    })?;
+    registers.store(register, yank);
+    Ok(Outcome::Done)
+}
+
+/// Builds the error for a command with the wrong arguments.
+fn usage(expected: &str) -> SheetError {
+    SheetError::InvalidCommand(format!("expected: {}", expected))
 }
 
 /// Parses and executes visual mode commands for the spreadsheet.
@@ -95,24 +296,42 @@ pub fn string_to_int(num_str: &str) -> i32 {
 ///
 /// ## Cutting a Single Cell
 /// * **Command**: `dc <source_cell> <destination_cell>`
-/// * **Description**: Cuts the value from the source cell and pastes it into the destination cell.
+/// * **Description**: Moves the formula from the source cell to the destination cell.
 /// * **Example**: `dc A1 B1`
 ///
 /// ## Copying a Single Cell
 /// * **Command**: `yc <source_cell> <destination_cell>`
-/// * **Description**: Copies the value from the source cell and pastes it into the destination cell.
+/// * **Description**: Copies the formula from the source cell to the destination cell,
+///   moving its relative references by the same distance.
 /// * **Example**: `yc A1 B1`
 ///
 /// ## Cutting a Range of Cells
 /// * **Command**: `d <source_range> <destination_range>`
-/// * **Description**: Cuts the values from the source range and pastes them into the destination range.
+/// * **Description**: Moves the formulas from the source range to the destination range.
 /// * **Example**: `d A1:B2 C1:D2`
 ///
-/// /// ## Copying a Range of Cells
+/// ## Copying a Range of Cells
 /// * **Command**: `y <source_range> <destination_range>`
-/// * **Description**: Copies the values from the source range and pastes them into the destination range.
+/// * **Description**: Copies the formulas from the source range to the destination range,
+///   moving their relative references by the same distance.
 /// * **Example**: `y A1:B2 C1:D2`
 ///
+/// ## Yanking into a Register
+/// * **Command**: `y ["<register>] <range>` or `d ["<register>] <range>`
+/// * **Description**: Copies the cells of a range (or a single cell) into a register, which
+///   is kept for the session. `d` also clears the cells. Without a register name, the
+///   default register is used; a yank into a named register (`"a` to `"z`) also fills the
+///   default register.
+/// * **Example**: `y "a A1:B2`
+///
+/// ## Pasting from a Register
+/// * **Command**: `p ["<register>] <cell> [all|values|formulas|formats]`
+/// * **Description**: Pastes a register with its top-left cell at the given cell. `all` (the
+///   default) pastes formulas and formatting, `values` pastes the yanked values as constants,
+///   `formulas` pastes only the formulas and `formats` pastes only bold and italics. Relative
+///   references in pasted formulas move with the paste.
+/// * **Example**: `p "a C1 values`
+///
 /// ## Plotting a Histogram
 /// * **Command**: `plot_histogram <range> <filename>`
 /// * **Description**: Generates a histogram for the values in the specified range and saves it to the given file.
@@ -142,820 +361,277 @@ pub fn string_to_int(num_str: &str) -> i32 {
 /// * **Description**: Applies italics formatting to the specified cell.
 /// * **Example**: `i A1`
 ///
+/// # Returns
+/// * `Ok(Outcome)` - If the command was applied; filters and plots report a message.
+/// * `Err(SheetError)` - If the command is unknown, malformed, refers to cells outside the
+///   sheet, or a plot cannot be written.
+///
 /// # Behavior
 /// Executes commands such as filtering, copying, cutting, pasting, plotting, and forecasting.
+/// Every change a command makes to cells, including formatting, is undone as one step.
 pub fn parser_visual(
     input: &str,
     sheet: &mut SpreadsheetExtension,
     undo_manager: &mut UndoRedoStack,
-) {
-    unsafe {
-        let parts: Vec<&str> = input.split_whitespace().collect();
-        if parts.is_empty() {
-            STATUS_EXTENSION = 1;
-            return;
-        }
-
-        match parts[0] {
-            "b" => {
-                if parts.len() != 2 {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-                let mut row = 0;
-                let mut col = 0;
-                parse_cell_name(parts[1], &mut row, &mut col);
-
-                if row < 0
-                    || col < 0
-                    || row >= sheet.rows
-                    || col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
+    registers: &mut Registers,
+) -> CommandResult {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    if parts.is_empty() {
+        return Err(SheetError::InvalidCommand("empty command".to_string()));
+    }
 
-                sheet.all_cells[row as usize][col as usize].is_bold = true;
-                //     println!("Applied bold formatting to cell {}", parts[1]);
+    match parts[0] {
+        "b" => {
+            if parts.len() != 2 {
+                return Err(usage("b <cell_name>"));
             }
+            let (row, col) = parse_cell_in_sheet(parts[1], sheet)?;
+            record_cell_state(sheet, undo_manager, row, col);
+            sheet.all_cells[row as usize][col as usize].is_bold = true;
+            Ok(Outcome::Done)
+        }
 
-            "i" => {
-                if parts.len() != 2 {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-                let mut row = 0;
-                let mut col = 0;
-                parse_cell_name(parts[1], &mut row, &mut col);
-
-                if row < 0
-                    || col < 0
-                    || row >= sheet.rows
-                    || col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                sheet.all_cells[row as usize][col as usize].is_italics = true;
-                // println!("Applied italics formatting to cell {}", parts[1]);
+        "i" => {
+            if parts.len() != 2 {
+                return Err(usage("i <cell_name>"));
             }
-            "filter" => {
-                if parts.len() != 4 {
-                    eprintln!("Invalid format. Expected: filter <range> <comparator> <value>");
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                let mut start_row = 0;
-                let mut start_col = 0;
-                let mut end_row = 0;
-                let mut end_col = 0;
-
-                // Parse range
-                parse_cell_name(
-                    parts[1].split(':').next().unwrap(),
-                    &mut start_row,
-                    &mut start_col,
-                );
-                parse_cell_name(
-                    parts[1].split(':').nth(1).unwrap(),
-                    &mut end_row,
-                    &mut end_col,
-                );
-
-                if start_row < 0
-                    || start_col < 0
-                    || start_row >= sheet.rows
-                    || start_col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if end_row < 0 || end_col < 0 || end_row >= sheet.rows || end_col >= sheet.columns {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                // Extract comparator and value
-                let comparator = parts[2];
-                let value: f64 = match parts[3].parse() {
-                    Ok(v) => v,
-                    Err(_) => {
-                        eprintln!("Invalid value for filter condition");
-                        STATUS_EXTENSION = 1;
-                        return;
-                    }
-                };
-
-                // Vector to store filtered results
-                let mut filtered_cells: Vec<(String, f64)> = Vec::new();
-
-                // Traverse the range and apply the filter
-                for r in start_row..=end_row {
-                    for c in start_col..=end_col {
-                        let cell_value = sheet.all_cells[r as usize][c as usize].value as f64;
-
-                        // Apply the comparator
-                        let condition_met = match comparator {
-                            ">" => cell_value > value,
-                            "<" => cell_value < value,
-                            ">=" => cell_value >= value,
-                            "<=" => cell_value <= value,
-                            "=" => cell_value == value,
-                            _ => {
-                                eprintln!("Invalid comparator. Expected one of: >, <, >=, <=, =");
-                                STATUS_EXTENSION = 1;
-                                return;
-                            }
-                        };
-
-                        // If the condition is met, add the cell to the result
-                        if condition_met {
-                            let cell_name = format!(
-                                "{}{}",
-                                (b'A' + c as u8) as char, // Convert column index to letter
-                                r + 1                     // Convert row index to 1-based
-                            );
-                            filtered_cells.push((cell_name, cell_value));
-                        }
-                    }
-                }
+            let (row, col) = parse_cell_in_sheet(parts[1], sheet)?;
+            record_cell_state(sheet, undo_manager, row, col);
+            sheet.all_cells[row as usize][col as usize].is_italics = true;
+            Ok(Outcome::Done)
+        }
 
-                // Print the filtered results
-                if filtered_cells.is_empty() {
-                    println!("No cells match the filter condition");
-                } else {
-                    println!("Filtered cells:");
-                    for (cell_name, cell_value) in filtered_cells {
-                        println!("{}: {}", cell_name, cell_value);
-                    }
-                }
+        "filter" => {
+            if parts.len() != 4 {
+                return Err(usage("filter <range> <comparator> <value>"));
             }
-            "dc" => {
-                //cut cell
-                if parts.len() != 3 {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-                let mut start_row = 0;
-                let mut start_col = 0;
-                let mut end_row = 0;
-                let mut end_col = 0;
-
-                parse_cell_name(parts[1], &mut start_row, &mut start_col);
-                parse_cell_name(parts[2], &mut end_row, &mut end_col);
-
-                if start_row < 0
-                    || start_col < 0
-                    || start_row >= sheet.rows
-                    || start_col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if end_row < 0 || end_col < 0 || end_row >= sheet.rows || end_col >= sheet.columns {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                let mut value = sheet.all_cells[start_row as usize][start_col as usize]
-                    .value
-                    .to_string();
-                if sheet.all_cells[start_row as usize][start_col as usize].is_error {
-                    value = "1/0".to_string();
-                }
-                let value2 = "0";
-                if let Ok(parsed_formula) = parse_formula(value2) {
-                    assign_cell_extension(
-                        sheet,
-                        undo_manager,
-                        start_row,
-                        start_col,
-                        *parsed_formula,
-                    );
-                }
-                if let Ok(parsed_formula) = parse_formula(&value) {
-                    assign_cell_extension(sheet, undo_manager, end_row, end_col, *parsed_formula);
-                }
+            let ((start_row, start_col), (end_row, end_col)) =
+                parse_range_in_sheet(parts[1], sheet)?;
+
+            // Extract comparator and value
+            let comparator = parts[2];
+            if !matches!(comparator, ">" | "<" | ">=" | "<=" | "=") {
+                return Err(SheetError::InvalidCommand(
+                    "invalid comparator, expected one of: >, <, >=, <=, =".to_string(),
+                ));
             }
-            "yc" => {
-                //copy cell
-                if parts.len() != 3 {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-                let mut start_row = 0;
-                let mut start_col = 0;
-                let mut end_row = 0;
-                let mut end_col = 0;
-                parse_cell_name(parts[1], &mut start_row, &mut start_col);
-                parse_cell_name(parts[2], &mut end_row, &mut end_col);
-
-                if start_row < 0
-                    || start_col < 0
-                    || start_row >= sheet.rows
-                    || start_col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if end_row < 0 || end_col < 0 || end_row >= sheet.rows || end_col >= sheet.columns {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                let mut value = sheet.all_cells[start_row as usize][start_col as usize]
-                    .value
-                    .to_string();
-                if sheet.all_cells[start_row as usize][start_col as usize].is_error {
-                    value = "1/0".to_string();
-                }
-
-                if let Ok(parsed_formula) = parse_formula(&value) {
-                    assign_cell_extension(sheet, undo_manager, end_row, end_col, *parsed_formula);
-                }
-            }
-            "d" => {
-                // cut and paste fro a range of cells
-                if parts.len() != 3 {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                let mut start_row = 0;
-                let mut start_col = 0;
-                let mut end_row = 0;
-                let mut end_col = 0;
-                let mut dest_start_row = 0;
-                let mut dest_start_col = 0;
-                let mut dest_end_row = 0;
-                let mut dest_end_col = 0;
-
-                parse_cell_name(
-                    parts[1].split(':').next().unwrap(),
-                    &mut start_row,
-                    &mut start_col,
-                );
-                parse_cell_name(
-                    parts[1].split(':').nth(1).unwrap(),
-                    &mut end_row,
-                    &mut end_col,
-                );
-
-                parse_cell_name(
-                    parts[2].split(':').next().unwrap(),
-                    &mut dest_start_row,
-                    &mut dest_start_col,
-                );
-                parse_cell_name(
-                    parts[2].split(':').nth(1).unwrap(),
-                    &mut dest_end_row,
-                    &mut dest_end_col,
-                );
-
-                if start_row < 0
-                    || start_col < 0
-                    || start_row >= sheet.rows
-                    || start_col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if end_row < 0 || end_col < 0 || end_row >= sheet.rows || end_col >= sheet.columns {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if dest_start_row < 0
-                    || dest_start_col < 0
-                    || dest_start_row >= sheet.rows
-                    || dest_start_col >= sheet.columns
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if dest_end_row < 0
-                    || dest_end_col < 0
-                    || dest_end_row >= sheet.rows
-                    || dest_end_col >= sheet.columns
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                // Ensure the source and destination ranges have the same dimensions
-                if (end_row - start_row) != (dest_end_row - dest_start_row)
-                    || (end_col - start_col) != (dest_end_col - dest_start_col)
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                // Cut (move) the values/formulas
-                for r in 0..=(end_row - start_row) {
-                    for c in 0..=(end_col - start_col) {
-                        let src_row = start_row + r;
-                        let src_col = start_col + c;
-                        let dest_row = dest_start_row + r;
-                        let dest_col = dest_start_col + c;
-
-                        // Get the value/formula from the source cell
-                        let mut value = sheet.all_cells[src_row as usize][src_col as usize]
-                            .value
-                            .to_string();
-                        if sheet.all_cells[start_row as usize][start_col as usize].is_error {
-                            value = "1/0".to_string();
-                        }
-
-                        // Clear the source cell
-                        let clear_formula = "0";
-                        if let Ok(parsed_formula) = parse_formula(clear_formula) {
-                            assign_cell_extension(
-                                sheet,
-                                undo_manager,
-                                src_row,
-                                src_col,
-                                *parsed_formula,
-                            );
-                        }
-
-                        // Assign the value/formula to the destination cell
-                        if let Ok(parsed_formula) = parse_formula(&value) {
-                            assign_cell_extension(
-                                sheet,
-                                undo_manager,
-                                dest_row,
-                                dest_col,
-                                *parsed_formula,
-                            );
-                        }
+            let value: f64 = parts[3].parse().map_err(|_| {
+                SheetError::InvalidCommand(format!(
+                    "invalid value for filter condition: {}",
+                    parts[3]
+                ))
+            })?;
+
+            // Vector to store filtered results
+            let mut filtered_cells: Vec<String> = Vec::new();
+
+            // Traverse the range and apply the filter
+            for r in start_row..=end_row {
+                for c in start_col..=end_col {
+                    // Text cells never match a numeric condition
+                    let Some(cell_value) =
+                        sheet.all_cells[r as usize][c as usize].value.as_number()
+                    else {
+                        continue;
+                    };
+
+                    // Apply the comparator
+                    let condition_met = match comparator {
+                        ">" => cell_value > value,
+                        "<" => cell_value < value,
+                        ">=" => cell_value >= value,
+                        "<=" => cell_value <= value,
+                        _ => cell_value == value,
+                    };
+
+                    // If the condition is met, add the cell to the result
+                    if condition_met {
+                        let cell_ref = CellReference::new(r, c);
+                        filtered_cells.push(format!("{}: {}", cell_ref, cell_value));
                     }
                 }
             }
-            "y" => {
-                if parts.len() != 3 {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                let mut start_row = 0;
-                let mut start_col = 0;
-                let mut end_row = 0;
-                let mut end_col = 0;
-                let mut dest_start_row = 0;
-                let mut dest_start_col = 0;
-                let mut dest_end_row = 0;
-                let mut dest_end_col = 0;
-
-                parse_cell_name(
-                    parts[1].split(':').next().unwrap(),
-                    &mut start_row,
-                    &mut start_col,
-                );
-                parse_cell_name(
-                    parts[1].split(':').nth(1).unwrap(),
-                    &mut end_row,
-                    &mut end_col,
-                );
-
-                parse_cell_name(
-                    parts[2].split(':').next().unwrap(),
-                    &mut dest_start_row,
-                    &mut dest_start_col,
-                );
-                parse_cell_name(
-                    parts[2].split(':').nth(1).unwrap(),
-                    &mut dest_end_row,
-                    &mut dest_end_col,
-                );
-
-                if start_row < 0
-                    || start_col < 0
-                    || start_row >= sheet.rows
-                    || start_col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if end_row < 0 || end_col < 0 || end_row >= sheet.rows || end_col >= sheet.columns {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
 
-                if dest_start_row < 0
-                    || dest_start_col < 0
-                    || dest_start_row >= sheet.rows
-                    || dest_start_col >= sheet.columns
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if dest_end_row < 0
-                    || dest_end_col < 0
-                    || dest_end_row >= sheet.rows
-                    || dest_end_col >= sheet.columns
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                // Ensure the source and destination ranges have the same dimensions
-                if (end_row - start_row) != (dest_end_row - dest_start_row)
-                    || (end_col - start_col) != (dest_end_col - dest_start_col)
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                // Copy the values/formulas
-                for r in 0..=(end_row - start_row) {
-                    for c in 0..=(end_col - start_col) {
-                        let src_row = start_row + r;
-                        let src_col = start_col + c;
-                        let dest_row = dest_start_row + r;
-                        let dest_col = dest_start_col + c;
-
-                        // Get the value/formula from the source cell
-                        let mut value = sheet.all_cells[src_row as usize][src_col as usize]
-                            .value
-                            .to_string();
-                        if sheet.all_cells[start_row as usize][start_col as usize].is_error {
-                            value = "1/0".to_string();
-                        }
-
-                        // Assign the value/formula to the destination cell
-                        if let Ok(parsed_formula) = parse_formula(&value) {
-                            assign_cell_extension(
-                                sheet,
-                                undo_manager,
-                                dest_row,
-                                dest_col,
-                                *parsed_formula,
-                            );
-                        }
-                    }
-                }
+            if filtered_cells.is_empty() {
+                Ok(Outcome::Message(
+                    "No cells match the filter condition".to_string(),
+                ))
+            } else {
+                Ok(Outcome::Message(format!(
+                    "Filtered cells: {}",
+                    filtered_cells.join(", ")
+                )))
             }
-            "plot_histogram" => {
-                if parts.len() != 3 {
-                    eprintln!(
-                        "Invalid format for histogram plot. Expected: plot_histogram <range> <filename>"
-                    );
-                    return;
-                }
-
-                let mut start_row = 0;
-                let mut start_col = 0;
-                let mut end_row = 0;
-                let mut end_col = 0;
-
-                // Parse range
-                parse_cell_name(
-                    parts[1].split(':').next().unwrap(),
-                    &mut start_row,
-                    &mut start_col,
-                );
-                parse_cell_name(
-                    parts[1].split(':').nth(1).unwrap(),
-                    &mut end_row,
-                    &mut end_col,
-                );
-
-                if start_row < 0
-                    || start_col < 0
-                    || start_row >= sheet.rows
-                    || start_col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if end_row < 0 || end_col < 0 || end_row >= sheet.rows || end_col >= sheet.columns {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                // Extract data
-                let mut data = Vec::new();
-                for r in start_row..=end_row {
-                    for c in start_col..=end_col {
-                        let value = sheet.all_cells[r as usize][c as usize].value as f64;
-                        data.push(value);
-                    }
-                }
+        }
 
-                // Call the histogram plot function
-                if let Err(e) = plot_histogram(&data, parts[2]) {
-                    eprintln!("Error generating histogram: {}", e);
-                }
+        "dc" | "yc" => {
+            // cut or copy a single cell
+            if parts.len() != 3 {
+                return Err(usage(&format!(
+                    "{} <source_cell> <destination_cell>",
+                    parts[0]
+                )));
             }
+            let source = parse_cell_in_sheet(parts[1], sheet)?;
+            let dest = parse_cell_in_sheet(parts[2], sheet)?;
+            paste_range(
+                sheet,
+                undo_manager,
+                registers,
+                DEFAULT_REGISTER,
+                (source, source),
+                (dest, dest),
+                parts[0] == "dc",
+            )
+        }
 
-            "plot_line" => {
-                if parts.len() != 3 {
-                    eprintln!("Invalid format. Expected: plot_line <range> <filename>");
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                let mut start_row = 0;
-                let mut start_col = 0;
-                let mut end_row = 0;
-                let mut end_col = 0;
-
-                // Parse the start and end cell names from the range
-                parse_cell_name(
-                    parts[1].split(':').next().unwrap(),
-                    &mut start_row,
-                    &mut start_col,
-                );
-                parse_cell_name(
-                    parts[1].split(':').nth(1).unwrap(),
-                    &mut end_row,
-                    &mut end_col,
-                );
-
-                if start_row < 0
-                    || start_col < 0
-                    || start_row >= sheet.rows
-                    || start_col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if end_row < 0 || end_col < 0 || end_row >= sheet.rows || end_col >= sheet.columns {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                // Initialize a Vec for each column
-                let num_cols = end_col - start_col + 1;
-                let mut data: Vec<Vec<f64>> = vec![Vec::new(); num_cols as usize];
-
-                // Populate the data for each column within the specified range
-                for r in start_row..=end_row {
-                    for (i, c) in (start_col..=end_col).enumerate() {
-                        let value = sheet.all_cells[r as usize][c as usize].value as f64;
-                        data[i].push(value); // Group by column
+        "d" | "y" => {
+            let cut = parts[0] == "d";
+            let expected = || {
+                usage(&format!(
+                    "{} [\"<register>] <source_range> [<destination_range>]",
+                    parts[0]
+                ))
+            };
+            let (register, args) = split_register(&parts[1..])?;
+            match args {
+                // Yank (or cut) into a register
+                [source] => {
+                    let source = parse_block_in_sheet(source, sheet)?;
+                    registers.store(register, Yank::from_sheet(sheet, source.0, source.1));
+                    if cut {
+                        clear_range(sheet, undo_manager, source, None)?;
                     }
+                    Ok(Outcome::Done)
                 }
-
-                // Pass `start_row` to plot_line to correctly reflect the row numbers on the x-axis
-                if let Err(e) = plot_line(&data, parts[2]) {
-                    eprintln!("Error generating line plot: {}", e);
+                // Cut or copy straight to the destination
+                [source, dest] => {
+                    let source = parse_range_in_sheet(source, sheet)?;
+                    let dest = parse_range_in_sheet(dest, sheet)?;
+                    paste_range(sheet, undo_manager, registers, register, source, dest, cut)
                 }
+                _ => Err(expected()),
             }
+        }
 
-            "plot_scatter" => {
-                if parts.len() != 4 {
-                    eprintln!(
-                        "Invalid format. Expected: plot_scatter <x_range> <y_range> <filename>"
-                    );
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                let mut x_start_row = 0;
-                let mut x_start_col = 0;
-                let mut x_end_row = 0;
-                let mut x_end_col = 0;
-
-                let mut y_start_row = 0;
-                let mut y_start_col = 0;
-                let mut y_end_row = 0;
-                let mut y_end_col = 0;
-
-                parse_cell_name(
-                    parts[1].split(':').next().unwrap(),
-                    &mut x_start_row,
-                    &mut x_start_col,
-                );
-                parse_cell_name(
-                    parts[1].split(':').nth(1).unwrap(),
-                    &mut x_end_row,
-                    &mut x_end_col,
-                );
-
-                parse_cell_name(
-                    parts[2].split(':').next().unwrap(),
-                    &mut y_start_row,
-                    &mut y_start_col,
-                );
-                parse_cell_name(
-                    parts[2].split(':').nth(1).unwrap(),
-                    &mut y_end_row,
-                    &mut y_end_col,
-                );
-
-                if x_start_row < 0
-                    || x_start_col < 0
-                    || x_start_row >= sheet.rows
-                    || x_start_col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if x_end_row < 0
-                    || x_end_col < 0
-                    || x_end_row >= sheet.rows
-                    || x_end_col >= sheet.columns
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if y_start_row < 0
-                    || y_start_col < 0
-                    || y_start_row >= sheet.rows
-                    || y_start_col >= sheet.columns
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if y_end_row < 0
-                    || y_end_col < 0
-                    || y_end_row >= sheet.rows
-                    || y_end_col >= sheet.columns
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                let mut x_data = Vec::new();
-                let mut y_data = Vec::new();
-
-                for r in x_start_row..=x_end_row {
-                    for c in x_start_col..=x_end_col {
-                        x_data.push(sheet.all_cells[r as usize][c as usize].value as f64);
-                    }
-                }
-
-                for r in y_start_row..=y_end_row {
-                    for c in y_start_col..=y_end_col {
-                        y_data.push(sheet.all_cells[r as usize][c as usize].value as f64);
-                    }
-                }
-
-                if x_data.len() != y_data.len() {
-                    eprintln!("x and y data must be the same length");
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
+        "p" => {
+            let (register, args) = split_register(&parts[1..])?;
+            let (anchor, mode) = match args {
+                [anchor] => (anchor, PasteMode::All),
+                [anchor, mode] => (anchor, parse_paste_mode(mode)?),
+                _ => {
+                    return Err(usage(
+                        "p [\"<register>] <cell> [all|values|formulas|formats]",
+                    ));
+                }
+            };
+            let anchor = parse_cell_in_sheet(anchor, sheet)?;
+            let yank = registers.get(register).cloned().ok_or_else(|| {
+                SheetError::InvalidCommand(format!("register \"{} is empty", register))
+            })?;
+            yank.paste(sheet, undo_manager, anchor, mode)?;
+            Ok(Outcome::Done)
+        }
 
-                if let Err(e) = plot_scatter(&x_data, &y_data, parts[3]) {
-                    eprintln!("Error generating scatter plot: {}", e);
-                }
+        "plot_histogram" => {
+            if parts.len() != 3 {
+                return Err(usage("plot_histogram <range> <filename>"));
             }
+            let range = parse_range_in_sheet(parts[1], sheet)?;
+            let data = range_values(sheet, range);
 
-            "forecast" => {
-                if parts.len() != 5 {
-                    eprintln!(
-                        "Invalid format. Expected: plot_scatter <x_range> <y_range> <filename>"
-                    );
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                println!("enterde into foreacst");
-                let forecast_len = string_to_int(parts[1]);
-
-                let mut x_start_row = 0;
-                let mut x_start_col = 0;
-                let mut x_end_row = 0;
-                let mut x_end_col = 0;
-
-                let mut y_start_row = 0;
-                let mut y_start_col = 0;
-                let mut y_end_row = 0;
-                let mut y_end_col = 0;
-
-                parse_cell_name(
-                    parts[2].split(':').next().unwrap(),
-                    &mut x_start_row,
-                    &mut x_start_col,
-                );
-                parse_cell_name(
-                    parts[2].split(':').nth(1).unwrap(),
-                    &mut x_end_row,
-                    &mut x_end_col,
-                );
-
-                parse_cell_name(
-                    parts[3].split(':').next().unwrap(),
-                    &mut y_start_row,
-                    &mut y_start_col,
-                );
-                parse_cell_name(
-                    parts[3].split(':').nth(1).unwrap(),
-                    &mut y_end_row,
-                    &mut y_end_col,
-                );
-
-                if x_start_row < 0
-                    || x_start_col < 0
-                    || x_start_row >= sheet.rows
-                    || x_start_col >= sheet.columns
-                    || STATUS_EXTENSION == 1
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if x_end_row < 0
-                    || x_end_col < 0
-                    || x_end_row >= sheet.rows
-                    || x_end_col >= sheet.columns
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
-
-                if y_start_row < 0
-                    || y_start_col < 0
-                    || y_start_row >= sheet.rows
-                    || y_start_col >= sheet.columns
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
+            plot_histogram(&data, parts[2])
+                .map_err(|e| SheetError::Io(format!("Error generating histogram: {}", e)))?;
+            Ok(Outcome::Message(format!("Saved histogram to {}", parts[2])))
+        }
 
-                if y_end_row < 0
-                    || y_end_col < 0
-                    || y_end_row >= sheet.rows
-                    || y_end_col >= sheet.columns
-                {
-                    STATUS_EXTENSION = 1;
-                    return;
+        "plot_line" => {
+            if parts.len() != 3 {
+                return Err(usage("plot_line <range> <filename>"));
+            }
+            let ((start_row, start_col), (end_row, end_col)) =
+                parse_range_in_sheet(parts[1], sheet)?;
+
+            // Initialize a Vec for each column
+            let num_cols = end_col - start_col + 1;
+            let mut data: Vec<Vec<f64>> = vec![Vec::new(); num_cols.max(0) as usize];
+
+            // Populate the data for each column within the specified range
+            for r in start_row..=end_row {
+                for (i, c) in (start_col..=end_col).enumerate() {
+                    let value = sheet.all_cells[r as usize][c as usize]
+                        .value
+                        .as_number()
+                        .unwrap_or(0.0);
+                    data[i].push(value); // Group by column
                 }
+            }
 
-                let mut x_data = Vec::new();
-                let mut y_data = Vec::new();
-
-                for r in x_start_row..=x_end_row {
-                    for c in x_start_col..=x_end_col {
-                        x_data.push(sheet.all_cells[r as usize][c as usize].value as f64);
-                    }
-                }
+            plot_line(&data, parts[2])
+                .map_err(|e| SheetError::Io(format!("Error generating line plot: {}", e)))?;
+            Ok(Outcome::Message(format!("Saved line plot to {}", parts[2])))
+        }
 
-                for r in y_start_row..=y_end_row {
-                    for c in y_start_col..=y_end_col {
-                        y_data.push(sheet.all_cells[r as usize][c as usize].value as f64);
-                    }
-                }
+        "plot_scatter" => {
+            if parts.len() != 4 {
+                return Err(usage("plot_scatter <x_range> <y_range> <filename>"));
+            }
+            let x_data = range_values(sheet, parse_range_in_sheet(parts[1], sheet)?);
+            let y_data = range_values(sheet, parse_range_in_sheet(parts[2], sheet)?);
 
-                let temp_x = x_data.clone();
-                let temp_y = y_data.clone();
-                let common_diff = x_data[1] - x_data[0];
-                let mut next_x = x_data[x_data.len() - 1];
-
-                for i in 0..forecast_len {
-                    println!("Iteration: {}", i);
-                    next_x += common_diff;
-                    let next_y = ((forecast(next_x, &temp_x, &temp_y)) as i32) as f64;
-                    x_data.push(next_x);
-                    y_data.push(next_y);
-                }
+            if x_data.len() != y_data.len() {
+                return Err(SheetError::InvalidCommand(
+                    "x and y data must be the same length".to_string(),
+                ));
+            }
 
-                println!("entered into second stage");
+            plot_scatter(&x_data, &y_data, parts[3])
+                .map_err(|e| SheetError::Io(format!("Error generating scatter plot: {}", e)))?;
+            Ok(Outcome::Message(format!(
+                "Saved scatter plot to {}",
+                parts[3]
+            )))
+        }
 
-                if x_data.len() != y_data.len() {
-                    println!("x and y data must be the same length");
-                    STATUS_EXTENSION = 1;
-                    return;
-                }
+        "forecast" => {
+            if parts.len() != 5 {
+                return Err(usage("forecast <length> <x_range> <y_range> <filename>"));
+            }
+            let forecast_len = string_to_int(parts[1])?;
+            let mut x_data = range_values(sheet, parse_range_in_sheet(parts[2], sheet)?);
+            let mut y_data = range_values(sheet, parse_range_in_sheet(parts[3], sheet)?);
+
+            if x_data.len() != y_data.len() {
+                return Err(SheetError::InvalidCommand(
+                    "x and y data must be the same length".to_string(),
+                ));
+            }
+            if x_data.len() < 2 {
+                return Err(SheetError::InvalidCommand(
+                    "forecast needs at least two data points".to_string(),
+                ));
+            }
 
-                println!("{:?}", x_data);
-                println!("{:?}", y_data);
+            let temp_x = x_data.clone();
+            let temp_y = y_data.clone();
+            let common_diff = x_data[1] - x_data[0];
+            let mut next_x = x_data[x_data.len() - 1];
 
-                if let Err(e) = plot_scatter(&x_data, &y_data, parts[4]) {
-                    println!("chud gaye");
-                    println!("Error generating scatter plot: {}", e);
-                }
+            for _ in 0..forecast_len {
+                next_x += common_diff;
+                let next_y = forecast(next_x, &temp_x, &temp_y);
+                x_data.push(next_x);
+                y_data.push(next_y);
             }
 
-            _ => {
-                STATUS_EXTENSION = 1;
-            }
+            plot_scatter(&x_data, &y_data, parts[4])
+                .map_err(|e| SheetError::Io(format!("Error generating scatter plot: {}", e)))?;
+            Ok(Outcome::Message(format!("Saved forecast to {}", parts[4])))
         }
+
+        _ => Err(SheetError::InvalidCommand(format!(
+            "unknown command {}",
+            parts[0]
+        ))),
     }
 }
@@ -0,0 +1,420 @@
+use crate::cell_extension::{CellValue, DefinedName};
+use crate::display::expr_to_string;
+use crate::expression_parser::Expr;
+use crate::expression_utils::parse_formula;
+use crate::graph_extension::{
+    CellState, UndoRedoStack, assign_cell_extension, initialise_extension,
+};
+use crate::iterative_calc::IterationSettings;
+use crate::sheet_error::{CommandResult, Outcome, SheetError};
+use crate::workbook::{Workbook, WorkbookSheet};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+/// Identifies a file as a native spreadsheet project.
+const PROJECT_FORMAT: &str = "rust_lab_sheet";
+
+/// The project file version written by this build. Files with any other version are rejected.
+pub const PROJECT_FORMAT_VERSION: u32 = 2;
+
+/// A non-empty cell as stored in a project file.
+#[derive(Serialize, Deserialize)]
+struct ProjectCell {
+    row: i32,
+    column: i32,
+    /// The formula text, in the form accepted by `parse_formula`.
+    formula: String,
+    is_bold: bool,
+    is_italics: bool,
+}
+
+/// A recorded undo/redo state as stored in a project file.
+#[derive(Serialize, Deserialize)]
+struct ProjectCellState {
+    row: i32,
+    column: i32,
+    formula: String,
+    #[serde(default)]
+    is_bold: bool,
+    #[serde(default)]
+    is_italics: bool,
+}
+
+/// An undo/redo step as stored in a project file: a single cell, or the cells of a command
+/// that changed several at once.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ProjectStep {
+    Cell(ProjectCellState),
+    Group(Vec<ProjectCellState>),
+}
+
+/// The undo and redo stacks as stored in a project file.
+#[derive(Serialize, Deserialize)]
+struct ProjectHistory {
+    undo: Vec<ProjectStep>,
+    redo: Vec<ProjectStep>,
+}
+
+/// The iterative calculation settings as stored in a project file.
+#[derive(Serialize, Deserialize)]
+struct ProjectIteration {
+    max_iterations: usize,
+    tolerance: f64,
+}
+
+/// A defined name as stored in a project file.
+#[derive(Serialize, Deserialize)]
+struct ProjectName {
+    name: String,
+    /// What the name stands for, in the form accepted by `parse_formula`.
+    target: String,
+}
+
+/// A sheet of the workbook as stored in a project file.
+#[derive(Serialize, Deserialize)]
+struct ProjectSheet {
+    name: String,
+    rows: i32,
+    columns: i32,
+    cells: Vec<ProjectCell>,
+    history: Option<ProjectHistory>,
+    /// Left out when iterative calculation is off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    iteration: Option<ProjectIteration>,
+    /// Left out when no names are defined.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    names: Vec<ProjectName>,
+}
+
+/// The top-level structure of a project file.
+#[derive(Serialize, Deserialize)]
+struct ProjectFile {
+    format: String,
+    version: u32,
+    /// The position of the active sheet in `sheets`.
+    active: usize,
+    /// The sheets of the workbook, in tab order.
+    sheets: Vec<ProjectSheet>,
+}
+
+impl ProjectStep {
+    fn from_states(states: Vec<&CellState>) -> Self {
+        match states.as_slice() {
+            [state] => ProjectStep::Cell(ProjectCellState::from_state(state)),
+            _ => ProjectStep::Group(
+                states
+                    .into_iter()
+                    .map(ProjectCellState::from_state)
+                    .collect(),
+            ),
+        }
+    }
+
+    fn to_states(&self) -> Result<Vec<CellState>, Box<dyn Error>> {
+        match self {
+            ProjectStep::Cell(state) => Ok(vec![state.to_state()?]),
+            ProjectStep::Group(states) => states.iter().map(ProjectCellState::to_state).collect(),
+        }
+    }
+}
+
+impl ProjectCellState {
+    fn from_state(state: &CellState) -> Self {
+        ProjectCellState {
+            row: state.row,
+            column: state.column,
+            formula: expr_to_string(&state.formula),
+            is_bold: state.is_bold,
+            is_italics: state.is_italics,
+        }
+    }
+
+    fn to_state(&self) -> Result<CellState, Box<dyn Error>> {
+        Ok(CellState {
+            row: self.row,
+            column: self.column,
+            formula: *parse_formula(&self.formula)?,
+            is_bold: self.is_bold,
+            is_italics: self.is_italics,
+        })
+    }
+}
+
+impl ProjectSheet {
+    fn from_sheet(entry: &WorkbookSheet, with_history: bool) -> Self {
+        let sheet = &entry.sheet;
+        let mut cells = Vec::new();
+        for (_, cell) in sheet.all_cells.cells() {
+            let is_empty = matches!(cell.formula, Expr::Number(n) if n == 0.0)
+                && cell.value == CellValue::Number(0.0)
+                && !cell.is_bold
+                && !cell.is_italics;
+            if is_empty {
+                continue;
+            }
+            cells.push(ProjectCell {
+                row: cell.r,
+                column: cell.c,
+                formula: expr_to_string(&cell.formula),
+                is_bold: cell.is_bold,
+                is_italics: cell.is_italics,
+            });
+        }
+
+        let manager = &entry.undo_manager;
+        let history = with_history.then(|| ProjectHistory {
+            undo: manager
+                .undo_steps()
+                .into_iter()
+                .map(ProjectStep::from_states)
+                .collect(),
+            redo: manager
+                .redo_steps()
+                .into_iter()
+                .map(ProjectStep::from_states)
+                .collect(),
+        });
+
+        ProjectSheet {
+            name: entry.name.clone(),
+            rows: sheet.rows,
+            columns: sheet.columns,
+            cells,
+            history,
+            iteration: sheet.iteration.map(|settings| ProjectIteration {
+                max_iterations: settings.max_iterations,
+                tolerance: settings.tolerance,
+            }),
+            names: sheet
+                .names
+                .values()
+                .map(|defined| ProjectName {
+                    name: defined.name.clone(),
+                    target: expr_to_string(&defined.target),
+                })
+                .collect(),
+        }
+    }
+
+    fn to_sheet(&self) -> Result<WorkbookSheet, Box<dyn Error>> {
+        if self.rows < 1 || self.columns < 1 {
+            return Err(
+                format!("Invalid dimensions of sheet {} in project file", self.name).into(),
+            );
+        }
+
+        let mut sheet = initialise_extension(self.rows, self.columns);
+        // Set before the cells are assigned, so loops of formulas load as loops
+        sheet.iteration = self.iteration.as_ref().map(|iteration| IterationSettings {
+            max_iterations: iteration.max_iterations,
+            tolerance: iteration.tolerance,
+        });
+        // Likewise names, so the formulas that use them are linked to their cells
+        for name in &self.names {
+            let defined = DefinedName {
+                name: name.name.clone(),
+                target: *parse_formula(&name.target)?,
+            };
+            sheet.names.insert(defined.key(), defined);
+        }
+        // Rebuilding the sheet should not show up in the undo history
+        let mut scratch_undo = UndoRedoStack::new();
+
+        for cell in &self.cells {
+            if cell.row < 0
+                || cell.row >= sheet.rows
+                || cell.column < 0
+                || cell.column >= sheet.columns
+            {
+                return Err(format!(
+                    "Cell at row {}, column {} is outside sheet {}",
+                    cell.row + 1,
+                    cell.column + 1,
+                    self.name
+                )
+                .into());
+            }
+
+            let formula = *parse_formula(&cell.formula)?;
+            assign_cell_extension(
+                &mut sheet,
+                &mut scratch_undo,
+                cell.row,
+                cell.column,
+                formula,
+            )?;
+
+            let target = &mut sheet.all_cells[cell.row as usize][cell.column as usize];
+            target.is_bold = cell.is_bold;
+            target.is_italics = cell.is_italics;
+        }
+
+        let undo_manager = match &self.history {
+            Some(history) => UndoRedoStack::from_steps(
+                history
+                    .undo
+                    .iter()
+                    .map(ProjectStep::to_states)
+                    .collect::<Result<_, _>>()?,
+                history
+                    .redo
+                    .iter()
+                    .map(ProjectStep::to_states)
+                    .collect::<Result<_, _>>()?,
+            ),
+            None => UndoRedoStack::new(),
+        };
+
+        Ok(WorkbookSheet {
+            name: self.name.clone(),
+            sheet,
+            undo_manager,
+        })
+    }
+}
+
+/// Saves the workbook in the native project format.
+///
+/// # Arguments
+/// * `filename` - The name of the project file to write.
+/// * `workbook` - A reference to the workbook.
+/// * `with_history` - Whether to store the undo/redo history of each sheet.
+///
+/// # Returns
+/// * `Ok(())` if the file is successfully written.
+/// * `Err` if an error occurs (e.g., the file cannot be created).
+///
+/// # Behavior
+/// Stores every sheet in tab order with its name, and which sheet is active, as versioned
+/// JSON. For each sheet it stores the dimensions, the iterative calculation settings, the
+/// defined names and, for every non-empty cell, its formula and formatting. Error values are
+/// not stored; they are recomputed from the formulas on load.
+/// History from before the last row or column insert or delete is not stored, and only the
+/// branch of the undo history leading to and redoing from the current state is stored.
+pub fn save_project(
+    filename: &str,
+    workbook: &Workbook,
+    with_history: bool,
+) -> Result<(), Box<dyn Error>> {
+    let project = ProjectFile {
+        format: PROJECT_FORMAT.to_string(),
+        version: PROJECT_FORMAT_VERSION,
+        active: workbook.active_index(),
+        sheets: workbook
+            .sheets()
+            .iter()
+            .map(|entry| ProjectSheet::from_sheet(entry, with_history))
+            .collect(),
+    };
+
+    let writer = BufWriter::new(File::create(filename)?);
+    serde_json::to_writer_pretty(writer, &project)?;
+    println!("Successfully saved project to {}", filename);
+    Ok(())
+}
+
+/// Loads a workbook saved with `save_project`.
+///
+/// # Arguments
+/// * `filename` - The name of the project file to read.
+///
+/// # Returns
+/// * `Ok(workbook)` - The rebuilt workbook, with the undo/redo history of each sheet
+///   (empty if the file was saved without history).
+/// * `Err` if the file cannot be read, is not a project file, or was written by an
+///   incompatible version.
+///
+/// # Behavior
+/// Every formula is re-parsed and assigned with `assign_cell_extension`, so values and the
+/// dependency graph are rebuilt rather than trusted from the file. References between
+/// sheets are linked once all the sheets are loaded.
+pub fn load_project(filename: &str) -> Result<Workbook, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(filename)?);
+    let project: serde_json::Value = serde_json::from_reader(reader)?;
+
+    // Check the header before deserializing the rest, so files from other versions
+    // get a version error rather than a confusing field error
+    if project.get("format").and_then(|f| f.as_str()) != Some(PROJECT_FORMAT) {
+        return Err(format!("{} is not a spreadsheet project file", filename).into());
+    }
+    let version = project.get("version").and_then(|v| v.as_u64());
+    if version != Some(PROJECT_FORMAT_VERSION as u64) {
+        return Err(format!(
+            "Unsupported project file version {} (this build reads version {})",
+            version.map_or("unknown".to_string(), |v| v.to_string()),
+            PROJECT_FORMAT_VERSION
+        )
+        .into());
+    }
+    let project: ProjectFile = serde_json::from_value(project)?;
+
+    let sheets = project
+        .sheets
+        .iter()
+        .map(ProjectSheet::to_sheet)
+        .collect::<Result<_, _>>()?;
+    Ok(Workbook::from_sheets(sheets, project.active)?)
+}
+
+/// Handles the `save` command to write the workbook as a project file.
+///
+/// # Arguments
+/// * `cmd` - The command string (e.g., `save model.json` or `save model.json history`).
+/// * `workbook` - A reference to the workbook.
+///
+/// # Returns
+/// * `Ok(Outcome::Message)` if the project is successfully saved.
+/// * `Err(SheetError)` if the command is invalid or the file cannot be written.
+///
+/// # Behavior
+/// The undo/redo history of each sheet is stored only when `history` is given.
+pub fn handle_save_command(cmd: &str, workbook: &Workbook) -> CommandResult {
+    let parts: Vec<&str> = cmd.split_whitespace().collect();
+    let usage = || SheetError::InvalidCommand("expected: save <file> [history]".into());
+
+    if parts.len() < 2 || parts.len() > 3 || parts[0] != "save" {
+        return Err(usage());
+    }
+
+    let with_history = match parts.get(2) {
+        None => false,
+        Some(&"history") => true,
+        Some(_) => return Err(usage()),
+    };
+
+    save_project(parts[1], workbook, with_history)
+        .map_err(|e| SheetError::Io(format!("Error saving project file: {}", e)))?;
+    Ok(Outcome::Message(format!("Saved {}", parts[1])))
+}
+
+/// Handles the `load` command to replace the workbook with a project file.
+///
+/// # Arguments
+/// * `cmd` - The command string (e.g., `load model.json`).
+/// * `workbook` - A mutable reference to the workbook, replaced on success.
+///
+/// # Returns
+/// * `Ok(Outcome::Message)` if the project is successfully loaded.
+/// * `Err(SheetError)` if the command is invalid or the file cannot be loaded; the
+///   workbook is left untouched.
+///
+/// # Behavior
+/// The loaded sheets keep the number of undo steps set for the current workbook.
+pub fn handle_load_command(cmd: &str, workbook: &mut Workbook) -> CommandResult {
+    let parts: Vec<&str> = cmd.split_whitespace().collect();
+
+    if parts.len() != 2 || parts[0] != "load" {
+        return Err(SheetError::InvalidCommand(
+            "expected: load <file>".to_string(),
+        ));
+    }
+
+    let mut loaded = load_project(parts[1])
+        .map_err(|e| SheetError::Io(format!("Error loading project file: {}", e)))?;
+    loaded.set_undo_depth(workbook.undo_depth());
+    *workbook = loaded;
+    Ok(Outcome::Message(format!("Loaded {}", parts[1])))
+}
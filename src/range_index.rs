@@ -0,0 +1,131 @@
+use crate::cellsp::CellReference;
+use std::collections::HashMap;
+
+/// The number of rows covered by one bucket of a `RangeIndex`.
+const BUCKET_ROWS: i32 = 32;
+/// The number of columns covered by one bucket of a `RangeIndex`.
+const BUCKET_COLS: i32 = 32;
+
+/// A formula's dependency on a range of cells.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RangeEdge {
+    start: CellReference,
+    end: CellReference,
+    dependent: CellReference,
+}
+
+impl RangeEdge {
+    fn contains(&self, row: i32, column: i32) -> bool {
+        self.start.row <= row
+            && row <= self.end.row
+            && self.start.column <= column
+            && column <= self.end.column
+    }
+}
+
+/// Finds the formulas that depend on a cell through a range such as `A1:B10`.
+///
+/// Each range is stored once per block of `BUCKET_ROWS` by `BUCKET_COLS` cells that it
+/// overlaps, rather than once per cell, so `SUM(A1:A999)` costs 32 entries instead of 999.
+/// Looking up a cell only checks the ranges in its own block.
+#[derive(Clone, Debug, Default)]
+pub struct RangeIndex {
+    buckets: HashMap<(i32, i32), Vec<RangeEdge>>,
+}
+
+impl RangeIndex {
+    /// Creates an empty index.
+    pub fn new() -> Self {
+        RangeIndex::default()
+    }
+
+    /// Returns the blocks a range overlaps.
+    fn buckets_of(start: &CellReference, end: &CellReference) -> impl Iterator<Item = (i32, i32)> {
+        let rows = start.row / BUCKET_ROWS..=end.row / BUCKET_ROWS;
+        let columns = start.column / BUCKET_COLS..=end.column / BUCKET_COLS;
+        rows.flat_map(move |r| columns.clone().map(move |c| (r, c)))
+    }
+
+    /// Records that `dependent` depends on every cell from `start` to `end`.
+    ///
+    /// Recording the same range for the same dependent twice has no further effect.
+    pub fn insert(
+        &mut self,
+        start: &CellReference,
+        end: &CellReference,
+        dependent: &CellReference,
+    ) {
+        let edge = RangeEdge {
+            start: start.clone(),
+            end: end.clone(),
+            dependent: dependent.clone(),
+        };
+        for bucket in Self::buckets_of(start, end) {
+            let edges = self.buckets.entry(bucket).or_default();
+            if !edges.contains(&edge) {
+                edges.push(edge.clone());
+            }
+        }
+    }
+
+    /// Removes the record that `dependent` depends on the range from `start` to `end`.
+    pub fn remove(
+        &mut self,
+        start: &CellReference,
+        end: &CellReference,
+        dependent: &CellReference,
+    ) {
+        let edge = RangeEdge {
+            start: start.clone(),
+            end: end.clone(),
+            dependent: dependent.clone(),
+        };
+        for bucket in Self::buckets_of(start, end) {
+            if let Some(edges) = self.buckets.get_mut(&bucket) {
+                edges.retain(|e| e != &edge);
+                if edges.is_empty() {
+                    self.buckets.remove(&bucket);
+                }
+            }
+        }
+    }
+
+    /// Removes every range.
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+    }
+
+    /// Returns the cells whose formulas depend on a cell through a range.
+    ///
+    /// A dependent appears once for each of its ranges that contains the cell.
+    pub fn dependents_of(&self, row: i32, column: i32) -> impl Iterator<Item = &CellReference> {
+        self.buckets
+            .get(&(row / BUCKET_ROWS, column / BUCKET_COLS))
+            .into_iter()
+            .flatten()
+            .filter(move |edge| edge.contains(row, column))
+            .map(|edge| &edge.dependent)
+    }
+
+    /// Returns each range with its dependent, once each, as `(start, end, dependent)`.
+    pub fn ranges(&self) -> impl Iterator<Item = (&CellReference, &CellReference, &CellReference)> {
+        // A range is stored in every block it overlaps; take it from the block it starts in
+        self.buckets.iter().flat_map(|(&bucket, edges)| {
+            edges
+                .iter()
+                .filter(move |edge| {
+                    bucket
+                        == (
+                            edge.start.row / BUCKET_ROWS,
+                            edge.start.column / BUCKET_COLS,
+                        )
+                })
+                .map(|edge| (&edge.start, &edge.end, &edge.dependent))
+        })
+    }
+
+    /// Returns the number of entries stored, counting a range once per block it overlaps.
+    pub fn entry_count(&self) -> usize {
+        self.buckets.values().map(Vec::len).sum()
+    }
+}
@@ -1,6 +1,9 @@
-use crate::cell_extension::SpreadsheetExtension;
+use crate::cell_extension::{CellError, SpreadsheetExtension};
+use crate::expression_parser::Expr;
 use crate::expression_utils::parse_formula;
 use crate::graph_extension::{UndoRedoStack, assign_cell_extension};
+use crate::sheet_error::{CommandResult, Outcome, SheetError};
+use crate::sheet_structure::{MAX_COLS, MAX_ROWS, resize_extension};
 use std::error::Error;
 use std::fs::File;
 use std::path::Path;
@@ -19,6 +22,10 @@ use std::path::Path;
 /// * Trims whitespace from cell values.
 /// * Supports flexible row lengths in the CSV file.
 /// * Handles formula cells (starting with `=`) by parsing and assigning them.
+/// * Empty fields leave the matching cell empty.
+/// * Grows the sheet to fit the file, up to `MAX_ROWS` by `MAX_COLS`. Anything beyond
+///   that is skipped with a warning.
+/// * The formulas read are undone as a single step.
 ///
 pub fn read_csv_file(
     filename: &str,
@@ -39,90 +46,126 @@ pub fn read_csv_file(
         .trim(csv::Trim::All) // Trim whitespace
         .from_reader(file);
 
-    let mut row_num = 0;
-    for result in rdr.records() {
-        let record = result?;
-
-        // Don't exceed spreadsheet bounds
-        if row_num >= sheet.rows as usize {
-            println!("Warning: CSV file has more rows than the spreadsheet can accommodate");
-            break;
+    let records = rdr.records().collect::<Result<Vec<_>, _>>()?;
+    let needed_rows = (records.len() as i32).min(MAX_ROWS);
+    let needed_columns = (records.iter().map(|r| r.len()).max().unwrap_or(0) as i32).min(MAX_COLS);
+    // The whole file is undone as one step, including growing the sheet
+    let row_num = undo_manager.transaction(|undo_manager| -> Result<usize, Box<dyn Error>> {
+        if needed_rows > sheet.rows || needed_columns > sheet.columns {
+            resize_extension(
+                sheet,
+                undo_manager,
+                needed_rows.max(sheet.rows),
+                needed_columns.max(sheet.columns),
+            )?;
         }
 
-        // Process each cell in the row
-        for (col_num, cell_value) in record.iter().enumerate() {
+        let mut row_num = 0;
+        for record in records {
             // Don't exceed spreadsheet bounds
-            if col_num >= sheet.columns as usize {
-                println!("Warning: CSV file has more columns than the spreadsheet can accommodate");
+            if row_num >= sheet.rows as usize {
+                println!("Warning: CSV file has more rows than the spreadsheet can accommodate");
                 break;
             }
 
-            // Process cell value
-            if let Some(formula_str) = cell_value.strip_prefix("=") {
-                // Handle formula cells
-                match parse_formula(formula_str) {
-                    Ok(expr) => {
-                        assign_cell_extension(
-                            sheet,
-                            undo_manager,
-                            row_num as i32,
-                            col_num as i32,
-                            *expr,
-                        );
-                    }
-                    Err(_) => {
-                        // Invalid formula, treat as text or number
-                        store_cell_value(sheet, row_num, col_num, cell_value);
-                    }
-                }
-            } else {
-                // Handle non-formula cells
-                match parse_formula(cell_value) {
-                    Ok(expr) => {
-                        assign_cell_extension(
-                            sheet,
-                            undo_manager,
-                            row_num as i32,
-                            col_num as i32,
-                            *expr,
-                        );
-                    }
-                    Err(_) => {
-                        // Invalid formula, treat as text or number
-                        store_cell_value(sheet, row_num, col_num, cell_value);
-                    }
+            // Process each cell in the row
+            for (col_num, cell_value) in record.iter().enumerate() {
+                // Don't exceed spreadsheet bounds
+                if col_num >= sheet.columns as usize {
+                    println!(
+                        "Warning: CSV file has more columns than the spreadsheet can accommodate"
+                    );
+                    break;
                 }
+
+                assign_imported_formula(
+                    sheet,
+                    undo_manager,
+                    row_num,
+                    col_num,
+                    field_formula(cell_value),
+                );
             }
-        }
 
-        row_num += 1;
-    }
+            row_num += 1;
+        }
+        Ok(row_num)
+    })?;
 
     println!("Successfully loaded {} rows from {}", row_num, filename);
     Ok(())
 }
 
-/// Stores a cell value in the spreadsheet.
+/// Assigns a formula read from a CSV file to a cell.
 ///
 /// # Arguments
 /// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
 /// * `row` - The row index of the cell.
 /// * `col` - The column index of the cell.
-/// * `value` - The value to store in the cell.
+/// * `formula` - The parsed formula.
 ///
 /// # Behavior
-/// * If the value is numeric, it is stored as an integer.
-/// * If the value is non-numeric, it is stored as `0` (or as a string if supported).
+/// A formula that would create a cycle is skipped with a warning, so the rest of the file
+/// is still read.
+fn assign_imported_formula(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    row: usize,
+    col: usize,
+    formula: Expr,
+) {
+    if let Err(e) = assign_cell_extension(sheet, undo_manager, row as i32, col as i32, formula) {
+        println!("Warning: skipped a cell from the CSV file: {}", e);
+    }
+}
+
+/// Works out the formula a CSV field is read as.
+///
+/// # Arguments
+/// * `field` - The trimmed text of the field.
 ///
-fn store_cell_value(sheet: &mut SpreadsheetExtension, row: usize, col: usize, value: &str) {
-    // Try to parse as integer
-    if let Ok(num_value) = value.parse::<i32>() {
-        sheet.all_cells[row][col].value = num_value;
-        sheet.all_cells[row][col].is_error = false;
+/// # Returns
+/// The parsed formula for fields starting with `=` and for plain fields that parse as a
+/// formula other than a bare name. An empty field gives the empty `0` formula. Any other
+/// field is read as a value by `value_formula`.
+pub(crate) fn field_formula(field: &str) -> Expr {
+    if field.is_empty() {
+        return Expr::Number(0.0);
+    }
+    // A bare word is text, not a defined name; only a formula refers to a name
+    let parsed = match field.strip_prefix("=") {
+        Some(formula_str) => parse_formula(formula_str).ok(),
+        None => parse_formula(field)
+            .ok()
+            .filter(|expr| !matches!(**expr, Expr::Name(_))),
+    };
+    match parsed {
+        Some(expr) => *expr,
+        // Invalid formula, treat as text or number
+        None => value_formula(field),
+    }
+}
+
+/// Builds the constant formula for a CSV field that is not a formula.
+///
+/// # Arguments
+/// * `value` - The text of the field.
+///
+/// # Returns
+/// * A number if the value is numeric (including decimals and scientific notation). Words
+///   such as `inf` or `NaN`, which Rust would parse as non-finite numbers, stay text.
+/// * An error value for error codes such as `#DIV/0!`.
+/// * Otherwise a text formula, so the text survives recalculation.
+///
+fn value_formula(value: &str) -> Expr {
+    if let Some(num_value) = value.parse::<f64>().ok().filter(|n| n.is_finite()) {
+        Expr::Number(num_value)
+    } else if let Some(error) = CellError::from_code(value) {
+        // Error codes written by `write` are read back as errors, not text
+        Expr::Error(error)
     } else {
-        // Non-numeric value - store as 0 or implement string storage
-        sheet.all_cells[row][col].value = 0;
-        // If your spreadsheet supports string values, you would store it here
+        Expr::Text(value.to_string())
     }
 }
 
@@ -133,26 +176,24 @@ fn store_cell_value(sheet: &mut SpreadsheetExtension, row: usize, col: usize, va
 /// * `sheet` - A mutable reference to the spreadsheet.
 ///
 /// # Returns
-/// * `true` if the command is successfully executed.
-/// * `false` if the command is invalid or an error occurs.
+/// * `Ok(Outcome::Message)` if the file is successfully read.
+/// * `Err(SheetError)` if the command is invalid or the file cannot be read.
 ///
 pub fn handle_read_command(
     cmd: &str,
     sheet: &mut SpreadsheetExtension,
     undo_manager: &mut UndoRedoStack,
-) -> bool {
+) -> CommandResult {
     let parts: Vec<&str> = cmd.split_whitespace().collect();
 
     if parts.len() != 2 || parts[0] != "read" {
-        return false;
+        return Err(SheetError::InvalidCommand(
+            "expected: read <file>".to_string(),
+        ));
     }
 
     let filename = parts[1];
-    match read_csv_file(filename, sheet, undo_manager) {
-        Ok(_) => true,
-        Err(e) => {
-            println!("Error reading CSV file: {}", e);
-            false
-        }
-    }
+    read_csv_file(filename, sheet, undo_manager)
+        .map_err(|e| SheetError::Io(format!("Error reading CSV file: {}", e)))?;
+    Ok(Outcome::Message(format!("Read {}", filename)))
 }
@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+/// Orders the cells that need recalculating after a cell changes.
+///
+/// # Arguments
+/// * `start` - The `(row, column)` of the cell that changed.
+/// * `dependents` - Returns the cells whose formulas refer to a cell.
+///
+/// # Returns
+/// Every cell that depends on `start`, directly or through other cells, each once. A cell
+/// comes after every cell it depends on that is also in the list, so evaluating them in
+/// order evaluates each cell exactly once, after its precedents are up to date. `start`
+/// itself is not included.
+///
+/// # Behavior
+/// Only the cells reachable from `start` are visited. Cells on a cycle, and the cells that
+/// depend on them, are left out.
+pub fn recalculation_order<F, I>(start: (i32, i32), mut dependents: F) -> Vec<(i32, i32)>
+where
+    F: FnMut((i32, i32)) -> I,
+    I: IntoIterator<Item = (i32, i32)>,
+{
+    // Find the affected cells and count how many affected cells each one depends on
+    let mut edges: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+    let mut pending: HashMap<(i32, i32), usize> = HashMap::new();
+    let mut visited: HashSet<(i32, i32)> = HashSet::from([start]);
+    let mut stack = vec![start];
+    while let Some(cell) = stack.pop() {
+        let next: Vec<(i32, i32)> = dependents(cell)
+            .into_iter()
+            .filter(|dependent| *dependent != start)
+            .collect();
+        for &dependent in &next {
+            *pending.entry(dependent).or_insert(0) += 1;
+            if visited.insert(dependent) {
+                stack.push(dependent);
+            }
+        }
+        edges.insert(cell, next);
+    }
+
+    // Release each cell once all the affected cells it depends on have been released
+    let mut order = Vec::with_capacity(pending.len());
+    let mut q: VecDeque<(i32, i32)> = VecDeque::from([start]);
+    while let Some(cell) = q.pop_front() {
+        for dependent in &edges[&cell] {
+            let count = pending.get_mut(dependent).expect("dependents are counted");
+            *count -= 1;
+            if *count == 0 {
+                order.push(*dependent);
+                q.push_back(*dependent);
+            }
+        }
+    }
+    order
+}
+
+/// Looks for a loop of formulas running through a cell.
+///
+/// # Arguments
+/// * `start` - The `(row, column)` of the cell to check.
+/// * `dependents` - Returns the cells whose formulas refer to a cell.
+///
+/// # Returns
+/// The cells forming the loop, starting and ending with `start`, each referring to the
+/// next: for `[A1, B2, C3, A1]`, A1's formula refers to B2, B2's to C3 and C3's to A1.
+/// `None` if `start` is not on a loop.
+pub fn cycle_through<F, I>(start: (i32, i32), mut dependents: F) -> Option<Vec<(i32, i32)>>
+where
+    F: FnMut((i32, i32)) -> I,
+    I: IntoIterator<Item = (i32, i32)>,
+{
+    let mut visited: HashSet<(i32, i32)> = HashSet::from([start]);
+    // Each cell on the current path with the dependents still to be explored
+    let mut path = vec![(start, dependents(start).into_iter().collect::<Vec<_>>())];
+    while let Some((_, next)) = path.last_mut() {
+        match next.pop() {
+            Some(cell) if cell == start => {
+                // The path follows dependents, so reverse it to follow references
+                let mut cycle: Vec<(i32, i32)> = path.iter().map(|(cell, _)| *cell).collect();
+                cycle.push(start);
+                cycle.reverse();
+                return Some(cycle);
+            }
+            Some(cell) => {
+                // A cell explored before without reaching `start` cannot reach it now
+                if visited.insert(cell) {
+                    path.push((cell, dependents(cell).into_iter().collect()));
+                }
+            }
+            None => {
+                path.pop();
+            }
+        }
+    }
+    None
+}
+
+/// A group of cells that are recalculated together. See `recalculation_steps`.
+///
+/// Cells are `(row, column)` pairs unless another kind of node is used, such as the
+/// `(sheet, row, column)` of a cell in a workbook.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecalcStep<N = (i32, i32)> {
+    /// A cell that is not on a loop, evaluated once.
+    Cell(N),
+    /// Cells whose formulas refer to each other in a loop, evaluated repeatedly.
+    Cycle(Vec<N>),
+}
+
+/// The bookkeeping of Tarjan's strongly connected components algorithm.
+struct Components<N> {
+    index: HashMap<N, usize>,
+    lowlink: HashMap<N, usize>,
+    on_stack: HashSet<N>,
+    stack: Vec<N>,
+    self_loops: HashSet<N>,
+    steps: Vec<RecalcStep<N>>,
+}
+
+impl<N: Copy + Eq + Hash> Components<N> {
+    /// Starts with no cells visited.
+    fn new() -> Self {
+        Components {
+            index: HashMap::new(),
+            lowlink: HashMap::new(),
+            on_stack: HashSet::new(),
+            stack: Vec::new(),
+            self_loops: HashSet::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Numbers a newly reached cell and puts it on the stack.
+    fn visit(&mut self, cell: N) {
+        self.index.insert(cell, self.index.len());
+        self.lowlink.insert(cell, self.lowlink.len());
+        self.on_stack.insert(cell);
+        self.stack.push(cell);
+    }
+
+    /// Lowers a cell's link to `value` if that is smaller.
+    fn lower(&mut self, cell: N, value: usize) {
+        let low = self.lowlink[&cell].min(value);
+        self.lowlink.insert(cell, low);
+    }
+
+    /// Pops the component rooted at `cell` once all of its cells have been explored.
+    fn finish(&mut self, cell: N) {
+        if self.lowlink[&cell] != self.index[&cell] {
+            return;
+        }
+        let mut component = Vec::new();
+        loop {
+            let member = self.stack.pop().expect("the cell is on the stack");
+            self.on_stack.remove(&member);
+            component.push(member);
+            if member == cell {
+                break;
+            }
+        }
+        self.steps
+            .push(if component.len() > 1 || self.self_loops.contains(&cell) {
+                component.reverse();
+                RecalcStep::Cycle(component)
+            } else {
+                RecalcStep::Cell(cell)
+            });
+    }
+}
+
+/// Orders the cells reachable from some starting cells, grouping loops of formulas.
+///
+/// # Arguments
+/// * `starts` - The `(row, column)` of the cells to start from.
+/// * `dependents` - Returns the cells whose formulas refer to a cell.
+///
+/// # Returns
+/// Every cell reachable from `starts`, including the starting cells, in one step. Cells
+/// that depend on each other in a loop share a `RecalcStep::Cycle` step. Each step comes
+/// after every step it depends on.
+///
+/// # Behavior
+/// Loops are found with Tarjan's strongly connected components algorithm, run without
+/// recursion so long chains of formulas cannot overflow the stack.
+pub fn recalculation_steps<N, F, I>(starts: &[N], mut dependents: F) -> Vec<RecalcStep<N>>
+where
+    N: Copy + Eq + Hash,
+    F: FnMut(N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    let mut components = Components::new();
+    for &start in starts {
+        if components.index.contains_key(&start) {
+            continue;
+        }
+        components.visit(start);
+        // Each cell being explored, with its dependents and how many have been looked at
+        let mut work = vec![(start, dependents(start).into_iter().collect::<Vec<_>>(), 0)];
+
+        while let Some((cell, next, seen)) = work.last_mut() {
+            let cell = *cell;
+            let Some(&dependent) = next.get(*seen) else {
+                work.pop();
+                if let Some((parent, _, _)) = work.last() {
+                    components.lower(*parent, components.lowlink[&cell]);
+                }
+                components.finish(cell);
+                continue;
+            };
+            *seen += 1;
+
+            if dependent == cell {
+                components.self_loops.insert(cell);
+            } else if !components.index.contains_key(&dependent) {
+                components.visit(dependent);
+                work.push((dependent, dependents(dependent).into_iter().collect(), 0));
+            } else if components.on_stack.contains(&dependent) {
+                components.lower(cell, components.index[&dependent]);
+            }
+        }
+    }
+
+    // Tarjan's algorithm finishes a component after everything that depends on it
+    let mut steps = components.steps;
+    steps.reverse();
+    steps
+}
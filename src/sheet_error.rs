@@ -0,0 +1,89 @@
+use crate::cellsp::CellReference;
+use std::error::Error;
+use std::fmt;
+
+/// The result of a command that succeeded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    /// The command completed with nothing to report.
+    Done,
+    /// The command completed; the message is shown in the status bar.
+    Message(String),
+}
+
+impl Outcome {
+    /// Returns the message to show for this outcome, if any.
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Outcome::Done => None,
+            Outcome::Message(message) => Some(message),
+        }
+    }
+}
+
+/// An error that stopped a command from being applied.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SheetError {
+    /// The command is unknown or its arguments are malformed; the text says what was expected.
+    InvalidCommand(String),
+    /// A formula could not be parsed.
+    Parse(String),
+    /// A cell name is not of the form `A1`.
+    InvalidCell(String),
+    /// A cell lies outside the sheet.
+    OutOfBounds(String),
+    /// Assigning a formula would make a cell depend on itself. Holds the cells forming the
+    /// loop, starting and ending with the assigned cell, each referring to the next.
+    CyclicDependency(Vec<CellReference>),
+    /// Reading or writing a file failed.
+    Io(String),
+    /// No sheet of the workbook has the given name.
+    UnknownSheet(String),
+    /// Another sheet of the workbook already has the given name.
+    DuplicateSheet(String),
+    /// No name such as `revenue` is defined with the given name.
+    UnknownName(String),
+    /// No state in the undo history has the given number.
+    UnknownHistoryState(usize),
+}
+
+impl fmt::Display for SheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SheetError::InvalidCommand(message) => write!(f, "Invalid command: {}", message),
+            SheetError::Parse(message) => write!(f, "Parse error: {}", message),
+            SheetError::InvalidCell(name) => write!(f, "Invalid cell name: {}", name),
+            SheetError::OutOfBounds(name) => write!(f, "{} is outside the sheet", name),
+            SheetError::CyclicDependency(cycle) => {
+                let cells: Vec<String> = cycle.iter().map(ToString::to_string).collect();
+                write!(f, "Cyclic dependency: {}", cells.join(" → "))
+            }
+            SheetError::Io(message) => write!(f, "{}", message),
+            SheetError::UnknownSheet(name) => write!(f, "No sheet is named {}", name),
+            SheetError::DuplicateSheet(name) => write!(f, "A sheet named {} already exists", name),
+            SheetError::UnknownName(name) => write!(f, "No name {} is defined", name),
+            SheetError::UnknownHistoryState(number) => {
+                write!(f, "No state {} in the undo history", number)
+            }
+        }
+    }
+}
+
+impl Error for SheetError {}
+
+/// The result of running a command against the spreadsheet.
+pub type CommandResult = Result<Outcome, SheetError>;
+
+/// Converts a command result into the text shown in the status bar.
+///
+/// # Arguments
+/// * `result` - The result of the last command.
+///
+/// # Returns
+/// The outcome's message, `ok` if there is none, or the error message.
+pub fn status_text(result: &CommandResult) -> String {
+    match result {
+        Ok(outcome) => outcome.message().unwrap_or("ok").to_string(),
+        Err(e) => e.to_string(),
+    }
+}
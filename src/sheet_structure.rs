@@ -0,0 +1,635 @@
+use crate::cell_extension::{CellExtension, DefinedName, SpreadsheetExtension};
+use crate::cell_grid::GridCell;
+use crate::cellsp::CellReference;
+use crate::display::get_column_name;
+use crate::expression_utils::{map_references, map_sheet_references};
+use crate::graph_extension::{
+    CellState, UndoEntry, UndoRedoStack, rebuild_dependencies_extension, recalculate_all_extension,
+};
+use crate::parser_visual_mode::{parse_column_name, string_to_int};
+use crate::sheet_error::{CommandResult, Outcome, SheetError};
+use std::cmp::Ordering;
+
+pub use crate::cell_grid::{MAX_COLS, MAX_ROWS};
+
+/// Whether a structural change applies to a row or a column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    Row,
+    Column,
+}
+
+/// A row or column insert or delete, as recorded for undo.
+#[derive(Clone)]
+pub enum StructureChange {
+    /// An empty line was inserted at `index`.
+    Inserted { axis: Axis, index: i32 },
+    /// The line at `index` was deleted.
+    Deleted {
+        axis: Axis,
+        index: i32,
+        /// The cells of the deleted line that had been written, in order.
+        removed: Vec<CellExtension>,
+        /// The formulas that were rewritten, at their positions before the delete.
+        rewritten: Vec<CellState>,
+        /// The defined names that were rewritten, as they were before the delete.
+        names: Vec<DefinedName>,
+    },
+    /// The sheet was resized from `rows` by `columns`.
+    Resized {
+        rows: i32,
+        columns: i32,
+        /// The non-empty cells that were cut off, in order.
+        removed: Vec<CellExtension>,
+        /// The formulas that were rewritten, at their positions before the resize.
+        rewritten: Vec<CellState>,
+        /// The defined names that were rewritten, as they were before the resize.
+        names: Vec<DefinedName>,
+    },
+}
+
+impl StructureChange {
+    /// Describes the change as the command that made it, e.g. `insert_row 3` or
+    /// `delete_col C`.
+    pub fn label(&self) -> String {
+        let (action, axis, index) = match self {
+            StructureChange::Inserted { axis, index } => ("insert", axis, index),
+            StructureChange::Deleted { axis, index, .. } => ("delete", axis, index),
+            StructureChange::Resized { .. } => return "resize".to_string(),
+        };
+        let kind = if *axis == Axis::Row { "row" } else { "col" };
+        format!("{}_{} {}", action, kind, line_name(*axis, *index))
+    }
+}
+
+/// How row or column indices move when a line is inserted or deleted.
+#[derive(Clone, Copy, Debug)]
+enum LineEdit {
+    Insert(i32),
+    Delete(i32),
+    /// Every line from the index onwards is removed.
+    Truncate(i32),
+}
+
+impl LineEdit {
+    /// Moves a single index, or returns `None` if its line was deleted.
+    fn move_index(self, index: i32) -> Option<i32> {
+        match self {
+            LineEdit::Insert(at) => Some(if index >= at { index + 1 } else { index }),
+            LineEdit::Delete(at) => match index.cmp(&at) {
+                Ordering::Less => Some(index),
+                Ordering::Equal => None,
+                Ordering::Greater => Some(index - 1),
+            },
+            LineEdit::Truncate(count) => (index < count).then_some(index),
+        }
+    }
+
+    /// Moves the ends of a span of lines, or returns `None` if every line in it was deleted.
+    ///
+    /// Inserting inside the span grows it and deleting inside the span shrinks it.
+    fn move_span(self, start: i32, end: i32) -> Option<(i32, i32)> {
+        match self {
+            LineEdit::Insert(_) => Some((self.move_index(start)?, self.move_index(end)?)),
+            LineEdit::Delete(at) if start == at && end == at => None,
+            LineEdit::Delete(at) => Some((
+                if start > at { start - 1 } else { start },
+                if end >= at { end - 1 } else { end },
+            )),
+            LineEdit::Truncate(count) if start >= count => None,
+            LineEdit::Truncate(count) => Some((start, end.min(count - 1))),
+        }
+    }
+}
+
+/// A row or column inserted into or deleted from a sheet, as passed on to the formulas of
+/// other sheets by `rewrite_sheet_references`.
+#[derive(Clone, Copy, Debug)]
+pub struct LineMove {
+    axis: Axis,
+    edit: LineEdit,
+}
+
+impl LineMove {
+    /// Moves a single reference, or returns `None` if its line was deleted.
+    fn map_cell(self, cell_ref: &CellReference) -> Option<CellReference> {
+        let index = self.edit.move_index(index_on(self.axis, cell_ref))?;
+        Some(with_index(self.axis, cell_ref, index))
+    }
+
+    /// Moves the ends of a range, or returns `None` if every line in it was deleted.
+    fn map_range(
+        self,
+        start: &CellReference,
+        end: &CellReference,
+    ) -> Option<(CellReference, CellReference)> {
+        let (start_index, end_index) = self
+            .edit
+            .move_span(index_on(self.axis, start), index_on(self.axis, end))?;
+        Some((
+            with_index(self.axis, start, start_index),
+            with_index(self.axis, end, end_index),
+        ))
+    }
+}
+
+/// Returns the index of a reference along an axis.
+fn index_on(axis: Axis, cell_ref: &CellReference) -> i32 {
+    match axis {
+        Axis::Row => cell_ref.row,
+        Axis::Column => cell_ref.column,
+    }
+}
+
+/// Returns a copy of a reference with its index along an axis replaced.
+fn with_index(axis: Axis, cell_ref: &CellReference, index: i32) -> CellReference {
+    let mut moved = cell_ref.clone();
+    match axis {
+        Axis::Row => moved.row = index,
+        Axis::Column => moved.column = index,
+    }
+    moved
+}
+
+/// Rewrites every formula and defined name for a line edit. Returns the pre-edit state of
+/// each changed cell and each changed name.
+fn rewrite_formulas(
+    sheet: &mut SpreadsheetExtension,
+    axis: Axis,
+    edit: LineEdit,
+) -> (Vec<CellState>, Vec<DefinedName>) {
+    let line_move = LineMove { axis, edit };
+    let map_cell = |cell_ref: &CellReference| line_move.map_cell(cell_ref);
+    let map_range = |start: &CellReference, end: &CellReference| line_move.map_range(start, end);
+
+    let mut rewritten = Vec::new();
+    for (_, cell) in sheet.all_cells.cells_mut() {
+        let formula = map_references(&cell.formula, &map_cell, &map_range);
+        if formula != cell.formula {
+            rewritten.push(CellState::capture(cell));
+            cell.formula = formula;
+        }
+    }
+
+    let mut names = Vec::new();
+    for defined in sheet.names.values_mut() {
+        let target = map_references(&defined.target, &map_cell, &map_range);
+        if target != defined.target {
+            names.push(DefinedName {
+                name: defined.name.clone(),
+                target: std::mem::replace(&mut defined.target, target),
+            });
+        }
+    }
+    (rewritten, names)
+}
+
+/// Rewrites the references to another sheet after a row or column of it was inserted or
+/// deleted, without recalculating.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet whose formulas are rewritten.
+/// * `key` - The upper-case name of the sheet that changed, as returned by `sheet_key`.
+/// * `line_move` - The row or column that was inserted or deleted.
+///
+/// # Returns
+/// `true` if any formula or defined name was rewritten.
+///
+/// # Behavior
+/// References to the other sheet follow its cells as references within a sheet do.
+/// References to deleted cells become `#REF!`.
+pub fn rewrite_sheet_references(
+    sheet: &mut SpreadsheetExtension,
+    key: &str,
+    line_move: LineMove,
+) -> bool {
+    let map_cell = |cell_ref: &CellReference| line_move.map_cell(cell_ref);
+    let map_range = |start: &CellReference, end: &CellReference| line_move.map_range(start, end);
+
+    let mut changed = false;
+    for (_, cell) in sheet.all_cells.cells_mut() {
+        let formula = map_sheet_references(&cell.formula, key, &map_cell, &map_range);
+        if formula != cell.formula {
+            cell.formula = formula;
+            changed = true;
+        }
+    }
+    for defined in sheet.names.values_mut() {
+        let target = map_sheet_references(&defined.target, key, &map_cell, &map_range);
+        if target != defined.target {
+            defined.target = target;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Puts back defined names as they were before a change.
+fn restore_names(sheet: &mut SpreadsheetExtension, names: Vec<DefinedName>) {
+    for defined in names {
+        sheet.names.insert(defined.key(), defined);
+    }
+}
+
+/// Updates each cell's stored position after cells have moved.
+fn renumber_cells(sheet: &mut SpreadsheetExtension) {
+    for ((r, c), cell) in sheet.all_cells.cells_mut() {
+        cell.r = r as i32;
+        cell.c = c as i32;
+    }
+}
+
+/// Inserts a line of empty cells and rewrites the formulas, without recalculating.
+fn insert_line(sheet: &mut SpreadsheetExtension, axis: Axis, index: i32) {
+    rewrite_formulas(sheet, axis, LineEdit::Insert(index));
+    sheet.line_moves.push(LineMove {
+        axis,
+        edit: LineEdit::Insert(index),
+    });
+    match axis {
+        Axis::Row => {
+            sheet.all_cells.insert_row(index as usize);
+            sheet.rows += 1;
+        }
+        Axis::Column => {
+            sheet.all_cells.insert_column(index as usize);
+            sheet.columns += 1;
+        }
+    }
+    renumber_cells(sheet);
+}
+
+/// Deletes a line and rewrites the formulas, without recalculating.
+fn delete_line(sheet: &mut SpreadsheetExtension, axis: Axis, index: i32) -> StructureChange {
+    let (rewritten, names) = rewrite_formulas(sheet, axis, LineEdit::Delete(index));
+    sheet.line_moves.push(LineMove {
+        axis,
+        edit: LineEdit::Delete(index),
+    });
+    let removed = match axis {
+        Axis::Row => {
+            sheet.rows -= 1;
+            sheet.all_cells.remove_row(index as usize)
+        }
+        Axis::Column => {
+            sheet.columns -= 1;
+            sheet.all_cells.remove_column(index as usize)
+        }
+    };
+    renumber_cells(sheet);
+    // Cells on the deleted line were rewritten too, but they are restored from `removed`
+    let rewritten = rewritten
+        .into_iter()
+        .filter(|state| index_on(axis, &CellReference::new(state.row, state.column)) != index)
+        .collect();
+    StructureChange::Deleted {
+        axis,
+        index,
+        removed,
+        rewritten,
+        names,
+    }
+}
+
+/// Resizes the sheet, adding empty cells or cutting cells off at the bottom and right, and
+/// rewrites the formulas, without recalculating.
+fn resize_cells(sheet: &mut SpreadsheetExtension, rows: i32, columns: i32) -> StructureChange {
+    let (old_rows, old_columns) = (sheet.rows, sheet.columns);
+    let mut rewritten = Vec::new();
+    let mut names = Vec::new();
+    if rows < old_rows {
+        (rewritten, names) = rewrite_formulas(sheet, Axis::Row, LineEdit::Truncate(rows));
+        sheet.line_moves.push(LineMove {
+            axis: Axis::Row,
+            edit: LineEdit::Truncate(rows),
+        });
+    }
+    if columns < old_columns {
+        // Keep the state from before both rewrites for cells and names rewritten twice
+        let (states, column_names) =
+            rewrite_formulas(sheet, Axis::Column, LineEdit::Truncate(columns));
+        sheet.line_moves.push(LineMove {
+            axis: Axis::Column,
+            edit: LineEdit::Truncate(columns),
+        });
+        for state in states {
+            if !rewritten
+                .iter()
+                .any(|old: &CellState| old.row == state.row && old.column == state.column)
+            {
+                rewritten.push(state);
+            }
+        }
+        for defined in column_names {
+            if !names
+                .iter()
+                .any(|old: &DefinedName| old.name == defined.name)
+            {
+                names.push(defined);
+            }
+        }
+    }
+    rewritten.retain(|state| state.row < rows && state.column < columns);
+
+    let cut = sheet.all_cells.resize(rows as usize, columns as usize);
+    sheet.rows = rows;
+    sheet.columns = columns;
+
+    // Empty cells are recreated when the sheet grows back, so only keep the rest
+    let empty = CellExtension::empty(0, 0);
+    let removed = cut
+        .into_iter()
+        .filter(|cell| {
+            cell.value != empty.value
+                || cell.formula != empty.formula
+                || cell.is_bold
+                || cell.is_italics
+        })
+        .collect();
+    StructureChange::Resized {
+        rows: old_rows,
+        columns: old_columns,
+        removed,
+        rewritten,
+        names,
+    }
+}
+
+/// Rebuilds the dependency graph and every value after cells have moved.
+fn refresh(sheet: &mut SpreadsheetExtension) {
+    rebuild_dependencies_extension(sheet);
+    recalculate_all_extension(sheet);
+}
+
+/// Undoes a structural change.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `change` - The change to undo.
+///
+/// # Returns
+/// The change that undoes this one, to be recorded for redo.
+pub fn revert_structure_change(
+    sheet: &mut SpreadsheetExtension,
+    change: StructureChange,
+) -> StructureChange {
+    let reverse = match change {
+        StructureChange::Inserted { axis, index } => delete_line(sheet, axis, index),
+        StructureChange::Deleted {
+            axis,
+            index,
+            removed,
+            rewritten,
+            names,
+        } => {
+            insert_line(sheet, axis, index);
+            for cell in removed {
+                let (r, c) = (cell.r as usize, cell.c as usize);
+                sheet.all_cells[r][c] = cell;
+            }
+            // Put back the formulas exactly, including references that became #REF!
+            for state in rewritten {
+                sheet.all_cells[state.row as usize][state.column as usize].formula = state.formula;
+            }
+            restore_names(sheet, names);
+            StructureChange::Inserted { axis, index }
+        }
+        StructureChange::Resized {
+            rows,
+            columns,
+            removed,
+            rewritten,
+            names,
+        } => {
+            let reverse = resize_cells(sheet, rows, columns);
+            for cell in removed {
+                let (r, c) = (cell.r as usize, cell.c as usize);
+                sheet.all_cells[r][c] = cell;
+            }
+            for state in rewritten {
+                sheet.all_cells[state.row as usize][state.column as usize].formula = state.formula;
+            }
+            restore_names(sheet, names);
+            reverse
+        }
+    };
+    refresh(sheet);
+    reverse
+}
+
+/// Names a line for error messages, e.g. row `3` or column `C`.
+fn line_name(axis: Axis, index: i32) -> String {
+    match axis {
+        Axis::Row => (index + 1).to_string(),
+        Axis::Column => get_column_name(index),
+    }
+}
+
+/// Returns the number of lines along an axis.
+fn line_count(sheet: &SpreadsheetExtension, axis: Axis) -> i32 {
+    match axis {
+        Axis::Row => sheet.rows,
+        Axis::Column => sheet.columns,
+    }
+}
+
+/// Returns the largest number of lines a sheet can have along an axis.
+fn line_limit(axis: Axis) -> i32 {
+    match axis {
+        Axis::Row => MAX_ROWS,
+        Axis::Column => MAX_COLS,
+    }
+}
+
+/// Inserts an empty row or column, moving the cells at and after `index` along by one.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+/// * `axis` - Whether to insert a row or a column.
+/// * `index` - The 0-based index of the new line; the sheet's size appends a line at the end.
+///
+/// # Returns
+/// * `Ok(Outcome::Done)` if the line was inserted.
+/// * `Err(SheetError::OutOfBounds)` if `index` is past the end of the sheet, or the sheet
+///   already has `MAX_ROWS` rows or `MAX_COLS` columns.
+///
+/// # Behavior
+/// * The sheet grows by one row or column.
+/// * References to moved cells are rewritten to follow them, including anchored (`$`)
+///   references. Ranges that the new line falls inside grow to include it. Defined names
+///   follow their cells the same way. References from other sheets of a workbook follow
+///   when `Workbook::refresh_links` next runs.
+/// * The change is recorded as a single undo step.
+pub fn insert_line_extension(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    axis: Axis,
+    index: i32,
+) -> CommandResult {
+    if index < 0 || index > line_count(sheet, axis) {
+        return Err(SheetError::OutOfBounds(line_name(axis, index)));
+    }
+    if line_count(sheet, axis) >= line_limit(axis) {
+        return Err(SheetError::OutOfBounds(line_name(axis, line_limit(axis))));
+    }
+    insert_line(sheet, axis, index);
+    refresh(sheet);
+    undo_manager.push_entry(UndoEntry::Structure(StructureChange::Inserted {
+        axis,
+        index,
+    }));
+    Ok(Outcome::Done)
+}
+
+/// Deletes a row or column, moving the cells after it back by one.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+/// * `axis` - Whether to delete a row or a column.
+/// * `index` - The 0-based index of the line to delete.
+///
+/// # Returns
+/// * `Ok(Outcome::Done)` if the line was deleted.
+/// * `Err(SheetError::OutOfBounds)` if there is no such line.
+/// * `Err(SheetError::InvalidCommand)` if it is the sheet's only row or column.
+///
+/// # Behavior
+/// * The sheet shrinks by one row or column.
+/// * References to moved cells are rewritten to follow them. References to deleted cells
+///   become `#REF!`; ranges lose the deleted line and become `#REF!` only if nothing is left.
+///   Defined names follow their cells the same way, as do references from other sheets of a
+///   workbook when `Workbook::refresh_links` next runs.
+/// * The change is recorded as a single undo step that restores the deleted cells and every
+///   formula and name of this sheet that was rewritten.
+pub fn delete_line_extension(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    axis: Axis,
+    index: i32,
+) -> CommandResult {
+    if index < 0 || index >= line_count(sheet, axis) {
+        return Err(SheetError::OutOfBounds(line_name(axis, index)));
+    }
+    if line_count(sheet, axis) == 1 {
+        return Err(SheetError::InvalidCommand(format!(
+            "cannot delete the only {}",
+            if axis == Axis::Row { "row" } else { "column" }
+        )));
+    }
+    let change = delete_line(sheet, axis, index);
+    refresh(sheet);
+    undo_manager.push_entry(UndoEntry::Structure(change));
+    Ok(Outcome::Done)
+}
+
+/// Resizes the sheet.
+///
+/// # Arguments
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+/// * `rows` - The new number of rows, from 1 to `MAX_ROWS`.
+/// * `columns` - The new number of columns, from 1 to `MAX_COLS`.
+///
+/// # Returns
+/// * `Ok(Outcome::Done)` if the sheet was resized.
+/// * `Err(SheetError::InvalidCommand)` if the size is outside the limits.
+///
+/// # Behavior
+/// * Rows and columns are added or cut off at the bottom and right of the sheet.
+/// * References to cut-off cells become `#REF!`; ranges are clipped to the new size and
+///   become `#REF!` only if nothing is left. Defined names are clipped the same way.
+/// * The change is recorded as a single undo step that restores the cut-off cells.
+pub fn resize_extension(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    rows: i32,
+    columns: i32,
+) -> CommandResult {
+    if !(1..=MAX_ROWS).contains(&rows) || !(1..=MAX_COLS).contains(&columns) {
+        return Err(SheetError::InvalidCommand(format!(
+            "size must be from 1x1 to {}x{}",
+            MAX_ROWS, MAX_COLS
+        )));
+    }
+    if (rows, columns) == (sheet.rows, sheet.columns) {
+        return Ok(Outcome::Done);
+    }
+    let change = resize_cells(sheet, rows, columns);
+    refresh(sheet);
+    undo_manager.push_entry(UndoEntry::Structure(change));
+    Ok(Outcome::Done)
+}
+
+/// Inserts an empty row before `row` (0-based). See `insert_line_extension`.
+pub fn insert_row(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    row: i32,
+) -> CommandResult {
+    insert_line_extension(sheet, undo_manager, Axis::Row, row)
+}
+
+/// Deletes the row `row` (0-based). See `delete_line_extension`.
+pub fn delete_row(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    row: i32,
+) -> CommandResult {
+    delete_line_extension(sheet, undo_manager, Axis::Row, row)
+}
+
+/// Inserts an empty column before `col` (0-based). See `insert_line_extension`.
+pub fn insert_col(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    col: i32,
+) -> CommandResult {
+    insert_line_extension(sheet, undo_manager, Axis::Column, col)
+}
+
+/// Deletes the column `col` (0-based). See `delete_line_extension`.
+pub fn delete_col(
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+    col: i32,
+) -> CommandResult {
+    delete_line_extension(sheet, undo_manager, Axis::Column, col)
+}
+
+/// Handles the `insert_row`, `delete_row`, `insert_col`, `delete_col` and `resize` commands.
+///
+/// # Arguments
+/// * `cmd` - The command string, e.g. `insert_row 3`, `delete_col C` or `resize 100 30`.
+///   Rows are given by number and columns by letter, except for `resize`, which takes the
+///   new number of rows and columns.
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+///
+/// # Returns
+/// * `Ok(Outcome::Done)` if the sheet was changed.
+/// * `Err(SheetError)` if the command is malformed or names a line outside the sheet.
+pub fn handle_structure_command(
+    cmd: &str,
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+) -> CommandResult {
+    let parts: Vec<&str> = cmd.split_whitespace().collect();
+    let row_index = |line: &str| -> Result<i32, SheetError> { Ok(string_to_int(line)? - 1) };
+    match parts.as_slice() {
+        ["resize", rows, columns] => resize_extension(
+            sheet,
+            undo_manager,
+            string_to_int(rows)?,
+            string_to_int(columns)?,
+        ),
+        ["insert_row", line] => insert_row(sheet, undo_manager, row_index(line)?),
+        ["delete_row", line] => delete_row(sheet, undo_manager, row_index(line)?),
+        ["insert_col", line] => insert_col(sheet, undo_manager, parse_column_name(line)?),
+        ["delete_col", line] => delete_col(sheet, undo_manager, parse_column_name(line)?),
+        _ => Err(SheetError::InvalidCommand(
+            "expected: insert_row|delete_row <row>, insert_col|delete_col <column> or resize <rows> <columns>".into(),
+        )),
+    }
+}
@@ -0,0 +1,79 @@
+use crate::cell_extension::SpreadsheetExtension;
+use crate::graph_extension::{HistoryState, UndoRedoStack, undo_to};
+use crate::sheet_error::{CommandResult, Outcome, SheetError};
+
+/// Formats a state of the undo history as one line, e.g. `*3 14:02:51 A1:E5 (from 1)`.
+///
+/// # Arguments
+/// * `state` - The state, as listed by `UndoRedoStack::history`.
+/// * `previous` - The number of the state listed before it, if any.
+///
+/// # Returns
+/// The number of the state, marked with `*` if the sheet is in it, the time of the change
+/// and what it changed. States that start a new branch name the state they branch from.
+pub fn format_history_state(state: &HistoryState, previous: Option<usize>) -> String {
+    let marker = if state.is_current { "*" } else { "" };
+    let mut line = format!(
+        "{}{} {} {}",
+        marker,
+        state.number,
+        state.time.format("%H:%M:%S"),
+        state.label
+    );
+    if let Some(parent) = state.parent
+        && Some(parent) != previous
+    {
+        line.push_str(&format!(" (from {})", parent));
+    }
+    line
+}
+
+/// Formats the whole undo history, one line per state, oldest first.
+pub fn format_history(undo_manager: &UndoRedoStack) -> Vec<String> {
+    let history = undo_manager.history();
+    let mut previous = None;
+    history
+        .iter()
+        .map(|state| {
+            let line = format_history_state(state, previous);
+            previous = Some(state.number);
+            line
+        })
+        .collect()
+}
+
+/// Handles the `history` and `undo_to` commands.
+///
+/// # Arguments
+/// * `cmd` - `history` to list the states of the undo history, or `undo_to <n>` to move the
+///   sheet to state `n`.
+/// * `sheet` - A mutable reference to the spreadsheet.
+/// * `undo_manager` - A mutable reference to the undo manager.
+///
+/// # Returns
+/// * `Ok(Outcome::Message)` with the history for `history`, `Ok(Outcome::Done)` otherwise.
+/// * `Err(SheetError)` if the command is malformed or names a state that is not kept.
+///
+/// # Behavior
+/// Changes made after an undo start a new branch instead of discarding the undone changes,
+/// so `undo_to` can return to any state listed, on any branch.
+pub fn handle_history_command(
+    cmd: &str,
+    sheet: &mut SpreadsheetExtension,
+    undo_manager: &mut UndoRedoStack,
+) -> CommandResult {
+    let parts: Vec<&str> = cmd.split_whitespace().collect();
+    match parts.as_slice() {
+        ["history"] => Ok(Outcome::Message(format_history(undo_manager).join("; "))),
+        ["undo_to", number] => {
+            let number = number.parse::<usize>().map_err(|_| {
+                SheetError::InvalidCommand(format!("{} is not a state number", number))
+            })?;
+            undo_to(sheet, undo_manager, number)?;
+            Ok(Outcome::Done)
+        }
+        _ => Err(SheetError::InvalidCommand(
+            "expected: history or undo_to <n>".into(),
+        )),
+    }
+}
@@ -0,0 +1,642 @@
+use crate::cell_extension::{CellError, CellValue, LinkedSheet, SpreadsheetExtension, sheet_key};
+use crate::expression_parser::Expr;
+use crate::graph_extension::{
+    UndoRedoStack, calculate_cell_value_extension, dependents_of_extension, initialise_extension,
+    rebuild_dependencies_extension, recalculate_all_extension, recalculate_dependents_extension,
+};
+use crate::parser_visual_mode::string_to_int;
+use crate::range_index::RangeIndex;
+use crate::recalc_order::{RecalcStep, recalculation_steps};
+use crate::sheet_error::{CommandResult, Outcome, SheetError};
+use crate::sheet_structure::rewrite_sheet_references;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// The most times `Workbook::refresh_links` passes values between sheets in one refresh.
+const MAX_LINK_PASSES: usize = 100;
+
+/// A cell of a workbook, as `(sheet index, row, column)`.
+type WorkbookCell = (usize, i32, i32);
+
+/// A named sheet of a workbook, with its own undo history.
+pub struct WorkbookSheet {
+    /// The name of the sheet, as shown on its tab and written in references such as
+    /// `Sheet2!A1`.
+    pub name: String,
+    /// The cells of the sheet.
+    pub sheet: SpreadsheetExtension,
+    /// The edits made to the sheet that can be undone.
+    pub undo_manager: UndoRedoStack,
+}
+
+/// A workbook of named sheets whose formulas may refer to each other.
+///
+/// One sheet is active at a time; commands that edit cells apply to it. Formulas on any
+/// sheet may refer to cells of another sheet, as `Sheet2!A1` or `'My Sheet'!A1:B5`.
+/// Each sheet reads those cells from its `linked` values, which `refresh_links` brings up
+/// to date after every change.
+pub struct Workbook {
+    sheets: Vec<WorkbookSheet>,
+    active: usize,
+}
+
+impl Workbook {
+    /// Creates a workbook with one empty sheet named `Sheet1`.
+    ///
+    /// # Arguments
+    /// * `rows` - The number of rows of the sheet.
+    /// * `columns` - The number of columns of the sheet.
+    pub fn new(rows: i32, columns: i32) -> Self {
+        Workbook {
+            sheets: vec![WorkbookSheet {
+                name: "Sheet1".to_string(),
+                sheet: initialise_extension(rows, columns),
+                undo_manager: UndoRedoStack::new(),
+            }],
+            active: 0,
+        }
+    }
+
+    /// Creates a workbook from named sheets, such as those read from a project file.
+    ///
+    /// # Arguments
+    /// * `sheets` - The sheets, in tab order.
+    /// * `active` - The position of the active sheet in `sheets`.
+    ///
+    /// # Returns
+    /// * `Ok(workbook)` with the references between the sheets linked.
+    /// * `Err(SheetError)` if there are no sheets, a name is not allowed or is repeated, or
+    ///   `active` is not the position of a sheet.
+    pub fn from_sheets(sheets: Vec<WorkbookSheet>, active: usize) -> Result<Self, SheetError> {
+        if active >= sheets.len() {
+            return Err(SheetError::InvalidCommand(
+                "the active sheet is not one of the workbook's sheets".into(),
+            ));
+        }
+        let mut workbook = Workbook {
+            sheets: Vec::with_capacity(sheets.len()),
+            active,
+        };
+        for entry in sheets {
+            workbook.check_name(&entry.name, None)?;
+            workbook.sheets.push(entry);
+        }
+        workbook.refresh_links();
+        Ok(workbook)
+    }
+
+    /// Returns the sheets, in tab order.
+    pub fn sheets(&self) -> &[WorkbookSheet] {
+        &self.sheets
+    }
+
+    /// Returns the names of the sheets, in tab order.
+    pub fn names(&self) -> Vec<&str> {
+        self.sheets
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    /// Returns the position of the active sheet in `names`.
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    /// Returns the name of the active sheet.
+    pub fn active_name(&self) -> &str {
+        &self.sheets[self.active].name
+    }
+
+    /// Returns the active sheet.
+    pub fn active(&self) -> &SpreadsheetExtension {
+        &self.sheets[self.active].sheet
+    }
+
+    /// Returns the active sheet for editing.
+    pub fn active_mut(&mut self) -> &mut SpreadsheetExtension {
+        &mut self.sheets[self.active].sheet
+    }
+
+    /// Returns the active sheet and its undo history, for commands that edit cells.
+    pub fn active_parts(&mut self) -> (&mut SpreadsheetExtension, &mut UndoRedoStack) {
+        let entry = &mut self.sheets[self.active];
+        (&mut entry.sheet, &mut entry.undo_manager)
+    }
+
+    /// Returns the most undo steps each sheet keeps.
+    pub fn undo_depth(&self) -> usize {
+        self.sheets[self.active].undo_manager.max_depth()
+    }
+
+    /// Changes the most undo steps each sheet keeps, dropping the oldest steps of sheets
+    /// that have more.
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        for entry in &mut self.sheets {
+            entry.undo_manager.set_max_depth(depth);
+        }
+    }
+
+    /// Returns the sheet with the given name, in any case.
+    pub fn sheet(&self, name: &str) -> Option<&SpreadsheetExtension> {
+        self.position(name).map(|index| &self.sheets[index].sheet)
+    }
+
+    /// Returns the position of the sheet with the given name, in any case.
+    fn position(&self, name: &str) -> Option<usize> {
+        let key = sheet_key(name);
+        self.sheets
+            .iter()
+            .position(|entry| sheet_key(&entry.name) == key)
+    }
+
+    /// Returns the position of a sheet, or `SheetError::UnknownSheet`.
+    fn find(&self, name: &str) -> Result<usize, SheetError> {
+        self.position(name)
+            .ok_or_else(|| SheetError::UnknownSheet(name.to_string()))
+    }
+
+    /// Checks that a name can be given to a sheet, other than the sheet at `except`.
+    fn check_name(&self, name: &str, except: Option<usize>) -> Result<(), SheetError> {
+        let valid = !name.is_empty()
+            && name.trim() == name
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ' ');
+        if !valid {
+            return Err(SheetError::InvalidCommand(
+                "sheet names may only contain letters, digits, spaces and _".into(),
+            ));
+        }
+        match self.position(name) {
+            Some(index) if Some(index) != except => {
+                Err(SheetError::DuplicateSheet(name.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds an empty sheet after the others and makes it the active sheet.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the new sheet. It may contain letters, digits, spaces and `_`,
+    ///   and must differ from every other sheet's name in more than case.
+    ///
+    /// # Returns
+    /// * `Ok(Outcome::Done)` if the sheet was added.
+    /// * `Err(SheetError)` if the name is not allowed or already taken.
+    ///
+    /// # Behavior
+    /// The new sheet is the same size as the active sheet. It keeps the same number of undo
+    /// steps. Formulas that already refer to a sheet of this name start reading its cells.
+    pub fn add_sheet(&mut self, name: &str) -> CommandResult {
+        self.check_name(name, None)?;
+        let active = self.active();
+        let sheet = initialise_extension(active.rows, active.columns);
+        let mut undo_manager = UndoRedoStack::new();
+        undo_manager.set_max_depth(self.undo_depth());
+        self.sheets.push(WorkbookSheet {
+            name: name.to_string(),
+            sheet,
+            undo_manager,
+        });
+        self.active = self.sheets.len() - 1;
+        self.refresh_links();
+        Ok(Outcome::Done)
+    }
+
+    /// Returns a name for a new sheet, `SheetN` for the smallest `N` not taken.
+    pub fn next_sheet_name(&self) -> String {
+        (1..)
+            .map(|n| format!("Sheet{}", n))
+            .find(|name| self.position(name).is_none())
+            .expect("some sheet name is free")
+    }
+
+    /// Renames a sheet and every reference to it.
+    ///
+    /// # Arguments
+    /// * `old_name` - The name of the sheet, in any case.
+    /// * `new_name` - The new name, following the rules of `add_sheet`.
+    ///
+    /// # Returns
+    /// * `Ok(Outcome::Done)` if the sheet was renamed.
+    /// * `Err(SheetError)` if there is no such sheet, or the new name is not allowed.
+    ///
+    /// # Behavior
+    /// Formulas and defined names on every sheet that refer to the old name are rewritten to
+    /// use the new one, so their values do not change. The rewrite is not recorded in the
+    /// undo history.
+    pub fn rename_sheet(&mut self, old_name: &str, new_name: &str) -> CommandResult {
+        let index = self.find(old_name)?;
+        self.check_name(new_name, Some(index))?;
+        let old_key = sheet_key(&self.sheets[index].name);
+        self.sheets[index].name = new_name.to_string();
+
+        for entry in &mut self.sheets {
+            let sheet = &mut entry.sheet;
+            let mut changed = false;
+            for (_, cell) in sheet.all_cells.cells_mut() {
+                let formula = rename_references(&cell.formula, &old_key, new_name);
+                changed |= formula != cell.formula;
+                cell.formula = formula;
+            }
+            // Names may point into the sheet without any formula using them yet
+            for defined in sheet.names.values_mut() {
+                let target = rename_references(&defined.target, &old_key, new_name);
+                changed |= target != defined.target;
+                defined.target = target;
+            }
+            if changed {
+                rebuild_dependencies_extension(sheet);
+                recalculate_all_extension(sheet);
+            }
+        }
+        self.refresh_links();
+        Ok(Outcome::Done)
+    }
+
+    /// Deletes a sheet.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the sheet, in any case.
+    ///
+    /// # Returns
+    /// * `Ok(Outcome::Done)` if the sheet was deleted.
+    /// * `Err(SheetError)` if there is no such sheet, or it is the only one.
+    ///
+    /// # Behavior
+    /// Formulas that refer to the deleted sheet keep their text and evaluate to `#REF!`
+    /// until a sheet of that name is added again. If the active sheet is deleted, the sheet
+    /// that took its place becomes active.
+    pub fn delete_sheet(&mut self, name: &str) -> CommandResult {
+        let index = self.find(name)?;
+        if self.sheets.len() == 1 {
+            return Err(SheetError::InvalidCommand(
+                "the only sheet of a workbook cannot be deleted".into(),
+            ));
+        }
+        self.sheets.remove(index);
+        if index < self.active || self.active == self.sheets.len() {
+            self.active -= 1;
+        }
+        self.refresh_links();
+        Ok(Outcome::Done)
+    }
+
+    /// Makes another sheet the active sheet.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the sheet, in any case.
+    ///
+    /// # Returns
+    /// * `Ok(Outcome::Done)` if the sheet is now active.
+    /// * `Err(SheetError::UnknownSheet)` if there is no such sheet.
+    pub fn switch_to(&mut self, name: &str) -> CommandResult {
+        self.active = self.find(name)?;
+        Ok(Outcome::Done)
+    }
+
+    /// Brings every sheet's `linked` values up to date and recalculates the formulas that
+    /// read them.
+    ///
+    /// # Behavior
+    /// Values are passed between sheets until nothing changes, so a chain of references
+    /// through several sheets settles in one refresh. Formulas that refer to each other in a
+    /// loop running through more than one sheet evaluate to `#CYCLE!`, whether or not
+    /// iterative calculation is on. Rows and columns inserted, deleted or cut off since the
+    /// last refresh, including by undo and redo, are passed on to the formulas and defined
+    /// names of the other sheets first. Run this after every command that changes a sheet.
+    pub fn refresh_links(&mut self) {
+        self.pass_on_line_moves();
+        let cycles = self.cross_sheet_cycles();
+        for _ in 0..MAX_LINK_PASSES {
+            let mut changed = false;
+            for index in 0..self.sheets.len() {
+                changed |= self.update_links(index, &cycles);
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Rewrites the references into each sheet whose rows or columns moved, on every other
+    /// sheet, and recalculates the sheets that changed.
+    ///
+    /// # Behavior
+    /// References to deleted cells become `#REF!`. The rewrite is not recorded in the undo
+    /// history of the other sheets; undoing the insert or delete moves the references back,
+    /// but references that became `#REF!` stay so.
+    fn pass_on_line_moves(&mut self) {
+        let mut changed = vec![false; self.sheets.len()];
+        for index in 0..self.sheets.len() {
+            let line_moves = std::mem::take(&mut self.sheets[index].sheet.line_moves);
+            let key = sheet_key(&self.sheets[index].name);
+            for line_move in line_moves {
+                for (other, entry) in self.sheets.iter_mut().enumerate() {
+                    if other != index {
+                        changed[other] |=
+                            rewrite_sheet_references(&mut entry.sheet, &key, line_move);
+                    }
+                }
+            }
+        }
+        for (entry, changed) in self.sheets.iter_mut().zip(changed) {
+            if changed {
+                rebuild_dependencies_extension(&mut entry.sheet);
+                recalculate_all_extension(&mut entry.sheet);
+            }
+        }
+    }
+
+    /// Re-reads the cells of other sheets that a sheet refers to, and recalculates the
+    /// formulas whose references changed.
+    ///
+    /// # Returns
+    /// `true` if any value the sheet reads from another sheet changed.
+    fn update_links(&mut self, index: usize, cycles: &HashSet<WorkbookCell>) -> bool {
+        let sheet = &self.sheets[index].sheet;
+        let mut updates = Vec::new();
+        for (key, references) in &sheet.external_dependents {
+            let linked = self.read_linked(key, references, cycles);
+            let old = sheet.linked.get(key);
+            if linked.as_ref() != old {
+                let dependents = changed_dependents(references, old, linked.as_ref());
+                updates.push((key.clone(), linked, dependents));
+            }
+        }
+
+        let sheet = &mut self.sheets[index].sheet;
+        let external_dependents = &sheet.external_dependents;
+        sheet
+            .linked
+            .retain(|key, _| external_dependents.contains_key(key));
+        if updates.is_empty() {
+            return false;
+        }
+
+        let mut dirty = BTreeSet::new();
+        for (key, linked, dependents) in updates {
+            match linked {
+                Some(linked) => sheet.linked.insert(key, linked),
+                None => sheet.linked.remove(&key),
+            };
+            dirty.extend(dependents);
+        }
+        for (r, c) in dirty {
+            calculate_cell_value_extension(sheet, r, c);
+            recalculate_dependents_extension(sheet, r, c);
+        }
+        true
+    }
+
+    /// Reads the cells of the sheet stored under `key` that a sheet's formulas refer to.
+    ///
+    /// # Returns
+    /// The values, or `None` if no sheet has that name. Cells on a loop through several
+    /// sheets read as `#CYCLE!`.
+    fn read_linked(
+        &self,
+        key: &str,
+        references: &RangeIndex,
+        cycles: &HashSet<WorkbookCell>,
+    ) -> Option<LinkedSheet> {
+        let index = self.position(key)?;
+        let target = &self.sheets[index].sheet;
+        let mut values = HashMap::new();
+        for (start, end, _) in references.ranges() {
+            let end_row = end.row.min(target.rows - 1);
+            let end_column = end.column.min(target.columns - 1);
+            if start.row > end_row || start.column > end_column {
+                continue;
+            }
+            let cells = target.all_cells.cells_in(
+                (start.row as usize, start.column as usize),
+                (end_row as usize, end_column as usize),
+            );
+            for ((r, c), cell) in cells {
+                let (r, c) = (r as i32, c as i32);
+                let value = if cycles.contains(&(index, r, c)) {
+                    CellValue::Error(CellError::Cycle)
+                } else {
+                    cell.value.clone()
+                };
+                if value != CellValue::default() {
+                    values.insert((r, c), value);
+                }
+            }
+        }
+        Some(LinkedSheet {
+            rows: target.rows,
+            columns: target.columns,
+            values,
+        })
+    }
+
+    /// Returns the cells of other sheets whose formulas refer to a cell.
+    fn external_dependents_of(&self, (index, row, column): WorkbookCell) -> Vec<WorkbookCell> {
+        let key = sheet_key(&self.sheets[index].name);
+        let mut dependents = Vec::new();
+        for (other, entry) in self.sheets.iter().enumerate() {
+            if let Some(references) = entry.sheet.external_dependents.get(&key) {
+                dependents.extend(
+                    references
+                        .dependents_of(row, column)
+                        .map(|dependent| (other, dependent.row, dependent.column)),
+                );
+            }
+        }
+        dependents
+    }
+
+    /// Finds the cells on loops of formulas that run through more than one sheet, or
+    /// through a reference by name to their own sheet.
+    fn cross_sheet_cycles(&self) -> HashSet<WorkbookCell> {
+        // Every such loop passes through a formula that refers to another sheet
+        let mut starts = Vec::new();
+        for (index, entry) in self.sheets.iter().enumerate() {
+            for (key, references) in &entry.sheet.external_dependents {
+                if self.position(key).is_some() {
+                    starts.extend(
+                        references
+                            .ranges()
+                            .map(|(_, _, dependent)| (index, dependent.row, dependent.column)),
+                    );
+                }
+            }
+        }
+
+        let steps = recalculation_steps(&starts, |cell: WorkbookCell| {
+            let (index, row, column) = cell;
+            let mut next: Vec<WorkbookCell> =
+                dependents_of_extension(&self.sheets[index].sheet, row, column)
+                    .into_iter()
+                    .map(|dependent| (index, dependent.row, dependent.column))
+                    .collect();
+            next.extend(self.external_dependents_of(cell));
+            next
+        });
+
+        let mut cycles = HashSet::new();
+        for step in steps {
+            let RecalcStep::Cycle(cells) = step else {
+                continue;
+            };
+            let members: HashSet<WorkbookCell> = cells.iter().copied().collect();
+            // Loops within one sheet are handled by the sheet itself
+            let crosses_sheets = cells.iter().any(|&cell| {
+                self.external_dependents_of(cell)
+                    .iter()
+                    .any(|dependent| members.contains(dependent))
+            });
+            if crosses_sheets {
+                cycles.extend(members);
+            }
+        }
+        cycles
+    }
+}
+
+/// Returns the cells whose formulas read a value that differs between two readings of
+/// another sheet.
+fn changed_dependents(
+    references: &RangeIndex,
+    old: Option<&LinkedSheet>,
+    new: Option<&LinkedSheet>,
+) -> Vec<(i32, i32)> {
+    let (Some(old), Some(new)) = (old, new) else {
+        return all_dependents(references);
+    };
+    if (old.rows, old.columns) != (new.rows, new.columns) {
+        return all_dependents(references);
+    }
+
+    let mut dependents = Vec::new();
+    for &(r, c) in old.values.keys().chain(new.values.keys()) {
+        if old.values.get(&(r, c)) != new.values.get(&(r, c)) {
+            dependents.extend(
+                references
+                    .dependents_of(r, c)
+                    .map(|dependent| (dependent.row, dependent.column)),
+            );
+        }
+    }
+    dependents
+}
+
+/// Returns every cell whose formula refers to a range in `references`.
+fn all_dependents(references: &RangeIndex) -> Vec<(i32, i32)> {
+    references
+        .ranges()
+        .map(|(_, _, dependent)| (dependent.row, dependent.column))
+        .collect()
+}
+
+/// Rewrites the references to the sheet stored under `old_key` to use `new_name`.
+fn rename_references(expr: &Expr, old_key: &str, new_name: &str) -> Expr {
+    let rename = |e: &Expr| rename_references(e, old_key, new_name);
+    match expr {
+        Expr::SheetRef(name, target) if sheet_key(name) == old_key => {
+            Expr::SheetRef(new_name.to_string(), target.clone())
+        }
+        Expr::UnaryOp(op, operand) => Expr::UnaryOp(*op, Box::new(rename(operand))),
+        Expr::BinaryOp(left, op, right) => {
+            Expr::BinaryOp(Box::new(rename(left)), *op, Box::new(rename(right)))
+        }
+        Expr::Compare(left, op, right) => {
+            Expr::Compare(Box::new(rename(left)), *op, Box::new(rename(right)))
+        }
+        Expr::Function(name, args) => {
+            Expr::Function(name.clone(), args.iter().map(rename).collect())
+        }
+        _ => expr.clone(),
+    }
+}
+
+/// Splits the arguments of a `sheet` command into sheet names. Names containing spaces
+/// are quoted, e.g. `'My Sheet'`.
+fn split_names(args: &str) -> Result<Vec<&str>, SheetError> {
+    let mut names = Vec::new();
+    let mut rest = args.trim_start();
+    while !rest.is_empty() {
+        let (name, after) = match rest.strip_prefix('\'') {
+            Some(quoted) => {
+                let end = quoted
+                    .find('\'')
+                    .ok_or_else(|| SheetError::InvalidCommand("unclosed quote".into()))?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => rest.split_at(rest.find(char::is_whitespace).unwrap_or(rest.len())),
+        };
+        names.push(name);
+        rest = after.trim_start();
+    }
+    Ok(names)
+}
+
+/// Handles the `sheet` commands, which add, rename, delete and switch between the sheets
+/// of a workbook.
+///
+/// # Arguments
+/// * `cmd` - One of `sheet add [name]`, `sheet rename <old> <new>`, `sheet delete <name>`
+///   or `sheet switch <name>`. Names containing spaces are quoted, e.g.
+///   `sheet add 'My Sheet'`. Without a name, `sheet add` picks the first free `SheetN`.
+/// * `workbook` - A mutable reference to the workbook.
+///
+/// # Returns
+/// * `Ok(Outcome)` if the workbook was changed.
+/// * `Err(SheetError)` if the command is malformed or names a sheet that does not exist.
+pub fn handle_sheet_command(cmd: &str, workbook: &mut Workbook) -> CommandResult {
+    let (action, args) = cmd
+        .trim()
+        .strip_prefix("sheet")
+        .map(|rest| rest.trim_start())
+        .and_then(|rest| rest.split_once(' ').or(Some((rest, ""))))
+        .unwrap_or(("", ""));
+    match (action, split_names(args)?.as_slice()) {
+        ("add", []) => {
+            let name = workbook.next_sheet_name();
+            workbook.add_sheet(&name)?;
+            Ok(Outcome::Message(format!("Added {}", name)))
+        }
+        ("add", [name]) => workbook.add_sheet(name),
+        ("rename", [old_name, new_name]) => workbook.rename_sheet(old_name, new_name),
+        ("delete", [name]) => workbook.delete_sheet(name),
+        ("switch", [name]) => workbook.switch_to(name),
+        _ => Err(SheetError::InvalidCommand(
+            "expected: sheet add [name], sheet rename <old> <new>, sheet delete <name> or sheet switch <name>".into(),
+        )),
+    }
+}
+
+/// Handles the `undo_depth` command, which shows or changes how many undo steps each sheet
+/// keeps.
+///
+/// # Arguments
+/// * `cmd` - `undo_depth` to show the current depth, or `undo_depth <steps>` to change it.
+/// * `workbook` - A mutable reference to the workbook.
+///
+/// # Returns
+/// * `Ok(Outcome::Message)` with the depth now in use.
+/// * `Err(SheetError::InvalidCommand)` if the command is malformed or the depth is not a
+///   positive number.
+///
+/// # Behavior
+/// Each step undoes one whole command, however many cells it changed. Lowering the depth
+/// drops the oldest steps at once.
+pub fn handle_undo_depth_command(cmd: &str, workbook: &mut Workbook) -> CommandResult {
+    let parts: Vec<&str> = cmd.split_whitespace().collect();
+    let invalid = || SheetError::InvalidCommand("expected: undo_depth [steps]".into());
+    match parts.as_slice() {
+        ["undo_depth"] => {}
+        ["undo_depth", steps] => match string_to_int(steps).map_err(|_| invalid())? {
+            steps if steps > 0 => workbook.set_undo_depth(steps as usize),
+            _ => return Err(invalid()),
+        },
+        _ => return Err(invalid()),
+    }
+    Ok(Outcome::Message(format!(
+        "Undo keeps up to {} steps",
+        workbook.undo_depth()
+    )))
+}
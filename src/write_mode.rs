@@ -0,0 +1,152 @@
+use crate::cell_extension::{CellExtension, CellValue, SpreadsheetExtension};
+use crate::display::expr_to_string;
+use crate::expression_parser::Expr;
+use crate::read_mode::field_formula;
+use crate::sheet_error::{CommandResult, Outcome, SheetError};
+use std::error::Error;
+use std::fs::File;
+
+/// Chooses what is written for each cell when exporting to CSV.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteMode {
+    /// Write the evaluated value of every cell.
+    Values,
+    /// Write the original formula of every cell, prefixed with `=`.
+    Formulas,
+}
+
+/// Checks whether a cell has never been assigned anything.
+///
+/// # Arguments
+/// * `cell` - A reference to the cell to inspect.
+///
+/// # Returns
+/// `true` if the cell holds the default `0` formula and no formatting, `false` otherwise.
+/// A formula that evaluates to `0` is not empty.
+fn is_empty_cell(cell: &CellExtension) -> bool {
+    matches!(cell.formula, Expr::Number(n) if n == 0.0) && !cell.is_bold && !cell.is_italics
+}
+
+/// Converts a text value into a CSV field that `read` loads back as the same text.
+///
+/// # Arguments
+/// * `text` - The text held by the cell.
+///
+/// # Returns
+/// The text itself, or a quoted text formula such as `="A1"` when the bare text would be
+/// read as a number, a reference, an error code or a formula.
+fn text_to_field(text: &str) -> String {
+    let formula = Expr::Text(text.to_string());
+    if field_formula(text) == formula {
+        text.to_string()
+    } else {
+        format!("={}", expr_to_string(&formula))
+    }
+}
+
+/// Converts a cell into the text stored in its CSV field.
+///
+/// # Arguments
+/// * `cell` - A reference to the cell to convert.
+/// * `mode` - Whether to emit the evaluated value or the formula.
+///
+/// # Returns
+/// The CSV field for the cell. Empty cells produce an empty string.
+fn cell_to_field(cell: &CellExtension, mode: WriteMode) -> String {
+    if is_empty_cell(cell) {
+        return String::new();
+    }
+
+    match mode {
+        WriteMode::Values => match &cell.value {
+            CellValue::Text(text) => text_to_field(text),
+            // Errors are written as their codes, e.g. `#DIV/0!`
+            value => value.to_string(),
+        },
+        WriteMode::Formulas => match &cell.formula {
+            // Constants are written as plain numbers
+            Expr::Number(_) => cell.value.to_string(),
+            // Everything else is written as a formula, so text such as `A1` is quoted
+            // and is not mistaken for a reference when read back
+            formula => format!("={}", expr_to_string(formula)),
+        },
+    }
+}
+
+/// Writes the spreadsheet to a CSV file.
+///
+/// # Arguments
+/// * `filename` - The name of the CSV file to write.
+/// * `sheet` - A reference to the spreadsheet.
+/// * `mode` - Whether to write evaluated values or formulas.
+///
+/// # Returns
+/// * `Ok(())` if the file is successfully written.
+/// * `Err` if an error occurs (e.g., the file cannot be created).
+///
+/// # Behavior
+/// * Only the smallest block starting at `A1` that covers every non-empty cell is written.
+/// * Files written in either mode can be loaded back with `read_csv_file`. Text that would
+///   otherwise be read as something else is written as a quoted text formula.
+///
+pub fn write_csv_file(
+    filename: &str,
+    sheet: &SpreadsheetExtension,
+    mode: WriteMode,
+) -> Result<(), Box<dyn Error>> {
+    // Find the bounds of the used area so trailing empty rows/columns are skipped
+    let mut last_row = -1;
+    let mut last_col = -1;
+    for ((r, c), cell) in sheet.all_cells.cells() {
+        if !is_empty_cell(cell) {
+            last_row = last_row.max(r as i32);
+            last_col = last_col.max(c as i32);
+        }
+    }
+
+    let file = File::create(filename)?;
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(file);
+
+    for row in sheet.all_cells.iter().take((last_row + 1) as usize) {
+        let record: Vec<String> = (0..=last_col as usize)
+            .map(|c| cell_to_field(&row[c], mode))
+            .collect();
+        wtr.write_record(&record)?;
+    }
+
+    wtr.flush()?;
+    println!("Successfully wrote {} rows to {}", last_row + 1, filename);
+    Ok(())
+}
+
+/// Handles the `write` command to save the spreadsheet as a CSV file.
+///
+/// # Arguments
+/// * `cmd` - The command string (e.g., `write data.csv` or `write data.csv formulas`).
+/// * `sheet` - A reference to the spreadsheet.
+///
+/// # Returns
+/// * `Ok(Outcome::Message)` if the file is successfully written.
+/// * `Err(SheetError)` if the command is invalid or the file cannot be written.
+///
+pub fn handle_write_command(cmd: &str, sheet: &SpreadsheetExtension) -> CommandResult {
+    let parts: Vec<&str> = cmd.split_whitespace().collect();
+    let usage = || SheetError::InvalidCommand("expected: write <file> [values|formulas]".into());
+
+    if parts.len() < 2 || parts.len() > 3 || parts[0] != "write" {
+        return Err(usage());
+    }
+
+    let mode = match parts.get(2) {
+        None | Some(&"values") => WriteMode::Values,
+        Some(&"formulas") => WriteMode::Formulas,
+        Some(_) => return Err(usage()),
+    };
+
+    let filename = parts[1];
+    write_csv_file(filename, sheet, mode)
+        .map_err(|e| SheetError::Io(format!("Error writing CSV file: {}", e)))?;
+    Ok(Outcome::Message(format!("Wrote {}", filename)))
+}
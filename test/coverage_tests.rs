@@ -1675,9 +1675,9 @@ fn test_avg_function_no_error() {
     assert_eq!(sheet.all_cells[2][0].value, 35); // AVG(30, 40) = 35
 
     // Ensure no error in the cells
-    assert_eq!(sheet.all_cells[0][0].is_error, false);
-    assert_eq!(sheet.all_cells[1][0].is_error, false);
-    assert_eq!(sheet.all_cells[2][0].is_error, false);
+    assert!(!sheet.all_cells[0][0].is_error);
+    assert!(!sheet.all_cells[1][0].is_error);
+    assert!(!sheet.all_cells[2][0].is_error);
 }
 
 #[test]
@@ -1981,3 +1981,134 @@ fn test_max_chain_with_division_by_zero() {
     assert!(sheet.all_cells[4][0].is_error); // A5 = MAX(A1:A4)
     assert!(!sheet.all_cells[5][0].is_error); // A6 = MAX(A1:A6)
 }
+
+#[test]
+fn test_insert_and_delete_rows_and_columns() {
+    let mut sheet = initialise(4, 3);
+    assign_cell(&mut sheet, 0, 0, 1, vec![Operand::Constant(1)]);
+    assign_cell(&mut sheet, 1, 0, 1, vec![Operand::Constant(2)]);
+    assign_cell(&mut sheet, 2, 0, 1, vec![Operand::Constant(3)]);
+    // B1 = SUM(A1:A3)
+    assign_cell(
+        &mut sheet,
+        0,
+        1,
+        10,
+        vec![
+            Operand::CellOperand(CellReference { row: 0, column: 0 }),
+            Operand::CellOperand(CellReference { row: 1, column: 0 }),
+            Operand::CellOperand(CellReference { row: 2, column: 0 }),
+        ],
+    );
+    // C1 = A2 * 10
+    assign_cell(
+        &mut sheet,
+        0,
+        2,
+        5,
+        vec![
+            Operand::CellOperand(CellReference { row: 1, column: 0 }),
+            Operand::Constant(10),
+        ],
+    );
+
+    // The new row falls inside the range, which grows to A1:A4
+    parse_structure_command("insert_row 2", &mut sheet);
+    assert_eq!(sheet.rows, 5);
+    assert_eq!(sheet.all_cells[0][1].formula.len(), 4);
+    assign_cell(&mut sheet, 1, 0, 1, vec![Operand::Constant(4)]);
+    assert_eq!(sheet.all_cells[0][1].value, 10);
+    assert_eq!(sheet.all_cells[0][2].value, 20);
+
+    // Deleting A3 shrinks the range and breaks C1
+    parse_structure_command("delete_row 3", &mut sheet);
+    assert_eq!(sheet.rows, 4);
+    assert_eq!(sheet.all_cells[0][1].value, 8);
+    assert!(sheet.all_cells[0][2].is_error);
+    assert_eq!(sheet.all_cells[0][2].operation_id, REF_ERROR_OPERATION);
+
+    parse_structure_command("insert_col A", &mut sheet);
+    assert_eq!(sheet.columns, 4);
+    assign_cell(&mut sheet, 2, 1, 1, vec![Operand::Constant(5)]);
+    assert_eq!(sheet.all_cells[0][2].value, 10);
+
+    parse_structure_command("delete_col Z", &mut sheet);
+    assert_eq!(unsafe { STATUS }, 1);
+    assert_eq!(sheet.columns, 4);
+}
+
+#[test]
+fn test_recalculation_follows_dependency_order() {
+    let mut sheet = initialise(2, 4);
+    assign_cell(&mut sheet, 0, 0, 1, vec![Operand::Constant(1)]);
+    // B1 = A1 + 1, C1 = A1 + B1, D1 = B1 + C1
+    for (col, precedents) in [(1, vec![0]), (2, vec![0, 1]), (3, vec![1, 2])] {
+        let mut formula: Vec<Operand> = precedents
+            .iter()
+            .map(|&column| Operand::CellOperand(CellReference { row: 0, column }))
+            .collect();
+        if formula.len() == 1 {
+            formula.push(Operand::Constant(1));
+        }
+        assign_cell(&mut sheet, 0, col, 3, formula);
+    }
+
+    let order = rust_lab::recalc_order::recalculation_order((0, 0), |(r, c)| {
+        sheet.all_cells[r as usize][c as usize]
+            .dependents
+            .iter()
+            .map(|dependent| (dependent.row, dependent.column))
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(order, vec![(0, 1), (0, 2), (0, 3)]);
+
+    assign_cell(&mut sheet, 0, 0, 1, vec![Operand::Constant(10)]);
+    assert_eq!(sheet.all_cells[0][3].value, 32);
+}
+
+#[test]
+fn test_cycle_reports_the_loop() {
+    let mut sheet = initialise(3, 3);
+    // B2 = C3 + 1, C3 = A1 + 1
+    assign_cell(
+        &mut sheet,
+        1,
+        1,
+        3,
+        vec![
+            Operand::CellOperand(CellReference { row: 2, column: 2 }),
+            Operand::Constant(1),
+        ],
+    );
+    assign_cell(
+        &mut sheet,
+        2,
+        2,
+        3,
+        vec![
+            Operand::CellOperand(CellReference { row: 0, column: 0 }),
+            Operand::Constant(1),
+        ],
+    );
+
+    // A1 = B2 + 1 closes the loop
+    assign_cell(
+        &mut sheet,
+        0,
+        0,
+        3,
+        vec![
+            Operand::CellOperand(CellReference { row: 1, column: 1 }),
+            Operand::Constant(1),
+        ],
+    );
+    assert_eq!(unsafe { STATUS }, 3);
+    let cycle: Vec<String> = LAST_CYCLE
+        .lock()
+        .unwrap()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(cycle, ["A1", "B2", "C3", "A1"]);
+    assert!(!has_cycle(&sheet, 0, 0));
+}
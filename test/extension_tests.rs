@@ -0,0 +1,1744 @@
+#![cfg(feature = "main2")]
+
+use rust_lab::cell_extension::{CellError, CellValue, SpreadsheetExtension};
+use rust_lab::cellsp::CellReference;
+use rust_lab::clipboard::Registers;
+use rust_lab::defined_names::handle_name_command;
+use rust_lab::display::expr_to_string;
+use rust_lab::expression_parser::Expr;
+use rust_lab::expression_utils::{eval_expr, eval_number, parse_formula, shift_formula};
+use rust_lab::function_registry::{FunctionRegistry, FunctionSpec, register_function};
+use rust_lab::graph_extension::*;
+use rust_lab::iterative_calc::{IterationSettings, handle_iterate_command};
+use rust_lab::parser_visual_mode::parser_visual;
+use rust_lab::project_file::*;
+use rust_lab::read_mode::read_csv_file;
+use rust_lab::sheet_error::{Outcome, SheetError};
+use rust_lab::sheet_structure::{MAX_ROWS, handle_structure_command};
+use rust_lab::undo_history::{format_history, handle_history_command};
+use rust_lab::workbook::{
+    Workbook, WorkbookSheet, handle_sheet_command, handle_undo_depth_command,
+};
+use rust_lab::write_mode::*;
+use std::fs;
+
+fn temp_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("rust_lab_{}_{}", std::process::id(), name))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Saves a sheet with its history as the only sheet of a project file, and loads it back.
+fn save_and_load(
+    name: &str,
+    sheet: &SpreadsheetExtension,
+    undo: &UndoRedoStack,
+) -> (SpreadsheetExtension, UndoRedoStack) {
+    let entry = WorkbookSheet {
+        name: "Sheet1".to_string(),
+        sheet: sheet.clone(),
+        undo_manager: undo.clone(),
+    };
+    let workbook = Workbook::from_sheets(vec![entry], 0).unwrap();
+    let path = temp_path(name);
+    save_project(&path, &workbook, true).unwrap();
+    let mut loaded = load_project(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let (sheet, undo) = loaded.active_parts();
+    (sheet.clone(), undo.clone())
+}
+
+fn assign(
+    sheet: &mut rust_lab::cell_extension::SpreadsheetExtension,
+    undo: &mut UndoRedoStack,
+    row: i32,
+    col: i32,
+    formula: &str,
+) {
+    let expr = parse_formula(formula).unwrap();
+    assign_cell_extension(sheet, undo, row, col, *expr).unwrap();
+}
+
+#[test]
+fn test_write_csv_values() {
+    let mut sheet = initialise_extension(5, 5);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "2");
+    assign(&mut sheet, &mut undo, 1, 1, "A1*3");
+
+    let path = temp_path("values.csv");
+    write_csv_file(&path, &sheet, WriteMode::Values).unwrap();
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(contents, "2,\n,6\n");
+}
+
+#[test]
+fn test_write_csv_values_round_trip_keeps_text() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, r#""A1""#);
+    assign(&mut sheet, &mut undo, 0, 1, r#""1-2""#);
+    assign(&mut sheet, &mut undo, 0, 2, r#""2024-01-05""#);
+    assign(&mut sheet, &mut undo, 1, 0, r#""hello""#);
+    assign(&mut sheet, &mut undo, 1, 1, "0");
+    assign(&mut sheet, &mut undo, 1, 2, "B2*5");
+
+    let path = temp_path("values_text.csv");
+    write_csv_file(&path, &sheet, WriteMode::Values).unwrap();
+    let contents = fs::read_to_string(&path).unwrap();
+    let mut loaded = initialise_extension(3, 3);
+    read_csv_file(&path, &mut loaded, &mut undo).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    // The csv writer quotes the text formulas; a constant 0 is written like an empty cell
+    assert_eq!(
+        contents,
+        "\"=\"\"A1\"\"\",\"=\"\"1-2\"\"\",\"=\"\"2024-01-05\"\"\"\nhello,,0\n"
+    );
+    for (row, col) in [(0, 0), (0, 1), (0, 2), (1, 0)] {
+        assert_eq!(
+            loaded.all_cells[row][col].value,
+            sheet.all_cells[row][col].value
+        );
+    }
+    assert_eq!(loaded.all_cells[1][1].value, CellValue::Number(0.0));
+    assert_eq!(loaded.all_cells[1][2].value, CellValue::Number(0.0));
+}
+
+#[test]
+fn test_write_csv_formulas_round_trip() {
+    let mut sheet = initialise_extension(5, 5);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "4");
+    assign(&mut sheet, &mut undo, 1, 0, "6");
+    assign(&mut sheet, &mut undo, 2, 0, "Sum(A1:A2)");
+    assign(&mut sheet, &mut undo, 2, 1, "A3/2");
+
+    let path = temp_path("formulas.csv");
+    write_csv_file(&path, &sheet, WriteMode::Formulas).unwrap();
+
+    let mut loaded = initialise_extension(5, 5);
+    let mut loaded_undo = UndoRedoStack::new();
+    read_csv_file(&path, &mut loaded, &mut loaded_undo).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.all_cells[2][0].value, CellValue::Number(10.0));
+    assert_eq!(loaded.all_cells[2][1].value, CellValue::Number(5.0));
+
+    // Formulas survive the round trip, so dependents still update
+    assign(&mut loaded, &mut loaded_undo, 0, 0, "14");
+    assert_eq!(loaded.all_cells[2][0].value, CellValue::Number(20.0));
+    assert_eq!(loaded.all_cells[2][1].value, CellValue::Number(10.0));
+}
+
+#[test]
+fn test_handle_write_command_rejects_bad_mode() {
+    let sheet = initialise_extension(2, 2);
+    assert!(matches!(
+        handle_write_command("write out.csv everything", &sheet),
+        Err(SheetError::InvalidCommand(_))
+    ));
+    assert!(handle_write_command("write", &sheet).is_err());
+}
+
+#[test]
+fn test_project_round_trip_keeps_formulas_and_formatting() {
+    let mut sheet = initialise_extension(4, 4);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "3");
+    assign(&mut sheet, &mut undo, 0, 1, "A1*2");
+    sheet.all_cells[0][1].is_bold = true;
+    sheet.all_cells[3][3].is_italics = true;
+
+    let (mut loaded, mut loaded_undo) = save_and_load("project.json", &sheet, &undo);
+
+    assert_eq!(loaded.rows, 4);
+    assert_eq!(loaded.columns, 4);
+    assert_eq!(loaded.all_cells[0][1].value, CellValue::Number(6.0));
+    assert!(loaded.all_cells[0][1].is_bold);
+    assert!(loaded.all_cells[3][3].is_italics);
+
+    // The dependency graph is rebuilt
+    assign(&mut loaded, &mut loaded_undo, 0, 0, "5");
+    assert_eq!(loaded.all_cells[0][1].value, CellValue::Number(10.0));
+
+    // The saved history is restored: undo the edit above, then the two saved edits
+    assert!(perform_undo(&mut loaded, &mut loaded_undo));
+    assert_eq!(loaded.all_cells[0][1].value, CellValue::Number(6.0));
+    assert!(perform_undo(&mut loaded, &mut loaded_undo));
+    assert_eq!(loaded.all_cells[0][1].value, CellValue::Number(0.0));
+}
+
+#[test]
+fn test_project_load_rejects_other_versions() {
+    let path = temp_path("future.json");
+    fs::write(
+        &path,
+        r#"{"format":"rust_lab_sheet","version":99,"rows":1,"columns":1,"cells":[]}"#,
+    )
+    .unwrap();
+    let result = load_project(&path);
+    fs::remove_file(&path).unwrap();
+
+    let err = result.err().unwrap().to_string();
+    assert!(err.contains("version 99"));
+}
+
+#[test]
+fn test_project_round_trip_keeps_every_sheet() {
+    let mut workbook = Workbook::new(3, 3);
+    assign_in(&mut workbook, 0, 0, "'Sales Data'!B2*2");
+    handle_sheet_command("sheet add 'Sales Data'", &mut workbook).unwrap();
+    assign_in(&mut workbook, 1, 1, "21");
+    handle_sheet_command("sheet add Notes", &mut workbook).unwrap();
+    handle_sheet_command("sheet switch 'Sales Data'", &mut workbook).unwrap();
+
+    let path = temp_path("workbook.json");
+    save_project(&path, &workbook, true).unwrap();
+    let mut loaded = load_project(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.names(), vec!["Sheet1", "Sales Data", "Notes"]);
+    assert_eq!(loaded.active_name(), "Sales Data");
+    let sheet1 = loaded.sheet("Sheet1").unwrap();
+    assert_eq!(sheet1.all_cells[0][0].value, CellValue::Number(42.0));
+
+    // The reference between the sheets is linked again
+    assign_in(&mut loaded, 1, 1, "5");
+    let sheet1 = loaded.sheet("Sheet1").unwrap();
+    assert_eq!(sheet1.all_cells[0][0].value, CellValue::Number(10.0));
+
+    // Each sheet keeps its own history
+    let (sheet, undo) = loaded.active_parts();
+    assert!(perform_undo(sheet, undo));
+    assert!(perform_undo(sheet, undo));
+    assert!(!perform_undo(sheet, undo));
+    loaded.refresh_links();
+    let sheet1 = loaded.sheet("Sheet1").unwrap();
+    assert_eq!(sheet1.all_cells[0][0].value, CellValue::Number(0.0));
+}
+
+#[test]
+fn test_decimal_and_scientific_literals() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1.5");
+    assign(&mut sheet, &mut undo, 0, 1, "2e3");
+    assign(&mut sheet, &mut undo, 0, 2, ".25+1.2E-1");
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(1.5));
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(2000.0));
+    assert!((sheet.all_cells[0][2].value.as_number().unwrap() - 0.37).abs() < 1e-12);
+}
+
+#[test]
+fn test_division_and_aggregates_do_not_truncate() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1");
+    assign(&mut sheet, &mut undo, 1, 0, "2");
+    assign(&mut sheet, &mut undo, 0, 1, "A1/A2");
+    assign(&mut sheet, &mut undo, 1, 1, "Avg(A1:A2)");
+    assign(&mut sheet, &mut undo, 2, 1, "Stdev(A1:A2)");
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(0.5));
+    assert_eq!(sheet.all_cells[1][1].value, CellValue::Number(1.5));
+    assert_eq!(sheet.all_cells[2][1].value, CellValue::Number(0.5));
+}
+
+#[test]
+fn test_csv_import_reads_decimals() {
+    let path = temp_path("decimals.csv");
+    fs::write(&path, "0.5,1e2\n").unwrap();
+    let mut sheet = initialise_extension(2, 2);
+    let mut undo = UndoRedoStack::new();
+    read_csv_file(&path, &mut sheet, &mut undo).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(0.5));
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(100.0));
+}
+
+#[test]
+fn test_csv_import_of_a_value_updates_dependents() {
+    let mut sheet = initialise_extension(2, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "C1+1");
+    assign(&mut sheet, &mut undo, 0, 1, "A1*2");
+    assign(&mut sheet, &mut undo, 0, 2, "4");
+
+    let path = temp_path("value_over_formula.csv");
+    fs::write(&path, "hello\n").unwrap();
+    read_csv_file(&path, &mut sheet, &mut undo).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        sheet.all_cells[0][0].formula,
+        Expr::Text("hello".to_string())
+    );
+    assert_eq!(
+        sheet.all_cells[0][1].value,
+        CellValue::Error(CellError::Value)
+    );
+    // A1 no longer depends on C1
+    assign(&mut sheet, &mut undo, 0, 2, "5");
+    assert_eq!(
+        sheet.all_cells[0][0].value,
+        CellValue::Text("hello".to_string())
+    );
+    assert!(sheet.all_cells[0][2].dependents.is_empty());
+}
+
+#[test]
+fn test_text_literals_and_references() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, r#""say ""hi""""#);
+    assign(&mut sheet, &mut undo, 0, 1, "A1");
+    assert_eq!(
+        sheet.all_cells[0][0].value,
+        CellValue::Text("say \"hi\"".to_string())
+    );
+    assert_eq!(sheet.all_cells[0][1].value, sheet.all_cells[0][0].value);
+}
+
+#[test]
+fn test_text_in_arithmetic_is_an_error() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, r#""abc""#);
+    assign(&mut sheet, &mut undo, 1, 0, "4");
+    assign(&mut sheet, &mut undo, 0, 1, "A1+1");
+    assign(&mut sheet, &mut undo, 1, 1, "Sum(A1:A2)");
+    assert_eq!(
+        sheet.all_cells[0][1].value,
+        CellValue::Error(CellError::Value)
+    );
+    // Text inside a range is skipped rather than treated as an error
+    assert_eq!(sheet.all_cells[1][1].value, CellValue::Number(4.0));
+}
+
+#[test]
+fn test_csv_text_survives_import_and_export() {
+    let path = temp_path("text.csv");
+    fs::write(&path, "hello world,3\nhello,=hello\n").unwrap();
+    let mut sheet = initialise_extension(2, 2);
+    let mut undo = UndoRedoStack::new();
+    read_csv_file(&path, &mut sheet, &mut undo).unwrap();
+    assert_eq!(
+        sheet.all_cells[0][0].value,
+        CellValue::Text("hello world".to_string())
+    );
+    // A bare word is text; only a formula refers to a defined name
+    assert_eq!(
+        sheet.all_cells[1][0].value,
+        CellValue::Text("hello".to_string())
+    );
+    assert_eq!(
+        sheet.all_cells[1][1].value,
+        CellValue::Error(CellError::Name)
+    );
+
+    // Text that looks like a reference is quoted when formulas are written
+    assign(&mut sheet, &mut undo, 1, 0, r#""A1""#);
+    write_csv_file(&path, &sheet, WriteMode::Formulas).unwrap();
+    let mut loaded = initialise_extension(2, 2);
+    read_csv_file(&path, &mut loaded, &mut undo).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        loaded.all_cells[0][0].value,
+        CellValue::Text("hello world".to_string())
+    );
+    assert_eq!(
+        loaded.all_cells[1][0].value,
+        CellValue::Text("A1".to_string())
+    );
+}
+
+#[test]
+fn test_csv_import_keeps_non_finite_words_as_text() {
+    let path = temp_path("non_finite.csv");
+    fs::write(&path, "inf,NaN,infinity\n").unwrap();
+    let mut sheet = initialise_extension(2, 3);
+    let mut undo = UndoRedoStack::new();
+    read_csv_file(&path, &mut sheet, &mut undo).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    for (col, word) in ["inf", "NaN", "infinity"].into_iter().enumerate() {
+        assert_eq!(
+            sheet.all_cells[0][col].value,
+            CellValue::Text(word.to_string())
+        );
+    }
+}
+
+#[test]
+fn test_error_values_propagate() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1/0");
+    assign(&mut sheet, &mut undo, 1, 0, "A1+1");
+    assign(&mut sheet, &mut undo, 2, 0, "Sum(A1:A2)");
+    assign(&mut sheet, &mut undo, 0, 1, "Foo(1)");
+    let div = CellValue::Error(CellError::DivByZero);
+    assert_eq!(sheet.all_cells[0][0].value, div);
+    assert_eq!(sheet.all_cells[1][0].value, div);
+    assert_eq!(sheet.all_cells[2][0].value, div);
+    assert_eq!(
+        sheet.all_cells[0][1].value,
+        CellValue::Error(CellError::Name)
+    );
+
+    // Fixing the source clears the error in every dependent
+    assign(&mut sheet, &mut undo, 0, 0, "2");
+    assert_eq!(sheet.all_cells[1][0].value, CellValue::Number(3.0));
+    assert_eq!(sheet.all_cells[2][0].value, CellValue::Number(5.0));
+}
+
+#[test]
+fn test_iserror_and_iferror() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1/0");
+    assign(&mut sheet, &mut undo, 0, 1, "Iserror(A1)");
+    assign(&mut sheet, &mut undo, 0, 2, r#"Iferror(A1, "none")"#);
+    assign(&mut sheet, &mut undo, 1, 0, "Iferror(4, 0)");
+    assign(&mut sheet, &mut undo, 1, 1, "#REF!");
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Bool(true));
+    assert_eq!(
+        sheet.all_cells[0][2].value,
+        CellValue::Text("none".to_string())
+    );
+    assert_eq!(sheet.all_cells[1][0].value, CellValue::Number(4.0));
+    assert_eq!(
+        sheet.all_cells[1][1].value,
+        CellValue::Error(CellError::Ref)
+    );
+}
+
+#[test]
+fn test_csv_error_codes_round_trip() {
+    let mut sheet = initialise_extension(2, 2);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1/0");
+
+    let path = temp_path("errors.csv");
+    write_csv_file(&path, &sheet, WriteMode::Values).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "#DIV/0!\n");
+    let mut loaded = initialise_extension(2, 2);
+    read_csv_file(&path, &mut loaded, &mut undo).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        loaded.all_cells[0][0].value,
+        CellValue::Error(CellError::DivByZero)
+    );
+}
+
+#[test]
+fn test_assign_reports_cycles_and_error_values() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 1, "A1+1");
+
+    let cyclic = *parse_formula("B1").unwrap();
+    let result = assign_cell_extension(&mut sheet, &mut undo, 0, 0, cyclic);
+    let cycle = vec![
+        CellReference::new(0, 0),
+        CellReference::new(0, 1),
+        CellReference::new(0, 0),
+    ];
+    assert_eq!(result, Err(SheetError::CyclicDependency(cycle)));
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(0.0));
+
+    let div = *parse_formula("1/0").unwrap();
+    let result = assign_cell_extension(&mut sheet, &mut undo, 2, 2, div);
+    assert_eq!(
+        result,
+        Ok(Outcome::Message("C3 evaluates to #DIV/0!".to_string()))
+    );
+
+    assert!(matches!(parse_formula("1+"), Err(SheetError::Parse(_))));
+}
+
+#[test]
+fn test_parser_visual_returns_messages_and_errors() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    let mut registers = Registers::new();
+    assign(&mut sheet, &mut undo, 0, 0, "5");
+    assign(&mut sheet, &mut undo, 1, 0, "1");
+
+    assert_eq!(
+        parser_visual("filter A1:A3 > 2", &mut sheet, &mut undo, &mut registers),
+        Ok(Outcome::Message("Filtered cells: A1: 5".to_string()))
+    );
+    assert_eq!(
+        parser_visual("b D9", &mut sheet, &mut undo, &mut registers),
+        Err(SheetError::OutOfBounds("D9".to_string()))
+    );
+    assert!(matches!(
+        parser_visual("frobnicate", &mut sheet, &mut undo, &mut registers),
+        Err(SheetError::InvalidCommand(_))
+    ));
+
+    assert_eq!(
+        parser_visual("yc A1 C3", &mut sheet, &mut undo, &mut registers),
+        Ok(Outcome::Done)
+    );
+    assert_eq!(sheet.all_cells[2][2].value, CellValue::Number(5.0));
+}
+
+#[test]
+fn test_comparisons_produce_booleans() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "3");
+    assign(&mut sheet, &mut undo, 0, 1, "A1*2 >= 6");
+    assign(&mut sheet, &mut undo, 0, 2, "A1 <> 3");
+    assign(&mut sheet, &mut undo, 1, 0, r#""abc" = "ABC""#);
+    assign(&mut sheet, &mut undo, 1, 1, "B1 + 1");
+    assign(&mut sheet, &mut undo, 1, 2, "1/0 < 2");
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Bool(true));
+    assert_eq!(sheet.all_cells[0][2].value, CellValue::Bool(false));
+    assert_eq!(sheet.all_cells[1][0].value, CellValue::Bool(true));
+    // Booleans count as 1 and 0 in arithmetic
+    assert_eq!(sheet.all_cells[1][1].value, CellValue::Number(2.0));
+    assert_eq!(
+        sheet.all_cells[1][2].value,
+        CellValue::Error(CellError::DivByZero)
+    );
+
+    // Comparisons are recalculated like any other formula
+    assign(&mut sheet, &mut undo, 0, 0, "2");
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Bool(false));
+}
+
+#[test]
+fn test_logical_functions() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "5");
+    assign(&mut sheet, &mut undo, 0, 1, r#"If(A1 > 3, "big", "small")"#);
+    // The branch that is not taken is never evaluated, so its error does not matter
+    assign(&mut sheet, &mut undo, 0, 2, "If(A1 = 5, 1, 1/0)");
+    assign(&mut sheet, &mut undo, 1, 0, "And(A1 > 1, A1 < 10)");
+    assign(&mut sheet, &mut undo, 1, 1, "Or(FALSE, A1 = 4)");
+    assign(&mut sheet, &mut undo, 1, 2, "Not(A1)");
+    assign(&mut sheet, &mut undo, 2, 0, "If(FALSE, 1)");
+    assert_eq!(
+        sheet.all_cells[0][1].value,
+        CellValue::Text("big".to_string())
+    );
+    assert_eq!(sheet.all_cells[0][2].value, CellValue::Number(1.0));
+    assert_eq!(sheet.all_cells[1][0].value, CellValue::Bool(true));
+    assert_eq!(sheet.all_cells[1][1].value, CellValue::Bool(false));
+    assert_eq!(sheet.all_cells[1][2].value, CellValue::Bool(false));
+    assert_eq!(sheet.all_cells[2][0].value, CellValue::Bool(false));
+
+    assign(&mut sheet, &mut undo, 0, 0, "1");
+    assert_eq!(
+        sheet.all_cells[0][1].value,
+        CellValue::Text("small".to_string())
+    );
+    assert_eq!(
+        sheet.all_cells[0][2].value,
+        CellValue::Error(CellError::DivByZero)
+    );
+}
+
+#[test]
+fn test_unary_exponent_and_modulo() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "-5");
+    assign(&mut sheet, &mut undo, 0, 1, "-A1");
+    assign(&mut sheet, &mut undo, 0, 2, "2^3^2");
+    assign(&mut sheet, &mut undo, 1, 0, "-2^2");
+    assign(&mut sheet, &mut undo, 1, 1, "2*3^2 + 2^-1");
+    assign(&mut sheet, &mut undo, 1, 2, "A1 % 3");
+    assign(&mut sheet, &mut undo, 2, 0, "Mod(7, -3)");
+    assign(&mut sheet, &mut undo, 2, 1, "5 % 0");
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(-5.0));
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(5.0));
+    assert_eq!(sheet.all_cells[0][2].value, CellValue::Number(512.0));
+    assert_eq!(sheet.all_cells[1][0].value, CellValue::Number(-4.0));
+    assert_eq!(sheet.all_cells[1][1].value, CellValue::Number(18.5));
+    // The result takes the sign of the divisor
+    assert_eq!(sheet.all_cells[1][2].value, CellValue::Number(1.0));
+    assert_eq!(sheet.all_cells[2][0].value, CellValue::Number(-2.0));
+    assert_eq!(
+        sheet.all_cells[2][1].value,
+        CellValue::Error(CellError::DivByZero)
+    );
+}
+
+#[test]
+fn test_overflow_is_an_error_not_a_panic() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "10^400");
+    assign(&mut sheet, &mut undo, 0, 1, "(-8)^0.5");
+    assign(&mut sheet, &mut undo, 0, 2, "1e999");
+    for col in 0..3 {
+        assert_eq!(
+            sheet.all_cells[0][col].value,
+            CellValue::Error(CellError::Num)
+        );
+    }
+    assert!(parse_formula("A99999999999").is_err());
+}
+
+#[test]
+fn test_function_names_and_references_ignore_case() {
+    let mut sheet = initialise_extension(5, 410);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1");
+    assign(&mut sheet, &mut undo, 1, 0, "2");
+    assign(&mut sheet, &mut undo, 2, 0, "3");
+    assign(&mut sheet, &mut undo, 0, 1, "sum(a1:a3)");
+    assign(&mut sheet, &mut undo, 1, 1, "SUM(A1:A3)");
+    assign(
+        &mut sheet,
+        &mut undo,
+        2,
+        1,
+        "StDeV(a1, A2, a3) + max(b1, 0)",
+    );
+    assign(&mut sheet, &mut undo, 3, 1, "if(true, a2, False)");
+    // Column names that spell a function name are still cell references
+    assign(&mut sheet, &mut undo, 4, 407, "7");
+    assign(&mut sheet, &mut undo, 3, 0, "or5 + Or(FALSE, TRUE)");
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(6.0));
+    assert_eq!(sheet.all_cells[1][1].value, CellValue::Number(6.0));
+    assert!(
+        matches!(sheet.all_cells[2][1].value, CellValue::Number(n) if (n - 6.816496580927726).abs() < 1e-9)
+    );
+    assert_eq!(sheet.all_cells[3][1].value, CellValue::Number(2.0));
+    assert_eq!(sheet.all_cells[3][0].value, CellValue::Number(8.0));
+    assert!(
+        matches!(*parse_formula("sum(a1)").unwrap(), Expr::Function(ref name, _) if name == "SUM")
+    );
+}
+
+#[test]
+fn test_registered_functions_are_callable() {
+    register_function(FunctionSpec::new(
+        "HYPOT",
+        2,
+        Some(2),
+        false,
+        |args, sheet| match (eval_number(&args[0], sheet), eval_number(&args[1], sheet)) {
+            (Ok(x), Ok(y)) => CellValue::Number(x.hypot(y)),
+            (Err(e), _) | (_, Err(e)) => CellValue::Error(e),
+        },
+    ));
+
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "3");
+    assign(&mut sheet, &mut undo, 0, 1, "hypot(A1, 4)");
+    assign(&mut sheet, &mut undo, 0, 2, "Hypot(A1)");
+    assign(&mut sheet, &mut undo, 1, 2, "HYPOT(A1:A2, 4)");
+    assign(&mut sheet, &mut undo, 1, 1, "NoSuchFunction(A1)");
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(5.0));
+    // Calls that do not match the declared signature are #VALUE! errors
+    let value_error = CellValue::Error(CellError::Value);
+    assert_eq!(sheet.all_cells[0][2].value, value_error);
+    assert_eq!(sheet.all_cells[1][2].value, value_error);
+    assert_eq!(
+        sheet.all_cells[1][1].value,
+        CellValue::Error(CellError::Name)
+    );
+
+    // A local registry starts with only the functions given to it
+    let mut registry = FunctionRegistry::new();
+    registry.register(FunctionSpec::new("ONE", 0, Some(0), false, |_, _| {
+        CellValue::Number(1.0)
+    }));
+    assert!(registry.get("one").is_some());
+    assert!(registry.get("SUM").is_none());
+    assert_eq!(registry.call("One", &[], &sheet), CellValue::Number(1.0));
+    assert!(FunctionRegistry::with_builtins().get("sum").is_some());
+}
+
+#[test]
+fn test_absolute_references_stay_fixed_when_shifted() {
+    let mut sheet = initialise_extension(4, 4);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "2");
+    assign(&mut sheet, &mut undo, 1, 1, "$A$1 * 3 + a$1");
+    assert_eq!(sheet.all_cells[1][1].value, CellValue::Number(8.0));
+    // Anchors do not change which cell is referenced
+    assert!(
+        sheet.all_cells[0][0]
+            .dependents
+            .contains(&CellReference::new(1, 1))
+    );
+
+    let formula = parse_formula("Sum($A$1, A$1, $A1, A1:$B$2)").unwrap();
+    assert_eq!(expr_to_string(&formula), "SUM($A$1, A$1, $A1, A1:$B$2)");
+    let moved = shift_formula(&formula, 2, 1, &sheet);
+    assert_eq!(expr_to_string(&moved), "SUM($A$1, B$1, $A3, B3:$B$2)");
+
+    // Relative references that move off the sheet become #REF!
+    let moved = shift_formula(&formula, -1, 0, &sheet);
+    assert_eq!(expr_to_string(&moved), "SUM($A$1, A$1, #REF!, #REF!)");
+}
+
+#[test]
+fn test_paste_moves_relative_references() {
+    let mut sheet = initialise_extension(4, 4);
+    let mut undo = UndoRedoStack::new();
+    let mut registers = Registers::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1");
+    assign(&mut sheet, &mut undo, 1, 0, "2");
+    assign(&mut sheet, &mut undo, 0, 1, "A1*10");
+    assign(&mut sheet, &mut undo, 1, 1, "$A$1 + A2");
+
+    parser_visual("y B1:B2 C1:C2", &mut sheet, &mut undo, &mut registers).unwrap();
+    assert_eq!(expr_to_string(&sheet.all_cells[0][2].formula), "(B1 * 10)");
+    assert_eq!(sheet.all_cells[0][2].value, CellValue::Number(100.0));
+    assert_eq!(sheet.all_cells[1][2].value, CellValue::Number(4.0));
+
+    // A reference moved off the sheet becomes #REF!
+    parser_visual("yc C2 A4", &mut sheet, &mut undo, &mut registers).unwrap();
+    assert_eq!(
+        expr_to_string(&sheet.all_cells[3][0].formula),
+        "($A$1 + #REF!)"
+    );
+    assert_eq!(
+        sheet.all_cells[3][0].value,
+        CellValue::Error(CellError::Ref)
+    );
+
+    // Overlapping ranges paste the original formulas
+    parser_visual("d A1:A2 A2:A3", &mut sheet, &mut undo, &mut registers).unwrap();
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(0.0));
+    assert_eq!(sheet.all_cells[1][0].value, CellValue::Number(1.0));
+    assert_eq!(sheet.all_cells[2][0].value, CellValue::Number(2.0));
+}
+
+#[test]
+fn test_paste_moves_references_into_other_sheets() {
+    let mut workbook = Workbook::new(4, 4);
+    handle_sheet_command("sheet add Data", &mut workbook).unwrap();
+    assign_in(&mut workbook, 0, 0, "1");
+    assign_in(&mut workbook, 0, 1, "20");
+    handle_sheet_command("sheet switch Sheet1", &mut workbook).unwrap();
+    assign_in(&mut workbook, 1, 1, "300");
+    assign_in(&mut workbook, 0, 0, "Data!A1+A2+Data!$A$1");
+
+    let (sheet, undo) = workbook.active_parts();
+    parser_visual("yc A1 B1", sheet, undo, &mut Registers::new()).unwrap();
+    workbook.refresh_links();
+    let sheet1 = workbook.active();
+    assert_eq!(
+        expr_to_string(&sheet1.all_cells[0][1].formula),
+        "((Data!B1 + B2) + Data!$A$1)"
+    );
+    assert_eq!(sheet1.all_cells[0][1].value, CellValue::Number(321.0));
+
+    // References into other sheets become #REF! when moved off any sheet
+    let formula = parse_formula("Data!A1 + SUM(Data!A1:B2)").unwrap();
+    let moved = shift_formula(&formula, -1, 0, sheet1);
+    assert_eq!(expr_to_string(&moved), "(#REF! + SUM(#REF!))");
+    let formula = parse_formula(&format!("Data!A{}", MAX_ROWS)).unwrap();
+    let moved = shift_formula(&formula, 1, 0, sheet1);
+    assert_eq!(moved, Expr::Error(CellError::Ref));
+}
+
+#[test]
+fn test_yank_and_put_with_registers() {
+    let mut sheet = initialise_extension(4, 4);
+    let mut undo = UndoRedoStack::new();
+    let mut registers = Registers::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1");
+    assign(&mut sheet, &mut undo, 0, 1, "A1+1");
+    sheet.all_cells[0][1].is_bold = true;
+
+    // Yanking alone changes nothing
+    parser_visual("y A1:B1", &mut sheet, &mut undo, &mut registers).unwrap();
+    parser_visual(r#"y "a B1"#, &mut sheet, &mut undo, &mut registers).unwrap();
+    assert_eq!(sheet.all_cells[1][0].value, CellValue::Number(0.0));
+
+    // The default register now holds the last yank, B1
+    parser_visual("p B2", &mut sheet, &mut undo, &mut registers).unwrap();
+    assert_eq!(expr_to_string(&sheet.all_cells[1][1].formula), "(A2 + 1)");
+    assert!(sheet.all_cells[1][1].is_bold);
+
+    parser_visual(r#"p "a C1 values"#, &mut sheet, &mut undo, &mut registers).unwrap();
+    assert!(matches!(sheet.all_cells[0][2].formula, Expr::Number(n) if n == 2.0));
+    assert!(!sheet.all_cells[0][2].is_bold);
+
+    parser_visual(r#"p "A D1 formulas"#, &mut sheet, &mut undo, &mut registers).unwrap();
+    assert_eq!(sheet.all_cells[0][3].value, CellValue::Number(3.0));
+    assert!(!sheet.all_cells[0][3].is_bold);
+
+    parser_visual(r#"p "a A3 formats"#, &mut sheet, &mut undo, &mut registers).unwrap();
+    assert!(sheet.all_cells[2][0].is_bold);
+    assert_eq!(sheet.all_cells[2][0].value, CellValue::Number(0.0));
+
+    // Cutting into a register clears the cells
+    parser_visual(r#"d "b A1:B1"#, &mut sheet, &mut undo, &mut registers).unwrap();
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(0.0));
+    parser_visual(r#"p "b A4"#, &mut sheet, &mut undo, &mut registers).unwrap();
+    assert_eq!(sheet.all_cells[3][0].value, CellValue::Number(1.0));
+    assert_eq!(sheet.all_cells[3][1].value, CellValue::Number(2.0));
+
+    assert_eq!(
+        parser_visual(r#"p "z A1"#, &mut sheet, &mut undo, &mut registers),
+        Err(SheetError::InvalidCommand(
+            "register \"z is empty".to_string()
+        ))
+    );
+    assert_eq!(
+        parser_visual(r#"p "b D4"#, &mut sheet, &mut undo, &mut registers),
+        Err(SheetError::OutOfBounds("E4".to_string()))
+    );
+    assert!(matches!(
+        parser_visual(
+            r#"p "b A1 everything"#,
+            &mut sheet,
+            &mut undo,
+            &mut registers
+        ),
+        Err(SheetError::InvalidCommand(_))
+    ));
+}
+
+#[test]
+fn test_insert_and_delete_rows_and_columns() {
+    let mut sheet = initialise_extension(4, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1");
+    assign(&mut sheet, &mut undo, 1, 0, "2");
+    assign(&mut sheet, &mut undo, 2, 0, "3");
+    assign(&mut sheet, &mut undo, 0, 1, "SUM(A1:A3)");
+    assign(&mut sheet, &mut undo, 0, 2, "A2*10");
+
+    // A row inserted inside the range grows it; references below move down
+    handle_structure_command("insert_row 2", &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.rows, 5);
+    assert_eq!(expr_to_string(&sheet.all_cells[0][1].formula), "SUM(A1:A4)");
+    assert_eq!(expr_to_string(&sheet.all_cells[0][2].formula), "(A3 * 10)");
+    assign(&mut sheet, &mut undo, 1, 0, "4");
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(10.0));
+
+    // Deleting a referenced row leaves #REF!, while the range only shrinks
+    handle_structure_command("delete_row 3", &mut sheet, &mut undo).unwrap();
+    assert_eq!(expr_to_string(&sheet.all_cells[0][1].formula), "SUM(A1:A3)");
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(8.0));
+    assert_eq!(
+        sheet.all_cells[0][2].value,
+        CellValue::Error(CellError::Ref)
+    );
+
+    // One undo restores the deleted row and the rewritten formulas
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.rows, 5);
+    assert_eq!(sheet.all_cells[2][0].value, CellValue::Number(2.0));
+    assert_eq!(expr_to_string(&sheet.all_cells[0][2].formula), "(A3 * 10)");
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(10.0));
+    assert!(perform_redo(&mut sheet, &mut undo));
+    assert_eq!(sheet.rows, 4);
+    assert_eq!(
+        sheet.all_cells[0][2].value,
+        CellValue::Error(CellError::Ref)
+    );
+
+    // Columns are named by letter
+    handle_structure_command("delete_col A", &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.columns, 2);
+    assert_eq!(
+        sheet.all_cells[0][0].value,
+        CellValue::Error(CellError::Ref)
+    );
+
+    assert_eq!(
+        handle_structure_command("insert_row 9", &mut sheet, &mut undo),
+        Err(SheetError::OutOfBounds("9".to_string()))
+    );
+    assert!(matches!(
+        handle_structure_command("delete_col", &mut sheet, &mut undo),
+        Err(SheetError::InvalidCommand(_))
+    ));
+}
+
+#[test]
+fn test_insert_stops_at_the_size_limit() {
+    let mut sheet = initialise_extension(MAX_ROWS, 2);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "7");
+
+    assert_eq!(
+        handle_structure_command("insert_row 1", &mut sheet, &mut undo),
+        Err(SheetError::OutOfBounds((MAX_ROWS + 1).to_string()))
+    );
+    assert_eq!(sheet.rows, MAX_ROWS);
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(7.0));
+    // Nothing was recorded, so undo goes straight back to before the assignment
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(0.0));
+    assert!(!undo.can_undo());
+
+    // Columns can still be inserted
+    handle_structure_command("insert_col A", &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.columns, 3);
+}
+
+#[test]
+fn test_resize_and_csv_growth() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 2, 2, "7");
+    assign(&mut sheet, &mut undo, 0, 0, "SUM(B2:C3)");
+    assign(&mut sheet, &mut undo, 0, 1, "C3+1");
+
+    handle_structure_command("resize 10 5", &mut sheet, &mut undo).unwrap();
+    assert_eq!((sheet.rows, sheet.columns), (10, 5));
+    assert_eq!(sheet.all_cells[9].len(), 5);
+    assign(&mut sheet, &mut undo, 9, 4, "A1*2");
+    assert_eq!(sheet.all_cells[9][4].value, CellValue::Number(14.0));
+    assert!(perform_undo(&mut sheet, &mut undo));
+
+    // Shrinking clips ranges and breaks references to cut-off cells
+    handle_structure_command("resize 2 2", &mut sheet, &mut undo).unwrap();
+    assert_eq!(expr_to_string(&sheet.all_cells[0][0].formula), "SUM(B2:B2)");
+    assert_eq!(
+        sheet.all_cells[0][1].value,
+        CellValue::Error(CellError::Ref)
+    );
+
+    // Undo brings back both the cut-off cells and the size change before it
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!((sheet.rows, sheet.columns), (10, 5));
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(7.0));
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(8.0));
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!((sheet.rows, sheet.columns), (3, 3));
+
+    assert!(matches!(
+        handle_structure_command("resize 0 5", &mut sheet, &mut undo),
+        Err(SheetError::InvalidCommand(_))
+    ));
+
+    // Reading a larger file grows the sheet instead of truncating it
+    let path = temp_path("growth.csv");
+    fs::write(&path, "1,2,3,4\n5\n6\n=A1+D1\n").unwrap();
+    read_csv_file(&path, &mut sheet, &mut undo).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!((sheet.rows, sheet.columns), (4, 4));
+    assert_eq!(sheet.all_cells[3][0].value, CellValue::Number(5.0));
+}
+
+#[test]
+fn test_large_sheets_only_store_written_cells() {
+    let mut sheet = initialise_extension(999, 18278);
+    let mut undo = UndoRedoStack::new();
+    assert_eq!(sheet.all_cells.len(), 999);
+    assert_eq!(sheet.all_cells[998].len(), 18278);
+    assert_eq!(sheet.all_cells.cells().count(), 0);
+
+    // Reading an empty cell does not store it
+    assert_eq!(sheet.all_cells[500][9000].value, CellValue::Number(0.0));
+    assert_eq!(sheet.all_cells.cells().count(), 0);
+
+    assign(&mut sheet, &mut undo, 998, 18277, "5");
+    assign(&mut sheet, &mut undo, 0, 0, "SUM(ZZY999:ZZZ999)*2");
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(10.0));
+    // The range's empty cell is not stored to track that A1 depends on it
+    assert_eq!(sheet.all_cells.cells().count(), 2);
+
+    handle_structure_command("delete_row 1", &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.all_cells[997][18277].value, CellValue::Number(5.0));
+    assert_eq!(sheet.all_cells.cells().count(), 1);
+}
+
+#[test]
+fn test_range_dependencies_are_stored_once_per_range() {
+    let mut sheet = initialise_extension(999, 100);
+    let mut undo = UndoRedoStack::new();
+
+    // A range over 999 rows costs one entry per 32-row block, not one per cell
+    assign(&mut sheet, &mut undo, 0, 1, "SUM(A1:A999)");
+    assert_eq!(sheet.range_dependents.entry_count(), 32);
+    assert_eq!(sheet.all_cells.cells().count(), 1);
+
+    // Writing a cell the range covers updates the dependent
+    assign(&mut sheet, &mut undo, 500, 0, "4");
+    assign(&mut sheet, &mut undo, 998, 0, "6");
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(10.0));
+    assert!(dependents_of_extension(&sheet, 700, 0).contains(&CellReference::new(0, 1)));
+
+    // A cycle through a range is still detected
+    let expr = parse_formula("B1+1").unwrap();
+    assert!(matches!(
+        assign_cell_extension(&mut sheet, &mut undo, 300, 0, *expr),
+        Err(SheetError::CyclicDependency(_))
+    ));
+    assert_eq!(sheet.range_dependents.entry_count(), 32);
+
+    // Changing the range replaces its entries
+    assign(&mut sheet, &mut undo, 0, 1, "SUM(A1:A10)");
+    assert_eq!(sheet.range_dependents.entry_count(), 1);
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(0.0));
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.range_dependents.entry_count(), 32);
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(10.0));
+}
+
+#[test]
+fn test_recalculation_evaluates_each_dependent_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    register_function(FunctionSpec::new(
+        "COUNTED",
+        1,
+        Some(1),
+        false,
+        |args, sheet| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            eval_expr(&args[0], sheet)
+        },
+    ));
+
+    // A diamond: D1 is reached from A1 through both B1 and C1
+    let mut sheet = initialise_extension(3, 5);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1");
+    assign(&mut sheet, &mut undo, 0, 1, "A1+1");
+    assign(&mut sheet, &mut undo, 0, 2, "A1+B1");
+    assign(&mut sheet, &mut undo, 0, 3, "COUNTED(B1+C1)");
+    assign(&mut sheet, &mut undo, 0, 4, "D1+C1+B1");
+
+    CALLS.store(0, Ordering::SeqCst);
+    assign(&mut sheet, &mut undo, 0, 0, "10");
+    assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    assert_eq!(sheet.all_cells[0][3].value, CellValue::Number(32.0));
+    assert_eq!(sheet.all_cells[0][4].value, CellValue::Number(64.0));
+
+    // Each affected cell comes once, after the affected cells it depends on
+    let order = rust_lab::recalc_order::recalculation_order((0, 0), |(r, c)| {
+        dependents_of_extension(&sheet, r, c)
+            .into_iter()
+            .map(|dependent| (dependent.row, dependent.column))
+    });
+    assert_eq!(order, vec![(0, 1), (0, 2), (0, 3), (0, 4)]);
+}
+
+#[test]
+fn test_cycle_errors_name_every_cell_on_the_loop() {
+    let mut sheet = initialise_extension(5, 5);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 1, 1, "C3*2");
+    assign(&mut sheet, &mut undo, 2, 2, "SUM(A1:A5)");
+    assign(&mut sheet, &mut undo, 4, 4, "B2");
+
+    // A1 -> B2 -> C3 -> A1, with the last step through a range
+    let expr = parse_formula("B2+1").unwrap();
+    let error = assign_cell_extension(&mut sheet, &mut undo, 0, 0, *expr).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Cyclic dependency: A1 \u{2192} B2 \u{2192} C3 \u{2192} A1"
+    );
+    assert_eq!(find_cycle_extension(&sheet, 0, 0), None);
+
+    // Cells hanging off the loop are left out
+    assert_eq!(find_cycle_extension(&sheet, 4, 4), None);
+}
+
+#[test]
+fn test_iterative_calculation_of_circular_references() {
+    let mut sheet = initialise_extension(3, 4);
+    let mut undo = UndoRedoStack::new();
+    handle_iterate_command("iterate on 200 0.0001", &mut sheet).unwrap();
+    assert_eq!(
+        sheet.iteration,
+        Some(IterationSettings {
+            max_iterations: 200,
+            tolerance: 0.0001,
+        })
+    );
+
+    // Interest accrued on a balance that includes the interest
+    assign(&mut sheet, &mut undo, 0, 0, "1000");
+    assign(&mut sheet, &mut undo, 0, 1, "A1+C1");
+    assign(&mut sheet, &mut undo, 0, 2, "B1*0.05");
+    assign(&mut sheet, &mut undo, 0, 3, "B1*2");
+    let balance = sheet.all_cells[0][1].value.as_number().unwrap();
+    assert!((balance - 1000.0 / 0.95).abs() < 0.001);
+    let doubled = sheet.all_cells[0][3].value.as_number().unwrap();
+    assert!((doubled - 2000.0 / 0.95).abs() < 0.01);
+
+    // Changing an input outside the loop iterates it again
+    assign(&mut sheet, &mut undo, 0, 0, "1900");
+    let balance = sheet.all_cells[0][1].value.as_number().unwrap();
+    assert!((balance - 2000.0).abs() < 0.001);
+
+    // A loop that never settles is an error, until it is fixed
+    assign(&mut sheet, &mut undo, 1, 0, "B2+1");
+    assign(&mut sheet, &mut undo, 1, 1, "A2");
+    let no_convergence = CellValue::Error(CellError::Convergence);
+    assert_eq!(sheet.all_cells[1][0].value, no_convergence);
+    assert_eq!(sheet.all_cells[1][1].value, no_convergence);
+    assign(&mut sheet, &mut undo, 1, 0, "B2/2+1");
+    assert!((sheet.all_cells[1][0].value.as_number().unwrap() - 2.0).abs() < 0.001);
+
+    // The setting is saved with the project
+    let (loaded, _) = save_and_load("iterative.json", &sheet, &UndoRedoStack::new());
+    assert_eq!(loaded.iteration, sheet.iteration);
+    let balance = loaded.all_cells[0][1].value.as_number().unwrap();
+    assert!((balance - 2000.0).abs() < 0.001);
+
+    // Turning it off turns the loops into cycle errors
+    handle_iterate_command("iterate off", &mut sheet).unwrap();
+    assert_eq!(
+        sheet.all_cells[0][1].value,
+        CellValue::Error(CellError::Cycle)
+    );
+    assert_eq!(
+        sheet.all_cells[0][3].value,
+        CellValue::Error(CellError::Cycle)
+    );
+    assert!(matches!(
+        handle_iterate_command("iterate on 0", &mut sheet),
+        Err(SheetError::InvalidCommand(_))
+    ));
+}
+
+/// Assigns a formula to a cell of the active sheet, as the command loop does.
+fn assign_in(workbook: &mut Workbook, row: i32, col: i32, formula: &str) {
+    let (sheet, undo) = workbook.active_parts();
+    assign(sheet, undo, row, col, formula);
+    workbook.refresh_links();
+}
+
+#[test]
+fn test_cross_sheet_references() {
+    let mut workbook = Workbook::new(5, 5);
+    handle_sheet_command("sheet add 'My Sheet'", &mut workbook).unwrap();
+    assert_eq!(workbook.names(), ["Sheet1", "My Sheet"]);
+    assert_eq!(workbook.active_name(), "My Sheet");
+    assign_in(&mut workbook, 0, 0, "10");
+    assign_in(&mut workbook, 1, 0, "A1*2");
+
+    handle_sheet_command("sheet switch sheet1", &mut workbook).unwrap();
+    assign_in(&mut workbook, 0, 0, "'My Sheet'!A1+1");
+    assign_in(&mut workbook, 0, 1, "SUM('my sheet'!A1:A2)");
+    assign_in(&mut workbook, 0, 2, "A1*2");
+    assert_eq!(
+        workbook.active().all_cells[0][0].value,
+        CellValue::Number(11.0)
+    );
+    assert_eq!(
+        workbook.active().all_cells[0][1].value,
+        CellValue::Number(30.0)
+    );
+    assert_eq!(
+        expr_to_string(&workbook.active().all_cells[0][1].formula),
+        "SUM('my sheet'!A1:A2)"
+    );
+
+    // Edits on one sheet reach the formulas of the other, and their dependents
+    handle_sheet_command("sheet switch 'My Sheet'", &mut workbook).unwrap();
+    assign_in(&mut workbook, 0, 0, "20");
+    let sheet1 = workbook.sheet("Sheet1").unwrap();
+    assert_eq!(sheet1.all_cells[0][0].value, CellValue::Number(21.0));
+    assert_eq!(sheet1.all_cells[0][1].value, CellValue::Number(60.0));
+    assert_eq!(sheet1.all_cells[0][2].value, CellValue::Number(42.0));
+
+    // Renaming rewrites the references; deleting turns them into #REF!
+    handle_sheet_command("sheet rename 'My Sheet' Inputs", &mut workbook).unwrap();
+    let sheet1 = workbook.sheet("Sheet1").unwrap();
+    assert_eq!(
+        expr_to_string(&sheet1.all_cells[0][0].formula),
+        "(Inputs!A1 + 1)"
+    );
+    assert_eq!(sheet1.all_cells[0][0].value, CellValue::Number(21.0));
+    handle_sheet_command("sheet delete inputs", &mut workbook).unwrap();
+    assert_eq!(workbook.names(), ["Sheet1"]);
+    assert_eq!(workbook.active_name(), "Sheet1");
+    let ref_error = CellValue::Error(CellError::Ref);
+    assert_eq!(workbook.active().all_cells[0][0].value, ref_error);
+    assert_eq!(workbook.active().all_cells[0][2].value, ref_error);
+
+    // A sheet of that name brings the references back
+    handle_sheet_command("sheet add Inputs", &mut workbook).unwrap();
+    let sheet1 = workbook.sheet("Sheet1").unwrap();
+    assert_eq!(sheet1.all_cells[0][0].value, CellValue::Number(1.0));
+
+    assert!(matches!(
+        handle_sheet_command("sheet add INPUTS", &mut workbook),
+        Err(SheetError::DuplicateSheet(_))
+    ));
+    assert!(matches!(
+        handle_sheet_command("sheet switch Missing", &mut workbook),
+        Err(SheetError::UnknownSheet(_))
+    ));
+    assert!(matches!(
+        handle_sheet_command("sheet add 'a!b'", &mut workbook),
+        Err(SheetError::InvalidCommand(_))
+    ));
+    handle_sheet_command("sheet delete Inputs", &mut workbook).unwrap();
+    assert!(matches!(
+        handle_sheet_command("sheet delete Sheet1", &mut workbook),
+        Err(SheetError::InvalidCommand(_))
+    ));
+    assert_eq!(
+        handle_sheet_command("sheet add", &mut workbook),
+        Ok(Outcome::Message("Added Sheet2".to_string()))
+    );
+}
+
+#[test]
+fn test_rename_sheet_rewrites_defined_names() {
+    let mut workbook = Workbook::new(3, 3);
+    handle_sheet_command("sheet add Data", &mut workbook).unwrap();
+    assign_in(&mut workbook, 0, 0, "0.5");
+    handle_sheet_command("sheet switch Sheet1", &mut workbook).unwrap();
+    let (sheet, undo) = workbook.active_parts();
+    handle_name_command("name rate Data!A1", sheet, undo).unwrap();
+    assign_in(&mut workbook, 0, 0, "rate*10");
+    assert_eq!(
+        workbook.active().all_cells[0][0].value,
+        CellValue::Number(5.0)
+    );
+
+    handle_sheet_command("sheet rename Data Inputs", &mut workbook).unwrap();
+    let sheet1 = workbook.sheet("Sheet1").unwrap();
+    assert_eq!(expr_to_string(&sheet1.names["RATE"].target), "Inputs!A1");
+    assert_eq!(sheet1.all_cells[0][0].value, CellValue::Number(5.0));
+
+    // The name keeps following the sheet's cells
+    handle_sheet_command("sheet switch Inputs", &mut workbook).unwrap();
+    assign_in(&mut workbook, 0, 0, "2");
+    let sheet1 = workbook.sheet("Sheet1").unwrap();
+    assert_eq!(sheet1.all_cells[0][0].value, CellValue::Number(20.0));
+}
+
+#[test]
+fn test_insert_and_delete_rewrite_other_sheets() {
+    let mut workbook = Workbook::new(5, 3);
+    assign_in(&mut workbook, 0, 0, "1");
+    assign_in(&mut workbook, 1, 0, "2");
+    assign_in(&mut workbook, 2, 0, "3");
+    handle_sheet_command("sheet add", &mut workbook).unwrap();
+    assign_in(&mut workbook, 0, 0, "Sheet1!A3*10");
+    assign_in(&mut workbook, 0, 1, "SUM(Sheet1!A1:A3)");
+    assign_in(&mut workbook, 0, 2, "Sheet1!A2");
+    let (sheet, undo) = workbook.active_parts();
+    handle_name_command("name last Sheet1!A3", sheet, undo).unwrap();
+
+    let structure = |workbook: &mut Workbook, cmd: &str| {
+        let (sheet, undo) = workbook.active_parts();
+        handle_structure_command(cmd, sheet, undo).unwrap();
+        workbook.refresh_links();
+    };
+    handle_sheet_command("sheet switch Sheet1", &mut workbook).unwrap();
+    structure(&mut workbook, "insert_row 1");
+    let sheet2 = workbook.sheet("Sheet2").unwrap();
+    assert_eq!(
+        expr_to_string(&sheet2.all_cells[0][0].formula),
+        "(Sheet1!A4 * 10)"
+    );
+    assert_eq!(
+        expr_to_string(&sheet2.all_cells[0][1].formula),
+        "SUM(Sheet1!A2:A4)"
+    );
+    assert_eq!(sheet2.all_cells[0][0].value, CellValue::Number(30.0));
+    assert_eq!(sheet2.all_cells[0][1].value, CellValue::Number(6.0));
+    assert_eq!(expr_to_string(&sheet2.names["LAST"].target), "Sheet1!A4");
+
+    // Deleting the row `Sheet1!A3` now points at turns the reference into #REF!
+    structure(&mut workbook, "delete_row 3");
+    let sheet2 = workbook.sheet("Sheet2").unwrap();
+    assert_eq!(sheet2.all_cells[0][2].formula, Expr::Error(CellError::Ref));
+    assert_eq!(
+        sheet2.all_cells[0][2].value,
+        CellValue::Error(CellError::Ref)
+    );
+    assert_eq!(
+        expr_to_string(&sheet2.all_cells[0][1].formula),
+        "SUM(Sheet1!A2:A3)"
+    );
+    assert_eq!(sheet2.all_cells[0][1].value, CellValue::Number(4.0));
+    assert_eq!(expr_to_string(&sheet2.names["LAST"].target), "Sheet1!A3");
+
+    // Undoing the delete moves the references back with the cells
+    let (sheet, undo) = workbook.active_parts();
+    assert!(perform_undo(sheet, undo));
+    workbook.refresh_links();
+    let sheet2 = workbook.sheet("Sheet2").unwrap();
+    assert_eq!(
+        expr_to_string(&sheet2.all_cells[0][0].formula),
+        "(Sheet1!A4 * 10)"
+    );
+    assert_eq!(sheet2.all_cells[0][1].value, CellValue::Number(6.0));
+
+    // Cutting off rows with a resize turns references to them into #REF!
+    structure(&mut workbook, "resize 3 3");
+    let sheet2 = workbook.sheet("Sheet2").unwrap();
+    assert_eq!(
+        expr_to_string(&sheet2.all_cells[0][0].formula),
+        "(#REF! * 10)"
+    );
+    assert_eq!(
+        sheet2.all_cells[0][0].value,
+        CellValue::Error(CellError::Ref)
+    );
+    assert_eq!(
+        expr_to_string(&sheet2.all_cells[0][1].formula),
+        "SUM(Sheet1!A2:A3)"
+    );
+    assert_eq!(sheet2.all_cells[0][1].value, CellValue::Number(3.0));
+    assert_eq!(expr_to_string(&sheet2.names["LAST"].target), "#REF!");
+}
+
+#[test]
+fn test_cross_sheet_cycles_are_errors() {
+    let mut workbook = Workbook::new(3, 3);
+    handle_sheet_command("sheet add Sheet2", &mut workbook).unwrap();
+    assign_in(&mut workbook, 0, 0, "Sheet1!B1+1");
+    assign_in(&mut workbook, 0, 1, "A1*2");
+
+    handle_sheet_command("sheet switch Sheet1", &mut workbook).unwrap();
+    assign_in(&mut workbook, 0, 1, "Sheet2!B1");
+    let cycle = CellValue::Error(CellError::Cycle);
+    assert_eq!(workbook.active().all_cells[0][1].value, cycle);
+    let sheet2 = workbook.sheet("Sheet2").unwrap();
+    assert_eq!(sheet2.all_cells[0][0].value, cycle);
+    assert_eq!(sheet2.all_cells[0][1].value, cycle);
+
+    // Breaking the loop brings the values back
+    assign_in(&mut workbook, 0, 1, "5");
+    let sheet2 = workbook.sheet("Sheet2").unwrap();
+    assert_eq!(sheet2.all_cells[0][0].value, CellValue::Number(6.0));
+    assert_eq!(sheet2.all_cells[0][1].value, CellValue::Number(12.0));
+
+    // A sheet referring to itself by name is checked the same way
+    assign_in(&mut workbook, 1, 0, "Sheet1!A2+1");
+    assert_eq!(workbook.active().all_cells[1][0].value, cycle);
+}
+
+#[test]
+fn test_defined_names() {
+    let mut sheet = initialise_extension(10, 5);
+    let mut undo = UndoRedoStack::new();
+    for row in 1..4 {
+        assign(&mut sheet, &mut undo, row, 1, &(row * 100).to_string());
+    }
+    assign(&mut sheet, &mut undo, 0, 2, "0.25");
+
+    // Formulas may use a name before it is defined
+    assign(&mut sheet, &mut undo, 5, 0, "SUM(revenue)*(1-tax_rate)");
+    assert_eq!(
+        sheet.all_cells[5][0].value,
+        CellValue::Error(CellError::Name)
+    );
+    handle_name_command("name revenue B2:B4", &mut sheet, &mut undo).unwrap();
+    handle_name_command("name tax_rate C1", &mut sheet, &mut undo).unwrap();
+    handle_name_command("name Months 6*2", &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.all_cells[5][0].value, CellValue::Number(450.0));
+    assign(&mut sheet, &mut undo, 5, 1, "REVENUE_Q_1 + months");
+    assert_eq!(
+        sheet.all_cells[5][1].value,
+        CellValue::Error(CellError::Name)
+    );
+    assign(&mut sheet, &mut undo, 5, 1, "AVG(Revenue) / MONTHS");
+    assert_eq!(sheet.all_cells[5][1].value, CellValue::Number(200.0 / 12.0));
+    assert_eq!(
+        expr_to_string(&sheet.all_cells[5][1].formula),
+        "(AVG(REVENUE) / MONTHS)"
+    );
+
+    // Changing a named cell recalculates the formulas using the name
+    assign(&mut sheet, &mut undo, 2, 1, "500");
+    assert_eq!(sheet.all_cells[5][0].value, CellValue::Number(675.0));
+    assign(&mut sheet, &mut undo, 0, 2, "0.5");
+    assert_eq!(sheet.all_cells[5][0].value, CellValue::Number(450.0));
+
+    assert_eq!(
+        handle_name_command("names", &mut sheet, &mut undo),
+        Ok(Outcome::Message(
+            "Months = 12, revenue = B2:B4, tax_rate = C1".to_string()
+        ))
+    );
+
+    // Names follow their cells when rows move, and undo puts them back
+    handle_structure_command("insert_row 1", &mut sheet, &mut undo).unwrap();
+    assert_eq!(expr_to_string(&sheet.names["REVENUE"].target), "B3:B5");
+    assert_eq!(sheet.all_cells[6][0].value, CellValue::Number(450.0));
+    handle_structure_command("delete_row 2", &mut sheet, &mut undo).unwrap();
+    assert_eq!(expr_to_string(&sheet.names["TAX_RATE"].target), "#REF!");
+    assert_eq!(
+        sheet.all_cells[5][0].value,
+        CellValue::Error(CellError::Ref)
+    );
+    perform_undo(&mut sheet, &mut undo);
+    assert_eq!(expr_to_string(&sheet.names["TAX_RATE"].target), "C2");
+    assert_eq!(sheet.all_cells[6][0].value, CellValue::Number(450.0));
+
+    // Names are saved with the sheet
+    let (loaded, _) = save_and_load("names.json", &sheet, &UndoRedoStack::new());
+    assert_eq!(loaded.names, sheet.names);
+    assert_eq!(loaded.all_cells[6][0].value, CellValue::Number(450.0));
+
+    // Removing a name leaves its formulas with #NAME?
+    handle_name_command("name months", &mut sheet, &mut undo).unwrap();
+    assert_eq!(
+        sheet.all_cells[6][1].value,
+        CellValue::Error(CellError::Name)
+    );
+    assert!(matches!(
+        handle_name_command("name months", &mut sheet, &mut undo),
+        Err(SheetError::UnknownName(_))
+    ));
+    for bad in [
+        "name AB12 C1",
+        "name true C1",
+        "name rate SUM(B1:B2)",
+        "name rate Z1",
+    ] {
+        assert!(
+            handle_name_command(bad, &mut sheet, &mut undo).is_err(),
+            "{}",
+            bad
+        );
+    }
+}
+
+#[test]
+fn test_name_that_closes_a_loop_is_rejected() {
+    let mut sheet = initialise_extension(4, 4);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "foo_x+1");
+    assign(&mut sheet, &mut undo, 1, 0, "SUM(bar)");
+    assign(&mut sheet, &mut undo, 1, 1, "A2*2");
+    handle_name_command("name foo_x D4", &mut sheet, &mut undo).unwrap();
+
+    // A1 would refer to itself, and A2 to B2, which refers back to A2
+    for cmd in ["name foo_x A1", "name bar A2", "name bar B1:B3"] {
+        assert!(
+            matches!(
+                handle_name_command(cmd, &mut sheet, &mut undo),
+                Err(SheetError::CyclicDependency(_))
+            ),
+            "{}",
+            cmd
+        );
+    }
+    assert_eq!(expr_to_string(&sheet.names["FOO_X"].target), "D4");
+    assert!(!sheet.names.contains_key("BAR"));
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(1.0));
+
+    handle_name_command("name bar C1:C3", &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.all_cells[1][1].value, CellValue::Number(0.0));
+}
+
+#[test]
+fn test_name_changes_can_be_undone() {
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "5");
+    assign(&mut sheet, &mut undo, 1, 0, "7");
+    assign(&mut sheet, &mut undo, 2, 2, "rate*2");
+    handle_name_command("name rate A1", &mut sheet, &mut undo).unwrap();
+    handle_name_command("name Rate A2", &mut sheet, &mut undo).unwrap();
+    handle_name_command("name rate", &mut sheet, &mut undo).unwrap();
+    assert_eq!(
+        sheet.all_cells[2][2].value,
+        CellValue::Error(CellError::Name)
+    );
+    assert_eq!(undo.history().last().unwrap().label, "name Rate");
+
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[2][2].value, CellValue::Number(14.0));
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[2][2].value, CellValue::Number(10.0));
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert!(sheet.names.is_empty());
+    assert_eq!(
+        sheet.all_cells[2][2].value,
+        CellValue::Error(CellError::Name)
+    );
+
+    assert!(perform_redo(&mut sheet, &mut undo));
+    assert!(perform_redo(&mut sheet, &mut undo));
+    assert_eq!(expr_to_string(&sheet.names["RATE"].target), "A2");
+    assert_eq!(sheet.names["RATE"].name, "Rate");
+    assert_eq!(sheet.all_cells[2][2].value, CellValue::Number(14.0));
+}
+
+#[test]
+fn test_undo_transactions() {
+    let mut sheet = initialise_extension(10, 10);
+    let mut undo = UndoRedoStack::new();
+    let mut registers = Registers::new();
+    for row in 0..5 {
+        for col in 0..5 {
+            assign(
+                &mut sheet,
+                &mut undo,
+                row,
+                col,
+                &(row * 5 + col + 1).to_string(),
+            );
+        }
+    }
+
+    // A 5x5 paste is one step, and does not push the earlier edits out of the history
+    parser_visual("y A1:E5", &mut sheet, &mut undo, &mut registers).unwrap();
+    parser_visual("p F6", &mut sheet, &mut undo, &mut registers).unwrap();
+    assert_eq!(sheet.all_cells[9][9].value, CellValue::Number(25.0));
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert!(
+        (5..10).all(|r| (5..10).all(|c| sheet.all_cells[r][c].value == CellValue::Number(0.0)))
+    );
+    assert_eq!(sheet.all_cells[4][4].value, CellValue::Number(25.0));
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[4][4].value, CellValue::Number(0.0));
+    assert_eq!(sheet.all_cells[4][3].value, CellValue::Number(24.0));
+
+    // Redo replays the whole paste
+    assert!(perform_redo(&mut sheet, &mut undo));
+    assert!(perform_redo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[9][9].value, CellValue::Number(25.0));
+    assert_eq!(sheet.all_cells[5][5].value, CellValue::Number(1.0));
+
+    // Moving a range clears the source and fills the destination in one step
+    parser_visual("d A1:B2 H1:I2", &mut sheet, &mut undo, &mut registers).unwrap();
+    assert_eq!(sheet.all_cells[1][8].value, CellValue::Number(7.0));
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[1][1].value, CellValue::Number(7.0));
+    assert_eq!(sheet.all_cells[1][8].value, CellValue::Number(0.0));
+
+    // A fresh edit clears the redo stack; a failed one does not
+    let cyclic = parse_formula("A1+1").unwrap();
+    assert!(assign_cell_extension(&mut sheet, &mut undo, 0, 0, *cyclic).is_err());
+    assert!(undo.can_redo());
+    assign(&mut sheet, &mut undo, 0, 0, "100");
+    assert!(!undo.can_redo());
+    assert!(!perform_redo(&mut sheet, &mut undo));
+
+    // The depth limits the steps kept
+    undo.set_max_depth(2);
+    for value in ["1", "2", "3"] {
+        assign(&mut sheet, &mut undo, 9, 0, value);
+    }
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert!(!perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[9][0].value, CellValue::Number(1.0));
+}
+
+#[test]
+fn test_undo_csv_read_and_saved_steps() {
+    let path = temp_path("transaction.csv");
+    fs::write(&path, "1,2,3\n4,5,6\n7,8,=A1+B3\n").unwrap();
+    let mut sheet = initialise_extension(3, 3);
+    let mut undo = UndoRedoStack::new();
+    assign(&mut sheet, &mut undo, 0, 0, "42");
+    read_csv_file(&path, &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.all_cells[2][2].value, CellValue::Number(9.0));
+
+    // The saved history keeps the read as one step
+    let (mut loaded, mut loaded_undo) = save_and_load("transaction.json", &sheet, &undo);
+    let steps: Vec<usize> = loaded_undo.undo_steps().iter().map(Vec::len).collect();
+    assert_eq!(steps, vec![1, 9]);
+    assert!(perform_undo(&mut loaded, &mut loaded_undo));
+    assert_eq!(loaded.all_cells[0][0].value, CellValue::Number(42.0));
+    assert_eq!(loaded.all_cells[2][2].value, CellValue::Number(0.0));
+
+    // Undoing a read that grew the sheet also shrinks it back
+    let mut small = initialise_extension(2, 2);
+    let mut small_undo = UndoRedoStack::new();
+    assign(&mut small, &mut small_undo, 0, 0, "42");
+    read_csv_file(&path, &mut small, &mut small_undo).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!((small.rows, small.columns), (3, 3));
+    assert!(perform_undo(&mut small, &mut small_undo));
+    assert_eq!((small.rows, small.columns), (2, 2));
+    assert_eq!(small.all_cells[0][0].value, CellValue::Number(42.0));
+    assert!(perform_redo(&mut small, &mut small_undo));
+    assert_eq!(small.all_cells[2][2].value, CellValue::Number(9.0));
+
+    // The depth applies to every sheet of a workbook, including new ones
+    let mut workbook = Workbook::new(2, 2);
+    assert_eq!(
+        handle_undo_depth_command("undo_depth 50", &mut workbook),
+        Ok(Outcome::Message("Undo keeps up to 50 steps".to_string()))
+    );
+    handle_sheet_command("sheet add", &mut workbook).unwrap();
+    assert_eq!(workbook.active_parts().1.max_depth(), 50);
+    assert!(handle_undo_depth_command("undo_depth 0", &mut workbook).is_err());
+    assert!(handle_undo_depth_command("undo_depth many", &mut workbook).is_err());
+}
+
+#[test]
+fn test_cut_leaves_unwritten_cells_alone() {
+    let mut sheet = initialise_extension(MAX_ROWS, 26);
+    let mut undo = UndoRedoStack::new();
+    let mut registers = Registers::new();
+    assign(&mut sheet, &mut undo, 0, 0, "1");
+    assign(&mut sheet, &mut undo, 500, 20, "A1+1");
+    assert_eq!(sheet.all_cells.cells().count(), 2);
+
+    parser_visual(
+        &format!("d A1:Z{}", MAX_ROWS),
+        &mut sheet,
+        &mut undo,
+        &mut registers,
+    )
+    .unwrap();
+    assert_eq!(sheet.all_cells.cells().count(), 2);
+    assert_eq!(sheet.all_cells[500][20].value, CellValue::Number(0.0));
+
+    // Only the written cells are in the undo step
+    let steps: Vec<usize> = undo.undo_steps().iter().map(Vec::len).collect();
+    assert_eq!(steps, vec![1, 1, 2]);
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[500][20].value, CellValue::Number(2.0));
+}
+
+#[test]
+fn test_undo_formatting_and_imported_values() {
+    let mut sheet = initialise_extension(5, 5);
+    let mut undo = UndoRedoStack::new();
+    let mut registers = Registers::new();
+    assign(&mut sheet, &mut undo, 0, 0, "7");
+    assign(&mut sheet, &mut undo, 0, 1, "A1*2");
+
+    // Bold and italics are undone and redone like any other edit
+    parser_visual("b A1", &mut sheet, &mut undo, &mut registers).unwrap();
+    parser_visual("i A1", &mut sheet, &mut undo, &mut registers).unwrap();
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert!(sheet.all_cells[0][0].is_bold && !sheet.all_cells[0][0].is_italics);
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert!(!sheet.all_cells[0][0].is_bold);
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(14.0));
+    assert!(perform_redo(&mut sheet, &mut undo));
+    assert!(sheet.all_cells[0][0].is_bold);
+
+    // Pasting only formats, or clearing a cell, puts the formatting back on undo
+    parser_visual("y A1", &mut sheet, &mut undo, &mut registers).unwrap();
+    parser_visual("p C3 formats", &mut sheet, &mut undo, &mut registers).unwrap();
+    assert!(sheet.all_cells[2][2].is_bold);
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert!(!sheet.all_cells[2][2].is_bold);
+    parser_visual("p C3", &mut sheet, &mut undo, &mut registers).unwrap();
+    parser_visual("d C3", &mut sheet, &mut undo, &mut registers).unwrap();
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert!(sheet.all_cells[2][2].is_bold);
+    assert_eq!(sheet.all_cells[2][2].value, CellValue::Number(7.0));
+
+    // Values imported from CSV come back with undo and redo
+    let path = temp_path("imported_values.csv");
+    fs::write(&path, "1e400,-Infinity,hello world,#DIV/0!\n").unwrap();
+    read_csv_file(&path, &mut sheet, &mut undo).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        sheet.all_cells[0][2].value,
+        CellValue::Text("hello world".to_string())
+    );
+    let imported: Vec<CellValue> = (0..4)
+        .map(|c| sheet.all_cells[0][c].value.clone())
+        .collect();
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(7.0));
+    assert_eq!(sheet.all_cells[0][1].value, CellValue::Number(14.0));
+    assert_eq!(sheet.all_cells[0][2].value, CellValue::Number(0.0));
+    assert_eq!(sheet.all_cells[0][3].value, CellValue::Number(0.0));
+    assert!(perform_redo(&mut sheet, &mut undo));
+    let redone: Vec<CellValue> = (0..4)
+        .map(|c| sheet.all_cells[0][c].value.clone())
+        .collect();
+    assert_eq!(redone, imported);
+
+    // Formatting and imported values survive in saved history
+    let (mut loaded, mut loaded_undo) = save_and_load("imported_history.json", &sheet, &undo);
+    assert!(perform_undo(&mut loaded, &mut loaded_undo));
+    assert_eq!(loaded.all_cells[0][0].value, CellValue::Number(7.0));
+    assert!(loaded.all_cells[0][0].is_bold);
+}
+
+#[test]
+fn test_undo_tree_keeps_branches() {
+    let mut sheet = initialise_extension(10, 10);
+    let mut undo = UndoRedoStack::new();
+    for value in ["1", "2", "3"] {
+        assign(&mut sheet, &mut undo, 0, 0, value);
+    }
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert!(perform_undo(&mut sheet, &mut undo));
+
+    // An edit after undo starts a new branch; the undone edits stay in the history
+    assign(&mut sheet, &mut undo, 0, 0, "10");
+    assert!(!undo.can_redo());
+    assert_eq!(undo.current_state(), 4);
+    let states: Vec<(usize, Option<usize>)> = undo
+        .history()
+        .iter()
+        .map(|state| (state.number, state.parent))
+        .collect();
+    assert_eq!(
+        states,
+        vec![
+            (0, None),
+            (1, Some(0)),
+            (2, Some(1)),
+            (3, Some(2)),
+            (4, Some(1))
+        ]
+    );
+
+    handle_history_command("undo_to 3", &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(3.0));
+    assert!(perform_undo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(2.0));
+    // Redo follows the branch last visited
+    assert!(perform_redo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(3.0));
+    handle_history_command("undo_to 4", &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(10.0));
+    handle_history_command("undo_to 0", &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(0.0));
+    assert!(perform_redo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(1.0));
+    assert!(perform_redo(&mut sheet, &mut undo));
+    assert_eq!(sheet.all_cells[0][0].value, CellValue::Number(10.0));
+
+    // Commands are labelled by what they changed
+    let mut registers = Registers::new();
+    parser_visual("y A1:B2", &mut sheet, &mut undo, &mut registers).unwrap();
+    parser_visual("p C3", &mut sheet, &mut undo, &mut registers).unwrap();
+    handle_structure_command("insert_row 2", &mut sheet, &mut undo).unwrap();
+    let lines = format_history(&undo);
+    assert_eq!(lines.len(), 7);
+    assert!(lines[4].starts_with("4 ") && lines[4].ends_with(" A1 (from 1)"));
+    assert!(lines[5].ends_with(" C3:D4"));
+    assert!(lines[6].starts_with("*6 ") && lines[6].ends_with(" insert_row 2"));
+    match handle_history_command("history", &mut sheet, &mut undo) {
+        Ok(Outcome::Message(message)) => assert_eq!(message, lines.join("; ")),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // Dropping old states also drops the branches only they lead to
+    undo.set_max_depth(2);
+    let kept: Vec<usize> = undo.history().iter().map(|state| state.number).collect();
+    assert_eq!(kept, vec![4, 5, 6]);
+    assert_eq!(
+        handle_history_command("undo_to 2", &mut sheet, &mut undo),
+        Err(SheetError::UnknownHistoryState(2))
+    );
+    assert!(matches!(
+        handle_history_command("undo_to last", &mut sheet, &mut undo),
+        Err(SheetError::InvalidCommand(_))
+    ));
+    handle_history_command("undo_to 4", &mut sheet, &mut undo).unwrap();
+    assert_eq!(sheet.rows, 10);
+    assert_eq!(sheet.all_cells[2][2].value, CellValue::Number(0.0));
+    assert!(!perform_undo(&mut sheet, &mut undo));
+}